@@ -0,0 +1,3 @@
+-- Undo refresh_tokens migration: drop the index, then the table.
+DROP INDEX IF EXISTS idx_refresh_tokens_family_id;
+DROP TABLE IF EXISTS refresh_tokens;
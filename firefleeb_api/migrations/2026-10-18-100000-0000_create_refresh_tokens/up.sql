@@ -0,0 +1,12 @@
+CREATE TABLE refresh_tokens (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  family_id UUID NOT NULL,
+  token_hash TEXT NOT NULL UNIQUE,
+  expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  revoked_at TIMESTAMP WITH TIME ZONE NULL,
+  replaced_by UUID NULL REFERENCES refresh_tokens(id) ON DELETE SET NULL,
+  created_at TIMESTAMP WITH TIME ZONE DEFAULT now()
+);
+-- Reuse detection revokes every token issued from the same login
+CREATE INDEX idx_refresh_tokens_family_id ON refresh_tokens (family_id);
@@ -0,0 +1,3 @@
+-- Undo password_reset_tokens migration: drop the index, then the table.
+DROP INDEX IF EXISTS idx_password_reset_tokens_user_id;
+DROP TABLE IF EXISTS password_reset_tokens;
@@ -0,0 +1,4 @@
+-- Undo email verification migration: drop the token table, then the users column.
+DROP INDEX IF EXISTS idx_email_verification_tokens_user_id;
+DROP TABLE IF EXISTS email_verification_tokens;
+ALTER TABLE users DROP COLUMN IF EXISTS email_verified_at;
@@ -0,0 +1,2 @@
+-- Undo login_throttles migration.
+DROP TABLE IF EXISTS login_throttles;
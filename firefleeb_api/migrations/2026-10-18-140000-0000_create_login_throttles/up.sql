@@ -0,0 +1,9 @@
+-- Failed login bookkeeping, one row per throttled subject (an account email or a client IP).
+CREATE TABLE login_throttles (
+  scope TEXT NOT NULL,
+  subject TEXT NOT NULL,
+  failed_count INTEGER NOT NULL DEFAULT 0,
+  locked_until TIMESTAMP WITH TIME ZONE NULL,
+  last_failed_at TIMESTAMP WITH TIME ZONE NULL,
+  PRIMARY KEY (scope, subject)
+);
@@ -0,0 +1,6 @@
+-- Undo product listing migration.
+DROP INDEX IF EXISTS products_lower_name_pattern_idx;
+DROP INDEX IF EXISTS products_created_at_id_idx;
+DROP INDEX IF EXISTS products_name_id_idx;
+DROP INDEX IF EXISTS products_price_id_idx;
+ALTER TABLE products ALTER COLUMN created_at DROP NOT NULL;
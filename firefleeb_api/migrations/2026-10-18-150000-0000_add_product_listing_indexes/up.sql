@@ -0,0 +1,11 @@
+-- Listing sorts by created_at, so it must always be set.
+UPDATE products SET created_at = now() WHERE created_at IS NULL;
+ALTER TABLE products ALTER COLUMN created_at SET NOT NULL;
+
+-- Keyset pagination walks (sort column, id); the id breaks ties between equal sort values.
+CREATE INDEX products_price_id_idx ON products (price, id);
+CREATE INDEX products_name_id_idx ON products (product_name, id);
+CREATE INDEX products_created_at_id_idx ON products (created_at, id);
+
+-- Case-insensitive name prefix search: lower(product_name) LIKE 'abc%'.
+CREATE INDEX products_lower_name_pattern_idx ON products (lower(product_name) text_pattern_ops);
@@ -0,0 +1,3 @@
+-- Undo product search migration.
+DROP INDEX IF EXISTS products_search_document_idx;
+ALTER TABLE products DROP COLUMN IF EXISTS search_document;
@@ -0,0 +1,8 @@
+-- Full-text search document, kept in sync by Postgres. Names weigh more than descriptions.
+-- Not listed in schema.rs: only the raw search queries read it.
+ALTER TABLE products ADD COLUMN search_document tsvector GENERATED ALWAYS AS (
+  setweight(to_tsvector('english', product_name), 'A') ||
+  setweight(to_tsvector('english', coalesce(product_description, '')), 'B')
+) STORED;
+
+CREATE INDEX products_search_document_idx ON products USING GIN (search_document);
@@ -0,0 +1,2 @@
+-- Undo product name trigram migration. The extension stays, other objects may rely on it.
+DROP INDEX IF EXISTS products_name_trgm_idx;
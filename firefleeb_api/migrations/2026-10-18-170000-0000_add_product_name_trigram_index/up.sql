@@ -0,0 +1,4 @@
+-- Trigram matching for typo-tolerant name suggestions.
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX products_name_trgm_idx ON products USING GIN (product_name gin_trgm_ops);
@@ -0,0 +1,3 @@
+-- Undo categories migration.
+DROP TABLE IF EXISTS product_categories;
+DROP TABLE IF EXISTS categories;
@@ -0,0 +1,21 @@
+-- Category tree. A category without a parent is a root; parents with children cannot be deleted.
+CREATE TABLE categories (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  parent_id UUID NULL REFERENCES categories(id) ON DELETE RESTRICT,
+  name TEXT NOT NULL,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+  CHECK (parent_id IS NULL OR parent_id <> id)
+);
+
+CREATE INDEX categories_parent_id_idx ON categories (parent_id);
+-- Sibling names are unique, case-insensitively; roots count as siblings of each other.
+CREATE UNIQUE INDEX categories_sibling_name_idx
+  ON categories (COALESCE(parent_id, '00000000-0000-0000-0000-000000000000'::uuid), lower(name));
+
+CREATE TABLE product_categories (
+  product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+  category_id UUID NOT NULL REFERENCES categories(id) ON DELETE CASCADE,
+  PRIMARY KEY (product_id, category_id)
+);
+
+CREATE INDEX product_categories_category_id_idx ON product_categories (category_id);
@@ -0,0 +1,12 @@
+ALTER TABLE cart_items DROP CONSTRAINT uq_cart_items_cart_variant;
+
+-- Only one line per product fits the old constraint; keep the default variant's
+DELETE FROM cart_items ci
+USING product_variants v
+WHERE v.id = ci.variant_id AND NOT v.is_default;
+
+ALTER TABLE cart_items DROP COLUMN variant_id;
+ALTER TABLE cart_items ADD CONSTRAINT uq_cart_items_cart_id_item_id UNIQUE (cart_id, item_id);
+ALTER TABLE cart_items ADD CONSTRAINT uq_cart_items_cart_product UNIQUE (cart_id, item_id);
+
+DROP TABLE product_variants;
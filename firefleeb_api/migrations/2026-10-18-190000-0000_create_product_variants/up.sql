@@ -0,0 +1,38 @@
+-- Sellable versions of a product (size, colour, ...). `price` overrides the product's price when set.
+CREATE TABLE product_variants (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+  sku TEXT NOT NULL,
+  options JSONB NOT NULL DEFAULT '{}'::jsonb CHECK (jsonb_typeof(options) = 'object'),
+  price NUMERIC(10, 2) NULL CHECK (price >= 0),
+  stock INT NOT NULL DEFAULT 0 CHECK (stock >= 0),
+  is_default BOOLEAN NOT NULL DEFAULT false,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX product_variants_sku_key ON product_variants (lower(sku));
+CREATE UNIQUE INDEX product_variants_options_key ON product_variants (product_id, options);
+CREATE UNIQUE INDEX product_variants_default_key ON product_variants (product_id) WHERE is_default;
+
+-- Every existing product gets a default variant holding its stock
+INSERT INTO product_variants (product_id, sku, stock, is_default)
+SELECT id, 'SKU-' || upper(replace(id::text, '-', '')), GREATEST(stock, 0), true
+FROM products;
+
+-- products.stock is from now on the sum of its variants' stock
+UPDATE products SET stock = 0 WHERE stock < 0;
+
+-- Cart lines are order history once checked out, so a variant in any cart cannot be deleted
+ALTER TABLE cart_items ADD COLUMN variant_id UUID REFERENCES product_variants(id) ON DELETE RESTRICT;
+
+UPDATE cart_items ci
+SET variant_id = v.id
+FROM product_variants v
+WHERE v.product_id = ci.item_id AND v.is_default;
+
+ALTER TABLE cart_items ALTER COLUMN variant_id SET NOT NULL;
+
+-- A cart may hold several variants of one product, but each variant only once
+ALTER TABLE cart_items DROP CONSTRAINT IF EXISTS uq_cart_items_cart_id_item_id;
+ALTER TABLE cart_items DROP CONSTRAINT IF EXISTS uq_cart_items_cart_product;
+ALTER TABLE cart_items ADD CONSTRAINT uq_cart_items_cart_variant UNIQUE (cart_id, variant_id);
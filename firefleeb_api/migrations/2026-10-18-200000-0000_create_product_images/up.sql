@@ -0,0 +1,14 @@
+-- Uploaded product images. The files live in the blob store; thumbnail_sizes records which
+-- thumbnails were generated, so changing the configured sizes leaves older images intact.
+CREATE TABLE product_images (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+  content_type TEXT NOT NULL,
+  width INT NOT NULL CHECK (width > 0),
+  height INT NOT NULL CHECK (height > 0),
+  byte_size BIGINT NOT NULL CHECK (byte_size > 0),
+  thumbnail_sizes INT[] NOT NULL DEFAULT '{}',
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+);
+
+CREATE INDEX product_images_product_id_idx ON product_images (product_id, created_at);
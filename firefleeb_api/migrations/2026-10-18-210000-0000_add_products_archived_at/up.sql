@@ -0,0 +1,3 @@
+-- Products are archived instead of deleted, so cart lines that reference them survive.
+-- Archived products are hidden from the catalog; only a purge removes the row.
+ALTER TABLE products ADD COLUMN archived_at TIMESTAMP WITH TIME ZONE NULL;
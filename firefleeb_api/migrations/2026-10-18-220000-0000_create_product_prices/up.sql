@@ -0,0 +1,22 @@
+-- Every price a product has had or is scheduled to have, each valid from `valid_from` until
+-- `valid_until` (open-ended when NULL). Windows may overlap: at any moment the covering window
+-- that started last wins, so a sale scheduled on top of the regular price takes over while it
+-- runs. `products.price` caches the winning price and is kept in step by a background task.
+CREATE TABLE product_prices (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+  price NUMERIC(10, 2) NOT NULL CHECK (price >= 0),
+  valid_from TIMESTAMP WITH TIME ZONE NOT NULL,
+  valid_until TIMESTAMP WITH TIME ZONE NULL,
+  -- 'manual' for prices set through the product itself, 'scheduled' for planned changes
+  source TEXT NOT NULL CHECK (source IN ('manual', 'scheduled')),
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+  CHECK (valid_until IS NULL OR valid_until > valid_from)
+);
+
+CREATE INDEX product_prices_product_id_idx ON product_prices (product_id, valid_from);
+
+-- The current price of every existing product opens its history
+INSERT INTO product_prices (product_id, price, valid_from, source)
+SELECT id, price, created_at, 'manual'
+FROM products;
@@ -0,0 +1,14 @@
+-- Stock held for a cart line until it expires. A variant's available stock is its stock minus
+-- the quantities of its unexpired reservations. Each cart line holds at most one reservation,
+-- which goes away with the line; expired rows are deleted by a background sweeper.
+CREATE TABLE stock_reservations (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  cart_item_id UUID NOT NULL UNIQUE REFERENCES cart_items(id) ON DELETE CASCADE,
+  variant_id UUID NOT NULL REFERENCES product_variants(id) ON DELETE CASCADE,
+  quantity INT NOT NULL CHECK (quantity > 0),
+  expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+);
+
+CREATE INDEX stock_reservations_variant_id_idx ON stock_reservations (variant_id, expires_at);
+CREATE INDEX stock_reservations_expires_at_idx ON stock_reservations (expires_at);
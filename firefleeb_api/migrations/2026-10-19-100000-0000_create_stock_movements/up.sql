@@ -0,0 +1,31 @@
+-- Every change to a variant's on-hand stock. `quantity` is signed: receipts and returns add stock,
+-- sales take it away and adjustments correct it either way. `product_variants.stock` caches the
+-- running total, which each row also records as `stock_after`; `products.stock` sums the variants.
+CREATE TABLE stock_movements (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+  -- NULL once the variant has been deleted, so the product's history stays complete
+  variant_id UUID NULL REFERENCES product_variants(id) ON DELETE SET NULL,
+  movement_type TEXT NOT NULL CHECK (movement_type IN ('receipt', 'adjustment', 'sale', 'return')),
+  quantity INT NOT NULL CHECK (quantity <> 0),
+  stock_after INT NOT NULL CHECK (stock_after >= 0),
+  reason TEXT NOT NULL,
+  -- The user who made the change, NULL for system changes. Not a foreign key, so the trail
+  -- still names the user after their account is deleted.
+  actor_id UUID NULL,
+  -- The checked-out cart behind a sale
+  cart_id UUID NULL REFERENCES carts(id) ON DELETE SET NULL,
+  -- clock_timestamp() rather than now(), so movements made in one transaction keep their order
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT clock_timestamp(),
+  CHECK (movement_type NOT IN ('receipt', 'return') OR quantity > 0),
+  CHECK (movement_type <> 'sale' OR quantity < 0)
+);
+
+CREATE INDEX stock_movements_product_id_idx ON stock_movements (product_id, created_at DESC);
+CREATE INDEX stock_movements_variant_id_idx ON stock_movements (variant_id, created_at DESC);
+
+-- The stock every existing variant holds opens its ledger
+INSERT INTO stock_movements (product_id, variant_id, movement_type, quantity, stock_after, reason, created_at)
+SELECT product_id, id, 'receipt', stock, stock, 'Opening stock', created_at
+FROM product_variants
+WHERE stock > 0;
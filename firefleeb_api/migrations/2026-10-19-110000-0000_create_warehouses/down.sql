@@ -0,0 +1,3 @@
+ALTER TABLE stock_movements DROP COLUMN warehouse_id;
+DROP TABLE warehouse_stock;
+DROP TABLE warehouses;
@@ -0,0 +1,41 @@
+-- Locations stock is kept and shipped from. Exactly one is the default: it receives stock that
+-- is entered without naming a warehouse, such as a new product's opening stock.
+CREATE TABLE warehouses (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  code TEXT NOT NULL UNIQUE,
+  name TEXT NOT NULL,
+  -- Where the warehouse is, for nearest-first allocation; both set or both NULL
+  latitude DOUBLE PRECISION NULL CHECK (latitude BETWEEN -90 AND 90),
+  longitude DOUBLE PRECISION NULL CHECK (longitude BETWEEN -180 AND 180),
+  is_default BOOLEAN NOT NULL DEFAULT FALSE,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+  CHECK ((latitude IS NULL) = (longitude IS NULL))
+);
+
+CREATE UNIQUE INDEX warehouses_one_default_idx ON warehouses ((TRUE)) WHERE is_default;
+
+-- On-hand stock of each variant per warehouse. `product_variants.stock` caches the sum.
+CREATE TABLE warehouse_stock (
+  warehouse_id UUID NOT NULL REFERENCES warehouses(id) ON DELETE CASCADE,
+  variant_id UUID NOT NULL REFERENCES product_variants(id) ON DELETE CASCADE,
+  stock INT NOT NULL DEFAULT 0 CHECK (stock >= 0),
+  PRIMARY KEY (warehouse_id, variant_id)
+);
+
+CREATE INDEX warehouse_stock_variant_id_idx ON warehouse_stock (variant_id);
+
+-- Everything on hand so far sits in the one warehouse there was
+INSERT INTO warehouses (code, name, is_default) VALUES ('MAIN', 'Main warehouse', TRUE);
+
+INSERT INTO warehouse_stock (warehouse_id, variant_id, stock)
+SELECT w.id, v.id, v.stock
+FROM product_variants v
+CROSS JOIN warehouses w
+WHERE v.stock > 0;
+
+-- Every movement happens in a warehouse; `stock_after` keeps recording the variant's total
+ALTER TABLE stock_movements ADD COLUMN warehouse_id UUID NULL REFERENCES warehouses(id);
+UPDATE stock_movements SET warehouse_id = (SELECT id FROM warehouses);
+ALTER TABLE stock_movements ALTER COLUMN warehouse_id SET NOT NULL;
+
+CREATE INDEX stock_movements_warehouse_id_idx ON stock_movements (warehouse_id, created_at DESC);
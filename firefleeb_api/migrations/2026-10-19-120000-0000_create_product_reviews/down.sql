@@ -0,0 +1,3 @@
+ALTER TABLE products DROP COLUMN rating_average;
+ALTER TABLE products DROP COLUMN rating_count;
+DROP TABLE product_reviews;
@@ -0,0 +1,23 @@
+-- Customer reviews of products, at most one per customer and product. New reviews wait for
+-- staff to approve them; only approved reviews are shown and counted in the product's rating.
+CREATE TABLE product_reviews (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+  user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  rating INT NOT NULL CHECK (rating BETWEEN 1 AND 5),
+  title TEXT NOT NULL,
+  body TEXT NOT NULL,
+  -- Whether the reviewer had checked out a cart with the product when writing the review
+  verified_purchase BOOLEAN NOT NULL DEFAULT FALSE,
+  status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'approved', 'rejected')),
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+  UNIQUE (product_id, user_id)
+);
+
+CREATE INDEX product_reviews_product_id_idx ON product_reviews (product_id, status, created_at DESC);
+CREATE INDEX product_reviews_status_idx ON product_reviews (status, created_at);
+
+-- The rating over approved reviews, kept up to date whenever a review is written or moderated
+ALTER TABLE products ADD COLUMN rating_count INT NOT NULL DEFAULT 0;
+ALTER TABLE products ADD COLUMN rating_average NUMERIC(3, 2) NULL;
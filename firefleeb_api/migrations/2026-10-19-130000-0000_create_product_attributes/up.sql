@@ -0,0 +1,22 @@
+-- Attributes products can be described and filtered by, such as material or wattage. Admins
+-- define them; a product holds at most one value for each.
+CREATE TABLE attribute_definitions (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  -- The key used in product attributes and listing filters
+  code TEXT NOT NULL UNIQUE,
+  name TEXT NOT NULL,
+  value_type TEXT NOT NULL CHECK (value_type IN ('text', 'number', 'boolean')),
+  -- JSON array of the values products may use, of `value_type`; NULL allows any
+  allowed_values JSONB NULL CHECK (jsonb_typeof(allowed_values) = 'array'),
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+);
+
+-- A product's value for an attribute, as a JSON string, number or boolean per `value_type`
+CREATE TABLE product_attributes (
+  product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+  attribute_id UUID NOT NULL REFERENCES attribute_definitions(id) ON DELETE CASCADE,
+  value JSONB NOT NULL,
+  PRIMARY KEY (product_id, attribute_id)
+);
+
+CREATE INDEX product_attributes_value_idx ON product_attributes (attribute_id, value);
@@ -0,0 +1,15 @@
+-- "Frequently bought together": for each product, the products checked out in the same carts.
+-- Rebuilt from scratch by a background job, so rows are never edited in place.
+CREATE TABLE product_recommendations (
+  product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+  recommended_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+  -- Checked-out carts containing both products
+  carts_together INT NOT NULL CHECK (carts_together > 0),
+  -- Share of the checked-out carts containing `product_id` that also contain `recommended_id`
+  score DOUBLE PRECISION NOT NULL CHECK (score > 0 AND score <= 1),
+  computed_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  PRIMARY KEY (product_id, recommended_id),
+  CHECK (product_id <> recommended_id)
+);
+
+CREATE INDEX product_recommendations_recommended_idx ON product_recommendations (recommended_id);
@@ -0,0 +1,58 @@
+use uuid::Uuid;
+use warp::{Filter, Rejection};
+
+use crate::auth::jwt::{AuthConfig, decode_access_token};
+use crate::errors::AppError;
+use crate::types::role::Role;
+
+/// The caller identified by a valid `Authorization: Bearer` token.
+#[derive(Debug, Clone, Copy)]
+pub struct AuthUser {
+    pub user_id: Uuid,
+    pub role: Role,
+}
+
+/// Require a valid bearer token and extract the authenticated user.
+pub fn with_auth(
+    config: AuthConfig,
+) -> impl Filter<Extract = (AuthUser,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("authorization").and_then(move |header: Option<String>| {
+        let config = config.clone();
+        async move { authenticate(&config, header.as_deref()).map_err(warp::reject::custom) }
+    })
+}
+
+/// Require a valid bearer token whose role is at least `required`.
+///
+/// Missing or invalid credentials reject with `AppError::Unauthorized`; an authenticated
+/// caller with too little privilege rejects with `AppError::Forbidden`.
+pub fn require_role(
+    config: AuthConfig,
+    required: Role,
+) -> impl Filter<Extract = (AuthUser,), Error = Rejection> + Clone {
+    with_auth(config).and_then(move |user: AuthUser| async move {
+        if user.role.satisfies(required) {
+            Ok(user)
+        } else {
+            Err(warp::reject::custom(AppError::Forbidden(format!(
+                "This action requires the {required} role"
+            ))))
+        }
+    })
+}
+
+fn authenticate(config: &AuthConfig, header: Option<&str>) -> Result<AuthUser, AppError> {
+    let header =
+        header.ok_or_else(|| AppError::Unauthorized("Missing Authorization header".into()))?;
+    let token = header
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .ok_or_else(|| AppError::Unauthorized("Expected a Bearer token".into()))?;
+
+    let claims = decode_access_token(config, token)?;
+    Ok(AuthUser {
+        user_id: claims.sub,
+        role: claims.role,
+    })
+}
@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::auth::password::{Argon2idHasher, SharedPasswordHasher};
+use crate::auth::password_policy::PasswordPolicy;
+use crate::auth::policy::EmailVerificationRule;
+use crate::auth::throttle::LoginThrottlePolicy;
+use crate::errors::AppError;
+use crate::types::role::Role;
+
+const DEFAULT_ISSUER: &str = "firefleeb_api";
+const DEFAULT_AUDIENCE: &str = "firefleeb_clients";
+const DEFAULT_ACCESS_TOKEN_TTL_SECS: i64 = 15 * 60;
+const DEFAULT_REFRESH_TOKEN_TTL_SECS: i64 = 30 * 24 * 60 * 60;
+const DEFAULT_PASSWORD_RESET_TTL_SECS: i64 = 60 * 60;
+const DEFAULT_EMAIL_VERIFICATION_TTL_SECS: i64 = 24 * 60 * 60;
+
+/// Authentication settings: token signing, token lifetimes, account-recovery and
+/// email-verification links, login throttling, password hashing and password policy.
+#[derive(Debug, Clone)]
+pub struct AuthConfig {
+    pub jwt_secret: String,
+    pub issuer: String,
+    pub audience: String,
+    pub access_token_ttl_secs: i64,
+    pub refresh_token_ttl_secs: i64,
+    pub password_reset_ttl_secs: i64,
+    /// Front-end page that accepts `?token=`; when unset, emails contain the bare token.
+    pub password_reset_url: Option<String>,
+    pub email_verification_ttl_secs: i64,
+    /// Front-end page that accepts `?token=` for email confirmation.
+    pub email_verification_url: Option<String>,
+    pub require_verified_email: EmailVerificationRule,
+    pub login_throttle: LoginThrottlePolicy,
+    pub password_hasher: SharedPasswordHasher,
+    pub password_policy: PasswordPolicy,
+}
+
+impl AuthConfig {
+    pub fn new(jwt_secret: impl Into<String>) -> Self {
+        Self {
+            jwt_secret: jwt_secret.into(),
+            issuer: DEFAULT_ISSUER.into(),
+            audience: DEFAULT_AUDIENCE.into(),
+            access_token_ttl_secs: DEFAULT_ACCESS_TOKEN_TTL_SECS,
+            refresh_token_ttl_secs: DEFAULT_REFRESH_TOKEN_TTL_SECS,
+            password_reset_ttl_secs: DEFAULT_PASSWORD_RESET_TTL_SECS,
+            password_reset_url: None,
+            email_verification_ttl_secs: DEFAULT_EMAIL_VERIFICATION_TTL_SECS,
+            email_verification_url: None,
+            require_verified_email: EmailVerificationRule::Off,
+            login_throttle: LoginThrottlePolicy::default(),
+            password_hasher: Arc::new(Argon2idHasher::default()),
+            password_policy: PasswordPolicy::default(),
+        }
+    }
+}
+
+/// Registered claims carried by every access token.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: Uuid,
+    pub role: Role,
+    pub iss: String,
+    pub aud: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct AccessToken {
+    pub token: String,
+    pub expires_in: i64,
+}
+
+pub fn issue_access_token(
+    config: &AuthConfig,
+    user_id: Uuid,
+    role: Role,
+) -> Result<AccessToken, AppError> {
+    let now = Utc::now().timestamp();
+    let claims = Claims {
+        sub: user_id,
+        role,
+        iss: config.issuer.clone(),
+        aud: config.audience.clone(),
+        iat: now,
+        exp: now + config.access_token_ttl_secs,
+    };
+
+    let token = encode(
+        &Header::new(Algorithm::HS256),
+        &claims,
+        &EncodingKey::from_secret(config.jwt_secret.as_bytes()),
+    )
+    .map_err(|_| AppError::Internal("Failed to sign access token".into()))?;
+
+    Ok(AccessToken {
+        token,
+        expires_in: config.access_token_ttl_secs,
+    })
+}
+
+pub fn decode_access_token(config: &AuthConfig, token: &str) -> Result<Claims, AppError> {
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.set_issuer(&[&config.issuer]);
+    validation.set_audience(&[&config.audience]);
+    validation.set_required_spec_claims(&["exp", "sub", "iss", "aud"]);
+
+    decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(config.jwt_secret.as_bytes()),
+        &validation,
+    )
+    .map(|data| data.claims)
+    .map_err(|_| AppError::Unauthorized("Invalid or expired access token".into()))
+}
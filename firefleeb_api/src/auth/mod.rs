@@ -0,0 +1,17 @@
+pub mod guards;
+pub mod jwt;
+pub mod password;
+pub mod password_policy;
+pub mod policy;
+pub mod throttle;
+pub mod tokens;
+
+pub use guards::{AuthUser, require_role, with_auth};
+pub use jwt::{AccessToken, AuthConfig, Claims, decode_access_token, issue_access_token};
+pub use password::{
+    Argon2idHasher, BcryptHasher, PasswordHasher, SharedPasswordHasher, run_hasher,
+};
+pub use password_policy::{BreachedPasswordList, PasswordPolicy};
+pub use policy::{EmailVerificationRule, ensure_email_verified, ensure_owner_or_admin};
+pub use throttle::{LoginThrottlePolicy, ThrottleScope};
+pub use tokens::{generate_opaque_token, hash_token};
@@ -0,0 +1,168 @@
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+
+use crate::auth::tokens::generate_opaque_token;
+use crate::errors::AppError;
+
+/// Hashes new passwords with one scheme while still accepting hashes made by older schemes.
+pub trait PasswordHasher: fmt::Debug + Send + Sync {
+    fn hash(&self, password: &str) -> Result<String, AppError>;
+
+    /// True when `stored_hash` was made by another scheme or with other parameters than
+    /// `hash` would use now.
+    fn needs_rehash(&self, stored_hash: &str) -> bool;
+
+    /// A hash of a random password made with the current parameters. Verifying against it
+    /// costs the same as verifying a real account's hash.
+    fn dummy_hash(&self) -> &str;
+
+    /// Check a password against an argon2 or bcrypt hash.
+    fn verify(&self, password: &str, stored_hash: &str) -> Result<bool, AppError> {
+        if is_bcrypt_hash(stored_hash) {
+            return bcrypt::verify(password, stored_hash)
+                .map_err(|_| AppError::Internal("Failed to verify password".into()));
+        }
+
+        let parsed = PasswordHash::new(stored_hash)
+            .map_err(|_| AppError::Internal("Stored password hash is malformed".into()))?;
+        Ok(Argon2::default()
+            .verify_password(password.as_bytes(), &parsed)
+            .is_ok())
+    }
+}
+
+pub type SharedPasswordHasher = Arc<dyn PasswordHasher>;
+
+/// Run a hasher operation on a blocking thread; argon2 and bcrypt are slow by design and would
+/// otherwise stall the async runtime.
+pub async fn run_hasher<T, F>(hasher: SharedPasswordHasher, op: F) -> Result<T, AppError>
+where
+    F: FnOnce(&dyn PasswordHasher) -> Result<T, AppError> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(move || op(hasher.as_ref()))
+        .await
+        .map_err(|e| AppError::Internal(format!("password hashing task panicked: {e}")))?
+}
+
+/// argon2id with explicit memory (KiB), iteration and parallelism costs.
+#[derive(Debug)]
+pub struct Argon2idHasher {
+    params: Params,
+    dummy: OnceLock<String>,
+}
+
+impl Argon2idHasher {
+    pub fn new(memory_kib: u32, iterations: u32, parallelism: u32) -> Result<Self, String> {
+        let params = Params::new(memory_kib, iterations, parallelism, None)
+            .map_err(|e| format!("Invalid argon2 parameters: {e}"))?;
+        Ok(Self {
+            params,
+            dummy: OnceLock::new(),
+        })
+    }
+
+    fn argon2(&self) -> Argon2<'static> {
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone())
+    }
+}
+
+impl Default for Argon2idHasher {
+    /// The argon2 crate defaults (19 MiB, 2 iterations, 1 lane), as recommended by OWASP.
+    fn default() -> Self {
+        Self {
+            params: Params::default(),
+            dummy: OnceLock::new(),
+        }
+    }
+}
+
+impl PasswordHasher for Argon2idHasher {
+    fn hash(&self, password: &str) -> Result<String, AppError> {
+        use argon2::password_hash::PasswordHasher as _;
+
+        let salt = SaltString::generate(&mut OsRng);
+        self.argon2()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|_| AppError::Internal("Failed to hash password".into()))
+    }
+
+    fn needs_rehash(&self, stored_hash: &str) -> bool {
+        let Ok(parsed) = PasswordHash::new(stored_hash) else {
+            return true;
+        };
+        if parsed.algorithm != Algorithm::Argon2id.ident()
+            || parsed.version != Some(Version::V0x13.into())
+        {
+            return true;
+        }
+        match Params::try_from(&parsed) {
+            Ok(params) => {
+                params.m_cost() != self.params.m_cost()
+                    || params.t_cost() != self.params.t_cost()
+                    || params.p_cost() != self.params.p_cost()
+            }
+            Err(_) => true,
+        }
+    }
+
+    fn dummy_hash(&self) -> &str {
+        self.dummy.get_or_init(|| {
+            self.hash(&generate_opaque_token())
+                .expect("hashing a random password cannot fail")
+        })
+    }
+}
+
+/// bcrypt at a fixed cost; kept for deployments that cannot afford argon2's memory use.
+#[derive(Debug)]
+pub struct BcryptHasher {
+    cost: u32,
+    dummy: OnceLock<String>,
+}
+
+impl BcryptHasher {
+    pub fn new(cost: u32) -> Result<Self, String> {
+        if !(4..=31).contains(&cost) {
+            return Err(format!("bcrypt cost must be between 4 and 31: {cost}"));
+        }
+        Ok(Self {
+            cost,
+            dummy: OnceLock::new(),
+        })
+    }
+}
+
+impl PasswordHasher for BcryptHasher {
+    fn hash(&self, password: &str) -> Result<String, AppError> {
+        bcrypt::hash(password, self.cost)
+            .map_err(|_| AppError::Internal("Failed to hash password".into()))
+    }
+
+    fn needs_rehash(&self, stored_hash: &str) -> bool {
+        // Modular crypt format: $2b$<cost>$<salt+hash>
+        let cost = stored_hash
+            .split('$')
+            .nth(2)
+            .and_then(|cost| cost.parse::<u32>().ok());
+        !is_bcrypt_hash(stored_hash) || cost != Some(self.cost)
+    }
+
+    fn dummy_hash(&self) -> &str {
+        self.dummy.get_or_init(|| {
+            self.hash(&generate_opaque_token())
+                .expect("hashing a random password cannot fail")
+        })
+    }
+}
+
+fn is_bcrypt_hash(hash: &str) -> bool {
+    ["$2a$", "$2b$", "$2x$", "$2y$"]
+        .iter()
+        .any(|prefix| hash.starts_with(prefix))
+}
@@ -0,0 +1,178 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use sha1::{Digest, Sha1};
+
+use crate::errors::{AppError, RuleViolation};
+use crate::types::email::Email;
+
+/// Local parts shorter than this are too common to reject passwords over.
+const MIN_EMAIL_LOCAL_PART_CHARS: usize = 3;
+
+/// Rules a new password must satisfy. Lengths count Unicode characters, not bytes.
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    pub min_chars: usize,
+    pub max_chars: usize,
+    pub require_lowercase: bool,
+    pub require_uppercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+    /// Reject passwords that contain the local part of the account's email address.
+    pub reject_email_local_part: bool,
+    pub breached_passwords: Option<Arc<BreachedPasswordList>>,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_chars: 8,
+            max_chars: 128,
+            require_lowercase: false,
+            require_uppercase: false,
+            require_digit: false,
+            require_symbol: false,
+            reject_email_local_part: false,
+            breached_passwords: None,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// Every rule `password` breaks, in a stable order. Empty when the password is acceptable.
+    pub fn violations(&self, password: &str, email: Option<&Email>) -> Vec<RuleViolation> {
+        let mut violations = Vec::new();
+        let mut violate = |rule, message: String| violations.push(RuleViolation { rule, message });
+
+        let chars = password.chars().count();
+        if chars < self.min_chars {
+            violate(
+                "min_length",
+                format!(
+                    "Password must be at least {} characters long",
+                    self.min_chars
+                ),
+            );
+        }
+        if chars > self.max_chars {
+            violate(
+                "max_length",
+                format!(
+                    "Password must be at most {} characters long",
+                    self.max_chars
+                ),
+            );
+        }
+        if self.require_lowercase && !password.chars().any(char::is_lowercase) {
+            violate(
+                "lowercase",
+                "Password must contain a lowercase letter".into(),
+            );
+        }
+        if self.require_uppercase && !password.chars().any(char::is_uppercase) {
+            violate(
+                "uppercase",
+                "Password must contain an uppercase letter".into(),
+            );
+        }
+        if self.require_digit && !password.chars().any(char::is_numeric) {
+            violate("digit", "Password must contain a digit".into());
+        }
+        if self.require_symbol
+            && !password
+                .chars()
+                .any(|c| !c.is_alphanumeric() && !c.is_whitespace())
+        {
+            violate("symbol", "Password must contain a symbol".into());
+        }
+        if self.reject_email_local_part
+            && let Some(local_part) = email.and_then(|email| email.as_str().split('@').next())
+            && local_part.chars().count() >= MIN_EMAIL_LOCAL_PART_CHARS
+            && password.to_lowercase().contains(&local_part.to_lowercase())
+        {
+            violate(
+                "contains_email",
+                "Password must not contain your email address".into(),
+            );
+        }
+        if let Some(breached) = &self.breached_passwords
+            && breached.contains(password)
+        {
+            violate(
+                "breached",
+                "Password appears in a list of breached passwords".into(),
+            );
+        }
+
+        violations
+    }
+
+    pub fn check(&self, password: &str, email: Option<&Email>) -> Result<(), AppError> {
+        let violations = self.violations(password, email);
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::RuleViolations(
+                "Password does not meet the password policy".into(),
+                violations,
+            ))
+        }
+    }
+}
+
+/// Offline list of breached passwords, stored as uppercase hex SHA-1 prefixes of equal length,
+/// one per line and sorted. Lines may carry a `:count` suffix as in the HIBP downloads.
+#[derive(Debug)]
+pub struct BreachedPasswordList {
+    prefix_len: usize,
+    prefixes: Vec<String>,
+}
+
+impl BreachedPasswordList {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read breached password list {}: {e}", path.display()))?;
+        Self::parse(&contents).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut prefixes: Vec<String> = contents
+            .lines()
+            .map(|line| line.split(':').next().unwrap_or_default().trim())
+            .filter(|prefix| !prefix.is_empty())
+            .map(str::to_ascii_uppercase)
+            .collect();
+
+        let prefix_len = prefixes.first().map_or(0, String::len);
+        if let Some(bad) = prefixes.iter().find(|p| {
+            p.len() != prefix_len || p.len() > 40 || !p.bytes().all(|b| b.is_ascii_hexdigit())
+        }) {
+            return Err(format!(
+                "breached password list entries must be hex SHA-1 prefixes of equal length: {bad}"
+            ));
+        }
+        // Binary search needs sorted input, so sort any file that was not
+        if !prefixes.is_sorted() {
+            prefixes.sort_unstable();
+        }
+
+        Ok(Self {
+            prefix_len,
+            prefixes,
+        })
+    }
+
+    pub fn contains(&self, password: &str) -> bool {
+        if self.prefixes.is_empty() {
+            return false;
+        }
+        let digest: String = Sha1::digest(password.as_bytes())
+            .iter()
+            .map(|b| format!("{b:02X}"))
+            .collect();
+        self.prefixes
+            .binary_search_by(|prefix| prefix.as_str().cmp(&digest[..self.prefix_len]))
+            .is_ok()
+    }
+}
@@ -0,0 +1,60 @@
+use uuid::Uuid;
+
+use crate::auth::guards::AuthUser;
+use crate::errors::AppError;
+use crate::models::user::User;
+use crate::types::role::Role;
+
+/// Which actions stay closed to accounts whose email address has not been confirmed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EmailVerificationRule {
+    /// Unverified accounts are not restricted.
+    #[default]
+    Off,
+    /// Unverified accounts may sign in but not check out a cart.
+    Checkout,
+    /// Unverified accounts may neither sign in nor check out.
+    Login,
+}
+
+impl EmailVerificationRule {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "off" => Ok(Self::Off),
+            "checkout" => Ok(Self::Checkout),
+            "login" => Ok(Self::Login),
+            other => Err(format!(
+                "Unknown email verification rule (expected off, checkout or login): {other}"
+            )),
+        }
+    }
+
+    pub fn blocks_login(&self) -> bool {
+        *self == Self::Login
+    }
+
+    pub fn blocks_checkout(&self) -> bool {
+        *self != Self::Off
+    }
+}
+
+/// Allow the caller to act on a resource owned by `owner_id`. Admins may act on any resource.
+pub fn ensure_owner_or_admin(principal: &AuthUser, owner_id: Uuid) -> Result<(), AppError> {
+    if principal.user_id == owner_id || principal.role.satisfies(Role::Admin) {
+        Ok(())
+    } else {
+        Err(AppError::Forbidden(
+            "You do not have access to this resource".into(),
+        ))
+    }
+}
+
+pub fn ensure_email_verified(user: &User) -> Result<(), AppError> {
+    if user.email_verified_at.is_some() {
+        Ok(())
+    } else {
+        Err(AppError::Forbidden(
+            "Email address has not been verified".into(),
+        ))
+    }
+}
@@ -0,0 +1,114 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::models::login_throttle::{LoginThrottle, UpdateLoginThrottle};
+
+/// What a throttle row counts attempts against: failed logins per account or client IP, or
+/// account mail (password resets and verification resends) requested per address or client IP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThrottleScope {
+    Account,
+    ClientIp,
+    MailRecipient,
+    MailClientIp,
+}
+
+impl ThrottleScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ThrottleScope::Account => "account",
+            ThrottleScope::ClientIp => "ip",
+            ThrottleScope::MailRecipient => "mail_email",
+            ThrottleScope::MailClientIp => "mail_ip",
+        }
+    }
+}
+
+/// Limits on failed logins and on account mail requests. Once a subject has used up its
+/// allowance, every further attempt locks it out for twice as long as the previous one, up to
+/// `max_lockout_secs`.
+#[derive(Debug, Clone)]
+pub struct LoginThrottlePolicy {
+    pub max_failures_per_account: i32,
+    pub max_failures_per_ip: i32,
+    pub max_mails_per_email: i32,
+    pub max_mails_per_ip: i32,
+    pub base_lockout_secs: i64,
+    pub max_lockout_secs: i64,
+    /// A subject whose last failure is older than this starts counting from zero again.
+    pub failure_window_secs: i64,
+}
+
+impl Default for LoginThrottlePolicy {
+    fn default() -> Self {
+        Self {
+            max_failures_per_account: 5,
+            max_failures_per_ip: 20,
+            max_mails_per_email: 3,
+            max_mails_per_ip: 10,
+            base_lockout_secs: 30,
+            max_lockout_secs: 15 * 60,
+            failure_window_secs: 60 * 60,
+        }
+    }
+}
+
+impl LoginThrottlePolicy {
+    pub fn allowance(&self, scope: ThrottleScope) -> i32 {
+        match scope {
+            ThrottleScope::Account => self.max_failures_per_account,
+            ThrottleScope::ClientIp => self.max_failures_per_ip,
+            ThrottleScope::MailRecipient => self.max_mails_per_email,
+            ThrottleScope::MailClientIp => self.max_mails_per_ip,
+        }
+    }
+
+    /// Bookkeeping after one more failed attempt against `current`.
+    pub fn after_failure(
+        &self,
+        scope: ThrottleScope,
+        current: &LoginThrottle,
+        now: DateTime<Utc>,
+    ) -> UpdateLoginThrottle {
+        let window_start = now - Duration::seconds(self.failure_window_secs);
+        let previous = match current.last_failed_at {
+            Some(last) if last > window_start => current.failed_count,
+            _ => 0,
+        };
+        let failed_count = previous.saturating_add(1);
+
+        let overflow = failed_count - self.allowance(scope);
+        let locked_until = (overflow >= 0).then(|| {
+            let factor = 1_i64 << overflow.min(20);
+            let secs = self
+                .base_lockout_secs
+                .saturating_mul(factor)
+                .min(self.max_lockout_secs);
+            now + Duration::seconds(secs)
+        });
+
+        UpdateLoginThrottle {
+            failed_count,
+            locked_until,
+            last_failed_at: Some(now),
+        }
+    }
+
+    /// Bookkeeping after giving back an attempt counted against `current` that succeeded. A
+    /// lockout goes with it once the count is back under the allowance.
+    pub fn after_refund(
+        &self,
+        scope: ThrottleScope,
+        current: &LoginThrottle,
+    ) -> UpdateLoginThrottle {
+        let failed_count = current.failed_count.saturating_sub(1).max(0);
+        let locked_until = current
+            .locked_until
+            .filter(|_| failed_count >= self.allowance(scope));
+
+        UpdateLoginThrottle {
+            failed_count,
+            locked_until,
+            last_failed_at: current.last_failed_at,
+        }
+    }
+}
@@ -0,0 +1,18 @@
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+/// Generate a random, URL-safe token that is handed to the client exactly once.
+pub fn generate_opaque_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    to_hex(&bytes)
+}
+
+/// Hash an opaque token for storage; only the hash is ever persisted.
+pub fn hash_token(token: &str) -> String {
+    to_hex(&Sha256::digest(token.as_bytes()))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
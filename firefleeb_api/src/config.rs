@@ -0,0 +1,1046 @@
+//! Typed application configuration.
+//!
+//! Settings come from an optional TOML file (`APP_CONFIG`, default `config.toml`) and are then
+//! overridden by environment variables, so containers can keep using plain env vars. Everything
+//! is validated up front; `AppConfig::load` fails with a message naming the offending setting.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use lettre::message::Mailbox;
+use serde::Deserialize;
+use thiserror::Error;
+use tracing_subscriber::EnvFilter;
+
+use crate::auth::{
+    Argon2idHasher, AuthConfig, BcryptHasher, BreachedPasswordList, EmailVerificationRule,
+    LoginThrottlePolicy, PasswordPolicy, SharedPasswordHasher,
+};
+use crate::mail::SmtpSettings;
+use crate::types::AllocationStrategy;
+
+const DEFAULT_CONFIG_PATH: &str = "config.toml";
+const DEFAULT_PORT: u16 = 8080;
+const DEFAULT_BODY_LIMIT_BYTES: u64 = 16 * 1024;
+const DEFAULT_MAX_CONNECTIONS: u32 = 15;
+const DEFAULT_CONNECTION_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 10 * 60;
+const DEFAULT_MAX_LIFETIME_SECS: u64 = 30 * 60;
+const DEFAULT_SUGGESTION_THRESHOLD: f32 = 0.3;
+const DEFAULT_IMPORT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+const DEFAULT_STORAGE_ROOT: &str = "data/blobs";
+const DEFAULT_MAX_IMAGE_BYTES: u64 = 5 * 1024 * 1024;
+const DEFAULT_THUMBNAIL_SIZES: [u32; 2] = [128, 512];
+const MAX_THUMBNAIL_SIZE: u32 = 4096;
+const DEFAULT_RESERVATION_TTL_SECS: u64 = 15 * 60;
+const DEFAULT_PRICE_SCHEDULE_INTERVAL_SECS: u64 = 60;
+const DEFAULT_RESERVATION_SWEEP_INTERVAL_SECS: u64 = 60;
+const DEFAULT_RECOMMENDATION_INTERVAL_SECS: u64 = 60 * 60;
+const DEFAULT_LOGIN_THROTTLE_SWEEP_INTERVAL_SECS: u64 = 60 * 60;
+const DEFAULT_LOG_FILTER: &str = "info";
+const DEFAULT_MAIL_FROM: &str = "no-reply@firefleeb.local";
+const DEFAULT_MAIL_FILE_DIR: &str = "mail-outbox";
+const DEFAULT_SMTP_PORT: u16 = 587;
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("cannot read config file {}: {source}", path.display())]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("invalid config file {}: {source}", path.display())]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("{0} must be set")]
+    Missing(&'static str),
+    #[error("{key} is invalid: {message}")]
+    Invalid { key: &'static str, message: String },
+}
+
+impl ConfigError {
+    fn invalid(key: &'static str, message: impl Into<String>) -> Self {
+        ConfigError::Invalid {
+            key,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AppConfig {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub auth: AuthConfig,
+    pub catalog: CatalogConfig,
+    pub carts: CartConfig,
+    pub storage: StorageConfig,
+    pub images: ImageConfig,
+    pub jobs: JobsConfig,
+    pub logging: LoggingConfig,
+    pub mail: MailConfig,
+}
+
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub bind_address: SocketAddr,
+    /// Largest JSON request body accepted; bigger bodies get `413`.
+    pub body_limit_bytes: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind_address: SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), DEFAULT_PORT),
+            body_limit_bytes: DEFAULT_BODY_LIMIT_BYTES,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DatabaseConfig {
+    pub url: String,
+    pub max_connections: u32,
+    /// Connections kept open while idle; `None` keeps `max_connections` open.
+    pub min_idle: Option<u32>,
+    /// How long a request waits for a free connection before failing.
+    pub connection_timeout: Duration,
+    pub idle_timeout: Option<Duration>,
+    pub max_lifetime: Option<Duration>,
+}
+
+impl DatabaseConfig {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            min_idle: None,
+            connection_timeout: Duration::from_secs(DEFAULT_CONNECTION_TIMEOUT_SECS),
+            idle_timeout: Some(Duration::from_secs(DEFAULT_IDLE_TIMEOUT_SECS)),
+            max_lifetime: Some(Duration::from_secs(DEFAULT_MAX_LIFETIME_SECS)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CatalogConfig {
+    /// Minimum trigram word similarity (0–1] for `GET /products/suggest` matches.
+    pub suggestion_threshold: f32,
+    /// Largest body accepted by `POST /products/import`.
+    pub import_max_bytes: u64,
+}
+
+impl Default for CatalogConfig {
+    fn default() -> Self {
+        Self {
+            suggestion_threshold: DEFAULT_SUGGESTION_THRESHOLD,
+            import_max_bytes: DEFAULT_IMPORT_MAX_BYTES,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StorageConfig {
+    /// Directory the local blob store keeps uploaded files in.
+    pub local_root: PathBuf,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            local_root: PathBuf::from(DEFAULT_STORAGE_ROOT),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ImageConfig {
+    /// Largest image file accepted by `POST /products/:id/images`.
+    pub max_upload_bytes: u64,
+    /// Bounding boxes, in pixels, of the thumbnails generated for each upload.
+    pub thumbnail_sizes: Vec<u32>,
+}
+
+impl Default for ImageConfig {
+    fn default() -> Self {
+        Self {
+            max_upload_bytes: DEFAULT_MAX_IMAGE_BYTES,
+            thumbnail_sizes: DEFAULT_THUMBNAIL_SIZES.to_vec(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CartConfig {
+    /// How long stock stays reserved for a cart line after it was added or changed.
+    pub reservation_ttl: Duration,
+    /// How checkout picks the warehouses that ship each line.
+    pub allocation_strategy: AllocationStrategy,
+}
+
+impl Default for CartConfig {
+    fn default() -> Self {
+        Self {
+            reservation_ttl: Duration::from_secs(DEFAULT_RESERVATION_TTL_SECS),
+            allocation_strategy: AllocationStrategy::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct JobsConfig {
+    /// How often scheduled price changes are checked and applied.
+    pub price_schedule_interval: Duration,
+    /// How often expired stock reservations are deleted.
+    pub reservation_sweep_interval: Duration,
+    /// How often "frequently bought together" recommendations are recomputed.
+    pub recommendation_interval: Duration,
+    /// How often login throttle rows that no longer matter are deleted.
+    pub login_throttle_sweep_interval: Duration,
+}
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        Self {
+            price_schedule_interval: Duration::from_secs(DEFAULT_PRICE_SCHEDULE_INTERVAL_SECS),
+            reservation_sweep_interval: Duration::from_secs(
+                DEFAULT_RESERVATION_SWEEP_INTERVAL_SECS,
+            ),
+            recommendation_interval: Duration::from_secs(DEFAULT_RECOMMENDATION_INTERVAL_SECS),
+            login_throttle_sweep_interval: Duration::from_secs(
+                DEFAULT_LOGIN_THROTTLE_SWEEP_INTERVAL_SECS,
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Pretty,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw {
+            "pretty" => Ok(LogFormat::Pretty),
+            "json" => Ok(LogFormat::Json),
+            other => Err(format!("expected pretty or json, got {other}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LoggingConfig {
+    pub format: LogFormat,
+    /// `tracing` env-filter directives, e.g. `info,firefleeb_api=debug`.
+    pub filter: String,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::Pretty,
+            filter: DEFAULT_LOG_FILTER.into(),
+        }
+    }
+}
+
+/// Where outgoing mail goes.
+#[derive(Debug, Clone)]
+pub enum MailTransport {
+    /// Writes each message as an `.eml` file into the directory; meant for local development.
+    File(PathBuf),
+    Smtp(SmtpSettings),
+    /// Keeps messages in process; used by the tests.
+    Memory,
+}
+
+#[derive(Debug, Clone)]
+pub struct MailConfig {
+    pub transport: MailTransport,
+    /// Sender address, optionally with a display name: `FireFleeb <no-reply@example.com>`.
+    pub from: String,
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        Self {
+            transport: MailTransport::File(PathBuf::from(DEFAULT_MAIL_FILE_DIR)),
+            from: DEFAULT_MAIL_FROM.into(),
+        }
+    }
+}
+
+impl AppConfig {
+    /// Defaults for everything except auth; the database URL is left empty.
+    pub fn new(auth: AuthConfig) -> Self {
+        Self {
+            server: ServerConfig::default(),
+            database: DatabaseConfig::new(""),
+            auth,
+            catalog: CatalogConfig::default(),
+            carts: CartConfig::default(),
+            storage: StorageConfig::default(),
+            images: ImageConfig::default(),
+            jobs: JobsConfig::default(),
+            logging: LoggingConfig::default(),
+            mail: MailConfig::default(),
+        }
+    }
+
+    /// Read the file named by APP_CONFIG (or `config.toml` if it exists), apply environment
+    /// overrides and validate the result.
+    pub fn load() -> Result<Self, ConfigError> {
+        let mut file = match env_var("APP_CONFIG") {
+            Some(path) => FileConfig::read(Path::new(&path))?,
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                FileConfig::read(Path::new(DEFAULT_CONFIG_PATH))?
+            }
+            None => FileConfig::default(),
+        };
+        file.apply_env()?;
+
+        let auth = std::mem::take(&mut file.auth).into_auth_config()?;
+        Self::build(file, auth)
+    }
+
+    /// Parse and validate TOML alone, without looking at the environment.
+    pub fn from_toml_str(contents: &str) -> Result<Self, ConfigError> {
+        let mut file = FileConfig::parse(contents, Path::new("<inline>"))?;
+        let auth = std::mem::take(&mut file.auth).into_auth_config()?;
+        Self::build(file, auth)
+    }
+
+    fn build(file: FileConfig, auth: AuthConfig) -> Result<Self, ConfigError> {
+        let FileConfig {
+            server,
+            database,
+            catalog,
+            carts,
+            storage,
+            images,
+            jobs,
+            logging,
+            mail,
+            ..
+        } = file;
+        if auth.jwt_secret.trim().is_empty() {
+            return Err(ConfigError::Missing("auth.jwt_secret (or JWT_SECRET)"));
+        }
+
+        let mut bind_address = server
+            .bind_address
+            .unwrap_or(ServerConfig::default().bind_address);
+        if let Some(port) = server.port {
+            bind_address.set_port(port);
+        }
+        let body_limit_bytes = server.body_limit_bytes.unwrap_or(DEFAULT_BODY_LIMIT_BYTES);
+        if body_limit_bytes == 0 {
+            return Err(ConfigError::invalid(
+                "server.body_limit_bytes",
+                "must be greater than zero",
+            ));
+        }
+
+        let url = database
+            .url
+            .filter(|url| !url.trim().is_empty())
+            .ok_or(ConfigError::Missing("database.url (or DATABASE_URL)"))?;
+        let max_connections = database.max_connections.unwrap_or(DEFAULT_MAX_CONNECTIONS);
+        if max_connections == 0 {
+            return Err(ConfigError::invalid(
+                "database.max_connections",
+                "must be greater than zero",
+            ));
+        }
+        if let Some(min_idle) = database.min_idle
+            && min_idle > max_connections
+        {
+            return Err(ConfigError::invalid(
+                "database.min_idle",
+                format!("must not exceed max_connections ({max_connections})"),
+            ));
+        }
+        let connection_timeout_secs = database
+            .connection_timeout_secs
+            .unwrap_or(DEFAULT_CONNECTION_TIMEOUT_SECS);
+        if connection_timeout_secs == 0 {
+            return Err(ConfigError::invalid(
+                "database.connection_timeout_secs",
+                "must be greater than zero",
+            ));
+        }
+
+        let suggestion_threshold = catalog
+            .suggestion_threshold
+            .unwrap_or(DEFAULT_SUGGESTION_THRESHOLD);
+        if !(suggestion_threshold > 0.0 && suggestion_threshold <= 1.0) {
+            return Err(ConfigError::invalid(
+                "catalog.suggestion_threshold",
+                "must be greater than 0 and at most 1",
+            ));
+        }
+        let import_max_bytes = catalog.import_max_bytes.unwrap_or(DEFAULT_IMPORT_MAX_BYTES);
+        if import_max_bytes == 0 {
+            return Err(ConfigError::invalid(
+                "catalog.import_max_bytes",
+                "must be greater than zero",
+            ));
+        }
+
+        let reservation_ttl_secs = carts
+            .reservation_ttl_secs
+            .unwrap_or(DEFAULT_RESERVATION_TTL_SECS);
+        if reservation_ttl_secs == 0 {
+            return Err(ConfigError::invalid(
+                "carts.reservation_ttl_secs",
+                "must be greater than zero",
+            ));
+        }
+        let allocation_strategy = match carts.allocation_strategy {
+            Some(strategy) => AllocationStrategy::parse(strategy.trim())
+                .map_err(|message| ConfigError::invalid("carts.allocation_strategy", message))?,
+            None => AllocationStrategy::default(),
+        };
+
+        let local_root = storage
+            .local_root
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_STORAGE_ROOT));
+        if local_root.as_os_str().is_empty() {
+            return Err(ConfigError::invalid(
+                "storage.local_root",
+                "must not be empty",
+            ));
+        }
+
+        let max_upload_bytes = images.max_upload_bytes.unwrap_or(DEFAULT_MAX_IMAGE_BYTES);
+        if max_upload_bytes == 0 {
+            return Err(ConfigError::invalid(
+                "images.max_upload_bytes",
+                "must be greater than zero",
+            ));
+        }
+        let mut thumbnail_sizes = images
+            .thumbnail_sizes
+            .unwrap_or_else(|| DEFAULT_THUMBNAIL_SIZES.to_vec());
+        if thumbnail_sizes
+            .iter()
+            .any(|size| !(1..=MAX_THUMBNAIL_SIZE).contains(size))
+        {
+            return Err(ConfigError::invalid(
+                "images.thumbnail_sizes",
+                format!("sizes must be between 1 and {MAX_THUMBNAIL_SIZE}"),
+            ));
+        }
+        thumbnail_sizes.sort_unstable();
+        thumbnail_sizes.dedup();
+
+        let price_schedule_interval_secs = jobs
+            .price_schedule_interval_secs
+            .unwrap_or(DEFAULT_PRICE_SCHEDULE_INTERVAL_SECS);
+        if price_schedule_interval_secs == 0 {
+            return Err(ConfigError::invalid(
+                "jobs.price_schedule_interval_secs",
+                "must be greater than zero",
+            ));
+        }
+        let reservation_sweep_interval_secs = jobs
+            .reservation_sweep_interval_secs
+            .unwrap_or(DEFAULT_RESERVATION_SWEEP_INTERVAL_SECS);
+        if reservation_sweep_interval_secs == 0 {
+            return Err(ConfigError::invalid(
+                "jobs.reservation_sweep_interval_secs",
+                "must be greater than zero",
+            ));
+        }
+        let recommendation_interval_secs = jobs
+            .recommendation_interval_secs
+            .unwrap_or(DEFAULT_RECOMMENDATION_INTERVAL_SECS);
+        if recommendation_interval_secs == 0 {
+            return Err(ConfigError::invalid(
+                "jobs.recommendation_interval_secs",
+                "must be greater than zero",
+            ));
+        }
+        let login_throttle_sweep_interval_secs = jobs
+            .login_throttle_sweep_interval_secs
+            .unwrap_or(DEFAULT_LOGIN_THROTTLE_SWEEP_INTERVAL_SECS);
+        if login_throttle_sweep_interval_secs == 0 {
+            return Err(ConfigError::invalid(
+                "jobs.login_throttle_sweep_interval_secs",
+                "must be greater than zero",
+            ));
+        }
+
+        let filter = logging.filter.unwrap_or_else(|| DEFAULT_LOG_FILTER.into());
+        EnvFilter::try_new(&filter)
+            .map_err(|e| ConfigError::invalid("logging.filter", e.to_string()))?;
+
+        let mail = mail.into_mail_config()?;
+
+        Ok(Self {
+            server: ServerConfig {
+                bind_address,
+                body_limit_bytes,
+            },
+            database: DatabaseConfig {
+                url,
+                max_connections,
+                min_idle: database.min_idle,
+                connection_timeout: Duration::from_secs(connection_timeout_secs),
+                idle_timeout: optional_secs(database.idle_timeout_secs, DEFAULT_IDLE_TIMEOUT_SECS),
+                max_lifetime: optional_secs(database.max_lifetime_secs, DEFAULT_MAX_LIFETIME_SECS),
+            },
+            auth,
+            catalog: CatalogConfig {
+                suggestion_threshold,
+                import_max_bytes,
+            },
+            carts: CartConfig {
+                reservation_ttl: Duration::from_secs(reservation_ttl_secs),
+                allocation_strategy,
+            },
+            storage: StorageConfig { local_root },
+            images: ImageConfig {
+                max_upload_bytes,
+                thumbnail_sizes,
+            },
+            jobs: JobsConfig {
+                price_schedule_interval: Duration::from_secs(price_schedule_interval_secs),
+                reservation_sweep_interval: Duration::from_secs(reservation_sweep_interval_secs),
+                recommendation_interval: Duration::from_secs(recommendation_interval_secs),
+                login_throttle_sweep_interval: Duration::from_secs(
+                    login_throttle_sweep_interval_secs,
+                ),
+            },
+            logging: LoggingConfig {
+                format: logging.format.unwrap_or(LogFormat::Pretty),
+                filter,
+            },
+            mail,
+        })
+    }
+}
+
+/// `0` disables the timeout; unset falls back to the default.
+fn optional_secs(secs: Option<u64>, default: u64) -> Option<Duration> {
+    match secs.unwrap_or(default) {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    }
+}
+
+// On-disk shape of the config file. Every field is optional so that a partial file, or none at
+// all, can be completed from the environment before validation.
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    server: ServerFile,
+    database: DatabaseFile,
+    auth: AuthFile,
+    catalog: CatalogFile,
+    carts: CartsFile,
+    storage: StorageFile,
+    images: ImagesFile,
+    jobs: JobsFile,
+    logging: LoggingFile,
+    mail: MailFile,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ServerFile {
+    bind_address: Option<SocketAddr>,
+    /// Replaces the port of `bind_address`.
+    port: Option<u16>,
+    body_limit_bytes: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct DatabaseFile {
+    url: Option<String>,
+    max_connections: Option<u32>,
+    min_idle: Option<u32>,
+    connection_timeout_secs: Option<u64>,
+    idle_timeout_secs: Option<u64>,
+    max_lifetime_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AuthFile {
+    jwt_secret: Option<String>,
+    issuer: Option<String>,
+    audience: Option<String>,
+    access_token_ttl_secs: Option<i64>,
+    refresh_token_ttl_secs: Option<i64>,
+    password_reset_ttl_secs: Option<i64>,
+    password_reset_url: Option<String>,
+    email_verification_ttl_secs: Option<i64>,
+    email_verification_url: Option<String>,
+    require_verified_email: Option<String>,
+    hasher: HasherFile,
+    password_policy: PasswordPolicyFile,
+    login_throttle: LoginThrottleFile,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct HasherFile {
+    /// `argon2id` or `bcrypt`.
+    algorithm: Option<String>,
+    argon2_memory_kib: Option<u32>,
+    argon2_iterations: Option<u32>,
+    argon2_parallelism: Option<u32>,
+    bcrypt_cost: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PasswordPolicyFile {
+    min_chars: Option<usize>,
+    max_chars: Option<usize>,
+    require_lowercase: Option<bool>,
+    require_uppercase: Option<bool>,
+    require_digit: Option<bool>,
+    require_symbol: Option<bool>,
+    reject_email_local_part: Option<bool>,
+    breached_list_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct LoginThrottleFile {
+    max_failures_per_account: Option<i32>,
+    max_failures_per_ip: Option<i32>,
+    max_mails_per_email: Option<i32>,
+    max_mails_per_ip: Option<i32>,
+    lockout_base_secs: Option<i64>,
+    lockout_max_secs: Option<i64>,
+    failure_window_secs: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CatalogFile {
+    suggestion_threshold: Option<f32>,
+    import_max_bytes: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CartsFile {
+    reservation_ttl_secs: Option<u64>,
+    allocation_strategy: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct StorageFile {
+    local_root: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ImagesFile {
+    max_upload_bytes: Option<u64>,
+    thumbnail_sizes: Option<Vec<u32>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct JobsFile {
+    price_schedule_interval_secs: Option<u64>,
+    reservation_sweep_interval_secs: Option<u64>,
+    recommendation_interval_secs: Option<u64>,
+    login_throttle_sweep_interval_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct LoggingFile {
+    format: Option<LogFormat>,
+    filter: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct MailFile {
+    /// `file`, `smtp` or `memory`.
+    transport: Option<String>,
+    from: Option<String>,
+    file_dir: Option<PathBuf>,
+    smtp_host: Option<String>,
+    smtp_port: Option<u16>,
+    smtp_username: Option<String>,
+    smtp_password: Option<String>,
+}
+
+impl FileConfig {
+    fn read(path: &Path) -> Result<Self, ConfigError> {
+        let contents = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::parse(&contents, path)
+    }
+
+    fn parse(contents: &str, path: &Path) -> Result<Self, ConfigError> {
+        toml::from_str(contents).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// Replace file values with the environment variables that are set.
+    fn apply_env(&mut self) -> Result<(), ConfigError> {
+        override_parsed(&mut self.server.bind_address, "BIND_ADDRESS")?;
+        override_parsed(&mut self.server.port, "PORT")?;
+        override_parsed(&mut self.server.body_limit_bytes, "BODY_LIMIT_BYTES")?;
+
+        override_parsed(&mut self.database.url, "DATABASE_URL")?;
+        override_parsed(&mut self.database.max_connections, "DB_MAX_CONNECTIONS")?;
+        override_parsed(&mut self.database.min_idle, "DB_MIN_IDLE")?;
+        override_parsed(
+            &mut self.database.connection_timeout_secs,
+            "DB_CONNECTION_TIMEOUT_SECS",
+        )?;
+        override_parsed(&mut self.database.idle_timeout_secs, "DB_IDLE_TIMEOUT_SECS")?;
+        override_parsed(&mut self.database.max_lifetime_secs, "DB_MAX_LIFETIME_SECS")?;
+
+        let auth = &mut self.auth;
+        override_parsed(&mut auth.jwt_secret, "JWT_SECRET")?;
+        override_parsed(&mut auth.issuer, "JWT_ISSUER")?;
+        override_parsed(&mut auth.audience, "JWT_AUDIENCE")?;
+        override_parsed(&mut auth.access_token_ttl_secs, "JWT_ACCESS_TTL_SECS")?;
+        override_parsed(&mut auth.refresh_token_ttl_secs, "JWT_REFRESH_TTL_SECS")?;
+        override_parsed(&mut auth.password_reset_ttl_secs, "PASSWORD_RESET_TTL_SECS")?;
+        override_parsed(&mut auth.password_reset_url, "PASSWORD_RESET_URL")?;
+        override_parsed(
+            &mut auth.email_verification_ttl_secs,
+            "EMAIL_VERIFICATION_TTL_SECS",
+        )?;
+        override_parsed(&mut auth.email_verification_url, "EMAIL_VERIFICATION_URL")?;
+        override_parsed(&mut auth.require_verified_email, "REQUIRE_VERIFIED_EMAIL")?;
+
+        let hasher = &mut auth.hasher;
+        override_parsed(&mut hasher.algorithm, "PASSWORD_HASH_ALGORITHM")?;
+        override_parsed(&mut hasher.argon2_memory_kib, "ARGON2_MEMORY_KIB")?;
+        override_parsed(&mut hasher.argon2_iterations, "ARGON2_ITERATIONS")?;
+        override_parsed(&mut hasher.argon2_parallelism, "ARGON2_PARALLELISM")?;
+        override_parsed(&mut hasher.bcrypt_cost, "BCRYPT_COST")?;
+
+        let policy = &mut auth.password_policy;
+        override_parsed(&mut policy.min_chars, "PASSWORD_MIN_CHARS")?;
+        override_parsed(&mut policy.max_chars, "PASSWORD_MAX_CHARS")?;
+        override_parsed(&mut policy.require_lowercase, "PASSWORD_REQUIRE_LOWERCASE")?;
+        override_parsed(&mut policy.require_uppercase, "PASSWORD_REQUIRE_UPPERCASE")?;
+        override_parsed(&mut policy.require_digit, "PASSWORD_REQUIRE_DIGIT")?;
+        override_parsed(&mut policy.require_symbol, "PASSWORD_REQUIRE_SYMBOL")?;
+        override_parsed(
+            &mut policy.reject_email_local_part,
+            "PASSWORD_REJECT_EMAIL_LOCAL_PART",
+        )?;
+        override_parsed(
+            &mut policy.breached_list_path,
+            "PASSWORD_BREACHED_LIST_PATH",
+        )?;
+
+        let throttle = &mut auth.login_throttle;
+        override_parsed(
+            &mut throttle.max_failures_per_account,
+            "LOGIN_MAX_FAILURES_PER_ACCOUNT",
+        )?;
+        override_parsed(
+            &mut throttle.max_failures_per_ip,
+            "LOGIN_MAX_FAILURES_PER_IP",
+        )?;
+        override_parsed(
+            &mut throttle.max_mails_per_email,
+            "LOGIN_MAX_MAILS_PER_EMAIL",
+        )?;
+        override_parsed(&mut throttle.max_mails_per_ip, "LOGIN_MAX_MAILS_PER_IP")?;
+        override_parsed(&mut throttle.lockout_base_secs, "LOGIN_LOCKOUT_BASE_SECS")?;
+        override_parsed(&mut throttle.lockout_max_secs, "LOGIN_LOCKOUT_MAX_SECS")?;
+        override_parsed(
+            &mut throttle.failure_window_secs,
+            "LOGIN_FAILURE_WINDOW_SECS",
+        )?;
+
+        override_parsed(
+            &mut self.catalog.suggestion_threshold,
+            "SUGGESTION_SIMILARITY_THRESHOLD",
+        )?;
+        override_parsed(
+            &mut self.catalog.import_max_bytes,
+            "CATALOG_IMPORT_MAX_BYTES",
+        )?;
+
+        override_parsed(
+            &mut self.carts.reservation_ttl_secs,
+            "CART_RESERVATION_TTL_SECS",
+        )?;
+        override_parsed(
+            &mut self.carts.allocation_strategy,
+            "CART_ALLOCATION_STRATEGY",
+        )?;
+
+        override_parsed(&mut self.storage.local_root, "STORAGE_LOCAL_ROOT")?;
+        override_parsed(&mut self.images.max_upload_bytes, "IMAGE_MAX_UPLOAD_BYTES")?;
+
+        override_parsed(
+            &mut self.jobs.price_schedule_interval_secs,
+            "PRICE_SCHEDULE_INTERVAL_SECS",
+        )?;
+        override_parsed(
+            &mut self.jobs.reservation_sweep_interval_secs,
+            "RESERVATION_SWEEP_INTERVAL_SECS",
+        )?;
+        override_parsed(
+            &mut self.jobs.recommendation_interval_secs,
+            "RECOMMENDATION_INTERVAL_SECS",
+        )?;
+        override_parsed(
+            &mut self.jobs.login_throttle_sweep_interval_secs,
+            "LOGIN_THROTTLE_SWEEP_INTERVAL_SECS",
+        )?;
+
+        override_parsed(&mut self.logging.format, "LOG_FORMAT")?;
+        override_parsed(&mut self.logging.filter, "RUST_LOG")?;
+
+        override_parsed(&mut self.mail.transport, "MAIL_TRANSPORT")?;
+        override_parsed(&mut self.mail.from, "MAIL_FROM")?;
+        override_parsed(&mut self.mail.file_dir, "MAIL_FILE_DIR")?;
+        override_parsed(&mut self.mail.smtp_host, "SMTP_HOST")?;
+        override_parsed(&mut self.mail.smtp_port, "SMTP_PORT")?;
+        override_parsed(&mut self.mail.smtp_username, "SMTP_USERNAME")?;
+        override_parsed(&mut self.mail.smtp_password, "SMTP_PASSWORD")?;
+        Ok(())
+    }
+}
+
+impl AuthFile {
+    fn into_auth_config(self) -> Result<AuthConfig, ConfigError> {
+        let mut auth = AuthConfig::new(self.jwt_secret.unwrap_or_default());
+        if let Some(issuer) = self.issuer {
+            auth.issuer = issuer;
+        }
+        if let Some(audience) = self.audience {
+            auth.audience = audience;
+        }
+        if let Some(ttl) = self.access_token_ttl_secs {
+            auth.access_token_ttl_secs = positive("auth.access_token_ttl_secs", ttl)?;
+        }
+        if let Some(ttl) = self.refresh_token_ttl_secs {
+            auth.refresh_token_ttl_secs = positive("auth.refresh_token_ttl_secs", ttl)?;
+        }
+        if let Some(ttl) = self.password_reset_ttl_secs {
+            auth.password_reset_ttl_secs = positive("auth.password_reset_ttl_secs", ttl)?;
+        }
+        if let Some(ttl) = self.email_verification_ttl_secs {
+            auth.email_verification_ttl_secs = positive("auth.email_verification_ttl_secs", ttl)?;
+        }
+        auth.password_reset_url = self.password_reset_url.filter(|url| !url.trim().is_empty());
+        auth.email_verification_url = self
+            .email_verification_url
+            .filter(|url| !url.trim().is_empty());
+        if let Some(rule) = self.require_verified_email {
+            auth.require_verified_email = EmailVerificationRule::parse(rule.trim())
+                .map_err(|message| ConfigError::invalid("auth.require_verified_email", message))?;
+        }
+        auth.password_hasher = self.hasher.into_hasher()?;
+        self.password_policy.apply(&mut auth.password_policy)?;
+        self.login_throttle.apply(&mut auth.login_throttle)?;
+        Ok(auth)
+    }
+}
+
+impl HasherFile {
+    fn into_hasher(self) -> Result<SharedPasswordHasher, ConfigError> {
+        match self
+            .algorithm
+            .as_deref()
+            .map(str::trim)
+            .unwrap_or("argon2id")
+        {
+            "argon2id" => {
+                let hasher = Argon2idHasher::new(
+                    self.argon2_memory_kib
+                        .unwrap_or(argon2::Params::DEFAULT_M_COST),
+                    self.argon2_iterations
+                        .unwrap_or(argon2::Params::DEFAULT_T_COST),
+                    self.argon2_parallelism
+                        .unwrap_or(argon2::Params::DEFAULT_P_COST),
+                )
+                .map_err(|message| ConfigError::invalid("auth.hasher", message))?;
+                Ok(Arc::new(hasher))
+            }
+            "bcrypt" => {
+                let cost = self.bcrypt_cost.unwrap_or(bcrypt::DEFAULT_COST);
+                let hasher = BcryptHasher::new(cost)
+                    .map_err(|message| ConfigError::invalid("auth.hasher.bcrypt_cost", message))?;
+                Ok(Arc::new(hasher))
+            }
+            other => Err(ConfigError::invalid(
+                "auth.hasher.algorithm",
+                format!("expected argon2id or bcrypt, got {other}"),
+            )),
+        }
+    }
+}
+
+impl PasswordPolicyFile {
+    fn apply(self, policy: &mut PasswordPolicy) -> Result<(), ConfigError> {
+        if let Some(min) = self.min_chars {
+            policy.min_chars = min;
+        }
+        if let Some(max) = self.max_chars {
+            policy.max_chars = max;
+        }
+        if policy.min_chars > policy.max_chars {
+            return Err(ConfigError::invalid(
+                "auth.password_policy.min_chars",
+                format!("must not exceed max_chars ({})", policy.max_chars),
+            ));
+        }
+        if let Some(required) = self.require_lowercase {
+            policy.require_lowercase = required;
+        }
+        if let Some(required) = self.require_uppercase {
+            policy.require_uppercase = required;
+        }
+        if let Some(required) = self.require_digit {
+            policy.require_digit = required;
+        }
+        if let Some(required) = self.require_symbol {
+            policy.require_symbol = required;
+        }
+        if let Some(reject) = self.reject_email_local_part {
+            policy.reject_email_local_part = reject;
+        }
+        if let Some(path) = self
+            .breached_list_path
+            .filter(|p| !p.as_os_str().is_empty())
+        {
+            let list = BreachedPasswordList::load(&path).map_err(|message| {
+                ConfigError::invalid("auth.password_policy.breached_list_path", message)
+            })?;
+            policy.breached_passwords = Some(Arc::new(list));
+        }
+        Ok(())
+    }
+}
+
+impl LoginThrottleFile {
+    fn apply(self, throttle: &mut LoginThrottlePolicy) -> Result<(), ConfigError> {
+        if let Some(max) = self.max_failures_per_account {
+            throttle.max_failures_per_account =
+                positive("auth.login_throttle.max_failures_per_account", max)?;
+        }
+        if let Some(max) = self.max_failures_per_ip {
+            throttle.max_failures_per_ip =
+                positive("auth.login_throttle.max_failures_per_ip", max)?;
+        }
+        if let Some(max) = self.max_mails_per_email {
+            throttle.max_mails_per_email =
+                positive("auth.login_throttle.max_mails_per_email", max)?;
+        }
+        if let Some(max) = self.max_mails_per_ip {
+            throttle.max_mails_per_ip = positive("auth.login_throttle.max_mails_per_ip", max)?;
+        }
+        if let Some(secs) = self.lockout_base_secs {
+            throttle.base_lockout_secs = positive("auth.login_throttle.lockout_base_secs", secs)?;
+        }
+        if let Some(secs) = self.lockout_max_secs {
+            throttle.max_lockout_secs = positive("auth.login_throttle.lockout_max_secs", secs)?;
+        }
+        if throttle.base_lockout_secs > throttle.max_lockout_secs {
+            return Err(ConfigError::invalid(
+                "auth.login_throttle.lockout_base_secs",
+                format!(
+                    "must not exceed lockout_max_secs ({})",
+                    throttle.max_lockout_secs
+                ),
+            ));
+        }
+        if let Some(secs) = self.failure_window_secs {
+            throttle.failure_window_secs =
+                positive("auth.login_throttle.failure_window_secs", secs)?;
+        }
+        Ok(())
+    }
+}
+
+impl MailFile {
+    fn into_mail_config(self) -> Result<MailConfig, ConfigError> {
+        let from = self.from.unwrap_or_else(|| DEFAULT_MAIL_FROM.into());
+        from.parse::<Mailbox>()
+            .map_err(|e| ConfigError::invalid("mail.from", format!("{from}: {e}")))?;
+
+        let transport = match self.transport.as_deref().map(str::trim).unwrap_or("file") {
+            "file" => MailTransport::File(
+                self.file_dir
+                    .unwrap_or_else(|| PathBuf::from(DEFAULT_MAIL_FILE_DIR)),
+            ),
+            "smtp" => MailTransport::Smtp(SmtpSettings {
+                host: self
+                    .smtp_host
+                    .filter(|host| !host.trim().is_empty())
+                    .ok_or(ConfigError::Missing(
+                        "mail.smtp_host (or SMTP_HOST) when the transport is smtp",
+                    ))?,
+                port: self.smtp_port.unwrap_or(DEFAULT_SMTP_PORT),
+                username: self.smtp_username,
+                password: self.smtp_password,
+            }),
+            "memory" => MailTransport::Memory,
+            other => {
+                return Err(ConfigError::invalid(
+                    "mail.transport",
+                    format!("expected file, smtp or memory, got {other}"),
+                ));
+            }
+        };
+        Ok(MailConfig { transport, from })
+    }
+}
+
+fn positive<T: PartialOrd + Default>(key: &'static str, value: T) -> Result<T, ConfigError> {
+    if value > T::default() {
+        Ok(value)
+    } else {
+        Err(ConfigError::invalid(key, "must be a positive integer"))
+    }
+}
+
+fn override_parsed<T: FromStr>(slot: &mut Option<T>, key: &'static str) -> Result<(), ConfigError>
+where
+    T::Err: std::fmt::Display,
+{
+    if let Some(raw) = env_var(key) {
+        let value = raw
+            .trim()
+            .parse()
+            .map_err(|e| ConfigError::invalid(key, format!("{raw}: {e}")))?;
+        *slot = Some(value);
+    }
+    Ok(())
+}
+
+/// Unset and blank variables are treated alike, as docker-compose passes blanks through.
+pub(crate) fn env_var(key: &str) -> Option<String> {
+    std::env::var(key)
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+}
@@ -0,0 +1,96 @@
+use diesel::prelude::*;
+use diesel::{PgConnection, QueryResult};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::models::attribute::{
+    AttributeDefinition, NewAttributeDefinition, ProductAttribute, UpdateAttributeDefinition,
+};
+use crate::schema::{attribute_definitions, product_attributes};
+
+pub fn create_definition(
+    conn: &mut PgConnection,
+    new_definition: &NewAttributeDefinition,
+) -> QueryResult<AttributeDefinition> {
+    diesel::insert_into(attribute_definitions::table)
+        .values(new_definition)
+        .get_result(conn)
+}
+
+pub fn get_definition_by_id(
+    conn: &mut PgConnection,
+    attribute_id: Uuid,
+) -> QueryResult<Option<AttributeDefinition>> {
+    attribute_definitions::table
+        .find(attribute_id)
+        .first(conn)
+        .optional()
+}
+
+/// Every definition, by code.
+pub fn list_definitions(conn: &mut PgConnection) -> QueryResult<Vec<AttributeDefinition>> {
+    attribute_definitions::table
+        .order(attribute_definitions::code)
+        .load(conn)
+}
+
+pub fn update_definition(
+    conn: &mut PgConnection,
+    attribute_id: Uuid,
+    updated: &UpdateAttributeDefinition,
+) -> QueryResult<Option<AttributeDefinition>> {
+    diesel::update(attribute_definitions::table.find(attribute_id))
+        .set(updated)
+        .get_result(conn)
+        .optional()
+}
+
+/// Delete a definition along with every product's value for it.
+pub fn delete_definition(conn: &mut PgConnection, attribute_id: Uuid) -> QueryResult<usize> {
+    diesel::delete(attribute_definitions::table.find(attribute_id)).execute(conn)
+}
+
+/// Products whose value for the attribute is not among `allowed`.
+pub fn count_disallowed_values(
+    conn: &mut PgConnection,
+    attribute_id: Uuid,
+    allowed: &[Value],
+) -> QueryResult<i64> {
+    product_attributes::table
+        .filter(product_attributes::attribute_id.eq(attribute_id))
+        .filter(diesel::dsl::not(
+            product_attributes::value.eq_any(allowed.to_vec()),
+        ))
+        .count()
+        .get_result(conn)
+}
+
+/// Replace all of the product's attribute values.
+pub fn set_product_attributes(
+    conn: &mut PgConnection,
+    product_id: Uuid,
+    attributes: &[ProductAttribute],
+) -> QueryResult<usize> {
+    diesel::delete(product_attributes::table.filter(product_attributes::product_id.eq(product_id)))
+        .execute(conn)?;
+    diesel::insert_into(product_attributes::table)
+        .values(attributes)
+        .execute(conn)
+}
+
+/// Attribute values of the given products as `(product_id, code, value)`, by code.
+pub fn attributes_for_products(
+    conn: &mut PgConnection,
+    product_ids: &[Uuid],
+) -> QueryResult<Vec<(Uuid, String, Value)>> {
+    product_attributes::table
+        .inner_join(attribute_definitions::table)
+        .filter(product_attributes::product_id.eq_any(product_ids))
+        .order(attribute_definitions::code)
+        .select((
+            product_attributes::product_id,
+            attribute_definitions::code,
+            product_attributes::value,
+        ))
+        .load(conn)
+}
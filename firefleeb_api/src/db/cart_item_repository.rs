@@ -3,9 +3,9 @@ use diesel::{PgConnection, QueryResult};
 use uuid::Uuid;
 
 use crate::models::cart_item::{CartItem, NewCartItem, UpdateCartItem};
-use crate::schema::cart_items;
+use crate::schema::{cart_items, products};
 
-/// Insert a new cart item row (no merging). Fails if (cart_id, item_id) already exists.
+/// Insert a new cart item row (no merging). Fails if (cart_id, variant_id) already exists.
 pub fn create_cart_item(
     conn: &mut PgConnection,
     new_cart_item: &NewCartItem,
@@ -25,6 +25,41 @@ pub fn get_items_by_cart_id(conn: &mut PgConnection, cart_id: Uuid) -> QueryResu
         .load::<CartItem>(conn)
 }
 
+/// The cart's lines of a product, or its line of one variant when `item_id` is a variant id.
+pub fn find_lines(
+    conn: &mut PgConnection,
+    cart_id: Uuid,
+    item_id: Uuid,
+) -> QueryResult<Vec<CartItem>> {
+    cart_items::table
+        .filter(cart_items::cart_id.eq(cart_id))
+        .filter(
+            cart_items::item_id
+                .eq(item_id)
+                .or(cart_items::variant_id.eq(item_id)),
+        )
+        .order_by(cart_items::created_at.asc().nulls_last())
+        .load(conn)
+}
+
+/// Ids of the cart's items whose product has been archived.
+pub fn unavailable_item_ids(conn: &mut PgConnection, cart_id: Uuid) -> QueryResult<Vec<Uuid>> {
+    cart_items::table
+        .inner_join(products::table)
+        .filter(cart_items::cart_id.eq(cart_id))
+        .filter(products::archived_at.is_not_null())
+        .select(cart_items::id)
+        .load(conn)
+}
+
+/// Whether any cart, checked out or not, has a line of the variant.
+pub fn variant_in_carts(conn: &mut PgConnection, variant_id: Uuid) -> QueryResult<bool> {
+    diesel::select(diesel::dsl::exists(
+        cart_items::table.filter(cart_items::variant_id.eq(variant_id)),
+    ))
+    .get_result(conn)
+}
+
 /// Set quantity (0 removes the item).
 pub fn set_item_quantity(
     conn: &mut PgConnection,
@@ -61,34 +96,20 @@ pub fn set_item_quantity(
     .get_result::<CartItem>(conn)
 }
 
-/// Update quantity/unit price for a specific cart item.
+/// Update quantity/unit price of one cart line.
 pub fn update_cart_item(
     conn: &mut PgConnection,
-    cart_id: Uuid,
-    product_id: Uuid,
+    line_id: Uuid,
     updated: &UpdateCartItem,
 ) -> QueryResult<CartItem> {
-    use crate::schema::cart_items::dsl as ci;
-
-    diesel::update(
-        ci::cart_items
-            .filter(ci::cart_id.eq(cart_id))
-            .filter(ci::item_id.eq(product_id)),
-    )
-    .set(updated)
-    .get_result::<CartItem>(conn)
+    diesel::update(cart_items::table.find(line_id))
+        .set(updated)
+        .get_result::<CartItem>(conn)
 }
 
-/// Remove one item row
-pub fn delete_item(conn: &mut PgConnection, cart_id: Uuid, product_id: Uuid) -> QueryResult<usize> {
-    use crate::schema::cart_items::dsl as ci;
-
-    diesel::delete(
-        ci::cart_items
-            .filter(ci::cart_id.eq(cart_id))
-            .filter(ci::item_id.eq(product_id)),
-    )
-    .execute(conn)
+/// Remove one cart line.
+pub fn delete_item(conn: &mut PgConnection, line_id: Uuid) -> QueryResult<usize> {
+    diesel::delete(cart_items::table.find(line_id)).execute(conn)
 }
 
 /// Remove all items for a cart
@@ -97,3 +118,8 @@ pub fn delete_all_for_cart(conn: &mut PgConnection, cart_id: Uuid) -> QueryResul
 
     diesel::delete(ci::cart_items.filter(ci::cart_id.eq(cart_id))).execute(conn)
 }
+
+/// Remove every cart line of a product, in any cart.
+pub fn delete_all_for_product(conn: &mut PgConnection, product_id: Uuid) -> QueryResult<usize> {
+    diesel::delete(cart_items::table.filter(cart_items::item_id.eq(product_id))).execute(conn)
+}
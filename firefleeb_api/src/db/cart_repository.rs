@@ -4,13 +4,13 @@ use uuid::Uuid;
 use diesel::prelude::*;
 use diesel::{PgConnection, QueryResult};
 
-use crate::models::cart::{Cart, NewCart, UpdateCart};
+use crate::models::cart::{CART_STATUS_ACTIVE, Cart, NewCart, UpdateCart};
 use crate::schema::carts;
 
 pub fn create_default_cart(conn: &mut PgConnection, user_id: Uuid) -> QueryResult<Cart> {
     let new_cart = NewCart {
         user_id,
-        cart_status: "active".into(),
+        cart_status: CART_STATUS_ACTIVE.into(),
         cart_total: BigDecimal::from(0),
     };
 
@@ -23,10 +23,19 @@ pub fn get_cart_by_id(conn: &mut PgConnection, cart_id: Uuid) -> QueryResult<Opt
     carts::table.find(cart_id).first::<Cart>(conn).optional()
 }
 
+/// Fetch a cart and lock the row until the surrounding transaction ends.
+pub fn get_cart_for_update(conn: &mut PgConnection, cart_id: Uuid) -> QueryResult<Option<Cart>> {
+    carts::table
+        .find(cart_id)
+        .for_update()
+        .first::<Cart>(conn)
+        .optional()
+}
+
 pub fn get_active_by_user_id(conn: &mut PgConnection, user_id: Uuid) -> QueryResult<Option<Cart>> {
     carts::table
         .filter(carts::user_id.eq(user_id))
-        .filter(carts::cart_status.eq("active"))
+        .filter(carts::cart_status.eq(CART_STATUS_ACTIVE))
         .first::<Cart>(conn)
         .optional()
 }
@@ -41,6 +50,12 @@ pub fn update_cart(
         .get_result(conn)
 }
 
+pub fn set_cart_status(conn: &mut PgConnection, cart_id: Uuid, status: &str) -> QueryResult<Cart> {
+    diesel::update(carts::table.find(cart_id))
+        .set(carts::cart_status.eq(status))
+        .get_result(conn)
+}
+
 pub fn update_cart_total(
     conn: &mut PgConnection,
     cart_id: Uuid,
@@ -0,0 +1,213 @@
+use diesel::prelude::*;
+use diesel::sql_types::{Array, BigInt, Bool, Uuid as SqlUuid};
+use diesel::{PgConnection, QueryResult};
+use uuid::Uuid;
+
+use crate::models::category::{
+    Category, CategoryPathStep, NewCategory, NewProductCategory, UpdateCategory,
+};
+use crate::models::product::Product;
+use crate::schema::{categories, product_categories};
+
+/// Guards the recursive walks against runaway trees.
+const MAX_CATEGORY_DEPTH: i32 = 64;
+
+pub fn create_category(
+    conn: &mut PgConnection,
+    new_category: &NewCategory,
+) -> QueryResult<Category> {
+    diesel::insert_into(categories::table)
+        .values(new_category)
+        .get_result(conn)
+}
+
+pub fn get_category_by_id(
+    conn: &mut PgConnection,
+    category_id: Uuid,
+) -> QueryResult<Option<Category>> {
+    categories::table
+        .find(category_id)
+        .first::<Category>(conn)
+        .optional()
+}
+
+/// Every category by name; callers rebuild the tree from `parent_id`.
+pub fn list_categories(conn: &mut PgConnection) -> QueryResult<Vec<Category>> {
+    categories::table
+        .order((categories::name.asc(), categories::id.asc()))
+        .load(conn)
+}
+
+pub fn update_category(
+    conn: &mut PgConnection,
+    category_id: Uuid,
+    updated: &UpdateCategory,
+) -> QueryResult<Category> {
+    diesel::update(categories::table.find(category_id))
+        .set(updated)
+        .get_result(conn)
+}
+
+pub fn delete_category(conn: &mut PgConnection, category_id: Uuid) -> QueryResult<usize> {
+    diesel::delete(categories::table.find(category_id)).execute(conn)
+}
+
+pub fn has_children(conn: &mut PgConnection, category_id: Uuid) -> QueryResult<bool> {
+    diesel::select(diesel::dsl::exists(
+        categories::table.filter(categories::parent_id.eq(category_id)),
+    ))
+    .get_result(conn)
+}
+
+/// Serialize tree changes for the rest of the transaction, so two concurrent moves cannot
+/// combine into a cycle and a category cannot be created under a parent being deleted.
+pub fn lock_tree(conn: &mut PgConnection) -> QueryResult<()> {
+    diesel::sql_query("SELECT pg_advisory_xact_lock(hashtext('categories_tree'))")
+        .execute(conn)
+        .map(|_| ())
+}
+
+#[derive(QueryableByName)]
+struct Found {
+    #[diesel(sql_type = Bool)]
+    found: bool,
+}
+
+/// Whether `candidate_id` is `ancestor_id` itself or lies anywhere below it.
+pub fn is_descendant_or_self(
+    conn: &mut PgConnection,
+    ancestor_id: Uuid,
+    candidate_id: Uuid,
+) -> QueryResult<bool> {
+    diesel::sql_query(
+        "WITH RECURSIVE tree AS ( \
+             SELECT id FROM categories WHERE id = $1 \
+             UNION \
+             SELECT c.id FROM categories c JOIN tree t ON c.parent_id = t.id \
+         ) \
+         SELECT EXISTS (SELECT 1 FROM tree WHERE id = $2) AS found",
+    )
+    .bind::<SqlUuid, _>(ancestor_id)
+    .bind::<SqlUuid, _>(candidate_id)
+    .get_result::<Found>(conn)
+    .map(|row| row.found)
+}
+
+/// Root-to-leaf paths of the given categories, one step per row.
+pub fn category_paths(
+    conn: &mut PgConnection,
+    category_ids: &[Uuid],
+) -> QueryResult<Vec<CategoryPathStep>> {
+    diesel::sql_query(format!(
+        "WITH RECURSIVE chain AS ( \
+             SELECT NULL::uuid AS product_id, c.id AS leaf_id, c.id, c.parent_id, c.name, 0 AS depth \
+             FROM categories c \
+             WHERE c.id = ANY($1) \
+             UNION ALL \
+             SELECT chain.product_id, chain.leaf_id, c.id, c.parent_id, c.name, chain.depth + 1 \
+             FROM chain JOIN categories c ON c.id = chain.parent_id \
+             WHERE chain.depth < {MAX_CATEGORY_DEPTH} \
+         ) \
+         SELECT product_id, leaf_id, id, name, depth FROM chain"
+    ))
+    .bind::<Array<SqlUuid>, _>(category_ids)
+    .load(conn)
+}
+
+/// Root-to-leaf paths of every category each product is linked to, one step per row.
+pub fn product_category_paths(
+    conn: &mut PgConnection,
+    product_ids: &[Uuid],
+) -> QueryResult<Vec<CategoryPathStep>> {
+    diesel::sql_query(format!(
+        "WITH RECURSIVE chain AS ( \
+             SELECT pc.product_id, c.id AS leaf_id, c.id, c.parent_id, c.name, 0 AS depth \
+             FROM product_categories pc JOIN categories c ON c.id = pc.category_id \
+             WHERE pc.product_id = ANY($1) \
+             UNION ALL \
+             SELECT chain.product_id, chain.leaf_id, c.id, c.parent_id, c.name, chain.depth + 1 \
+             FROM chain JOIN categories c ON c.id = chain.parent_id \
+             WHERE chain.depth < {MAX_CATEGORY_DEPTH} \
+         ) \
+         SELECT product_id, leaf_id, id, name, depth FROM chain"
+    ))
+    .bind::<Array<SqlUuid>, _>(product_ids)
+    .load(conn)
+}
+
+/// Link a product to a category; linking twice is a no-op. Returns rows inserted.
+pub fn add_product(conn: &mut PgConnection, link: &NewProductCategory) -> QueryResult<usize> {
+    diesel::insert_into(product_categories::table)
+        .values(link)
+        .on_conflict_do_nothing()
+        .execute(conn)
+}
+
+pub fn remove_product(
+    conn: &mut PgConnection,
+    category_id: Uuid,
+    product_id: Uuid,
+) -> QueryResult<usize> {
+    diesel::delete(product_categories::table.find((product_id, category_id))).execute(conn)
+}
+
+/// Categories to match products against: the category alone, or with all its descendants.
+const CATEGORY_SCOPE: &str = "WITH RECURSIVE tree AS ( \
+         SELECT id FROM categories WHERE id = $1 \
+         UNION \
+         SELECT c.id FROM categories c JOIN tree t ON c.parent_id = t.id WHERE $2 \
+     )";
+
+#[derive(QueryableByName)]
+struct Count {
+    #[diesel(sql_type = BigInt)]
+    total: i64,
+}
+
+/// Products linked to the category (and, with `include_descendants`, to any category below
+/// it), ordered by name. A product in several matching categories appears once.
+pub fn list_products_in(
+    conn: &mut PgConnection,
+    category_id: Uuid,
+    include_descendants: bool,
+    offset: i64,
+    limit: i64,
+) -> QueryResult<Vec<Product>> {
+    diesel::sql_query(format!(
+        "{CATEGORY_SCOPE} \
+         SELECT p.id, p.product_name, p.product_description, p.price, p.stock, p.created_at, \
+                p.archived_at, p.rating_count, p.rating_average \
+         FROM products p \
+         WHERE p.archived_at IS NULL AND EXISTS ( \
+             SELECT 1 FROM product_categories pc \
+             WHERE pc.product_id = p.id AND pc.category_id IN (SELECT id FROM tree) \
+         ) \
+         ORDER BY p.product_name, p.id \
+         OFFSET $3 LIMIT $4"
+    ))
+    .bind::<SqlUuid, _>(category_id)
+    .bind::<Bool, _>(include_descendants)
+    .bind::<BigInt, _>(offset)
+    .bind::<BigInt, _>(limit)
+    .load(conn)
+}
+
+pub fn count_products_in(
+    conn: &mut PgConnection,
+    category_id: Uuid,
+    include_descendants: bool,
+) -> QueryResult<i64> {
+    diesel::sql_query(format!(
+        "{CATEGORY_SCOPE} \
+         SELECT count(*) AS total \
+         FROM products p \
+         WHERE p.archived_at IS NULL AND EXISTS ( \
+             SELECT 1 FROM product_categories pc \
+             WHERE pc.product_id = p.id AND pc.category_id IN (SELECT id FROM tree) \
+         )"
+    ))
+    .bind::<SqlUuid, _>(category_id)
+    .bind::<Bool, _>(include_descendants)
+    .get_result::<Count>(conn)
+    .map(|row| row.total)
+}
@@ -0,0 +1,43 @@
+use chrono::Utc;
+use uuid::Uuid;
+
+use diesel::prelude::*;
+use diesel::{PgConnection, QueryResult};
+
+use crate::models::email_verification_token::{EmailVerificationToken, NewEmailVerificationToken};
+use crate::schema::email_verification_tokens;
+
+pub fn create_verification_token(
+    conn: &mut PgConnection,
+    new_token: &NewEmailVerificationToken,
+) -> QueryResult<EmailVerificationToken> {
+    diesel::insert_into(email_verification_tokens::table)
+        .values(new_token)
+        .get_result(conn)
+}
+
+/// Look up a token by hash and lock the row until the surrounding transaction ends.
+pub fn get_by_hash_for_update(
+    conn: &mut PgConnection,
+    token_hash: &str,
+) -> QueryResult<Option<EmailVerificationToken>> {
+    email_verification_tokens::table
+        .filter(email_verification_tokens::token_hash.eq(token_hash))
+        .for_update()
+        .first::<EmailVerificationToken>(conn)
+        .optional()
+}
+
+/// Mark every unused token for the user as spent, e.g. once the address changes again.
+pub fn invalidate_outstanding_for_user(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+) -> QueryResult<usize> {
+    diesel::update(
+        email_verification_tokens::table
+            .filter(email_verification_tokens::user_id.eq(user_id))
+            .filter(email_verification_tokens::used_at.is_null()),
+    )
+    .set(email_verification_tokens::used_at.eq(Utc::now()))
+    .execute(conn)
+}
@@ -0,0 +1,75 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+use diesel::{PgConnection, QueryResult};
+
+use crate::models::login_throttle::{LoginThrottle, NewLoginThrottle, UpdateLoginThrottle};
+use crate::schema::login_throttles;
+
+pub fn get_throttle(
+    conn: &mut PgConnection,
+    scope: &str,
+    subject: &str,
+) -> QueryResult<Option<LoginThrottle>> {
+    login_throttles::table
+        .find((scope, subject))
+        .first::<LoginThrottle>(conn)
+        .optional()
+}
+
+/// Fetch the row for a subject, creating it first if needed, and lock it until the
+/// surrounding transaction ends. The no-op update on conflict takes the row lock in the same
+/// statement, so a concurrent cleanup cannot delete the row in between.
+pub fn get_or_create_for_update(
+    conn: &mut PgConnection,
+    scope: &str,
+    subject: &str,
+) -> QueryResult<LoginThrottle> {
+    diesel::insert_into(login_throttles::table)
+        .values(&NewLoginThrottle {
+            scope: scope.into(),
+            subject: subject.into(),
+        })
+        .on_conflict((login_throttles::scope, login_throttles::subject))
+        .do_update()
+        .set(login_throttles::scope.eq(excluded(login_throttles::scope)))
+        .get_result(conn)
+}
+
+pub fn update_throttle(
+    conn: &mut PgConnection,
+    scope: &str,
+    subject: &str,
+    updated: &UpdateLoginThrottle,
+) -> QueryResult<LoginThrottle> {
+    diesel::update(login_throttles::table.find((scope, subject)))
+        .set(updated)
+        .get_result(conn)
+}
+
+pub fn clear_throttle(conn: &mut PgConnection, scope: &str, subject: &str) -> QueryResult<usize> {
+    diesel::delete(login_throttles::table.find((scope, subject))).execute(conn)
+}
+
+/// Delete rows that no longer hold anything back: not locked out at `now` and without a
+/// failure since `window_start`.
+pub fn delete_stale(
+    conn: &mut PgConnection,
+    now: DateTime<Utc>,
+    window_start: DateTime<Utc>,
+) -> QueryResult<usize> {
+    diesel::delete(
+        login_throttles::table
+            .filter(
+                login_throttles::locked_until
+                    .is_null()
+                    .or(login_throttles::locked_until.le(now)),
+            )
+            .filter(
+                login_throttles::last_failed_at
+                    .is_null()
+                    .or(login_throttles::last_failed_at.le(window_start)),
+            ),
+    )
+    .execute(conn)
+}
@@ -2,10 +2,27 @@ use diesel::pg::PgConnection;
 use diesel::r2d2::{ConnectionManager, Pool, PooledConnection};
 use diesel_migrations::{EmbeddedMigrations, MigrationHarness, embed_migrations};
 
+use crate::config::DatabaseConfig;
+
+pub mod attribute_repository;
 pub mod cart_item_repository;
 pub mod cart_repository;
+pub mod category_repository;
+pub mod email_verification_repository;
+pub mod login_throttle_repository;
+pub mod pagination;
+pub mod password_reset_repository;
+pub mod product_image_repository;
+pub mod product_price_repository;
+pub mod product_recommendation_repository;
 pub mod product_repository;
+pub mod product_review_repository;
+pub mod product_variant_repository;
+pub mod refresh_token_repository;
+pub mod stock_movement_repository;
+pub mod stock_reservation_repository;
 pub mod user_repository;
+pub mod warehouse_repository;
 
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
 
@@ -13,8 +30,19 @@ pub type PgPool = Pool<ConnectionManager<PgConnection>>;
 
 /// Call once and pass pool around
 pub fn init_pool(database_url: &str) -> Result<PgPool, r2d2::Error> {
-    let manager = ConnectionManager::<PgConnection>::new(database_url);
-    Pool::builder().max_size(15).build(manager)
+    init_pool_with(&DatabaseConfig::new(database_url))
+}
+
+/// Build the pool with the configured size and timeouts.
+pub fn init_pool_with(config: &DatabaseConfig) -> Result<PgPool, r2d2::Error> {
+    let manager = ConnectionManager::<PgConnection>::new(&config.url);
+    Pool::builder()
+        .max_size(config.max_connections)
+        .min_idle(config.min_idle)
+        .connection_timeout(config.connection_timeout)
+        .idle_timeout(config.idle_timeout)
+        .max_lifetime(config.max_lifetime)
+        .build(manager)
 }
 
 /// Borrow a connection from the pool (blocking!!)
@@ -0,0 +1,53 @@
+use chrono::Utc;
+use uuid::Uuid;
+
+use diesel::prelude::*;
+use diesel::{PgConnection, QueryResult};
+
+use crate::models::password_reset_token::{NewPasswordResetToken, PasswordResetToken};
+use crate::schema::password_reset_tokens;
+
+pub fn create_reset_token(
+    conn: &mut PgConnection,
+    new_token: &NewPasswordResetToken,
+) -> QueryResult<PasswordResetToken> {
+    diesel::insert_into(password_reset_tokens::table)
+        .values(new_token)
+        .get_result(conn)
+}
+
+pub fn get_by_hash(
+    conn: &mut PgConnection,
+    token_hash: &str,
+) -> QueryResult<Option<PasswordResetToken>> {
+    password_reset_tokens::table
+        .filter(password_reset_tokens::token_hash.eq(token_hash))
+        .first::<PasswordResetToken>(conn)
+        .optional()
+}
+
+/// Look up a token by hash and lock the row until the surrounding transaction ends.
+pub fn get_by_hash_for_update(
+    conn: &mut PgConnection,
+    token_hash: &str,
+) -> QueryResult<Option<PasswordResetToken>> {
+    password_reset_tokens::table
+        .filter(password_reset_tokens::token_hash.eq(token_hash))
+        .for_update()
+        .first::<PasswordResetToken>(conn)
+        .optional()
+}
+
+/// Mark every unused token for the user as spent so only the newest link (if any) works.
+pub fn invalidate_outstanding_for_user(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+) -> QueryResult<usize> {
+    diesel::update(
+        password_reset_tokens::table
+            .filter(password_reset_tokens::user_id.eq(user_id))
+            .filter(password_reset_tokens::used_at.is_null()),
+    )
+    .set(password_reset_tokens::used_at.eq(Utc::now()))
+    .execute(conn)
+}
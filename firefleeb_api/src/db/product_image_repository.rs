@@ -0,0 +1,52 @@
+use diesel::prelude::*;
+use diesel::{PgConnection, QueryResult};
+use uuid::Uuid;
+
+use crate::models::product_image::{NewProductImage, ProductImage};
+use crate::schema::product_images;
+
+pub fn create_image(
+    conn: &mut PgConnection,
+    new_image: &NewProductImage,
+) -> QueryResult<ProductImage> {
+    diesel::insert_into(product_images::table)
+        .values(new_image)
+        .get_result(conn)
+}
+
+/// The image, if it belongs to `product_id`.
+pub fn get_image(
+    conn: &mut PgConnection,
+    product_id: Uuid,
+    image_id: Uuid,
+) -> QueryResult<Option<ProductImage>> {
+    product_images::table
+        .filter(product_images::id.eq(image_id))
+        .filter(product_images::product_id.eq(product_id))
+        .first(conn)
+        .optional()
+}
+
+/// Images of the given products, oldest first.
+pub fn list_images_for_products(
+    conn: &mut PgConnection,
+    product_ids: &[Uuid],
+) -> QueryResult<Vec<ProductImage>> {
+    product_images::table
+        .filter(product_images::product_id.eq_any(product_ids))
+        .order((product_images::created_at, product_images::id))
+        .load(conn)
+}
+
+pub fn delete_image(
+    conn: &mut PgConnection,
+    product_id: Uuid,
+    image_id: Uuid,
+) -> QueryResult<usize> {
+    diesel::delete(
+        product_images::table
+            .filter(product_images::id.eq(image_id))
+            .filter(product_images::product_id.eq(product_id)),
+    )
+    .execute(conn)
+}
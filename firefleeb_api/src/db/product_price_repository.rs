@@ -0,0 +1,99 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use diesel::dsl::now;
+use diesel::prelude::*;
+use diesel::sql_types::Timestamptz;
+use diesel::{PgConnection, QueryResult};
+use uuid::Uuid;
+
+use crate::models::product_price::{NewProductPrice, PRICE_SOURCE_MANUAL, ProductPrice};
+use crate::schema::product_prices;
+
+pub fn create_price(
+    conn: &mut PgConnection,
+    new_price: &NewProductPrice,
+) -> QueryResult<ProductPrice> {
+    diesel::insert_into(product_prices::table)
+        .values(new_price)
+        .get_result(conn)
+}
+
+/// Close the product's open manual window and open a new one at `price` from now on.
+pub fn record_manual_price(
+    conn: &mut PgConnection,
+    product_id: Uuid,
+    price: &BigDecimal,
+) -> QueryResult<ProductPrice> {
+    diesel::update(
+        product_prices::table
+            .filter(product_prices::product_id.eq(product_id))
+            .filter(product_prices::source.eq(PRICE_SOURCE_MANUAL))
+            .filter(product_prices::valid_until.is_null())
+            .filter(product_prices::valid_from.lt(now)),
+    )
+    .set(product_prices::valid_until.eq(now))
+    .execute(conn)?;
+
+    diesel::insert_into(product_prices::table)
+        .values((
+            product_prices::product_id.eq(product_id),
+            product_prices::price.eq(price),
+            product_prices::valid_from.eq(now),
+            product_prices::source.eq(PRICE_SOURCE_MANUAL),
+        ))
+        .get_result(conn)
+}
+
+/// The product's price history, latest start first.
+pub fn list_prices(conn: &mut PgConnection, product_id: Uuid) -> QueryResult<Vec<ProductPrice>> {
+    product_prices::table
+        .filter(product_prices::product_id.eq(product_id))
+        .order((
+            product_prices::valid_from.desc(),
+            product_prices::created_at.desc(),
+        ))
+        .load(conn)
+}
+
+/// The price row, if it belongs to `product_id`.
+pub fn get_price(
+    conn: &mut PgConnection,
+    product_id: Uuid,
+    price_id: Uuid,
+) -> QueryResult<Option<ProductPrice>> {
+    product_prices::table
+        .filter(product_prices::id.eq(price_id))
+        .filter(product_prices::product_id.eq(product_id))
+        .first(conn)
+        .optional()
+}
+
+pub fn delete_price(
+    conn: &mut PgConnection,
+    product_id: Uuid,
+    price_id: Uuid,
+) -> QueryResult<usize> {
+    diesel::delete(
+        product_prices::table
+            .filter(product_prices::id.eq(price_id))
+            .filter(product_prices::product_id.eq(product_id)),
+    )
+    .execute(conn)
+}
+
+/// Set `products.price` to the price in effect at `at` wherever it differs, returning the
+/// number of products changed. Products without a covering window keep their price.
+pub fn apply_prices_at(conn: &mut PgConnection, at: DateTime<Utc>) -> QueryResult<usize> {
+    diesel::sql_query(
+        "UPDATE products p SET price = effective.price \
+         FROM ( \
+             SELECT DISTINCT ON (product_id) product_id, price \
+             FROM product_prices \
+             WHERE valid_from <= $1 AND (valid_until IS NULL OR valid_until > $1) \
+             ORDER BY product_id, valid_from DESC, created_at DESC \
+         ) effective \
+         WHERE p.id = effective.product_id AND p.price <> effective.price",
+    )
+    .bind::<Timestamptz, _>(at)
+    .execute(conn)
+}
@@ -0,0 +1,66 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Array, BigInt, Text, Timestamptz};
+use diesel::{PgConnection, QueryResult};
+use uuid::Uuid;
+
+use crate::models::cart::CART_STATUS_CHECKED_OUT;
+use crate::models::product_recommendation::RecommendedProduct;
+use crate::schema::product_recommendations;
+
+/// Replace every recommendation with co-occurrence counts over all checked-out carts, stamped
+/// with `at`. Returns how many pairs were stored. Call in a transaction: rebuilds from other
+/// instances wait for it to commit instead of inserting the same pairs.
+pub fn rebuild_recommendations(conn: &mut PgConnection, at: DateTime<Utc>) -> QueryResult<usize> {
+    diesel::sql_query("SELECT pg_advisory_xact_lock(hashtext('product_recommendations'))")
+        .execute(conn)?;
+    diesel::delete(product_recommendations::table).execute(conn)?;
+    diesel::sql_query(
+        "WITH lines AS ( \
+             SELECT DISTINCT ci.cart_id, ci.item_id \
+             FROM cart_items ci JOIN carts c ON c.id = ci.cart_id \
+             WHERE c.cart_status = $1 \
+         ), totals AS ( \
+             SELECT item_id, count(*) AS carts FROM lines GROUP BY item_id \
+         ) \
+         INSERT INTO product_recommendations \
+             (product_id, recommended_id, carts_together, score, computed_at) \
+         SELECT a.item_id, b.item_id, count(*), count(*)::float8 / t.carts, $2 \
+         FROM lines a \
+         JOIN lines b ON b.cart_id = a.cart_id AND b.item_id <> a.item_id \
+         JOIN totals t ON t.item_id = a.item_id \
+         GROUP BY a.item_id, b.item_id, t.carts",
+    )
+    .bind::<Text, _>(CART_STATUS_CHECKED_OUT)
+    .bind::<Timestamptz, _>(at)
+    .execute(conn)
+}
+
+/// Available, unarchived products bought together with any of `product_ids`, best first. As with
+/// the `in_stock` listing filter, a product is available while its stock exceeds the reservations
+/// still unexpired at `at`. The products themselves are never recommended.
+pub fn recommended_products(
+    conn: &mut PgConnection,
+    product_ids: &[Uuid],
+    limit: i64,
+    at: DateTime<Utc>,
+) -> QueryResult<Vec<RecommendedProduct>> {
+    diesel::sql_query(
+        "SELECT p.id, p.product_name, p.product_description, p.price, p.stock, p.created_at, \
+                p.archived_at, p.rating_count, p.rating_average, sum(r.score) AS score \
+         FROM product_recommendations r JOIN products p ON p.id = r.recommended_id \
+         WHERE r.product_id = ANY($1) AND NOT (r.recommended_id = ANY($1)) \
+           AND p.archived_at IS NULL \
+           AND p.stock > COALESCE(( \
+               SELECT sum(sr.quantity) \
+               FROM stock_reservations sr JOIN product_variants v ON v.id = sr.variant_id \
+               WHERE v.product_id = p.id AND sr.expires_at > $3), 0) \
+         GROUP BY p.id \
+         ORDER BY score DESC, p.product_name, p.id \
+         LIMIT $2",
+    )
+    .bind::<Array<diesel::sql_types::Uuid>, _>(product_ids)
+    .bind::<BigInt, _>(limit)
+    .bind::<Timestamptz, _>(at)
+    .load(conn)
+}
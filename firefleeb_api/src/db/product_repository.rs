@@ -1,15 +1,132 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
+use diesel::dsl::{now, sql};
+use diesel::pg::Pg;
 use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Bool, Text};
 use diesel::{PgConnection, QueryResult};
+use serde_json::Value;
 
-use crate::models::product::{NewProduct, Product, UpdateProduct};
-use crate::schema::products;
+use crate::db::pagination::{SortOrder, escape_like};
+use crate::db::{product_price_repository, product_variant_repository, stock_movement_repository};
+use crate::models::product::{
+    NewProduct, Product, ProductSearchHit, ProductSuggestion, UpdateProduct,
+};
+use crate::models::product_price::{NewProductPrice, PRICE_SOURCE_MANUAL};
+use crate::models::product_variant::NewProductVariant;
+use crate::schema::{product_attributes, products};
 
-pub fn create_product(conn: &mut PgConnection, new_product: &NewProduct) -> QueryResult<Product> {
-    diesel::insert_into(products::table)
+#[diesel::declare_sql_function]
+extern "SQL" {
+    fn lower(x: Text) -> Text;
+}
+
+/// Column a product listing is ordered by; the id breaks ties.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProductSort {
+    Price,
+    Name,
+    CreatedAt,
+}
+
+impl ProductSort {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw {
+            "price" => Some(ProductSort::Price),
+            "name" => Some(ProductSort::Name),
+            "created_at" => Some(ProductSort::CreatedAt),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProductSort::Price => "price",
+            ProductSort::Name => "name",
+            ProductSort::CreatedAt => "created_at",
+        }
+    }
+
+    /// Newest first for `created_at`, cheapest / alphabetical first otherwise.
+    pub fn default_order(&self) -> SortOrder {
+        match self {
+            ProductSort::CreatedAt => SortOrder::Desc,
+            ProductSort::Price | ProductSort::Name => SortOrder::Asc,
+        }
+    }
+}
+
+/// Value of the sort column at a keyset position.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProductSortKey {
+    Price(BigDecimal),
+    Name(String),
+    CreatedAt(DateTime<Utc>),
+}
+
+impl ProductSortKey {
+    pub fn of(sort: ProductSort, product: &Product) -> Self {
+        match sort {
+            ProductSort::Price => ProductSortKey::Price(product.price.clone()),
+            ProductSort::Name => ProductSortKey::Name(product.product_name.clone()),
+            ProductSort::CreatedAt => ProductSortKey::CreatedAt(product.created_at),
+        }
+    }
+}
+
+/// Listing continues strictly after this product in the requested order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProductCursor {
+    pub key: ProductSortKey,
+    pub id: Uuid,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProductFilter {
+    pub min_price: Option<BigDecimal>,
+    pub max_price: Option<BigDecimal>,
+    pub in_stock_only: bool,
+    /// Case-insensitive prefix of `product_name`.
+    pub name_prefix: Option<String>,
+    /// Attribute ids with the values accepted for each: a product must match every attribute,
+    /// with any one of its values.
+    pub attributes: Vec<(Uuid, Vec<Value>)>,
+}
+
+/// Insert a product together with its default variant, which holds the product's stock. The
+/// opening price and any opening stock are recorded in the price history and stock ledger,
+/// the latter on behalf of `actor_id`.
+pub fn create_product(
+    conn: &mut PgConnection,
+    new_product: &NewProduct,
+    actor_id: Option<Uuid>,
+) -> QueryResult<Product> {
+    let product: Product = diesel::insert_into(products::table)
         .values(new_product)
-        .get_result(conn)
+        .get_result(conn)?;
+
+    let default_variant = NewProductVariant {
+        product_id: product.id,
+        sku: product_variant_repository::default_sku(product.id),
+        options: serde_json::json!({}),
+        price: None,
+        stock: product.stock,
+        is_default: true,
+    };
+    let variant = product_variant_repository::create_variant(conn, &default_variant)?;
+    stock_movement_repository::log_opening_stock(conn, &variant, actor_id)?;
+
+    let opening_price = NewProductPrice {
+        product_id: product.id,
+        price: product.price.clone(),
+        valid_from: product.created_at,
+        valid_until: None,
+        source: PRICE_SOURCE_MANUAL.to_string(),
+    };
+    product_price_repository::create_price(conn, &opening_price)?;
+    Ok(product)
 }
 
 pub fn get_product_by_id(
@@ -22,6 +139,237 @@ pub fn get_product_by_id(
         .optional()
 }
 
+/// Read the product and lock its row until the transaction ends, so changes to what it caches,
+/// such as its rating, happen one at a time.
+pub fn get_product_for_update(
+    conn: &mut PgConnection,
+    product_id: Uuid,
+) -> QueryResult<Option<Product>> {
+    products::table
+        .find(product_id)
+        .for_update()
+        .first(conn)
+        .optional()
+}
+
+/// The product named exactly `product_name`, archived or not.
+pub fn get_product_by_name(
+    conn: &mut PgConnection,
+    product_name: &str,
+) -> QueryResult<Option<Product>> {
+    products::table
+        .filter(products::product_name.eq(product_name))
+        .first::<Product>(conn)
+        .optional()
+}
+
+fn filtered(filter: &ProductFilter) -> products::BoxedQuery<'static, Pg> {
+    let mut query = products::table
+        .filter(products::archived_at.is_null())
+        .into_boxed();
+    if let Some(min_price) = &filter.min_price {
+        query = query.filter(products::price.ge(min_price.clone()));
+    }
+    if let Some(max_price) = &filter.max_price {
+        query = query.filter(products::price.le(max_price.clone()));
+    }
+    if filter.in_stock_only {
+        // Stock held by unexpired cart reservations is not available
+        query = query.filter(sql::<Bool>(
+            "products.stock > COALESCE(( \
+                 SELECT sum(sr.quantity) \
+                 FROM stock_reservations sr JOIN product_variants v ON v.id = sr.variant_id \
+                 WHERE v.product_id = products.id AND sr.expires_at > now()), 0)",
+        ));
+    }
+    if let Some(prefix) = &filter.name_prefix {
+        let pattern = format!("{}%", escape_like(&prefix.to_lowercase()));
+        query = query.filter(lower(products::product_name).like(pattern));
+    }
+    for (attribute_id, values) in &filter.attributes {
+        query = query.filter(
+            products::id.eq_any(
+                product_attributes::table
+                    .filter(product_attributes::attribute_id.eq(*attribute_id))
+                    .filter(product_attributes::value.eq_any(values.clone()))
+                    .select(product_attributes::product_id),
+            ),
+        );
+    }
+    query
+}
+
+/// How many products matching `filter` have each attribute value, as
+/// `(attribute_id, value, count)`.
+pub fn attribute_facets(
+    conn: &mut PgConnection,
+    filter: &ProductFilter,
+) -> QueryResult<Vec<(Uuid, Value, i64)>> {
+    product_attributes::table
+        .filter(product_attributes::product_id.eq_any(filtered(filter).select(products::id)))
+        .group_by((product_attributes::attribute_id, product_attributes::value))
+        .select((
+            product_attributes::attribute_id,
+            product_attributes::value,
+            diesel::dsl::count_star(),
+        ))
+        .load(conn)
+}
+
+pub fn count_products(conn: &mut PgConnection, filter: &ProductFilter) -> QueryResult<i64> {
+    filtered(filter).count().get_result(conn)
+}
+
+/// `column > value OR (column = value AND id > cursor id)`, or `<` when descending.
+macro_rules! after_key {
+    ($query:expr, $column:expr, $value:expr, $id:expr, $cmp:ident) => {
+        $query.filter(
+            $column
+                .$cmp($value.clone())
+                .or($column.eq($value.clone()).and(products::id.$cmp($id))),
+        )
+    };
+}
+
+/// One page of products matching `filter`. With a cursor, `offset` is normally zero and the
+/// page starts right after the cursor's product.
+pub fn list_products(
+    conn: &mut PgConnection,
+    filter: &ProductFilter,
+    sort: ProductSort,
+    order: SortOrder,
+    after: Option<&ProductCursor>,
+    offset: i64,
+    limit: i64,
+) -> QueryResult<Vec<Product>> {
+    let mut query = filtered(filter);
+
+    if let Some(cursor) = after {
+        let id = cursor.id;
+        query = match (&cursor.key, order) {
+            (ProductSortKey::Price(v), SortOrder::Asc) => {
+                after_key!(query, products::price, v, id, gt)
+            }
+            (ProductSortKey::Price(v), SortOrder::Desc) => {
+                after_key!(query, products::price, v, id, lt)
+            }
+            (ProductSortKey::Name(v), SortOrder::Asc) => {
+                after_key!(query, products::product_name, v, id, gt)
+            }
+            (ProductSortKey::Name(v), SortOrder::Desc) => {
+                after_key!(query, products::product_name, v, id, lt)
+            }
+            (ProductSortKey::CreatedAt(v), SortOrder::Asc) => {
+                after_key!(query, products::created_at, v, id, gt)
+            }
+            (ProductSortKey::CreatedAt(v), SortOrder::Desc) => {
+                after_key!(query, products::created_at, v, id, lt)
+            }
+        };
+    }
+
+    query = match (sort, order) {
+        (ProductSort::Price, SortOrder::Asc) => {
+            query.order((products::price.asc(), products::id.asc()))
+        }
+        (ProductSort::Price, SortOrder::Desc) => {
+            query.order((products::price.desc(), products::id.desc()))
+        }
+        (ProductSort::Name, SortOrder::Asc) => {
+            query.order((products::product_name.asc(), products::id.asc()))
+        }
+        (ProductSort::Name, SortOrder::Desc) => {
+            query.order((products::product_name.desc(), products::id.desc()))
+        }
+        (ProductSort::CreatedAt, SortOrder::Asc) => {
+            query.order((products::created_at.asc(), products::id.asc()))
+        }
+        (ProductSort::CreatedAt, SortOrder::Desc) => {
+            query.order((products::created_at.desc(), products::id.desc()))
+        }
+    };
+
+    query.offset(offset).limit(limit).load(conn)
+}
+
+/// Text search configuration used by `products.search_document`; queries must match it.
+const SEARCH_CONFIG: &str = "english";
+
+const NAME_HIGHLIGHT_OPTIONS: &str = "StartSel=<mark>, StopSel=</mark>, HighlightAll=true";
+const SNIPPET_OPTIONS: &str = "StartSel=<mark>, StopSel=</mark>, MinWords=10, MaxWords=30, MaxFragments=2, FragmentDelimiter=\" … \"";
+
+#[derive(QueryableByName)]
+struct SearchCount {
+    #[diesel(sql_type = BigInt)]
+    total: i64,
+}
+
+/// Products matching `tsquery` (in `to_tsquery` syntax), best match first.
+pub fn search_products(
+    conn: &mut PgConnection,
+    tsquery: &str,
+    offset: i64,
+    limit: i64,
+) -> QueryResult<Vec<ProductSearchHit>> {
+    diesel::sql_query(format!(
+        "SELECT p.id, p.product_name, p.product_description, p.price, p.stock, p.created_at, \
+                p.archived_at, p.rating_count, p.rating_average, \
+                ts_rank_cd(p.search_document, q.query) AS rank, \
+                ts_headline('{SEARCH_CONFIG}', p.product_name, q.query, $2) AS name_highlight, \
+                CASE WHEN p.product_description IS NULL THEN NULL \
+                     ELSE ts_headline('{SEARCH_CONFIG}', p.product_description, q.query, $3) \
+                END AS description_snippet \
+         FROM products p, to_tsquery('{SEARCH_CONFIG}', $1) AS q(query) \
+         WHERE p.search_document @@ q.query AND p.archived_at IS NULL \
+         ORDER BY rank DESC, p.id \
+         OFFSET $4 LIMIT $5"
+    ))
+    .bind::<Text, _>(tsquery)
+    .bind::<Text, _>(NAME_HIGHLIGHT_OPTIONS)
+    .bind::<Text, _>(SNIPPET_OPTIONS)
+    .bind::<BigInt, _>(offset)
+    .bind::<BigInt, _>(limit)
+    .load(conn)
+}
+
+pub fn count_search_matches(conn: &mut PgConnection, tsquery: &str) -> QueryResult<i64> {
+    diesel::sql_query(format!(
+        "SELECT count(*) AS total \
+         FROM products p, to_tsquery('{SEARCH_CONFIG}', $1) AS q(query) \
+         WHERE p.search_document @@ q.query AND p.archived_at IS NULL"
+    ))
+    .bind::<Text, _>(tsquery)
+    .get_result::<SearchCount>(conn)
+    .map(|row| row.total)
+}
+
+/// Product names that resemble `input` with a trigram word similarity of at least `threshold`,
+/// closest first. `input` is compared against the best-matching part of each name, so short
+/// or misspelt prefixes still find long names. Must run inside a transaction, as the threshold
+/// is set for the current transaction only.
+pub fn suggest_product_names(
+    conn: &mut PgConnection,
+    input: &str,
+    threshold: f32,
+    limit: i64,
+) -> QueryResult<Vec<ProductSuggestion>> {
+    // `<%` only uses the trigram index with the threshold from this setting
+    diesel::sql_query("SELECT set_config('pg_trgm.word_similarity_threshold', $1, true)")
+        .bind::<Text, _>(threshold.to_string())
+        .execute(conn)?;
+
+    diesel::sql_query(
+        "SELECT id, product_name, word_similarity($1, product_name) AS similarity \
+         FROM products \
+         WHERE $1 <% product_name AND archived_at IS NULL \
+         ORDER BY similarity DESC, similarity($1, product_name) DESC, product_name, id \
+         LIMIT $2",
+    )
+    .bind::<Text, _>(input)
+    .bind::<BigInt, _>(limit)
+    .load(conn)
+}
+
 pub fn update_product(
     conn: &mut PgConnection,
     product_id: Uuid,
@@ -32,6 +380,24 @@ pub fn update_product(
         .get_result(conn)
 }
 
+/// Withdraw a product from sale. Returns 0 if it does not exist or is already archived.
+pub fn archive_product(conn: &mut PgConnection, product_id: Uuid) -> QueryResult<usize> {
+    diesel::update(
+        products::table
+            .find(product_id)
+            .filter(products::archived_at.is_null()),
+    )
+    .set(products::archived_at.eq(now))
+    .execute(conn)
+}
+
+pub fn restore_product(conn: &mut PgConnection, product_id: Uuid) -> QueryResult<usize> {
+    diesel::update(products::table.find(product_id))
+        .set(products::archived_at.eq(None::<DateTime<Utc>>))
+        .execute(conn)
+}
+
+/// Remove the row for good; variants and links go with it. Its cart lines must be gone first.
 pub fn delete_product(conn: &mut PgConnection, product_id: Uuid) -> QueryResult<usize> {
     diesel::delete(products::table.find(product_id)).execute(conn)
 }
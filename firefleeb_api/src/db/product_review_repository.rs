@@ -0,0 +1,123 @@
+use diesel::dsl::now;
+use diesel::prelude::*;
+use diesel::sql_types::Uuid as SqlUuid;
+use diesel::{PgConnection, QueryResult};
+use uuid::Uuid;
+
+use crate::models::product_review::{NewProductReview, ProductReview, REVIEW_APPROVED};
+use crate::models::stock_movement::MOVEMENT_SALE;
+use crate::schema::{carts, product_reviews, stock_movements};
+
+/// Narrows reviews; unset fields match everything.
+#[derive(Debug, Clone, Default)]
+pub struct ReviewFilter {
+    pub product_id: Option<Uuid>,
+    pub status: Option<String>,
+    pub verified_only: bool,
+}
+
+/// Fails with a unique violation when the user already reviewed the product.
+pub fn create_review(
+    conn: &mut PgConnection,
+    new_review: &NewProductReview,
+) -> QueryResult<ProductReview> {
+    diesel::insert_into(product_reviews::table)
+        .values(new_review)
+        .get_result(conn)
+}
+
+/// Whether a checkout of the user's sold them the product. Goes by the sales in the stock ledger,
+/// since a cart line alone does not prove the product was paid for.
+pub fn has_purchased(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    product_id: Uuid,
+) -> QueryResult<bool> {
+    diesel::select(diesel::dsl::exists(
+        stock_movements::table
+            .inner_join(carts::table)
+            .filter(carts::user_id.eq(user_id))
+            .filter(stock_movements::movement_type.eq(MOVEMENT_SALE))
+            .filter(stock_movements::product_id.eq(product_id)),
+    ))
+    .get_result(conn)
+}
+
+fn filtered<'a>(filter: &ReviewFilter) -> product_reviews::BoxedQuery<'a, diesel::pg::Pg> {
+    let mut query = product_reviews::table.into_boxed();
+    if let Some(product_id) = filter.product_id {
+        query = query.filter(product_reviews::product_id.eq(product_id));
+    }
+    if let Some(status) = &filter.status {
+        query = query.filter(product_reviews::status.eq(status.clone()));
+    }
+    if filter.verified_only {
+        query = query.filter(product_reviews::verified_purchase.eq(true));
+    }
+    query
+}
+
+/// A page of reviews, newest first.
+pub fn list_reviews(
+    conn: &mut PgConnection,
+    filter: &ReviewFilter,
+    offset: i64,
+    limit: i64,
+) -> QueryResult<Vec<ProductReview>> {
+    filtered(filter)
+        .order((
+            product_reviews::created_at.desc(),
+            product_reviews::id.desc(),
+        ))
+        .offset(offset)
+        .limit(limit)
+        .load(conn)
+}
+
+pub fn count_reviews(conn: &mut PgConnection, filter: &ReviewFilter) -> QueryResult<i64> {
+    filtered(filter).count().get_result(conn)
+}
+
+pub fn get_review(conn: &mut PgConnection, review_id: Uuid) -> QueryResult<Option<ProductReview>> {
+    product_reviews::table
+        .find(review_id)
+        .first(conn)
+        .optional()
+}
+
+/// Products the user has reviewed, in id order so their rows can be locked without deadlocks.
+pub fn reviewed_product_ids(conn: &mut PgConnection, user_id: Uuid) -> QueryResult<Vec<Uuid>> {
+    product_reviews::table
+        .filter(product_reviews::user_id.eq(user_id))
+        .select(product_reviews::product_id)
+        .order(product_reviews::product_id)
+        .load(conn)
+}
+
+pub fn set_review_status(
+    conn: &mut PgConnection,
+    review_id: Uuid,
+    status: &str,
+) -> QueryResult<Option<ProductReview>> {
+    diesel::update(product_reviews::table.find(review_id))
+        .set((
+            product_reviews::status.eq(status),
+            product_reviews::updated_at.eq(now),
+        ))
+        .get_result(conn)
+        .optional()
+}
+
+/// Recompute `products.rating_count` and `products.rating_average` from the product's approved
+/// reviews. Call after any change to which of its reviews are approved, holding the product's
+/// row lock from [`crate::db::product_repository::get_product_for_update`] since before that change.
+pub fn sync_product_rating(conn: &mut PgConnection, product_id: Uuid) -> QueryResult<usize> {
+    diesel::sql_query(format!(
+        "UPDATE products SET (rating_count, rating_average) = ( \
+             SELECT COUNT(*), ROUND(AVG(rating), 2) FROM product_reviews \
+             WHERE product_id = $1 AND status = '{REVIEW_APPROVED}') \
+         WHERE id = $1"
+    ))
+    .bind::<SqlUuid, _>(product_id)
+    .execute(conn)
+}
@@ -0,0 +1,141 @@
+use diesel::prelude::*;
+use diesel::sql_types::Uuid as SqlUuid;
+use diesel::{PgConnection, QueryResult};
+use uuid::Uuid;
+
+use crate::models::product_variant::{NewProductVariant, ProductVariant, UpdateProductVariant};
+use crate::schema::product_variants;
+
+/// SKU given to a product's default variant until staff set their own.
+pub fn default_sku(product_id: Uuid) -> String {
+    format!("SKU-{}", product_id.simple().to_string().to_uppercase())
+}
+
+pub fn create_variant(
+    conn: &mut PgConnection,
+    new_variant: &NewProductVariant,
+) -> QueryResult<ProductVariant> {
+    diesel::insert_into(product_variants::table)
+        .values(new_variant)
+        .get_result(conn)
+}
+
+pub fn get_variant_by_id(
+    conn: &mut PgConnection,
+    variant_id: Uuid,
+) -> QueryResult<Option<ProductVariant>> {
+    product_variants::table
+        .find(variant_id)
+        .first(conn)
+        .optional()
+}
+
+/// Read the variant and lock its row until the transaction ends, so stock checks and changes
+/// against it happen one at a time.
+pub fn get_variant_for_update(
+    conn: &mut PgConnection,
+    variant_id: Uuid,
+) -> QueryResult<Option<ProductVariant>> {
+    product_variants::table
+        .find(variant_id)
+        .for_update()
+        .first(conn)
+        .optional()
+}
+
+pub fn get_default_variant(
+    conn: &mut PgConnection,
+    product_id: Uuid,
+) -> QueryResult<Option<ProductVariant>> {
+    product_variants::table
+        .filter(product_variants::product_id.eq(product_id))
+        .filter(product_variants::is_default.eq(true))
+        .first(conn)
+        .optional()
+}
+
+/// Variants of the given products, each product's default first and the rest oldest first.
+pub fn list_variants_for_products(
+    conn: &mut PgConnection,
+    product_ids: &[Uuid],
+) -> QueryResult<Vec<ProductVariant>> {
+    product_variants::table
+        .filter(product_variants::product_id.eq_any(product_ids))
+        .order((
+            product_variants::product_id,
+            product_variants::is_default.desc(),
+            product_variants::created_at,
+            product_variants::id,
+        ))
+        .load(conn)
+}
+
+/// Update a variant of `product_id`; `None` when the product has no such variant.
+pub fn update_variant(
+    conn: &mut PgConnection,
+    product_id: Uuid,
+    variant_id: Uuid,
+    updated: &UpdateProductVariant,
+) -> QueryResult<Option<ProductVariant>> {
+    diesel::update(
+        product_variants::table
+            .filter(product_variants::id.eq(variant_id))
+            .filter(product_variants::product_id.eq(product_id)),
+    )
+    .set(updated)
+    .get_result(conn)
+    .optional()
+}
+
+/// Make `variant_id` the product's default, clearing the flag on the previous one.
+pub fn set_default_variant(
+    conn: &mut PgConnection,
+    product_id: Uuid,
+    variant_id: Uuid,
+) -> QueryResult<usize> {
+    diesel::update(
+        product_variants::table
+            .filter(product_variants::product_id.eq(product_id))
+            .filter(product_variants::is_default.eq(true))
+            .filter(product_variants::id.ne(variant_id)),
+    )
+    .set(product_variants::is_default.eq(false))
+    .execute(conn)?;
+
+    diesel::update(product_variants::table.find(variant_id))
+        .set(product_variants::is_default.eq(true))
+        .execute(conn)
+}
+
+/// Add `delta` to the variant's stock, returning the new stock.
+pub fn adjust_stock(conn: &mut PgConnection, variant_id: Uuid, delta: i32) -> QueryResult<i32> {
+    diesel::update(product_variants::table.find(variant_id))
+        .set(product_variants::stock.eq(product_variants::stock + delta))
+        .returning(product_variants::stock)
+        .get_result(conn)
+}
+
+pub fn delete_variant(
+    conn: &mut PgConnection,
+    product_id: Uuid,
+    variant_id: Uuid,
+) -> QueryResult<usize> {
+    diesel::delete(
+        product_variants::table
+            .filter(product_variants::id.eq(variant_id))
+            .filter(product_variants::product_id.eq(product_id)),
+    )
+    .execute(conn)
+}
+
+/// Recompute `products.stock` as the sum of the product's variant stock, capped at the
+/// largest value the column holds.
+pub fn sync_product_stock(conn: &mut PgConnection, product_id: Uuid) -> QueryResult<usize> {
+    diesel::sql_query(
+        "UPDATE products SET stock = LEAST(COALESCE( \
+             (SELECT SUM(stock) FROM product_variants WHERE product_id = $1), 0), 2147483647)::int \
+         WHERE id = $1",
+    )
+    .bind::<SqlUuid, _>(product_id)
+    .execute(conn)
+}
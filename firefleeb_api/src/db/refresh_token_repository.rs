@@ -0,0 +1,65 @@
+use chrono::Utc;
+use uuid::Uuid;
+
+use diesel::prelude::*;
+use diesel::{PgConnection, QueryResult};
+
+use crate::models::refresh_token::{NewRefreshToken, RefreshToken};
+use crate::schema::refresh_tokens;
+
+pub fn create_refresh_token(
+    conn: &mut PgConnection,
+    new_token: &NewRefreshToken,
+) -> QueryResult<RefreshToken> {
+    diesel::insert_into(refresh_tokens::table)
+        .values(new_token)
+        .get_result(conn)
+}
+
+/// Look up a token by hash and lock the row until the surrounding transaction ends.
+pub fn get_by_hash_for_update(
+    conn: &mut PgConnection,
+    token_hash: &str,
+) -> QueryResult<Option<RefreshToken>> {
+    refresh_tokens::table
+        .filter(refresh_tokens::token_hash.eq(token_hash))
+        .for_update()
+        .first::<RefreshToken>(conn)
+        .optional()
+}
+
+/// Mark a single token as used, recording the token that replaced it (if any).
+pub fn revoke_token(
+    conn: &mut PgConnection,
+    token_id: Uuid,
+    replaced_by: Option<Uuid>,
+) -> QueryResult<usize> {
+    diesel::update(refresh_tokens::table.find(token_id))
+        .set((
+            refresh_tokens::revoked_at.eq(Utc::now()),
+            refresh_tokens::replaced_by.eq(replaced_by),
+        ))
+        .execute(conn)
+}
+
+/// Revoke every still-active token that descends from the same login.
+pub fn revoke_family(conn: &mut PgConnection, family_id: Uuid) -> QueryResult<usize> {
+    diesel::update(
+        refresh_tokens::table
+            .filter(refresh_tokens::family_id.eq(family_id))
+            .filter(refresh_tokens::revoked_at.is_null()),
+    )
+    .set(refresh_tokens::revoked_at.eq(Utc::now()))
+    .execute(conn)
+}
+
+/// Revoke every active token a user holds, e.g. after a password reset.
+pub fn revoke_all_for_user(conn: &mut PgConnection, user_id: Uuid) -> QueryResult<usize> {
+    diesel::update(
+        refresh_tokens::table
+            .filter(refresh_tokens::user_id.eq(user_id))
+            .filter(refresh_tokens::revoked_at.is_null()),
+    )
+    .set(refresh_tokens::revoked_at.eq(Utc::now()))
+    .execute(conn)
+}
@@ -0,0 +1,99 @@
+use diesel::prelude::*;
+use diesel::{PgConnection, QueryResult};
+use uuid::Uuid;
+
+use crate::db::warehouse_repository;
+use crate::models::product_variant::ProductVariant;
+use crate::models::stock_movement::{
+    MOVEMENT_RECEIPT, NewStockMovement, OPENING_STOCK_REASON, StockMovement,
+};
+use crate::schema::stock_movements;
+
+/// Narrows a product's movements; unset fields match everything.
+#[derive(Debug, Clone, Default)]
+pub struct MovementFilter {
+    pub variant_id: Option<Uuid>,
+    pub warehouse_id: Option<Uuid>,
+    pub movement_type: Option<String>,
+}
+
+/// Log a movement that left the variant at `stock_after`.
+pub fn create_movement(
+    conn: &mut PgConnection,
+    movement: &NewStockMovement,
+    stock_after: i32,
+) -> QueryResult<StockMovement> {
+    diesel::insert_into(stock_movements::table)
+        .values((movement, stock_movements::stock_after.eq(stock_after)))
+        .get_result(conn)
+}
+
+/// Put a newly created variant's stock into the default warehouse and log it as its opening
+/// receipt. The variant row already carries the stock, so only the warehouse level changes.
+pub fn log_opening_stock(
+    conn: &mut PgConnection,
+    variant: &ProductVariant,
+    actor_id: Option<Uuid>,
+) -> QueryResult<Option<StockMovement>> {
+    if variant.stock <= 0 {
+        return Ok(None);
+    }
+    let warehouse = warehouse_repository::get_default_warehouse(conn)?;
+    warehouse_repository::adjust_stock(conn, warehouse.id, variant.id, variant.stock)?;
+    let opening_stock = NewStockMovement {
+        product_id: variant.product_id,
+        variant_id: Some(variant.id),
+        warehouse_id: warehouse.id,
+        movement_type: MOVEMENT_RECEIPT.to_string(),
+        quantity: variant.stock,
+        reason: OPENING_STOCK_REASON.to_string(),
+        actor_id,
+        cart_id: None,
+    };
+    create_movement(conn, &opening_stock, variant.stock).map(Some)
+}
+
+fn filtered<'a>(
+    product_id: Uuid,
+    filter: &MovementFilter,
+) -> stock_movements::BoxedQuery<'a, diesel::pg::Pg> {
+    let mut query = stock_movements::table
+        .filter(stock_movements::product_id.eq(product_id))
+        .into_boxed();
+    if let Some(variant_id) = filter.variant_id {
+        query = query.filter(stock_movements::variant_id.eq(variant_id));
+    }
+    if let Some(warehouse_id) = filter.warehouse_id {
+        query = query.filter(stock_movements::warehouse_id.eq(warehouse_id));
+    }
+    if let Some(movement_type) = &filter.movement_type {
+        query = query.filter(stock_movements::movement_type.eq(movement_type.clone()));
+    }
+    query
+}
+
+/// A page of the product's movements, newest first.
+pub fn list_movements(
+    conn: &mut PgConnection,
+    product_id: Uuid,
+    filter: &MovementFilter,
+    offset: i64,
+    limit: i64,
+) -> QueryResult<Vec<StockMovement>> {
+    filtered(product_id, filter)
+        .order((
+            stock_movements::created_at.desc(),
+            stock_movements::id.desc(),
+        ))
+        .offset(offset)
+        .limit(limit)
+        .load(conn)
+}
+
+pub fn count_movements(
+    conn: &mut PgConnection,
+    product_id: Uuid,
+    filter: &MovementFilter,
+) -> QueryResult<i64> {
+    filtered(product_id, filter).count().get_result(conn)
+}
@@ -0,0 +1,80 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+use diesel::{PgConnection, QueryResult};
+use uuid::Uuid;
+
+use crate::models::stock_reservation::{NewStockReservation, StockReservation};
+use crate::schema::{cart_items, product_variants, stock_reservations};
+
+/// Quantity of the variant held by reservations still active at `at`, leaving out the one of
+/// `except_item` so a cart line can be re-reserved without counting against itself.
+pub fn reserved_quantity(
+    conn: &mut PgConnection,
+    variant_id: Uuid,
+    except_item: Option<Uuid>,
+    at: DateTime<Utc>,
+) -> QueryResult<i64> {
+    let mut query = stock_reservations::table
+        .filter(stock_reservations::variant_id.eq(variant_id))
+        .filter(stock_reservations::expires_at.gt(at))
+        .select(diesel::dsl::sum(stock_reservations::quantity))
+        .into_boxed();
+    if let Some(item_id) = except_item {
+        query = query.filter(stock_reservations::cart_item_id.ne(item_id));
+    }
+    Ok(query.first::<Option<i64>>(conn)?.unwrap_or(0))
+}
+
+/// Quantity of the given products held by unexpired reservations at `at`, per product. Products
+/// without reservations are left out.
+pub fn reserved_by_product(
+    conn: &mut PgConnection,
+    product_ids: &[Uuid],
+    at: DateTime<Utc>,
+) -> QueryResult<Vec<(Uuid, Option<i64>)>> {
+    stock_reservations::table
+        .inner_join(product_variants::table)
+        .filter(product_variants::product_id.eq_any(product_ids))
+        .filter(stock_reservations::expires_at.gt(at))
+        .group_by(product_variants::product_id)
+        .select((
+            product_variants::product_id,
+            diesel::dsl::sum(stock_reservations::quantity),
+        ))
+        .load(conn)
+}
+
+/// Create or replace the reservation of a cart line.
+pub fn upsert_reservation(
+    conn: &mut PgConnection,
+    reservation: &NewStockReservation,
+) -> QueryResult<StockReservation> {
+    diesel::insert_into(stock_reservations::table)
+        .values(reservation)
+        .on_conflict(stock_reservations::cart_item_id)
+        .do_update()
+        .set((
+            stock_reservations::variant_id.eq(excluded(stock_reservations::variant_id)),
+            stock_reservations::quantity.eq(excluded(stock_reservations::quantity)),
+            stock_reservations::expires_at.eq(excluded(stock_reservations::expires_at)),
+        ))
+        .get_result(conn)
+}
+
+/// Release every reservation held by the cart's lines.
+pub fn delete_for_cart(conn: &mut PgConnection, cart_id: Uuid) -> QueryResult<usize> {
+    let cart_item_ids = cart_items::table
+        .filter(cart_items::cart_id.eq(cart_id))
+        .select(cart_items::id);
+    diesel::delete(
+        stock_reservations::table.filter(stock_reservations::cart_item_id.eq_any(cart_item_ids)),
+    )
+    .execute(conn)
+}
+
+/// Delete reservations that expired at or before `at`, returning how many were released.
+pub fn delete_expired(conn: &mut PgConnection, at: DateTime<Utc>) -> QueryResult<usize> {
+    diesel::delete(stock_reservations::table.filter(stock_reservations::expires_at.le(at)))
+        .execute(conn)
+}
@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
 use diesel::prelude::*;
@@ -6,6 +7,7 @@ use diesel::{PgConnection, QueryResult};
 use crate::models::user::{NewUser, UpdateUser, User};
 use crate::schema::users;
 use crate::types::email::Email;
+use crate::types::role::Role;
 
 pub fn create_user(conn: &mut PgConnection, new_user: &NewUser) -> QueryResult<User> {
     diesel::insert_into(users::table)
@@ -37,6 +39,23 @@ pub fn update_user(
         .get_result(conn)
 }
 
+pub fn set_user_role(conn: &mut PgConnection, user_id: Uuid, role: Role) -> QueryResult<User> {
+    diesel::update(users::table.find(user_id))
+        .set(users::role.eq(role))
+        .get_result(conn)
+}
+
+/// Record when the current address was confirmed; `None` marks it unverified again.
+pub fn set_email_verified_at(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    verified_at: Option<DateTime<Utc>>,
+) -> QueryResult<User> {
+    diesel::update(users::table.find(user_id))
+        .set(users::email_verified_at.eq(verified_at))
+        .get_result(conn)
+}
+
 pub fn delete_user(conn: &mut PgConnection, user_id: Uuid) -> QueryResult<usize> {
     diesel::delete(users::table.find(user_id)).execute(conn)
 }
@@ -0,0 +1,134 @@
+use diesel::prelude::*;
+use diesel::{PgConnection, QueryResult};
+use uuid::Uuid;
+
+use crate::models::warehouse::{NewWarehouse, UpdateWarehouse, Warehouse, WarehouseStock};
+use crate::schema::{product_variants, warehouse_stock, warehouses};
+
+pub fn create_warehouse(
+    conn: &mut PgConnection,
+    new_warehouse: &NewWarehouse,
+) -> QueryResult<Warehouse> {
+    diesel::insert_into(warehouses::table)
+        .values(new_warehouse)
+        .get_result(conn)
+}
+
+pub fn get_warehouse_by_id(
+    conn: &mut PgConnection,
+    warehouse_id: Uuid,
+) -> QueryResult<Option<Warehouse>> {
+    warehouses::table.find(warehouse_id).first(conn).optional()
+}
+
+/// The warehouse that receives stock entered without naming one. The migrations create it and
+/// it can only be moved, never cleared, so it always exists.
+pub fn get_default_warehouse(conn: &mut PgConnection) -> QueryResult<Warehouse> {
+    warehouses::table
+        .filter(warehouses::is_default.eq(true))
+        .first(conn)
+}
+
+/// Every warehouse, the default first and the rest by code.
+pub fn list_warehouses(conn: &mut PgConnection) -> QueryResult<Vec<Warehouse>> {
+    warehouses::table
+        .order((
+            warehouses::is_default.desc(),
+            warehouses::code,
+            warehouses::id,
+        ))
+        .load(conn)
+}
+
+pub fn update_warehouse(
+    conn: &mut PgConnection,
+    warehouse_id: Uuid,
+    updated: &UpdateWarehouse,
+) -> QueryResult<Option<Warehouse>> {
+    diesel::update(warehouses::table.find(warehouse_id))
+        .set(updated)
+        .get_result(conn)
+        .optional()
+}
+
+/// Make `warehouse_id` the default, clearing the flag on the previous one.
+pub fn set_default_warehouse(conn: &mut PgConnection, warehouse_id: Uuid) -> QueryResult<usize> {
+    diesel::update(
+        warehouses::table
+            .filter(warehouses::is_default.eq(true))
+            .filter(warehouses::id.ne(warehouse_id)),
+    )
+    .set(warehouses::is_default.eq(false))
+    .execute(conn)?;
+
+    diesel::update(warehouses::table.find(warehouse_id))
+        .set(warehouses::is_default.eq(true))
+        .execute(conn)
+}
+
+/// Add `delta` to the variant's stock in the warehouse, returning the new level. The caller
+/// makes sure it stays non-negative.
+pub fn adjust_stock(
+    conn: &mut PgConnection,
+    warehouse_id: Uuid,
+    variant_id: Uuid,
+    delta: i32,
+) -> QueryResult<i32> {
+    // The row to insert is checked before the conflict is, so only additions can upsert
+    if delta < 0 {
+        return diesel::update(warehouse_stock::table.find((warehouse_id, variant_id)))
+            .set(warehouse_stock::stock.eq(warehouse_stock::stock + delta))
+            .returning(warehouse_stock::stock)
+            .get_result(conn);
+    }
+    diesel::insert_into(warehouse_stock::table)
+        .values((
+            warehouse_stock::warehouse_id.eq(warehouse_id),
+            warehouse_stock::variant_id.eq(variant_id),
+            warehouse_stock::stock.eq(delta),
+        ))
+        .on_conflict((warehouse_stock::warehouse_id, warehouse_stock::variant_id))
+        .do_update()
+        .set(warehouse_stock::stock.eq(warehouse_stock::stock + delta))
+        .returning(warehouse_stock::stock)
+        .get_result(conn)
+}
+
+/// The variant's stock in the warehouse; zero when it has never been stocked there.
+pub fn stock_level(
+    conn: &mut PgConnection,
+    warehouse_id: Uuid,
+    variant_id: Uuid,
+) -> QueryResult<i32> {
+    Ok(warehouse_stock::table
+        .find((warehouse_id, variant_id))
+        .select(warehouse_stock::stock)
+        .first(conn)
+        .optional()?
+        .unwrap_or(0))
+}
+
+/// Non-zero stock levels of the given variants.
+pub fn stock_levels_for_variants(
+    conn: &mut PgConnection,
+    variant_ids: &[Uuid],
+) -> QueryResult<Vec<WarehouseStock>> {
+    warehouse_stock::table
+        .filter(warehouse_stock::variant_id.eq_any(variant_ids))
+        .filter(warehouse_stock::stock.gt(0))
+        .order((warehouse_stock::variant_id, warehouse_stock::warehouse_id))
+        .load(conn)
+}
+
+/// Non-zero stock levels of every variant of the given products, with the product each belongs to.
+pub fn stock_levels_for_products(
+    conn: &mut PgConnection,
+    product_ids: &[Uuid],
+) -> QueryResult<Vec<(Uuid, WarehouseStock)>> {
+    warehouse_stock::table
+        .inner_join(product_variants::table)
+        .filter(product_variants::product_id.eq_any(product_ids))
+        .filter(warehouse_stock::stock.gt(0))
+        .select((product_variants::product_id, WarehouseStock::as_select()))
+        .load(conn)
+}
@@ -1,16 +1,34 @@
 use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use serde::Serialize;
 use std::convert::Infallible;
 use std::fmt;
 use warp::Reply;
 use warp::http::StatusCode;
-use warp::reply::{Response, json, with_status};
+use warp::http::header::RETRY_AFTER;
+use warp::reply::{Response, json, with_header, with_status};
+
+/// One failed rule of a multi-rule check, e.g. the password policy.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RuleViolation {
+    pub rule: &'static str,
+    pub message: String,
+}
 
 #[derive(Debug, Clone)]
 pub enum AppError {
     Validation(String),
+    /// A validation failure that lists every rule the input broke.
+    RuleViolations(String, Vec<RuleViolation>),
     Unauthorized(String),
+    Forbidden(String),
     Conflict(String),
     NotFound(String),
+    /// An upload exceeded its size limit.
+    PayloadTooLarge(String),
+    /// An upload was not one of the accepted file types.
+    UnsupportedMediaType(String),
+    /// Rejected because of rate limiting; carries the number of seconds until a retry may succeed.
+    TooManyRequests(String, u64),
     Db(String),
     Internal(String),
 }
@@ -29,9 +47,14 @@ impl fmt::Display for AppError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             AppError::Validation(msg)
+            | AppError::RuleViolations(msg, _)
             | AppError::Unauthorized(msg)
+            | AppError::Forbidden(msg)
             | AppError::Conflict(msg)
             | AppError::NotFound(msg)
+            | AppError::PayloadTooLarge(msg)
+            | AppError::UnsupportedMediaType(msg)
+            | AppError::TooManyRequests(msg, _)
             | AppError::Db(msg)
             | AppError::Internal(msg) => write!(f, "{msg}"),
         }
@@ -46,17 +69,30 @@ impl warp::reject::Reject for AppError {}
 impl Reply for AppError {
     fn into_response(self) -> Response {
         let code = match self {
-            AppError::Validation(_) => StatusCode::BAD_REQUEST,
+            AppError::Validation(_) | AppError::RuleViolations(..) => StatusCode::BAD_REQUEST,
             AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
             AppError::Conflict(_) => StatusCode::CONFLICT,
             AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            AppError::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            AppError::TooManyRequests(..) => StatusCode::TOO_MANY_REQUESTS,
             AppError::Db(_) | AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
         };
-        let body = serde_json::json!({
+        let mut body = serde_json::json!({
             "error": self.to_string(),
             "status": code.as_u16()
         });
-        with_status(json(&body), code).into_response()
+        if let AppError::RuleViolations(_, violations) = &self {
+            body["violations"] = serde_json::json!(violations);
+        }
+        let reply = with_status(json(&body), code);
+        match self {
+            AppError::TooManyRequests(_, retry_after) => {
+                with_header(reply, RETRY_AFTER, retry_after.to_string()).into_response()
+            }
+            _ => reply.into_response(),
+        }
     }
 }
 
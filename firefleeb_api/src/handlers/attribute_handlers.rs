@@ -0,0 +1,58 @@
+use crate::db::PgPool;
+use crate::errors::AppError;
+use crate::handlers::dtos::{
+    AttributeResponse, AttributesResponse, CreateAttributeRequest, UpdateAttributeRequest,
+};
+use crate::models::attribute::{NewAttributeDefinition, UpdateAttributeDefinition};
+use crate::services::attribute_service;
+use serde_json::Value;
+use uuid::Uuid;
+use warp::http::StatusCode;
+use warp::{Reply, reply};
+
+pub async fn create(pool: PgPool, req: CreateAttributeRequest) -> Result<impl Reply, AppError> {
+    let new_definition = NewAttributeDefinition {
+        code: req.code,
+        name: req.name,
+        value_type: req.value_type,
+        allowed_values: req.allowed_values.map(Value::Array),
+    };
+
+    let definition = attribute_service::create_definition(pool, new_definition).await?;
+    Ok(reply::with_status(
+        reply::json(&AttributeResponse::from(definition)),
+        StatusCode::CREATED,
+    ))
+}
+
+pub async fn list(pool: PgPool) -> Result<impl Reply, AppError> {
+    let definitions = attribute_service::list_definitions(pool).await?;
+    Ok(reply::json(&AttributesResponse {
+        attributes: definitions
+            .into_iter()
+            .map(AttributeResponse::from)
+            .collect(),
+    }))
+}
+
+pub async fn update(
+    pool: PgPool,
+    id: Uuid,
+    req: UpdateAttributeRequest,
+) -> Result<impl Reply, AppError> {
+    let update = UpdateAttributeDefinition {
+        name: req.name,
+        allowed_values: req.allowed_values.map(|allowed| allowed.map(Value::Array)),
+    };
+
+    let definition = attribute_service::update_definition(pool, id, update).await?;
+    Ok(reply::json(&AttributeResponse::from(definition)))
+}
+
+pub async fn delete(pool: PgPool, id: Uuid) -> Result<impl Reply, AppError> {
+    attribute_service::delete_definition(pool, id).await?;
+    Ok(reply::with_status(
+        reply::json(&serde_json::json!({ "message": "deleted" })),
+        StatusCode::NO_CONTENT,
+    ))
+}
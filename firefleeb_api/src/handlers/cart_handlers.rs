@@ -1,18 +1,27 @@
+use crate::auth::{AuthConfig, AuthUser};
 use crate::db::PgPool;
 use crate::errors::AppError;
-use crate::handlers::dtos::{CartResponse, CreateCartRequest, UpdateCartRequest};
+use crate::handlers::dtos::{
+    AllocationResponse, CartResponse, CheckoutQuery, CreateCartRequest, UpdateCartRequest,
+};
 use crate::models::cart::UpdateCart;
-use crate::services::cart_service;
+use crate::services::{cart_service, warehouse_service};
+use crate::types::AllocationStrategy;
 use uuid::Uuid;
 use warp::{Reply, reply};
 
-pub async fn create(pool: PgPool, req: CreateCartRequest) -> Result<impl Reply, AppError> {
-    let cart = cart_service::create_default_cart(pool, req.user_id).await?;
+pub async fn create(
+    pool: PgPool,
+    caller: AuthUser,
+    req: CreateCartRequest,
+) -> Result<impl Reply, AppError> {
+    let cart = cart_service::create_default_cart(pool, &caller, req.user_id).await?;
     Ok(reply::json(&CartResponse::from(cart)))
 }
 
 pub async fn update(
     pool: PgPool,
+    caller: AuthUser,
     cart_id: Uuid,
     req: UpdateCartRequest,
 ) -> Result<impl Reply, AppError> {
@@ -21,17 +30,36 @@ pub async fn update(
         cart_total: req.cart_total,
     };
 
-    let cart = cart_service::update_cart(pool, cart_id, updated_cart).await?;
+    let cart = cart_service::update_cart(pool, &caller, cart_id, updated_cart).await?;
     Ok(reply::json(&CartResponse::from(cart)))
 }
 
-pub async fn get(pool: PgPool, id: Uuid) -> Result<impl Reply, AppError> {
-    let cart = cart_service::get_active_by_user_id(pool, id).await?;
+pub async fn checkout(
+    pool: PgPool,
+    auth: AuthConfig,
+    strategy: AllocationStrategy,
+    caller: AuthUser,
+    cart_id: Uuid,
+    query: CheckoutQuery,
+) -> Result<impl Reply, AppError> {
+    let destination = warehouse_service::validate_location(query.latitude, query.longitude)?;
+    let (cart, allocations) =
+        cart_service::checkout(pool, &auth, strategy, &caller, cart_id, destination).await?;
+    let mut response = CartResponse::from(cart);
+    response.allocations = allocations
+        .into_iter()
+        .map(AllocationResponse::from)
+        .collect();
+    Ok(reply::json(&response))
+}
+
+pub async fn get(pool: PgPool, caller: AuthUser, id: Uuid) -> Result<impl Reply, AppError> {
+    let cart = cart_service::get_active_by_user_id(pool, &caller, id).await?;
     Ok(warp::reply::json(&CartResponse::from(cart)))
 }
 
-pub async fn delete(pool: PgPool, id: Uuid) -> Result<impl Reply, AppError> {
-    cart_service::delete_cart(pool, id).await?;
+pub async fn delete(pool: PgPool, caller: AuthUser, id: Uuid) -> Result<impl Reply, AppError> {
+    cart_service::delete_cart(pool, &caller, id).await?;
     Ok(warp::reply::with_status(
         warp::reply::json(&serde_json::json!({"message": "deleted"})),
         warp::http::StatusCode::NO_CONTENT,
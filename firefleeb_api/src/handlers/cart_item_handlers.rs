@@ -1,20 +1,28 @@
+use std::time::Duration;
+
 use uuid::Uuid;
 use warp::{Reply, http::StatusCode, reply};
 
+use crate::auth::AuthUser;
 use crate::db::PgPool;
 use crate::errors::AppError;
 use crate::handlers::dtos::{CreateCartItemRequest, UpdateCartItemRequest};
 use crate::models::cart_item::{CartItemResponse, NewCartItem, UpdateCartItem};
 use crate::services::cart_item_service;
 
-pub async fn list(pool: PgPool, cart_id: Uuid) -> Result<impl Reply, AppError> {
-    let items = cart_item_service::list_items(pool, cart_id).await?;
-    let response: Vec<CartItemResponse> = items.into_iter().map(|i| i.to_response()).collect();
+pub async fn list(pool: PgPool, caller: AuthUser, cart_id: Uuid) -> Result<impl Reply, AppError> {
+    let items = cart_item_service::list_items(pool, &caller, cart_id).await?;
+    let response: Vec<CartItemResponse> = items
+        .iter()
+        .map(|line| line.item.to_response(line.available))
+        .collect();
     Ok(reply::json(&response))
 }
 
 pub async fn create(
     pool: PgPool,
+    reservation_ttl: Duration,
+    caller: AuthUser,
     cart_id: Uuid,
     req: CreateCartItemRequest,
 ) -> Result<impl Reply, AppError> {
@@ -23,17 +31,20 @@ pub async fn create(
         item_id: req.item_id,
         quantity: req.quantity,
         unit_price: req.unit_price,
+        variant_id: None,
     };
 
-    let item = cart_item_service::add_item(pool, new_item).await?;
+    let item = cart_item_service::add_item(pool, &caller, new_item, reservation_ttl).await?;
     Ok(reply::with_status(
-        reply::json(&item.to_response()),
+        reply::json(&item.to_response(true)),
         StatusCode::CREATED,
     ))
 }
 
 pub async fn update(
     pool: PgPool,
+    reservation_ttl: Duration,
+    caller: AuthUser,
     cart_id: Uuid,
     item_id: Uuid,
     req: UpdateCartItemRequest,
@@ -43,8 +54,11 @@ pub async fn update(
         unit_price: req.unit_price,
     };
 
-    let response = match cart_item_service::update_item(pool, cart_id, item_id, updates).await? {
-        Some(item) => reply::json(&item.to_response()).into_response(),
+    let updated =
+        cart_item_service::update_item(pool, &caller, cart_id, item_id, updates, reservation_ttl)
+            .await?;
+    let response = match updated {
+        Some(item) => reply::json(&item.to_response(true)).into_response(),
         None => reply::with_status(
             reply::json(&serde_json::json!({ "message": "cart item removed" })),
             StatusCode::NO_CONTENT,
@@ -55,16 +69,21 @@ pub async fn update(
     Ok(response)
 }
 
-pub async fn delete(pool: PgPool, cart_id: Uuid, item_id: Uuid) -> Result<impl Reply, AppError> {
-    cart_item_service::remove_item(pool, cart_id, item_id).await?;
+pub async fn delete(
+    pool: PgPool,
+    caller: AuthUser,
+    cart_id: Uuid,
+    item_id: Uuid,
+) -> Result<impl Reply, AppError> {
+    cart_item_service::remove_item(pool, &caller, cart_id, item_id).await?;
     Ok(reply::with_status(
         reply::json(&serde_json::json!({ "message": "cart item deleted" })),
         StatusCode::NO_CONTENT,
     ))
 }
 
-pub async fn clear(pool: PgPool, cart_id: Uuid) -> Result<impl Reply, AppError> {
-    cart_item_service::clear_cart(pool, cart_id).await?;
+pub async fn clear(pool: PgPool, caller: AuthUser, cart_id: Uuid) -> Result<impl Reply, AppError> {
+    cart_item_service::clear_cart(pool, &caller, cart_id).await?;
     Ok(reply::with_status(
         reply::json(&serde_json::json!({ "message": "cart cleared" })),
         StatusCode::NO_CONTENT,
@@ -0,0 +1,132 @@
+use crate::db::PgPool;
+use crate::errors::AppError;
+use crate::handlers::dtos::{
+    CategoriesResponse, CategoryResponse, CreateCategoryRequest, ListCategoryProductsQuery,
+    PageLinks, PageResponse, ProductResponse, UpdateCategoryRequest,
+};
+use crate::handlers::paging::{page_limit, page_link, page_offset};
+use crate::handlers::product_handlers::attach_product_details;
+use crate::models::category::UpdateCategory;
+use crate::services::category_service;
+use uuid::Uuid;
+use warp::http::StatusCode;
+use warp::{Reply, reply};
+
+pub async fn create(pool: PgPool, req: CreateCategoryRequest) -> Result<impl Reply, AppError> {
+    let (category, path) = category_service::create_category(pool, req.name, req.parent_id).await?;
+    Ok(reply::with_status(
+        reply::json(&CategoryResponse::new(category, path)),
+        StatusCode::CREATED,
+    ))
+}
+
+pub async fn list(pool: PgPool) -> Result<impl Reply, AppError> {
+    let categories = category_service::list_categories(pool).await?;
+    Ok(reply::json(&CategoriesResponse {
+        categories: categories
+            .into_iter()
+            .map(|(category, path)| CategoryResponse::new(category, path))
+            .collect(),
+    }))
+}
+
+pub async fn get(pool: PgPool, id: Uuid) -> Result<impl Reply, AppError> {
+    let (category, path) = category_service::get_category(pool, id).await?;
+    Ok(reply::json(&CategoryResponse::new(category, path)))
+}
+
+pub async fn update(
+    pool: PgPool,
+    id: Uuid,
+    req: UpdateCategoryRequest,
+) -> Result<impl Reply, AppError> {
+    let update = UpdateCategory {
+        name: req.name,
+        parent_id: req.parent_id,
+    };
+
+    let (category, path) = category_service::update_category(pool, id, update).await?;
+    Ok(reply::json(&CategoryResponse::new(category, path)))
+}
+
+pub async fn delete(pool: PgPool, id: Uuid) -> Result<impl Reply, AppError> {
+    category_service::delete_category(pool, id).await?;
+    Ok(reply::with_status(
+        reply::json(&serde_json::json!({ "message": "deleted" })),
+        StatusCode::NO_CONTENT,
+    ))
+}
+
+pub async fn add_product(
+    pool: PgPool,
+    category_id: Uuid,
+    product_id: Uuid,
+) -> Result<impl Reply, AppError> {
+    category_service::add_product(pool, category_id, product_id).await?;
+    Ok(reply::with_status(
+        reply::json(&serde_json::json!({ "message": "product added" })),
+        StatusCode::NO_CONTENT,
+    ))
+}
+
+pub async fn remove_product(
+    pool: PgPool,
+    category_id: Uuid,
+    product_id: Uuid,
+) -> Result<impl Reply, AppError> {
+    category_service::remove_product(pool, category_id, product_id).await?;
+    Ok(reply::with_status(
+        reply::json(&serde_json::json!({ "message": "product removed" })),
+        StatusCode::NO_CONTENT,
+    ))
+}
+
+pub async fn list_products(
+    pool: PgPool,
+    category_id: Uuid,
+    query: ListCategoryProductsQuery,
+) -> Result<impl Reply, AppError> {
+    let limit = page_limit(query.limit)?;
+    let offset = page_offset(query.offset)?;
+
+    let (products, total) = category_service::list_products(
+        pool.clone(),
+        category_id,
+        query.include_descendants.unwrap_or(false),
+        offset,
+        limit,
+    )
+    .await?;
+
+    let mut links = PageLinks::default();
+    if offset + (products.len() as i64) < total {
+        links.next = Some(products_link(category_id, &query, offset + limit)?);
+    }
+    if offset > 0 {
+        links.prev = Some(products_link(category_id, &query, (offset - limit).max(0))?);
+    }
+
+    let mut items: Vec<ProductResponse> = products.into_iter().map(ProductResponse::from).collect();
+    attach_product_details(pool, &mut items).await?;
+
+    Ok(reply::json(&PageResponse {
+        items,
+        total,
+        limit,
+        offset: Some(offset),
+        next_cursor: None,
+        links,
+    }))
+}
+
+fn products_link(
+    category_id: Uuid,
+    query: &ListCategoryProductsQuery,
+    offset: i64,
+) -> Result<String, AppError> {
+    let page = ListCategoryProductsQuery {
+        offset: Some(offset),
+        ..query.clone()
+    };
+    page_link(&format!("/categories/{category_id}/products"), &page)
+}
@@ -0,0 +1,92 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::models::attribute::AttributeDefinition;
+use crate::services::attribute_service::{AttributeFacet, FacetValue};
+
+#[derive(Debug, Deserialize)]
+pub struct CreateAttributeRequest {
+    /// Lowercase key, e.g. `wattage`; used in product attributes and `attr.<code>` filters.
+    pub code: String,
+    pub name: String,
+    /// `text`, `number` or `boolean`.
+    pub value_type: String,
+    /// The only values products may use; omit to allow any value of the type.
+    pub allowed_values: Option<Vec<Value>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateAttributeRequest {
+    pub name: Option<String>,
+    /// Absent keeps the list; `null` allows any value of the type.
+    #[serde(default, deserialize_with = "super::present")]
+    pub allowed_values: Option<Option<Vec<Value>>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AttributeResponse {
+    pub id: Uuid,
+    pub code: String,
+    pub name: String,
+    pub value_type: String,
+    pub allowed_values: Option<Vec<Value>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<AttributeDefinition> for AttributeResponse {
+    fn from(m: AttributeDefinition) -> Self {
+        Self {
+            allowed_values: m.allowed_values().cloned(),
+            id: m.id,
+            code: m.code,
+            name: m.name,
+            value_type: m.value_type,
+            created_at: m.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AttributesResponse {
+    pub attributes: Vec<AttributeResponse>,
+}
+
+/// The values one attribute takes across a product listing, most common first.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AttributeFacetResponse {
+    pub code: String,
+    pub name: String,
+    pub values: Vec<FacetValueResponse>,
+}
+
+impl From<AttributeFacet> for AttributeFacetResponse {
+    fn from(facet: AttributeFacet) -> Self {
+        Self {
+            code: facet.attribute.code,
+            name: facet.attribute.name,
+            values: facet
+                .values
+                .into_iter()
+                .map(FacetValueResponse::from)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FacetValueResponse {
+    pub value: Value,
+    /// Matching products with this value.
+    pub count: i64,
+}
+
+impl From<FacetValue> for FacetValueResponse {
+    fn from(facet: FacetValue) -> Self {
+        Self {
+            value: facet.value,
+            count: facet.count,
+        }
+    }
+}
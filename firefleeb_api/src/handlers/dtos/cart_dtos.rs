@@ -5,6 +5,7 @@ use uuid::Uuid;
 use serde::{Deserialize, Serialize};
 
 use crate::models::cart::Cart;
+use crate::services::stock_allocation_service::Allocation;
 
 #[derive(Debug, Deserialize)]
 pub struct CreateCartRequest {
@@ -24,6 +25,9 @@ pub struct CartResponse {
     pub cart_status: String,
     pub cart_total: BigDecimal,
     pub created_at: Option<DateTime<Utc>>,
+    /// Where each line ships from; only filled in by checkout.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allocations: Vec<AllocationResponse>,
 }
 
 impl From<Cart> for CartResponse {
@@ -34,6 +38,37 @@ impl From<Cart> for CartResponse {
             cart_status: m.cart_status,
             cart_total: m.cart_total,
             created_at: m.created_at,
+            allocations: Vec::new(),
+        }
+    }
+}
+
+/// Query string of `POST /carts/:id/checkout`.
+#[derive(Debug, Default, Deserialize)]
+pub struct CheckoutQuery {
+    /// Where the order ships to; warehouses nearest to it are preferred. Give both or neither.
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+/// Part or all of a cart line, shipped from one warehouse.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AllocationResponse {
+    pub cart_item_id: Uuid,
+    pub product_id: Uuid,
+    pub variant_id: Uuid,
+    pub warehouse_id: Uuid,
+    pub quantity: i32,
+}
+
+impl From<Allocation> for AllocationResponse {
+    fn from(a: Allocation) -> Self {
+        Self {
+            cart_item_id: a.cart_item_id,
+            product_id: a.product_id,
+            variant_id: a.variant_id,
+            warehouse_id: a.warehouse_id,
+            quantity: a.quantity,
         }
     }
 }
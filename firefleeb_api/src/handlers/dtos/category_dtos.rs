@@ -0,0 +1,59 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::category::{Category, CategoryCrumb};
+
+#[derive(Debug, Deserialize)]
+pub struct CreateCategoryRequest {
+    pub name: String,
+    /// Omit to create a root category.
+    pub parent_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateCategoryRequest {
+    pub name: Option<String>,
+    /// Absent keeps the current parent; `null` moves the category to the root.
+    #[serde(default, deserialize_with = "super::present")]
+    pub parent_id: Option<Option<Uuid>>,
+}
+
+/// Query string of `GET /categories/:id/products`. Re-serialized to build the page links.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ListCategoryProductsQuery {
+    /// Also list products of every subcategory (default `false`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_descendants: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CategoryResponse {
+    pub id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    /// Root-first trail ending with this category.
+    pub path: Vec<CategoryCrumb>,
+}
+
+impl CategoryResponse {
+    pub fn new(category: Category, path: Vec<CategoryCrumb>) -> Self {
+        Self {
+            id: category.id,
+            parent_id: category.parent_id,
+            name: category.name,
+            created_at: category.created_at,
+            path,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CategoriesResponse {
+    pub categories: Vec<CategoryResponse>,
+}
@@ -1,3 +1,5 @@
+use serde::{Deserialize, Deserializer};
+
 pub mod product_dtos;
 pub use product_dtos::*;
 
@@ -9,3 +11,31 @@ pub use cart_item_dtos::*;
 
 pub mod user_dtos;
 pub use user_dtos::*;
+
+pub mod page_dtos;
+pub use page_dtos::*;
+
+pub mod category_dtos;
+pub use category_dtos::*;
+
+pub mod warehouse_dtos;
+pub use warehouse_dtos::*;
+
+pub mod review_dtos;
+pub use review_dtos::*;
+
+pub mod attribute_dtos;
+pub use attribute_dtos::*;
+
+pub mod recommendation_dtos;
+pub use recommendation_dtos::*;
+
+/// For `Option<Option<T>>` fields with `#[serde(default)]`: tells a field set to `null`
+/// (`Some(None)`) apart from one left out (`None`).
+pub(crate) fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+/// Envelope for paginated collections.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PageResponse<T> {
+    pub items: Vec<T>,
+    /// Items matching the request across all pages.
+    pub total: i64,
+    pub limit: i64,
+    /// Present for offset pagination, absent when the page was requested by cursor.
+    pub offset: Option<i64>,
+    /// Pass as `cursor` to continue after this page; absent on the last page.
+    pub next_cursor: Option<String>,
+    pub links: PageLinks,
+}
+
+/// Relative URLs of the neighbouring pages, keeping the request's filters and sort.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PageLinks {
+    pub next: Option<String>,
+    pub prev: Option<String>,
+}
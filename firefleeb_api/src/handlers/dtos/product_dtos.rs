@@ -1,17 +1,37 @@
+use std::collections::BTreeMap;
+
 use bigdecimal::BigDecimal;
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::models::category::CategoryCrumb;
+use crate::models::product::{Product, ProductSearchHit, ProductSuggestion};
+use crate::models::product_image::ProductImage;
+use crate::models::product_price::ProductPrice;
+use crate::models::product_variant::ProductVariant;
+use crate::models::stock_movement::StockMovement;
+use crate::services::product_price_service::{PriceHistoryEntry, PriceStatus};
+use crate::services::product_transfer_service::{ImportReport, ImportStatus};
 
-use crate::models::product::Product;
+use super::attribute_dtos::AttributeFacetResponse;
+use super::page_dtos::PageResponse;
+use super::warehouse_dtos::WarehouseStockResponse;
 
 #[derive(Debug, Deserialize)]
 pub struct CreateProductRequest {
     pub product_name: String,
     pub product_description: Option<String>,
     pub price: BigDecimal,
+    /// Stock of the default variant.
     pub stock: i32,
+    /// SKU of the default variant; generated from the product id when absent.
+    pub sku: Option<String>,
+    /// Attribute values by code, e.g. `{"material": "steel", "wattage": 1200}`.
+    #[serde(default)]
+    pub attributes: BTreeMap<String, Value>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -19,7 +39,353 @@ pub struct UpdateProductRequest {
     pub product_name: Option<String>,
     pub product_description: Option<String>,
     pub price: Option<BigDecimal>,
+    /// Stock of the default variant.
     pub stock: Option<i32>,
+    /// Replaces all of the product's attribute values; `{}` clears them.
+    pub attributes: Option<BTreeMap<String, Value>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateProductVariantRequest {
+    pub sku: String,
+    /// Option values, e.g. `{"size": "M", "colour": "red"}`.
+    #[serde(default = "empty_options")]
+    pub options: Value,
+    /// Overrides the product's price; omit to sell at the product's price.
+    pub price: Option<BigDecimal>,
+    #[serde(default)]
+    pub stock: i32,
+}
+
+fn empty_options() -> Value {
+    Value::Object(Default::default())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateProductVariantRequest {
+    pub sku: Option<String>,
+    pub options: Option<Value>,
+    /// Absent keeps the current price; `null` falls back to the product's price.
+    #[serde(default, deserialize_with = "super::present")]
+    pub price: Option<Option<BigDecimal>>,
+    pub stock: Option<i32>,
+    /// `true` makes this the product's default variant.
+    #[serde(default)]
+    pub is_default: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProductVariantResponse {
+    pub id: Uuid,
+    pub sku: String,
+    pub options: Value,
+    /// What the variant sells for: its own price, or else the product's.
+    pub price: BigDecimal,
+    /// The variant's own price, `null` when it inherits the product's.
+    pub price_override: Option<BigDecimal>,
+    pub stock: i32,
+    pub is_default: bool,
+}
+
+impl ProductVariantResponse {
+    pub fn new(variant: ProductVariant, product_price: &BigDecimal) -> Self {
+        Self {
+            id: variant.id,
+            price: variant.effective_price(product_price),
+            sku: variant.sku,
+            options: variant.options,
+            price_override: variant.price,
+            stock: variant.stock,
+            is_default: variant.is_default,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProductImageResponse {
+    pub id: Uuid,
+    pub url: String,
+    /// Thumbnail URLs keyed by the size of their bounding box in pixels.
+    pub thumbnails: BTreeMap<u32, String>,
+    pub content_type: String,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl From<ProductImage> for ProductImageResponse {
+    fn from(image: ProductImage) -> Self {
+        let url = format!("/products/{}/images/{}", image.product_id, image.id);
+        Self {
+            thumbnails: image
+                .thumbnail_sizes()
+                .map(|size| (size, format!("{url}/thumbnails/{size}")))
+                .collect(),
+            id: image.id,
+            url,
+            content_type: image.content_type,
+            width: image.width,
+            height: image.height,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SchedulePriceRequest {
+    pub price: BigDecimal,
+    pub valid_from: DateTime<Utc>,
+    /// Omit to keep the price until another one takes over.
+    pub valid_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProductPriceResponse {
+    pub id: Uuid,
+    pub price: BigDecimal,
+    pub valid_from: DateTime<Utc>,
+    pub valid_until: Option<DateTime<Utc>>,
+    /// `manual` or `scheduled`.
+    pub source: String,
+    /// `upcoming`, `current`, `overridden` or `past`.
+    pub status: String,
+}
+
+impl From<PriceHistoryEntry> for ProductPriceResponse {
+    fn from(entry: PriceHistoryEntry) -> Self {
+        Self::new(entry.price, entry.status)
+    }
+}
+
+impl ProductPriceResponse {
+    pub fn new(price: ProductPrice, status: PriceStatus) -> Self {
+        Self {
+            id: price.id,
+            price: price.price,
+            valid_from: price.valid_from,
+            valid_until: price.valid_until,
+            source: price.source,
+            status: status.as_str().to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateStockMovementRequest {
+    /// Defaults to the product's default variant.
+    pub variant_id: Option<Uuid>,
+    /// Defaults to the default warehouse.
+    pub warehouse_id: Option<Uuid>,
+    /// `receipt`, `adjustment`, `sale` or `return`.
+    pub movement_type: String,
+    /// Signed change to the stock: positive for receipts and returns, negative for sales.
+    pub quantity: i32,
+    pub reason: String,
+}
+
+/// Query string of `GET /products/:id/stock-movements`. Re-serialized to build the page links.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ListStockMovementsQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variant_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warehouse_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub movement_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StockMovementResponse {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    /// `null` once the variant has been deleted.
+    pub variant_id: Option<Uuid>,
+    pub warehouse_id: Uuid,
+    pub movement_type: String,
+    pub quantity: i32,
+    /// The variant's stock across all warehouses right after this movement.
+    pub stock_after: i32,
+    pub reason: String,
+    /// The user who made the change; `null` for system changes.
+    pub actor_id: Option<Uuid>,
+    /// The checked-out cart behind a sale.
+    pub cart_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<StockMovement> for StockMovementResponse {
+    fn from(m: StockMovement) -> Self {
+        Self {
+            id: m.id,
+            product_id: m.product_id,
+            variant_id: m.variant_id,
+            warehouse_id: m.warehouse_id,
+            movement_type: m.movement_type,
+            quantity: m.quantity,
+            stock_after: m.stock_after,
+            reason: m.reason,
+            actor_id: m.actor_id,
+            cart_id: m.cart_id,
+            created_at: m.created_at,
+        }
+    }
+}
+
+/// Query string of `GET /products`. Re-serialized to build the page links.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ListProductsQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    /// `price`, `name` or `created_at` (default).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>,
+    /// `asc` or `desc`; defaults to `desc` for `created_at` and `asc` otherwise.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_price: Option<BigDecimal>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_price: Option<BigDecimal>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub in_stock: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name_prefix: Option<String>,
+}
+
+/// Query string of `GET /products/:id`.
+#[derive(Debug, Default, Deserialize)]
+pub struct GetProductQuery {
+    /// Add the product's stock per warehouse.
+    #[serde(default)]
+    pub include_warehouses: bool,
+}
+
+/// Query string of `GET /products/search`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SearchProductsQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub q: Option<String>,
+    /// Treat the last word as a prefix (default `true`), for type-ahead.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<i64>,
+}
+
+/// Query string of `GET /products/suggest`.
+#[derive(Debug, Deserialize)]
+pub struct SuggestProductsQuery {
+    pub prefix: Option<String>,
+    /// Suggestions to return; default 10, at most 20.
+    pub limit: Option<i64>,
+}
+
+/// Query string of `POST /products/import`.
+#[derive(Debug, Default, Deserialize)]
+pub struct ImportProductsQuery {
+    /// Validate and report without saving anything.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Query string of `GET /products/export`.
+#[derive(Debug, Default, Deserialize)]
+pub struct ExportProductsQuery {
+    /// `csv` (default) or `ndjson`.
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportRowResponse {
+    pub line: u64,
+    pub product_name: Option<String>,
+    /// `created`, `updated` or `rejected`.
+    pub status: String,
+    pub product_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportReportResponse {
+    pub dry_run: bool,
+    pub created: usize,
+    pub updated: usize,
+    pub rejected: usize,
+    pub rows: Vec<ImportRowResponse>,
+}
+
+impl From<ImportReport> for ImportReportResponse {
+    fn from(report: ImportReport) -> Self {
+        Self {
+            dry_run: report.dry_run,
+            created: report.count(ImportStatus::Created),
+            updated: report.count(ImportStatus::Updated),
+            rejected: report.count(ImportStatus::Rejected),
+            rows: report
+                .rows
+                .into_iter()
+                .map(|row| ImportRowResponse {
+                    line: row.line,
+                    product_name: row.product_name,
+                    status: row.status.as_str().to_string(),
+                    product_id: row.product_id,
+                    error: row.error,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProductSuggestionResponse {
+    pub id: Uuid,
+    pub product_name: String,
+    pub similarity: f32,
+}
+
+impl From<ProductSuggestion> for ProductSuggestionResponse {
+    fn from(suggestion: ProductSuggestion) -> Self {
+        Self {
+            id: suggestion.id,
+            product_name: suggestion.product_name,
+            similarity: suggestion.similarity,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProductSuggestionsResponse {
+    pub suggestions: Vec<ProductSuggestionResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProductSearchResponse {
+    #[serde(flatten)]
+    pub product: ProductResponse,
+    pub rank: f32,
+    /// `product_name` with matching words wrapped in `<mark>`; the text itself is not HTML-escaped.
+    pub name_highlight: String,
+    pub description_snippet: Option<String>,
+}
+
+impl From<ProductSearchHit> for ProductSearchResponse {
+    fn from(hit: ProductSearchHit) -> Self {
+        Self {
+            product: ProductResponse::from(hit.product),
+            rank: hit.rank,
+            name_highlight: hit.name_highlight,
+            description_snippet: hit.description_snippet,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -28,8 +394,42 @@ pub struct ProductResponse {
     pub product_name: String,
     pub product_description: Option<String>,
     pub price: BigDecimal,
+    /// Total stock across all variants and warehouses.
     pub stock: i32,
-    pub created_at: Option<DateTime<Utc>>,
+    /// `stock` less what shoppers' carts currently hold.
+    pub available_stock: i64,
+    pub created_at: DateTime<Utc>,
+    /// When the product was withdrawn from sale; `null` while it is on sale.
+    pub archived_at: Option<DateTime<Utc>>,
+    /// Number of approved reviews.
+    pub rating_count: i32,
+    /// Mean rating of the approved reviews, to two decimals; `null` while there are none.
+    pub rating_average: Option<BigDecimal>,
+    /// Attribute values by code.
+    #[serde(default)]
+    pub attributes: BTreeMap<String, Value>,
+    /// Default variant first.
+    #[serde(default)]
+    pub variants: Vec<ProductVariantResponse>,
+    /// Root-first trail of every category the product is in, sorted by name.
+    #[serde(default)]
+    pub breadcrumbs: Vec<Vec<CategoryCrumb>>,
+    /// Oldest upload first.
+    #[serde(default)]
+    pub images: Vec<ProductImageResponse>,
+    /// Stock per warehouse, default warehouse first; only when asked for with
+    /// `include_warehouses=true`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warehouse_stock: Option<Vec<WarehouseStockResponse>>,
+}
+
+/// `GET /products`: a page of products, with the attribute facets of every matching product.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProductPageResponse {
+    #[serde(flatten)]
+    pub page: PageResponse<ProductResponse>,
+    /// By attribute code.
+    pub facets: Vec<AttributeFacetResponse>,
 }
 
 impl From<Product> for ProductResponse {
@@ -40,7 +440,16 @@ impl From<Product> for ProductResponse {
             product_description: m.product_description,
             price: m.price,
             stock: m.stock,
+            available_stock: i64::from(m.stock),
             created_at: m.created_at,
+            archived_at: m.archived_at,
+            rating_count: m.rating_count,
+            rating_average: m.rating_average,
+            attributes: BTreeMap::new(),
+            variants: Vec::new(),
+            breadcrumbs: Vec::new(),
+            images: Vec::new(),
+            warehouse_stock: None,
         }
     }
 }
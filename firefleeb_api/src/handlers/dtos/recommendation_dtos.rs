@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+use super::product_dtos::ProductResponse;
+use crate::models::product_recommendation::RecommendedProduct;
+
+/// Query string of `GET /products/:id/recommendations` and `GET /carts/:id/recommendations`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecommendationsQuery {
+    /// Products to return; default 10, at most 20.
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecommendationResponse {
+    #[serde(flatten)]
+    pub product: ProductResponse,
+    pub score: f64,
+}
+
+impl From<RecommendedProduct> for RecommendationResponse {
+    fn from(recommended: RecommendedProduct) -> Self {
+        Self {
+            product: ProductResponse::from(recommended.product),
+            score: recommended.score,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecommendationsResponse {
+    /// Best first.
+    pub items: Vec<RecommendationResponse>,
+}
@@ -0,0 +1,76 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::product_review::ProductReview;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateReviewRequest {
+    /// From 1 to 5 stars.
+    pub rating: i32,
+    pub title: String,
+    pub body: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ModerateReviewRequest {
+    /// `pending`, `approved` or `rejected`.
+    pub status: String,
+}
+
+/// Query string of `GET /products/:id/reviews`. Re-serialized to build the page links.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ListProductReviewsQuery {
+    /// Only reviews by customers who bought the product.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verified_only: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<i64>,
+}
+
+/// Query string of `GET /reviews`. Re-serialized to build the page links.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ListReviewsQuery {
+    /// Defaults to `pending`, the reviews waiting for moderation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReviewResponse {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    pub user_id: Uuid,
+    pub rating: i32,
+    pub title: String,
+    pub body: String,
+    /// The reviewer had checked out a cart with the product when writing the review.
+    pub verified_purchase: bool,
+    /// `pending`, `approved` or `rejected`.
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<ProductReview> for ReviewResponse {
+    fn from(m: ProductReview) -> Self {
+        Self {
+            id: m.id,
+            product_id: m.product_id,
+            user_id: m.user_id,
+            rating: m.rating,
+            title: m.title,
+            body: m.body,
+            verified_purchase: m.verified_purchase,
+            status: m.status,
+            created_at: m.created_at,
+            updated_at: m.updated_at,
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::types::email::Email;
+use crate::types::role::Role;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -22,6 +23,32 @@ pub struct UpdatePasswordRequest {
     pub new_password: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ForgotPasswordRequest {
+    pub email: Email,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResetPasswordRequest {
+    pub token: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailRequest {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResendVerificationRequest {
+    pub email: Email,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateRoleRequest {
+    pub role: Role,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct LoginRequest {
     pub email: Email,
@@ -32,14 +59,33 @@ pub struct LoginRequest {
 pub struct UserResponse {
     pub id: Uuid,
     pub email: Email,
+    pub role: Role,
+    pub email_verified_at: Option<DateTime<Utc>>,
     pub created_at: Option<DateTime<Utc>>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct RefreshTokenRequest {
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoginResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    pub refresh_token: String,
+    pub refresh_expires_in: i64,
+    pub user: UserResponse,
+}
+
 impl From<User> for UserResponse {
     fn from(m: User) -> Self {
         Self {
             id: m.id,
             email: m.email,
+            role: m.role,
+            email_verified_at: m.email_verified_at,
             created_at: m.created_at,
         }
     }
@@ -0,0 +1,79 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::warehouse::Warehouse;
+use crate::services::warehouse_service::WarehouseLevel;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateWarehouseRequest {
+    pub code: String,
+    pub name: String,
+    /// Give both coordinates or neither.
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateWarehouseRequest {
+    pub code: Option<String>,
+    pub name: Option<String>,
+    /// Absent keeps the location; `null` forgets it. Change both coordinates together.
+    #[serde(default, deserialize_with = "super::present")]
+    pub latitude: Option<Option<f64>>,
+    #[serde(default, deserialize_with = "super::present")]
+    pub longitude: Option<Option<f64>>,
+    /// `true` makes this the default warehouse.
+    #[serde(default)]
+    pub is_default: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WarehouseResponse {
+    pub id: Uuid,
+    pub code: String,
+    pub name: String,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub is_default: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Warehouse> for WarehouseResponse {
+    fn from(m: Warehouse) -> Self {
+        Self {
+            id: m.id,
+            code: m.code,
+            name: m.name,
+            latitude: m.latitude,
+            longitude: m.longitude,
+            is_default: m.is_default,
+            created_at: m.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WarehousesResponse {
+    pub warehouses: Vec<WarehouseResponse>,
+}
+
+/// A product's stock in one warehouse, summed over its variants.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WarehouseStockResponse {
+    pub warehouse_id: Uuid,
+    pub code: String,
+    pub name: String,
+    pub stock: i64,
+}
+
+impl From<WarehouseLevel> for WarehouseStockResponse {
+    fn from(level: WarehouseLevel) -> Self {
+        Self {
+            warehouse_id: level.warehouse.id,
+            code: level.warehouse.code,
+            name: level.warehouse.name,
+            stock: level.stock,
+        }
+    }
+}
@@ -1,5 +1,11 @@
+pub mod attribute_handlers;
 pub mod cart_handlers;
 pub mod cart_item_handlers;
+pub mod category_handlers;
 pub mod dtos;
+pub mod paging;
 pub mod product_handlers;
+pub mod recommendation_handlers;
+pub mod review_handlers;
 pub mod user_handlers;
+pub mod warehouse_handlers;
@@ -0,0 +1,39 @@
+use serde::Serialize;
+
+use crate::errors::AppError;
+
+pub const DEFAULT_PAGE_SIZE: i64 = 20;
+pub const MAX_PAGE_SIZE: i64 = 100;
+
+/// Requested page size, defaulting to [`DEFAULT_PAGE_SIZE`].
+pub fn page_limit(limit: Option<i64>) -> Result<i64, AppError> {
+    bounded_page_limit(limit, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE)
+}
+
+/// Requested page size for endpoints with their own default and maximum.
+pub fn bounded_page_limit(limit: Option<i64>, default: i64, max: i64) -> Result<i64, AppError> {
+    let limit = limit.unwrap_or(default);
+    if (1..=max).contains(&limit) {
+        Ok(limit)
+    } else {
+        Err(AppError::Validation(format!(
+            "limit must be between 1 and {max}"
+        )))
+    }
+}
+
+pub fn page_offset(offset: Option<i64>) -> Result<i64, AppError> {
+    match offset {
+        Some(offset) if offset < 0 => {
+            Err(AppError::Validation("offset must not be negative".into()))
+        }
+        offset => Ok(offset.unwrap_or(0)),
+    }
+}
+
+/// Relative link to `path` with `query` as its query string.
+pub fn page_link<Q: Serialize>(path: &str, query: &Q) -> Result<String, AppError> {
+    let query_string = serde_urlencoded::to_string(query)
+        .map_err(|e| AppError::Internal(format!("Failed to build page link: {e}")))?;
+    Ok(format!("{path}?{query_string}"))
+}
@@ -1,12 +1,49 @@
+use bytes::{BufMut, Bytes};
+use chrono::Utc;
+use futures_util::TryStreamExt;
+use warp::multipart::FormData;
+
+use crate::auth::AuthUser;
+use crate::config::ImageConfig;
 use crate::db::PgPool;
+use crate::db::pagination::SortOrder;
+use crate::db::product_repository::{ProductFilter, ProductSort};
+use crate::db::stock_movement_repository::MovementFilter;
 use crate::errors::AppError;
-use crate::handlers::dtos::{CreateProductRequest, ProductResponse, UpdateProductRequest};
+use crate::handlers::dtos::{
+    AttributeFacetResponse, CreateProductRequest, CreateProductVariantRequest,
+    CreateStockMovementRequest, ExportProductsQuery, GetProductQuery, ImportProductsQuery,
+    ImportReportResponse, ListProductsQuery, ListStockMovementsQuery, PageLinks, PageResponse,
+    ProductImageResponse, ProductPageResponse, ProductPriceResponse, ProductResponse,
+    ProductSearchResponse, ProductSuggestionResponse, ProductSuggestionsResponse,
+    ProductVariantResponse, SchedulePriceRequest, SearchProductsQuery, StockMovementResponse,
+    SuggestProductsQuery, UpdateProductRequest, UpdateProductVariantRequest,
+    WarehouseStockResponse,
+};
+use crate::handlers::paging::{bounded_page_limit, page_limit, page_link, page_offset};
 use crate::models::product::{NewProduct, UpdateProduct};
-use crate::services::product_service;
+use crate::models::product_variant::{NewProductVariant, UpdateProductVariant};
+use crate::services::product_image_service::{self, ImageRendition, ServedImage};
+use crate::services::product_price_service::{self, PriceStatus};
+use crate::services::product_service::{self, ProductPagination};
+use crate::services::product_transfer_service::{self, CatalogFormat};
+use crate::services::stock_movement_service::{self, MovementInput};
+use crate::services::{
+    attribute_service, category_service, product_variant_service, stock_reservation_service,
+    warehouse_service,
+};
+use crate::storage::SharedBlobStore;
 use uuid::Uuid;
+use warp::http::header::{CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_TYPE, ETAG};
+use warp::http::{Response, StatusCode};
+use warp::hyper::Body;
 use warp::{Reply, reply};
 
-pub async fn create(pool: PgPool, req: CreateProductRequest) -> Result<impl Reply, AppError> {
+pub async fn create(
+    pool: PgPool,
+    caller: AuthUser,
+    req: CreateProductRequest,
+) -> Result<impl Reply, AppError> {
     let new_product = NewProduct {
         product_name: req.product_name,
         product_description: req.product_description,
@@ -14,12 +51,22 @@ pub async fn create(pool: PgPool, req: CreateProductRequest) -> Result<impl Repl
         stock: req.stock,
     };
 
-    let product = product_service::create_product(pool, new_product).await?;
-    Ok(reply::json(&ProductResponse::from(product)))
+    let product = product_service::create_product(
+        pool.clone(),
+        new_product,
+        req.sku,
+        req.attributes,
+        caller.user_id,
+    )
+    .await?;
+    let mut response = ProductResponse::from(product);
+    attach_product_details(pool, [&mut response]).await?;
+    Ok(reply::json(&response))
 }
 
 pub async fn update(
     pool: PgPool,
+    caller: AuthUser,
     product_id: Uuid,
     req: UpdateProductRequest,
 ) -> Result<impl Reply, AppError> {
@@ -30,19 +77,636 @@ pub async fn update(
         stock: req.stock,
     };
 
-    let product = product_service::update_product(pool, product_id, updated_product).await?;
-    Ok(reply::json(&ProductResponse::from(product)))
+    let product = product_service::update_product(
+        pool.clone(),
+        product_id,
+        updated_product,
+        req.attributes,
+        caller.user_id,
+    )
+    .await?;
+    let mut response = ProductResponse::from(product);
+    attach_product_details(pool, [&mut response]).await?;
+    Ok(reply::json(&response))
+}
+
+/// Prefix of the query parameters that filter by attribute, as in `attr.material=steel`.
+const ATTRIBUTE_FILTER_PREFIX: &str = "attr.";
+
+/// `params` is the whole query string; repeat an `attr.<code>` parameter to accept any of
+/// several values.
+pub async fn list(
+    pool: PgPool,
+    query: ListProductsQuery,
+    params: Vec<(String, String)>,
+) -> Result<impl Reply, AppError> {
+    let attribute_params: Vec<(String, String)> = params
+        .into_iter()
+        .filter(|(key, _)| key.starts_with(ATTRIBUTE_FILTER_PREFIX))
+        .collect();
+    let limit = page_limit(query.limit)?;
+    let sort = match query.sort.as_deref() {
+        None => ProductSort::CreatedAt,
+        Some(raw) => ProductSort::parse(raw).ok_or_else(|| {
+            AppError::Validation("sort must be one of price, name or created_at".into())
+        })?,
+    };
+    let order = match query.order.as_deref() {
+        None => sort.default_order(),
+        Some(raw) => SortOrder::parse(raw)
+            .ok_or_else(|| AppError::Validation("order must be asc or desc".into()))?,
+    };
+    let pagination = match &query.cursor {
+        Some(_) if query.offset.is_some() => {
+            return Err(AppError::Validation(
+                "Use either offset or cursor, not both".into(),
+            ));
+        }
+        Some(cursor) => ProductPagination::Cursor(cursor.clone()),
+        None => ProductPagination::Offset(page_offset(query.offset)?),
+    };
+    if let (Some(min), Some(max)) = (&query.min_price, &query.max_price)
+        && min > max
+    {
+        return Err(AppError::Validation(
+            "min_price must not exceed max_price".into(),
+        ));
+    }
+
+    let filter = ProductFilter {
+        min_price: query.min_price.clone(),
+        max_price: query.max_price.clone(),
+        in_stock_only: query.in_stock.unwrap_or(false),
+        name_prefix: query.name_prefix.clone().filter(|p| !p.is_empty()),
+        attributes: attribute_service::resolve_filters(
+            pool.clone(),
+            attribute_params
+                .iter()
+                .map(|(key, value)| {
+                    (
+                        key[ATTRIBUTE_FILTER_PREFIX.len()..].to_string(),
+                        value.clone(),
+                    )
+                })
+                .collect(),
+        )
+        .await?,
+    };
+    let listing = product_service::list_products(
+        pool.clone(),
+        filter,
+        sort,
+        order,
+        pagination.clone(),
+        limit,
+    )
+    .await?;
+
+    let mut links = PageLinks::default();
+    let offset = match pagination {
+        ProductPagination::Offset(offset) => {
+            if offset + (listing.products.len() as i64) < listing.total {
+                links.next = Some(products_link(
+                    &query,
+                    &attribute_params,
+                    Some(offset + limit),
+                    None,
+                )?);
+            }
+            if offset > 0 {
+                links.prev = Some(products_link(
+                    &query,
+                    &attribute_params,
+                    Some((offset - limit).max(0)),
+                    None,
+                )?);
+            }
+            Some(offset)
+        }
+        ProductPagination::Cursor(_) => {
+            if let Some(cursor) = &listing.next_cursor {
+                links.next = Some(products_link(
+                    &query,
+                    &attribute_params,
+                    None,
+                    Some(cursor.clone()),
+                )?);
+            }
+            None
+        }
+    };
+
+    let mut items: Vec<ProductResponse> = listing
+        .products
+        .into_iter()
+        .map(ProductResponse::from)
+        .collect();
+    attach_product_details(pool, &mut items).await?;
+
+    Ok(reply::json(&ProductPageResponse {
+        page: PageResponse {
+            items,
+            total: listing.total,
+            limit,
+            offset,
+            next_cursor: listing.next_cursor,
+            links,
+        },
+        facets: listing
+            .facets
+            .into_iter()
+            .map(AttributeFacetResponse::from)
+            .collect(),
+    }))
+}
+
+/// `/products?...` with the caller's filters and sort, positioned by `offset` or `cursor`.
+fn products_link(
+    query: &ListProductsQuery,
+    attribute_params: &[(String, String)],
+    offset: Option<i64>,
+    cursor: Option<String>,
+) -> Result<String, AppError> {
+    let page = ListProductsQuery {
+        offset,
+        cursor,
+        ..query.clone()
+    };
+    let mut link = page_link("/products", &page)?;
+    if !attribute_params.is_empty() {
+        let attributes = serde_urlencoded::to_string(attribute_params)
+            .map_err(|e| AppError::Internal(format!("Failed to build page link: {e}")))?;
+        link.push('&');
+        link.push_str(&attributes);
+    }
+    Ok(link)
+}
+
+pub async fn search(pool: PgPool, query: SearchProductsQuery) -> Result<impl Reply, AppError> {
+    let limit = page_limit(query.limit)?;
+    let offset = page_offset(query.offset)?;
+    let text = query
+        .q
+        .as_deref()
+        .ok_or_else(|| AppError::Validation("q is required".into()))?;
+
+    let results = product_service::search_products(
+        pool.clone(),
+        text,
+        query.prefix.unwrap_or(true),
+        offset,
+        limit,
+    )
+    .await?;
+
+    let mut links = PageLinks::default();
+    if offset + (results.hits.len() as i64) < results.total {
+        links.next = Some(search_link(&query, offset + limit)?);
+    }
+    if offset > 0 {
+        links.prev = Some(search_link(&query, (offset - limit).max(0))?);
+    }
+
+    let mut items: Vec<ProductSearchResponse> = results
+        .hits
+        .into_iter()
+        .map(ProductSearchResponse::from)
+        .collect();
+    attach_product_details(pool, items.iter_mut().map(|hit| &mut hit.product)).await?;
+
+    Ok(reply::json(&PageResponse {
+        items,
+        total: results.total,
+        limit,
+        offset: Some(offset),
+        next_cursor: None,
+        links,
+    }))
+}
+
+const DEFAULT_SUGGESTIONS: i64 = 10;
+const MAX_SUGGESTIONS: i64 = 20;
+
+pub async fn suggest(
+    pool: PgPool,
+    threshold: f32,
+    query: SuggestProductsQuery,
+) -> Result<impl Reply, AppError> {
+    let limit = bounded_page_limit(query.limit, DEFAULT_SUGGESTIONS, MAX_SUGGESTIONS)?;
+    let prefix = query
+        .prefix
+        .ok_or_else(|| AppError::Validation("prefix is required".into()))?;
+
+    let suggestions = product_service::suggest_products(pool, prefix, threshold, limit).await?;
+    Ok(reply::json(&ProductSuggestionsResponse {
+        suggestions: suggestions
+            .into_iter()
+            .map(ProductSuggestionResponse::from)
+            .collect(),
+    }))
+}
+
+fn search_link(query: &SearchProductsQuery, offset: i64) -> Result<String, AppError> {
+    let page = SearchProductsQuery {
+        offset: Some(offset),
+        ..query.clone()
+    };
+    page_link("/products/search", &page)
 }
 
-pub async fn get(pool: PgPool, id: Uuid) -> Result<impl Reply, AppError> {
-    let product = product_service::get_product_by_id(pool, id).await?;
-    Ok(warp::reply::json(&ProductResponse::from(product)))
+pub async fn get(pool: PgPool, id: Uuid, query: GetProductQuery) -> Result<impl Reply, AppError> {
+    let product = product_service::get_product_by_id(pool.clone(), id).await?;
+    let mut response = ProductResponse::from(product);
+    attach_product_details(pool.clone(), [&mut response]).await?;
+    if query.include_warehouses {
+        let mut levels = warehouse_service::stock_by_warehouse(pool, vec![id]).await?;
+        response.warehouse_stock = Some(
+            levels
+                .remove(&id)
+                .unwrap_or_default()
+                .into_iter()
+                .map(WarehouseStockResponse::from)
+                .collect(),
+        );
+    }
+    Ok(warp::reply::json(&response))
 }
 
+/// Archives the product; see [`purge`] for removing it.
 pub async fn delete(pool: PgPool, id: Uuid) -> Result<impl Reply, AppError> {
-    product_service::delete_product(pool, id).await?;
+    product_service::archive_product(pool, id).await?;
     Ok(warp::reply::with_status(
-        warp::reply::json(&serde_json::json!({"message": "deleted"})),
+        warp::reply::json(&serde_json::json!({"message": "archived"})),
         warp::http::StatusCode::NO_CONTENT,
     ))
 }
+
+pub async fn restore(pool: PgPool, id: Uuid) -> Result<impl Reply, AppError> {
+    let product = product_service::restore_product(pool.clone(), id).await?;
+    let mut response = ProductResponse::from(product);
+    attach_product_details(pool, [&mut response]).await?;
+    Ok(reply::json(&response))
+}
+
+pub async fn purge(pool: PgPool, blobs: SharedBlobStore, id: Uuid) -> Result<impl Reply, AppError> {
+    product_service::purge_product(pool, blobs, id).await?;
+    Ok(reply::with_status(
+        reply::json(&serde_json::json!({ "message": "purged" })),
+        StatusCode::NO_CONTENT,
+    ))
+}
+
+/// Fill in the variants, category breadcrumbs and images of `products`, one query each.
+pub(crate) async fn attach_product_details<'a>(
+    pool: PgPool,
+    products: impl IntoIterator<Item = &'a mut ProductResponse>,
+) -> Result<(), AppError> {
+    let mut products: Vec<&mut ProductResponse> = products.into_iter().collect();
+    let ids: Vec<Uuid> = products.iter().map(|product| product.id).collect();
+    let mut variants =
+        product_variant_service::variants_for_products(pool.clone(), ids.clone()).await?;
+    let mut breadcrumbs = category_service::product_breadcrumbs(pool.clone(), ids.clone()).await?;
+    let mut images = product_image_service::images_for_products(pool.clone(), ids.clone()).await?;
+    let mut attributes =
+        attribute_service::attributes_for_products(pool.clone(), ids.clone()).await?;
+    let reserved = stock_reservation_service::reserved_for_products(pool, ids, Utc::now()).await?;
+    for product in &mut products {
+        let held = reserved.get(&product.id).copied().unwrap_or(0);
+        product.available_stock = (i64::from(product.stock) - held).max(0);
+        product.variants = variants
+            .remove(&product.id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|variant| ProductVariantResponse::new(variant, &product.price))
+            .collect();
+        product.attributes = attributes.remove(&product.id).unwrap_or_default();
+        product.breadcrumbs = breadcrumbs.remove(&product.id).unwrap_or_default();
+        product.images = images
+            .remove(&product.id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(ProductImageResponse::from)
+            .collect();
+    }
+    Ok(())
+}
+
+pub async fn create_variant(
+    pool: PgPool,
+    caller: AuthUser,
+    product_id: Uuid,
+    req: CreateProductVariantRequest,
+) -> Result<impl Reply, AppError> {
+    let new_variant = NewProductVariant {
+        product_id,
+        sku: req.sku,
+        options: req.options,
+        price: req.price,
+        stock: req.stock,
+        is_default: false,
+    };
+
+    let variant =
+        product_variant_service::create_variant(pool.clone(), new_variant, caller.user_id).await?;
+    let product = product_service::get_product_by_id(pool, product_id).await?;
+    Ok(reply::with_status(
+        reply::json(&ProductVariantResponse::new(variant, &product.price)),
+        StatusCode::CREATED,
+    ))
+}
+
+pub async fn update_variant(
+    pool: PgPool,
+    caller: AuthUser,
+    product_id: Uuid,
+    variant_id: Uuid,
+    req: UpdateProductVariantRequest,
+) -> Result<impl Reply, AppError> {
+    let update = UpdateProductVariant {
+        sku: req.sku,
+        options: req.options,
+        price: req.price,
+        stock: req.stock,
+    };
+
+    let variant = product_variant_service::update_variant(
+        pool.clone(),
+        product_id,
+        variant_id,
+        update,
+        req.is_default,
+        caller.user_id,
+    )
+    .await?;
+    let product = product_service::get_product_by_id(pool, product_id).await?;
+    Ok(reply::json(&ProductVariantResponse::new(
+        variant,
+        &product.price,
+    )))
+}
+
+pub async fn delete_variant(
+    pool: PgPool,
+    caller: AuthUser,
+    product_id: Uuid,
+    variant_id: Uuid,
+) -> Result<impl Reply, AppError> {
+    product_variant_service::delete_variant(pool, product_id, variant_id, caller.user_id).await?;
+    Ok(reply::with_status(
+        reply::json(&serde_json::json!({ "message": "deleted" })),
+        StatusCode::NO_CONTENT,
+    ))
+}
+
+/// Name of the multipart field carrying the uploaded file.
+const IMAGE_FIELD: &str = "image";
+
+pub async fn upload_image(
+    pool: PgPool,
+    blobs: SharedBlobStore,
+    config: ImageConfig,
+    product_id: Uuid,
+    form: FormData,
+) -> Result<impl Reply, AppError> {
+    let (content_type, bytes) = read_image_field(form, config.max_upload_bytes).await?;
+    let image =
+        product_image_service::upload_image(pool, blobs, &config, product_id, content_type, bytes)
+            .await?;
+    Ok(reply::with_status(
+        reply::json(&ProductImageResponse::from(image)),
+        StatusCode::CREATED,
+    ))
+}
+
+/// The declared content type and bytes of the form's `image` field. Other fields are skipped.
+async fn read_image_field(
+    mut form: FormData,
+    max_bytes: u64,
+) -> Result<(Option<String>, Vec<u8>), AppError> {
+    let invalid = |e: warp::Error| AppError::Validation(format!("invalid multipart body: {e}"));
+    while let Some(part) = form.try_next().await.map_err(invalid)? {
+        if part.name() != IMAGE_FIELD {
+            continue;
+        }
+        let content_type = part.content_type().map(str::to_string);
+        let mut bytes = Vec::new();
+        let mut chunks = part.stream();
+        while let Some(chunk) = chunks.try_next().await.map_err(invalid)? {
+            bytes.put(chunk);
+            if bytes.len() as u64 > max_bytes {
+                return Err(AppError::PayloadTooLarge(format!(
+                    "Images must be at most {max_bytes} bytes"
+                )));
+            }
+        }
+        return Ok((content_type, bytes));
+    }
+    Err(AppError::Validation(format!(
+        "Multipart field '{IMAGE_FIELD}' is required"
+    )))
+}
+
+pub async fn get_image(
+    pool: PgPool,
+    blobs: SharedBlobStore,
+    product_id: Uuid,
+    image_id: Uuid,
+    rendition: ImageRendition,
+    if_none_match: Option<String>,
+) -> Result<impl Reply, AppError> {
+    let served = product_image_service::serve_image(
+        pool,
+        blobs,
+        product_id,
+        image_id,
+        rendition,
+        if_none_match,
+    )
+    .await?;
+    image_response(served)
+}
+
+fn image_response(served: ServedImage) -> Result<Response<Vec<u8>>, AppError> {
+    let builder = Response::builder()
+        .header(ETAG, served.etag)
+        .header(CACHE_CONTROL, product_image_service::IMAGE_CACHE_CONTROL);
+    let response = match served.bytes {
+        Some(bytes) => builder
+            .status(StatusCode::OK)
+            .header(CONTENT_TYPE, served.content_type)
+            .body(bytes),
+        None => builder.status(StatusCode::NOT_MODIFIED).body(Vec::new()),
+    };
+    response.map_err(|e| AppError::Internal(format!("Failed to build image response: {e}")))
+}
+
+pub async fn delete_image(
+    pool: PgPool,
+    blobs: SharedBlobStore,
+    product_id: Uuid,
+    image_id: Uuid,
+) -> Result<impl Reply, AppError> {
+    product_image_service::delete_image(pool, blobs, product_id, image_id).await?;
+    Ok(reply::with_status(
+        reply::json(&serde_json::json!({ "message": "deleted" })),
+        StatusCode::NO_CONTENT,
+    ))
+}
+
+pub async fn price_history(pool: PgPool, product_id: Uuid) -> Result<impl Reply, AppError> {
+    let history = product_price_service::price_history(pool, product_id).await?;
+    let resp: Vec<ProductPriceResponse> = history.into_iter().map(Into::into).collect();
+    Ok(reply::json(&resp))
+}
+
+pub async fn schedule_price(
+    pool: PgPool,
+    product_id: Uuid,
+    req: SchedulePriceRequest,
+) -> Result<impl Reply, AppError> {
+    let price = product_price_service::schedule_price(
+        pool,
+        product_id,
+        req.price,
+        req.valid_from,
+        req.valid_until,
+    )
+    .await?;
+    Ok(reply::with_status(
+        reply::json(&ProductPriceResponse::new(price, PriceStatus::Upcoming)),
+        StatusCode::CREATED,
+    ))
+}
+
+pub async fn cancel_scheduled_price(
+    pool: PgPool,
+    product_id: Uuid,
+    price_id: Uuid,
+) -> Result<impl Reply, AppError> {
+    product_price_service::cancel_scheduled_price(pool, product_id, price_id).await?;
+    Ok(reply::with_status(
+        reply::json(&serde_json::json!({ "message": "deleted" })),
+        StatusCode::NO_CONTENT,
+    ))
+}
+
+pub async fn record_stock_movement(
+    pool: PgPool,
+    caller: AuthUser,
+    product_id: Uuid,
+    req: CreateStockMovementRequest,
+) -> Result<impl Reply, AppError> {
+    let input = MovementInput {
+        variant_id: req.variant_id,
+        warehouse_id: req.warehouse_id,
+        movement_type: req.movement_type,
+        quantity: req.quantity,
+        reason: req.reason,
+    };
+
+    let movement =
+        stock_movement_service::record_movement(pool, product_id, input, caller.user_id).await?;
+    Ok(reply::with_status(
+        reply::json(&StockMovementResponse::from(movement)),
+        StatusCode::CREATED,
+    ))
+}
+
+pub async fn list_stock_movements(
+    pool: PgPool,
+    product_id: Uuid,
+    query: ListStockMovementsQuery,
+) -> Result<impl Reply, AppError> {
+    let limit = page_limit(query.limit)?;
+    let offset = page_offset(query.offset)?;
+    let filter = MovementFilter {
+        variant_id: query.variant_id,
+        warehouse_id: query.warehouse_id,
+        movement_type: query.movement_type.clone(),
+    };
+
+    let (movements, total) =
+        stock_movement_service::list_movements(pool, product_id, filter, offset, limit).await?;
+
+    let mut links = PageLinks::default();
+    if offset + (movements.len() as i64) < total {
+        links.next = Some(stock_movements_link(product_id, &query, offset + limit)?);
+    }
+    if offset > 0 {
+        links.prev = Some(stock_movements_link(
+            product_id,
+            &query,
+            (offset - limit).max(0),
+        )?);
+    }
+
+    Ok(reply::json(&PageResponse {
+        items: movements
+            .into_iter()
+            .map(StockMovementResponse::from)
+            .collect(),
+        total,
+        limit,
+        offset: Some(offset),
+        next_cursor: None,
+        links,
+    }))
+}
+
+fn stock_movements_link(
+    product_id: Uuid,
+    query: &ListStockMovementsQuery,
+    offset: i64,
+) -> Result<String, AppError> {
+    let page = ListStockMovementsQuery {
+        offset: Some(offset),
+        ..query.clone()
+    };
+    page_link(&format!("/products/{product_id}/stock-movements"), &page)
+}
+
+pub async fn import_products(
+    pool: PgPool,
+    caller: AuthUser,
+    content_type: Option<String>,
+    query: ImportProductsQuery,
+    body: Bytes,
+) -> Result<impl Reply, AppError> {
+    let format = content_type
+        .as_deref()
+        .and_then(CatalogFormat::from_content_type)
+        .ok_or_else(|| {
+            AppError::UnsupportedMediaType(
+                "Send the import as text/csv or application/x-ndjson".into(),
+            )
+        })?;
+    let report = product_transfer_service::import_products(
+        pool,
+        format,
+        body,
+        query.dry_run,
+        caller.user_id,
+    )
+    .await?;
+    Ok(reply::json(&ImportReportResponse::from(report)))
+}
+
+pub async fn export_products(
+    pool: PgPool,
+    query: ExportProductsQuery,
+) -> Result<impl Reply, AppError> {
+    let format = match query.format.as_deref() {
+        None => CatalogFormat::Csv,
+        Some(raw) => CatalogFormat::parse(raw)
+            .ok_or_else(|| AppError::Validation("format must be csv or ndjson".into()))?,
+    };
+    let chunks = product_transfer_service::export_products(pool, format);
+    Response::builder()
+        .header(CONTENT_TYPE, format.content_type())
+        .header(
+            CONTENT_DISPOSITION,
+            format!("attachment; filename=\"products.{}\"", format.extension()),
+        )
+        .body(Body::wrap_stream(chunks))
+        .map_err(|e| AppError::Internal(format!("Failed to build export response: {e}")))
+}
@@ -0,0 +1,52 @@
+use crate::auth::AuthUser;
+use crate::db::PgPool;
+use crate::errors::AppError;
+use crate::handlers::dtos::{
+    RecommendationResponse, RecommendationsQuery, RecommendationsResponse,
+};
+use crate::handlers::paging::bounded_page_limit;
+use crate::handlers::product_handlers::attach_product_details;
+use crate::models::product_recommendation::RecommendedProduct;
+use crate::services::product_recommendation_service;
+use uuid::Uuid;
+use warp::{Reply, reply};
+
+const DEFAULT_RECOMMENDATIONS: i64 = 10;
+const MAX_RECOMMENDATIONS: i64 = 20;
+
+pub async fn for_product(
+    pool: PgPool,
+    product_id: Uuid,
+    query: RecommendationsQuery,
+) -> Result<impl Reply, AppError> {
+    let limit = bounded_page_limit(query.limit, DEFAULT_RECOMMENDATIONS, MAX_RECOMMENDATIONS)?;
+    let recommended =
+        product_recommendation_service::recommend_for_product(pool.clone(), product_id, limit)
+            .await?;
+    respond(pool, recommended).await
+}
+
+pub async fn for_cart(
+    pool: PgPool,
+    caller: AuthUser,
+    cart_id: Uuid,
+    query: RecommendationsQuery,
+) -> Result<impl Reply, AppError> {
+    let limit = bounded_page_limit(query.limit, DEFAULT_RECOMMENDATIONS, MAX_RECOMMENDATIONS)?;
+    let recommended =
+        product_recommendation_service::recommend_for_cart(pool.clone(), &caller, cart_id, limit)
+            .await?;
+    respond(pool, recommended).await
+}
+
+async fn respond(
+    pool: PgPool,
+    recommended: Vec<RecommendedProduct>,
+) -> Result<reply::Json, AppError> {
+    let mut items: Vec<RecommendationResponse> = recommended
+        .into_iter()
+        .map(RecommendationResponse::from)
+        .collect();
+    attach_product_details(pool, items.iter_mut().map(|item| &mut item.product)).await?;
+    Ok(reply::json(&RecommendationsResponse { items }))
+}
@@ -0,0 +1,122 @@
+use crate::auth::AuthUser;
+use crate::db::PgPool;
+use crate::errors::AppError;
+use crate::handlers::dtos::{
+    CreateReviewRequest, ListProductReviewsQuery, ListReviewsQuery, ModerateReviewRequest,
+    PageLinks, PageResponse, ReviewResponse,
+};
+use crate::handlers::paging::{page_limit, page_link, page_offset};
+use crate::models::product_review::{ProductReview, REVIEW_PENDING};
+use crate::services::product_review_service;
+use uuid::Uuid;
+use warp::http::StatusCode;
+use warp::{Reply, reply};
+
+pub async fn create(
+    pool: PgPool,
+    caller: AuthUser,
+    product_id: Uuid,
+    req: CreateReviewRequest,
+) -> Result<impl Reply, AppError> {
+    let review = product_review_service::create_review(
+        pool,
+        product_id,
+        caller.user_id,
+        req.rating,
+        req.title,
+        req.body,
+    )
+    .await?;
+    Ok(reply::with_status(
+        reply::json(&ReviewResponse::from(review)),
+        StatusCode::CREATED,
+    ))
+}
+
+pub async fn list_for_product(
+    pool: PgPool,
+    product_id: Uuid,
+    query: ListProductReviewsQuery,
+) -> Result<impl Reply, AppError> {
+    let limit = page_limit(query.limit)?;
+    let offset = page_offset(query.offset)?;
+    let verified_only = query.verified_only.unwrap_or(false);
+
+    let (reviews, total) = product_review_service::list_product_reviews(
+        pool,
+        product_id,
+        verified_only,
+        offset,
+        limit,
+    )
+    .await?;
+
+    let path = format!("/products/{product_id}/reviews");
+    let link = |offset| {
+        page_link(
+            &path,
+            &ListProductReviewsQuery {
+                offset: Some(offset),
+                ..query.clone()
+            },
+        )
+    };
+    page_of(reviews, total, limit, offset, link)
+}
+
+pub async fn list(pool: PgPool, query: ListReviewsQuery) -> Result<impl Reply, AppError> {
+    let limit = page_limit(query.limit)?;
+    let offset = page_offset(query.offset)?;
+    let status = query
+        .status
+        .clone()
+        .unwrap_or_else(|| REVIEW_PENDING.to_string());
+
+    let (reviews, total) =
+        product_review_service::list_reviews(pool, status, offset, limit).await?;
+
+    let link = |offset| {
+        page_link(
+            "/reviews",
+            &ListReviewsQuery {
+                offset: Some(offset),
+                ..query.clone()
+            },
+        )
+    };
+    page_of(reviews, total, limit, offset, link)
+}
+
+fn page_of(
+    reviews: Vec<ProductReview>,
+    total: i64,
+    limit: i64,
+    offset: i64,
+    link: impl Fn(i64) -> Result<String, AppError>,
+) -> Result<reply::Json, AppError> {
+    let mut links = PageLinks::default();
+    if offset + (reviews.len() as i64) < total {
+        links.next = Some(link(offset + limit)?);
+    }
+    if offset > 0 {
+        links.prev = Some(link((offset - limit).max(0))?);
+    }
+
+    Ok(reply::json(&PageResponse {
+        items: reviews.into_iter().map(ReviewResponse::from).collect(),
+        total,
+        limit,
+        offset: Some(offset),
+        next_cursor: None,
+        links,
+    }))
+}
+
+pub async fn moderate(
+    pool: PgPool,
+    review_id: Uuid,
+    req: ModerateReviewRequest,
+) -> Result<impl Reply, AppError> {
+    let review = product_review_service::moderate_review(pool, review_id, req.status).await?;
+    Ok(reply::json(&ReviewResponse::from(review)))
+}
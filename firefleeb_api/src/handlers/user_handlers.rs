@@ -1,21 +1,68 @@
+use crate::auth::{AuthConfig, AuthUser};
 use crate::db::PgPool;
 use crate::errors::AppError;
 use crate::handlers::dtos::{
-    CreateUserRequest, LoginRequest, UpdatePasswordRequest, UpdateUserRequest, UserResponse,
+    CreateUserRequest, ForgotPasswordRequest, LoginRequest, LoginResponse, RefreshTokenRequest,
+    ResendVerificationRequest, ResetPasswordRequest, UpdatePasswordRequest, UpdateRoleRequest,
+    UpdateUserRequest, UserResponse, VerifyEmailRequest,
 };
+use crate::mail::SharedMailer;
 use crate::models::user::UpdateUser;
-use crate::services::user_service;
+use crate::services::user_service::{self, Session};
+use std::net::SocketAddr;
 use uuid::Uuid;
 use warp::{Reply, reply};
 
-pub async fn register(pool: PgPool, req: CreateUserRequest) -> Result<impl Reply, AppError> {
-    let user = user_service::register_user(pool, req.email, req.password).await?;
+pub async fn register(
+    pool: PgPool,
+    auth: AuthConfig,
+    mailer: SharedMailer,
+    req: CreateUserRequest,
+) -> Result<impl Reply, AppError> {
+    let user = user_service::register_user(pool, &auth, mailer, req.email, req.password).await?;
     Ok(reply::json(&UserResponse::from(user)))
 }
 
-pub async fn login(pool: PgPool, req: LoginRequest) -> Result<impl Reply, AppError> {
-    let user = user_service::authenticate_user(pool, req.email, req.password).await?;
-    Ok(reply::json(&UserResponse::from(user)))
+pub async fn login(
+    pool: PgPool,
+    auth: AuthConfig,
+    remote: Option<SocketAddr>,
+    req: LoginRequest,
+) -> Result<impl Reply, AppError> {
+    let client_ip = remote.map(|addr| addr.ip());
+    let user =
+        user_service::authenticate_user(pool.clone(), &auth, req.email, req.password, client_ip)
+            .await?;
+    let session = user_service::start_session(pool, &auth, user).await?;
+    Ok(reply::json(&login_response(session)))
+}
+
+pub async fn refresh(
+    pool: PgPool,
+    auth: AuthConfig,
+    req: RefreshTokenRequest,
+) -> Result<impl Reply, AppError> {
+    let session = user_service::refresh_session(pool, &auth, req.refresh_token).await?;
+    Ok(reply::json(&login_response(session)))
+}
+
+fn login_response(session: Session) -> LoginResponse {
+    LoginResponse {
+        access_token: session.access.token,
+        token_type: "Bearer".into(),
+        expires_in: session.access.expires_in,
+        refresh_token: session.refresh_token,
+        refresh_expires_in: session.refresh_expires_in,
+        user: UserResponse::from(session.user),
+    }
+}
+
+pub async fn logout(pool: PgPool, req: RefreshTokenRequest) -> Result<impl Reply, AppError> {
+    user_service::revoke_session(pool, req.refresh_token).await?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&serde_json::json!({"message": "logged out"})),
+        warp::http::StatusCode::NO_CONTENT,
+    ))
 }
 
 pub async fn get(pool: PgPool, user_id: Uuid) -> Result<impl Reply, AppError> {
@@ -25,6 +72,9 @@ pub async fn get(pool: PgPool, user_id: Uuid) -> Result<impl Reply, AppError> {
 
 pub async fn update_user(
     pool: PgPool,
+    auth: AuthConfig,
+    mailer: SharedMailer,
+    caller: AuthUser,
     user_id: Uuid,
     req: UpdateUserRequest,
 ) -> Result<impl Reply, AppError> {
@@ -33,18 +83,88 @@ pub async fn update_user(
         password_hash: None,
     };
 
-    let user = user_service::update_user(pool, user_id, update).await?;
+    let user = user_service::update_user(pool, &auth, mailer, &caller, user_id, update).await?;
     Ok(reply::json(&UserResponse::from(user)))
 }
 
 pub async fn change_password(
     pool: PgPool,
+    auth: AuthConfig,
+    caller: AuthUser,
     user_id: Uuid,
     req: UpdatePasswordRequest,
 ) -> Result<impl Reply, AppError> {
-    user_service::update_user_password(pool, user_id, req.old_password, req.new_password).await?;
+    user_service::update_user_password(
+        pool,
+        &auth,
+        &caller,
+        user_id,
+        req.old_password,
+        req.new_password,
+    )
+    .await?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&serde_json::json!({"message": "password updated"})),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+pub async fn forgot_password(
+    pool: PgPool,
+    auth: AuthConfig,
+    mailer: SharedMailer,
+    remote: Option<SocketAddr>,
+    req: ForgotPasswordRequest,
+) -> Result<impl Reply, AppError> {
+    let client_ip = remote.map(|addr| addr.ip());
+    user_service::request_password_reset(pool, &auth, mailer, req.email, client_ip).await?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&serde_json::json!({
+            "message": "If the account exists, password reset instructions have been sent"
+        })),
+        warp::http::StatusCode::ACCEPTED,
+    ))
+}
+
+pub async fn reset_password(
+    pool: PgPool,
+    auth: AuthConfig,
+    req: ResetPasswordRequest,
+) -> Result<impl Reply, AppError> {
+    user_service::reset_password_with_token(pool, &auth, req.token, req.new_password).await?;
     Ok(warp::reply::with_status(
         warp::reply::json(&serde_json::json!({"message": "password updated"})),
         warp::http::StatusCode::OK,
     ))
 }
+
+pub async fn verify_email(pool: PgPool, req: VerifyEmailRequest) -> Result<impl Reply, AppError> {
+    let user = user_service::verify_email(pool, req.token).await?;
+    Ok(reply::json(&UserResponse::from(user)))
+}
+
+pub async fn resend_verification(
+    pool: PgPool,
+    auth: AuthConfig,
+    mailer: SharedMailer,
+    remote: Option<SocketAddr>,
+    req: ResendVerificationRequest,
+) -> Result<impl Reply, AppError> {
+    let client_ip = remote.map(|addr| addr.ip());
+    user_service::resend_email_verification(pool, &auth, mailer, req.email, client_ip).await?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&serde_json::json!({
+            "message": "If the account needs verification, a new email has been sent"
+        })),
+        warp::http::StatusCode::ACCEPTED,
+    ))
+}
+
+pub async fn update_role(
+    pool: PgPool,
+    user_id: Uuid,
+    req: UpdateRoleRequest,
+) -> Result<impl Reply, AppError> {
+    let user = user_service::set_user_role(pool, user_id, req.role).await?;
+    Ok(reply::json(&UserResponse::from(user)))
+}
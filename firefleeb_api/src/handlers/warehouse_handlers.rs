@@ -0,0 +1,51 @@
+use crate::db::PgPool;
+use crate::errors::AppError;
+use crate::handlers::dtos::{
+    CreateWarehouseRequest, UpdateWarehouseRequest, WarehouseResponse, WarehousesResponse,
+};
+use crate::models::warehouse::{NewWarehouse, UpdateWarehouse};
+use crate::services::warehouse_service;
+use uuid::Uuid;
+use warp::http::StatusCode;
+use warp::{Reply, reply};
+
+pub async fn create(pool: PgPool, req: CreateWarehouseRequest) -> Result<impl Reply, AppError> {
+    let new_warehouse = NewWarehouse {
+        code: req.code,
+        name: req.name,
+        latitude: req.latitude,
+        longitude: req.longitude,
+    };
+
+    let warehouse = warehouse_service::create_warehouse(pool, new_warehouse).await?;
+    Ok(reply::with_status(
+        reply::json(&WarehouseResponse::from(warehouse)),
+        StatusCode::CREATED,
+    ))
+}
+
+pub async fn list(pool: PgPool) -> Result<impl Reply, AppError> {
+    let warehouses = warehouse_service::list_warehouses(pool).await?;
+    Ok(reply::json(&WarehousesResponse {
+        warehouses: warehouses
+            .into_iter()
+            .map(WarehouseResponse::from)
+            .collect(),
+    }))
+}
+
+pub async fn update(
+    pool: PgPool,
+    id: Uuid,
+    req: UpdateWarehouseRequest,
+) -> Result<impl Reply, AppError> {
+    let update = UpdateWarehouse {
+        code: req.code,
+        name: req.name,
+        latitude: req.latitude,
+        longitude: req.longitude,
+    };
+
+    let warehouse = warehouse_service::update_warehouse(pool, id, update, req.is_default).await?;
+    Ok(reply::json(&WarehouseResponse::from(warehouse)))
+}
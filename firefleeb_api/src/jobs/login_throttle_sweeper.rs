@@ -0,0 +1,25 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use tokio::task::JoinHandle;
+
+use crate::auth::LoginThrottlePolicy;
+use crate::db::PgPool;
+use crate::jobs::spawn_periodic;
+use crate::services::login_throttle_service;
+
+/// Delete login throttle rows that no longer lock anyone out or count towards a limit,
+/// checking every `period`.
+pub fn spawn(pool: PgPool, period: Duration, policy: LoginThrottlePolicy) -> JoinHandle<()> {
+    spawn_periodic("login_throttle_sweeper", period, move || {
+        let pool = pool.clone();
+        let policy = policy.clone();
+        async move {
+            let deleted = login_throttle_service::purge_stale(pool, &policy, Utc::now()).await?;
+            if deleted > 0 {
+                tracing::info!(deleted, "deleted stale login throttle rows");
+            }
+            Ok(())
+        }
+    })
+}
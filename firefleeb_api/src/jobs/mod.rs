@@ -0,0 +1,33 @@
+//! Background tasks that run on a fixed interval alongside the server.
+
+use std::future::Future;
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+use crate::errors::AppError;
+
+pub mod login_throttle_sweeper;
+pub mod price_scheduler;
+pub mod recommendation_builder;
+pub mod reservation_sweeper;
+
+/// Run `tick` every `period`, starting right away. A failed run is logged and retried on the
+/// next tick; runs never overlap.
+pub fn spawn_periodic<F, Fut>(name: &'static str, period: Duration, mut tick: F) -> JoinHandle<()>
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<(), AppError>> + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            if let Err(err) = tick().await {
+                tracing::warn!(job = name, error = %err, "background job failed");
+            }
+        }
+    })
+}
@@ -0,0 +1,22 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use tokio::task::JoinHandle;
+
+use crate::db::PgPool;
+use crate::jobs::spawn_periodic;
+use crate::services::product_price_service;
+
+/// Keep product prices in step with their scheduled windows, checking every `period`.
+pub fn spawn(pool: PgPool, period: Duration) -> JoinHandle<()> {
+    spawn_periodic("price_scheduler", period, move || {
+        let pool = pool.clone();
+        async move {
+            let changed = product_price_service::apply_scheduled_prices(pool, Utc::now()).await?;
+            if changed > 0 {
+                tracing::info!(changed, "applied scheduled prices");
+            }
+            Ok(())
+        }
+    })
+}
@@ -0,0 +1,20 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use tokio::task::JoinHandle;
+
+use crate::db::PgPool;
+use crate::jobs::spawn_periodic;
+use crate::services::product_recommendation_service;
+
+/// Recompute "frequently bought together" recommendations from checked-out carts every `period`.
+pub fn spawn(pool: PgPool, period: Duration) -> JoinHandle<()> {
+    spawn_periodic("recommendation_builder", period, move || {
+        let pool = pool.clone();
+        async move {
+            let pairs = product_recommendation_service::rebuild(pool, Utc::now()).await?;
+            tracing::info!(pairs, "rebuilt product recommendations");
+            Ok(())
+        }
+    })
+}
@@ -0,0 +1,22 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use tokio::task::JoinHandle;
+
+use crate::db::PgPool;
+use crate::jobs::spawn_periodic;
+use crate::services::stock_reservation_service;
+
+/// Release expired cart stock reservations, checking every `period`.
+pub fn spawn(pool: PgPool, period: Duration) -> JoinHandle<()> {
+    spawn_periodic("reservation_sweeper", period, move || {
+        let pool = pool.clone();
+        async move {
+            let released = stock_reservation_service::release_expired(pool, Utc::now()).await?;
+            if released > 0 {
+                tracing::info!(released, "released expired stock reservations");
+            }
+            Ok(())
+        }
+    })
+}
@@ -1,13 +1,20 @@
+pub mod auth;
+pub mod config;
 pub mod db;
 pub mod errors;
 pub mod handlers;
+pub mod jobs;
+pub mod mail;
 pub mod models;
 pub mod routes;
 pub mod schema;
 pub mod services;
+pub mod storage;
 pub mod types;
 
 // Re-export submodules you need from models/db:
+pub use auth::*;
+pub use config::*;
 pub use db::*;
 pub use errors::*;
 pub use handlers::*;
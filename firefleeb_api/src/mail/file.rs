@@ -0,0 +1,43 @@
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::mail::{MailError, Mailer, OutgoingMail};
+
+/// Writes every message to its own `.eml` file; handy for local development.
+#[derive(Debug, Clone)]
+pub struct FileMailer {
+    dir: PathBuf,
+    from: String,
+}
+
+impl FileMailer {
+    pub fn new(dir: impl Into<PathBuf>, from: impl Into<String>) -> Self {
+        Self {
+            dir: dir.into(),
+            from: from.into(),
+        }
+    }
+}
+
+impl Mailer for FileMailer {
+    fn send(&self, mail: &OutgoingMail) -> Result<(), MailError> {
+        fs::create_dir_all(&self.dir)?;
+        let file_name = format!(
+            "{}-{}.eml",
+            Utc::now().format("%Y%m%dT%H%M%S"),
+            Uuid::new_v4()
+        );
+        let contents = format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\n\r\n{}",
+            self.from,
+            mail.to.as_str(),
+            mail.subject,
+            mail.body
+        );
+        fs::write(self.dir.join(file_name), contents)?;
+        Ok(())
+    }
+}
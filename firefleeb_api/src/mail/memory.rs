@@ -0,0 +1,30 @@
+use std::sync::{Arc, Mutex};
+
+use crate::mail::{MailError, Mailer, OutgoingMail};
+
+/// Keeps sent mail in memory. Clones share the same outbox, which lets tests
+/// hand one clone to the routes and inspect the other.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryMailer {
+    outbox: Arc<Mutex<Vec<OutgoingMail>>>,
+}
+
+impl InMemoryMailer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn sent(&self) -> Vec<OutgoingMail> {
+        self.outbox.lock().expect("mail outbox poisoned").clone()
+    }
+}
+
+impl Mailer for InMemoryMailer {
+    fn send(&self, mail: &OutgoingMail) -> Result<(), MailError> {
+        self.outbox
+            .lock()
+            .expect("mail outbox poisoned")
+            .push(mail.clone());
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::config::{MailConfig, MailTransport};
+use crate::types::email::Email;
+
+pub mod file;
+pub mod memory;
+pub mod smtp;
+
+pub use file::FileMailer;
+pub use memory::InMemoryMailer;
+pub use smtp::{SmtpMailer, SmtpSettings};
+
+/// A plain-text email addressed to a single recipient.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutgoingMail {
+    pub to: Email,
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Debug, Error)]
+pub enum MailError {
+    #[error("invalid mail address: {0}")]
+    Address(String),
+    #[error("failed to build message: {0}")]
+    Build(String),
+    #[error("mail transport failed: {0}")]
+    Transport(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Delivery backend for outgoing mail. Implementations may block.
+pub trait Mailer: Send + Sync {
+    fn send(&self, mail: &OutgoingMail) -> Result<(), MailError>;
+}
+
+pub type SharedMailer = Arc<dyn Mailer>;
+
+/// Send on a blocking thread so slow transports never stall the async runtime.
+pub async fn deliver(mailer: SharedMailer, mail: OutgoingMail) -> Result<(), MailError> {
+    tokio::task::spawn_blocking(move || mailer.send(&mail))
+        .await
+        .map_err(|e| MailError::Transport(format!("mail task panicked: {e}")))?
+}
+
+/// Build the mailer described by the mail config.
+pub fn mailer_from_config(config: &MailConfig) -> Result<SharedMailer, MailError> {
+    Ok(match &config.transport {
+        MailTransport::Smtp(settings) => Arc::new(SmtpMailer::new(settings, &config.from)?),
+        MailTransport::File(dir) => Arc::new(FileMailer::new(dir, config.from.clone())),
+        MailTransport::Memory => Arc::new(InMemoryMailer::new()),
+    })
+}
@@ -0,0 +1,62 @@
+use lettre::message::{Mailbox, header::ContentType};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+
+use crate::mail::{MailError, Mailer, OutgoingMail};
+
+#[derive(Debug, Clone)]
+pub struct SmtpSettings {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+/// Delivers mail through an SMTP relay using STARTTLS.
+pub struct SmtpMailer {
+    transport: SmtpTransport,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new(settings: &SmtpSettings, from: &str) -> Result<Self, MailError> {
+        let from = from
+            .parse::<Mailbox>()
+            .map_err(|e| MailError::Address(e.to_string()))?;
+
+        let mut builder = SmtpTransport::starttls_relay(&settings.host)
+            .map_err(|e| MailError::Transport(e.to_string()))?
+            .port(settings.port);
+        if let (Some(username), Some(password)) = (&settings.username, &settings.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            from,
+        })
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send(&self, mail: &OutgoingMail) -> Result<(), MailError> {
+        let to = mail
+            .to
+            .as_str()
+            .parse::<Mailbox>()
+            .map_err(|e| MailError::Address(e.to_string()))?;
+
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(mail.subject.clone())
+            .header(ContentType::TEXT_PLAIN)
+            .body(mail.body.clone())
+            .map_err(|e| MailError::Build(e.to_string()))?;
+
+        self.transport
+            .send(&message)
+            .map(|_| ())
+            .map_err(|e| MailError::Transport(e.to_string()))
+    }
+}
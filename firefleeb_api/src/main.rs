@@ -1,11 +1,18 @@
-use std::net::SocketAddr;
+use std::sync::Arc;
 
 use dotenv::dotenv;
-use firefleeb_api::db::{PgPool, get_conn, init_pool, run_migrations};
+use firefleeb_api::config::{AppConfig, LogFormat, LoggingConfig};
+use firefleeb_api::db::{PgPool, get_conn, init_pool_with, run_migrations};
+use firefleeb_api::jobs::{
+    login_throttle_sweeper, price_scheduler, recommendation_builder, reservation_sweeper,
+};
+use firefleeb_api::mail::mailer_from_config;
 use firefleeb_api::routes::{
-    cart_routes::cart_routes, handle_rejection, product_routes::product_routes,
-    user_routes::user_routes,
+    attribute_routes::attribute_routes, cart_routes::cart_routes, category_routes::category_routes,
+    handle_rejection, product_routes::product_routes, recommendation_routes::recommendation_routes,
+    review_routes::review_routes, user_routes::user_routes, warehouse_routes::warehouse_routes,
 };
+use firefleeb_api::storage::blob_store_from_config;
 use tracing_subscriber::EnvFilter;
 use warp::Filter;
 
@@ -19,24 +26,37 @@ async fn main() {
 
 async fn run() -> Result<(), Box<dyn std::error::Error>> {
     dotenv().ok();
-    init_tracing();
 
-    let database_url =
-        std::env::var("DATABASE_URL").expect("DATABASE_URL must be set to start the API");
-    let port = std::env::var("PORT")
-        .ok()
-        .and_then(|raw| raw.parse::<u16>().ok())
-        .unwrap_or(8080);
+    let config = Arc::new(AppConfig::load().map_err(|e| format!("invalid configuration: {e}"))?);
+    init_tracing(&config.logging);
+    let mailer = mailer_from_config(&config.mail)
+        .map_err(|e| format!("cannot set up mail delivery: {e}"))?;
 
-    let pool = init_pool(&database_url).expect("failed to create DB pool");
+    let pool = init_pool_with(&config.database)
+        .map_err(|e| format!("cannot connect to the database: {e}"))?;
     run_pending_migrations(&pool);
 
-    let api = product_routes(pool.clone())
-        .or(cart_routes(pool.clone()))
-        .or(user_routes(pool))
+    let blobs = blob_store_from_config(&config.storage);
+    price_scheduler::spawn(pool.clone(), config.jobs.price_schedule_interval);
+    reservation_sweeper::spawn(pool.clone(), config.jobs.reservation_sweep_interval);
+    recommendation_builder::spawn(pool.clone(), config.jobs.recommendation_interval);
+    login_throttle_sweeper::spawn(
+        pool.clone(),
+        config.jobs.login_throttle_sweep_interval,
+        config.auth.login_throttle.clone(),
+    );
+
+    let api = product_routes(pool.clone(), config.clone(), blobs)
+        .or(category_routes(pool.clone(), config.clone()))
+        .or(cart_routes(pool.clone(), config.clone()))
+        .or(warehouse_routes(pool.clone(), config.clone()))
+        .or(review_routes(pool.clone(), config.clone()))
+        .or(attribute_routes(pool.clone(), config.clone()))
+        .or(recommendation_routes(pool.clone(), config.clone()))
+        .or(user_routes(pool, config.clone(), mailer))
         .recover(handle_rejection);
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    let addr = config.server.bind_address;
     tracing::info!("🚀 FireFleeb API listening on http://{addr}");
     warp::serve(api).run(addr).await;
     Ok(())
@@ -47,7 +67,12 @@ fn run_pending_migrations(pool: &PgPool) {
     run_migrations(&mut conn).expect("migrations failed");
 }
 
-fn init_tracing() {
-    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
-    let _ = tracing_subscriber::fmt().with_env_filter(filter).try_init();
+fn init_tracing(logging: &LoggingConfig) {
+    // The filter was validated when the config was loaded
+    let filter = EnvFilter::try_new(&logging.filter).unwrap_or_else(|_| EnvFilter::new("info"));
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    let _ = match logging.format {
+        LogFormat::Pretty => builder.try_init(),
+        LogFormat::Json => builder.json().try_init(),
+    };
 }
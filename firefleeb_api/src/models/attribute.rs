@@ -0,0 +1,65 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::models::product::Product;
+use crate::schema::{attribute_definitions, product_attributes};
+
+/// Free text, stored as a JSON string.
+pub const ATTRIBUTE_TEXT: &str = "text";
+/// A JSON number, such as a wattage.
+pub const ATTRIBUTE_NUMBER: &str = "number";
+/// `true` or `false`.
+pub const ATTRIBUTE_BOOLEAN: &str = "boolean";
+
+pub const ATTRIBUTE_TYPES: [&str; 3] = [ATTRIBUTE_TEXT, ATTRIBUTE_NUMBER, ATTRIBUTE_BOOLEAN];
+
+/// An attribute products can carry, defined by an admin.
+#[derive(Debug, Clone, Queryable, Selectable, Identifiable, Serialize, Deserialize)]
+#[diesel(table_name = attribute_definitions)]
+pub struct AttributeDefinition {
+    pub id: Uuid,
+    /// Key of the attribute in product attributes and listing filters.
+    pub code: String,
+    pub name: String,
+    pub value_type: String,
+    /// JSON array of the values products may use; `None` allows any value of the type.
+    pub allowed_values: Option<Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AttributeDefinition {
+    pub fn allowed_values(&self) -> Option<&Vec<Value>> {
+        self.allowed_values.as_ref().and_then(Value::as_array)
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = attribute_definitions)]
+pub struct NewAttributeDefinition {
+    pub code: String,
+    pub name: String,
+    pub value_type: String,
+    pub allowed_values: Option<Value>,
+}
+
+#[derive(Debug, Default, AsChangeset)]
+#[diesel(table_name = attribute_definitions)]
+pub struct UpdateAttributeDefinition {
+    pub name: Option<String>,
+    /// `Some(None)` allows any value again.
+    pub allowed_values: Option<Option<Value>>,
+}
+
+/// A product's value for one attribute.
+#[derive(Debug, Clone, Queryable, Selectable, Insertable, Associations, Serialize, Deserialize)]
+#[diesel(belongs_to(Product))]
+#[diesel(belongs_to(AttributeDefinition, foreign_key = attribute_id))]
+#[diesel(table_name = product_attributes)]
+pub struct ProductAttribute {
+    pub product_id: Uuid,
+    pub attribute_id: Uuid,
+    pub value: Value,
+}
@@ -9,6 +9,9 @@ use uuid::Uuid;
 use crate::models::user::User;
 use crate::schema::carts;
 
+pub const CART_STATUS_ACTIVE: &str = "active";
+pub const CART_STATUS_CHECKED_OUT: &str = "checked_out";
+
 #[derive(Debug, Queryable, Selectable, Identifiable, Associations, Serialize, Deserialize)]
 #[diesel(belongs_to(User))]
 #[diesel(table_name = carts)]
@@ -4,20 +4,23 @@ use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::models::{cart::Cart, product::Product};
+use crate::models::{cart::Cart, product::Product, product_variant::ProductVariant};
 use crate::schema::cart_items;
 
 #[derive(Debug, Queryable, Identifiable, Associations, Serialize, Deserialize)]
 #[diesel(belongs_to(Cart))]
 #[diesel(belongs_to(Product, foreign_key = item_id))]
+#[diesel(belongs_to(ProductVariant, foreign_key = variant_id))]
 #[diesel(table_name = cart_items)]
 pub struct CartItem {
     pub id: Uuid,
+    /// The product; `variant_id` says which of its variants.
     pub item_id: Uuid,
     pub cart_id: Uuid,
     pub quantity: i32,
     pub unit_price: BigDecimal,
     pub created_at: Option<DateTime<Utc>>,
+    pub variant_id: Uuid,
 }
 
 #[derive(Debug, Insertable, Serialize, Deserialize)]
@@ -27,6 +30,8 @@ pub struct NewCartItem {
     pub cart_id: Uuid,
     pub quantity: i32,
     pub unit_price: BigDecimal,
+    /// Resolved by the cart item service from `item_id`, which may name a product or a variant.
+    pub variant_id: Option<Uuid>,
 }
 
 #[derive(Debug, AsChangeset, Serialize, Deserialize)]
@@ -40,11 +45,14 @@ pub struct UpdateCartItem {
 pub struct CartItemResponse {
     pub id: Uuid,
     pub item_id: Uuid,
+    pub variant_id: Uuid,
     pub cart_id: Uuid,
     pub quantity: i32,
     pub unit_price: BigDecimal,
     pub total_price: BigDecimal, // quantity * unit_price
     pub created_at: Option<DateTime<Utc>>,
+    /// `false` once the product has been archived; such lines block checkout until removed.
+    pub available: bool,
 }
 
 impl CartItem {
@@ -54,15 +62,17 @@ impl CartItem {
     }
 
     // Convert to response struct
-    pub fn to_response(&self) -> CartItemResponse {
+    pub fn to_response(&self, available: bool) -> CartItemResponse {
         CartItemResponse {
             id: self.id,
             item_id: self.item_id,
+            variant_id: self.variant_id,
             cart_id: self.cart_id,
             quantity: self.quantity,
             unit_price: self.unit_price.clone(),
             total_price: self.total_price(),
             created_at: self.created_at,
+            available,
         }
     }
 }
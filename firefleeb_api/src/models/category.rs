@@ -0,0 +1,62 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::schema::{categories, product_categories};
+
+#[derive(Debug, Clone, Queryable, Selectable, Identifiable, Serialize, Deserialize)]
+#[diesel(table_name = categories)]
+pub struct Category {
+    pub id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = categories)]
+pub struct NewCategory {
+    pub parent_id: Option<Uuid>,
+    pub name: String,
+}
+
+#[derive(Debug, Default, AsChangeset)]
+#[diesel(table_name = categories)]
+pub struct UpdateCategory {
+    pub name: Option<String>,
+    /// `Some(None)` moves the category to the root.
+    pub parent_id: Option<Option<Uuid>>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = product_categories)]
+pub struct NewProductCategory {
+    pub product_id: Uuid,
+    pub category_id: Uuid,
+}
+
+/// One ancestor-or-self step of a category path, as returned by the recursive path queries.
+#[derive(Debug, QueryableByName)]
+pub struct CategoryPathStep {
+    /// Product the path was requested for; `None` when paths were requested by category.
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Uuid>)]
+    pub product_id: Option<Uuid>,
+    /// Category the path leads to.
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    pub leaf_id: Uuid,
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    pub id: Uuid,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub name: String,
+    /// 0 for the leaf, growing towards the root.
+    #[diesel(sql_type = diesel::sql_types::Int4)]
+    pub depth: i32,
+}
+
+/// A category as one step of a breadcrumb trail.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CategoryCrumb {
+    pub id: Uuid,
+    pub name: String,
+}
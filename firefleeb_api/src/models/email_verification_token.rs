@@ -0,0 +1,37 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::user::User;
+use crate::schema::email_verification_tokens;
+use crate::types::email::Email;
+
+#[derive(Debug, Queryable, Selectable, Identifiable, Associations, Serialize, Deserialize)]
+#[diesel(belongs_to(User))]
+#[diesel(table_name = email_verification_tokens)]
+pub struct EmailVerificationToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub email: Email,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = email_verification_tokens)]
+pub struct NewEmailVerificationToken {
+    pub user_id: Uuid,
+    pub email: Email,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl EmailVerificationToken {
+    /// A token confirms the address it was sent to, once, before it expires.
+    pub fn is_redeemable_for(&self, current_email: &Email) -> bool {
+        self.used_at.is_none() && self.expires_at > Utc::now() && &self.email == current_email
+    }
+}
@@ -0,0 +1,40 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::schema::login_throttles;
+
+#[derive(Debug, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = login_throttles)]
+pub struct LoginThrottle {
+    pub scope: String,
+    pub subject: String,
+    pub failed_count: i32,
+    pub locked_until: Option<DateTime<Utc>>,
+    pub last_failed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = login_throttles)]
+pub struct NewLoginThrottle {
+    pub scope: String,
+    pub subject: String,
+}
+
+#[derive(Debug, AsChangeset, Serialize, Deserialize)]
+#[diesel(table_name = login_throttles)]
+#[diesel(treat_none_as_null = true)]
+pub struct UpdateLoginThrottle {
+    pub failed_count: i32,
+    pub locked_until: Option<DateTime<Utc>>,
+    pub last_failed_at: Option<DateTime<Utc>>,
+}
+
+impl LoginThrottle {
+    /// Whole seconds until the lockout ends, or `None` when the subject may try again now.
+    pub fn retry_after_secs(&self, now: DateTime<Utc>) -> Option<u64> {
+        let locked_until = self.locked_until.filter(|until| *until > now)?;
+        let millis = (locked_until - now).num_milliseconds();
+        Some(((millis + 999) / 1000) as u64)
+    }
+}
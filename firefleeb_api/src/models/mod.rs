@@ -1,9 +1,37 @@
+pub mod attribute;
 pub mod cart;
 pub mod cart_item;
+pub mod category;
+pub mod email_verification_token;
+pub mod login_throttle;
+pub mod password_reset_token;
 pub mod product;
+pub mod product_image;
+pub mod product_price;
+pub mod product_recommendation;
+pub mod product_review;
+pub mod product_variant;
+pub mod refresh_token;
+pub mod stock_movement;
+pub mod stock_reservation;
 pub mod user;
+pub mod warehouse;
 
+pub use attribute::*;
 pub use cart::*;
 pub use cart_item::*;
+pub use category::*;
+pub use email_verification_token::*;
+pub use login_throttle::*;
+pub use password_reset_token::*;
 pub use product::*;
+pub use product_image::*;
+pub use product_price::*;
+pub use product_recommendation::*;
+pub use product_review::*;
+pub use product_variant::*;
+pub use refresh_token::*;
+pub use stock_movement::*;
+pub use stock_reservation::*;
 pub use user::*;
+pub use warehouse::*;
@@ -0,0 +1,34 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::user::User;
+use crate::schema::password_reset_tokens;
+
+#[derive(Debug, Queryable, Selectable, Identifiable, Associations, Serialize, Deserialize)]
+#[diesel(belongs_to(User))]
+#[diesel(table_name = password_reset_tokens)]
+pub struct PasswordResetToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = password_reset_tokens)]
+pub struct NewPasswordResetToken {
+    pub user_id: Uuid,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl PasswordResetToken {
+    /// A token can be redeemed once, and only before it expires.
+    pub fn is_redeemable(&self) -> bool {
+        self.used_at.is_none() && self.expires_at > Utc::now()
+    }
+}
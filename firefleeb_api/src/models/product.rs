@@ -7,7 +7,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::schema::products;
 
-#[derive(Debug, Queryable, Selectable, Identifiable, Serialize, Deserialize)]
+#[derive(Debug, Queryable, QueryableByName, Selectable, Identifiable, Serialize, Deserialize)]
 #[diesel(table_name = products)]
 pub struct Product {
     pub id: Uuid,
@@ -15,7 +15,47 @@ pub struct Product {
     pub product_description: Option<String>,
     pub price: BigDecimal,
     pub stock: i32,
-    pub created_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    /// Set once the product is withdrawn from sale. Archived products are hidden from the
+    /// catalog and cannot be added to carts.
+    pub archived_at: Option<DateTime<Utc>>,
+    /// Approved reviews of the product.
+    pub rating_count: i32,
+    /// Mean rating of the approved reviews, to two decimals; `None` until one is approved.
+    pub rating_average: Option<BigDecimal>,
+}
+
+impl Product {
+    pub fn is_archived(&self) -> bool {
+        self.archived_at.is_some()
+    }
+}
+
+/// A full-text search match with its relevance and highlighted text.
+#[derive(Debug, QueryableByName)]
+pub struct ProductSearchHit {
+    #[diesel(embed)]
+    pub product: Product,
+    #[diesel(sql_type = diesel::sql_types::Float4)]
+    pub rank: f32,
+    /// `product_name` with matching words wrapped in `<mark>`.
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub name_highlight: String,
+    /// Best-matching fragments of `product_description`, highlighted the same way.
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Text>)]
+    pub description_snippet: Option<String>,
+}
+
+/// A product name close to what the user typed.
+#[derive(Debug, QueryableByName)]
+pub struct ProductSuggestion {
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    pub id: Uuid,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub product_name: String,
+    /// Trigram word similarity between the input and the name, from 0 to 1.
+    #[diesel(sql_type = diesel::sql_types::Float4)]
+    pub similarity: f32,
 }
 
 #[derive(Debug, Insertable, Serialize, Deserialize)]
@@ -43,5 +83,5 @@ pub struct ProductResponse {
     pub product_description: Option<String>,
     pub price: BigDecimal,
     pub stock: i32,
-    pub created_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
 }
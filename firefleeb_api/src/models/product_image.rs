@@ -0,0 +1,48 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::product::Product;
+use crate::schema::product_images;
+
+/// An uploaded product image. The original and its thumbnails are kept in the blob store.
+#[derive(
+    Debug, Clone, Queryable, Selectable, Identifiable, Associations, Serialize, Deserialize,
+)]
+#[diesel(belongs_to(Product))]
+#[diesel(table_name = product_images)]
+pub struct ProductImage {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    /// MIME type of the original upload.
+    pub content_type: String,
+    pub width: i32,
+    pub height: i32,
+    pub byte_size: i64,
+    /// Bounding boxes of the generated thumbnails, smallest first.
+    pub thumbnail_sizes: Vec<Option<i32>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ProductImage {
+    pub fn thumbnail_sizes(&self) -> impl Iterator<Item = u32> + '_ {
+        self.thumbnail_sizes
+            .iter()
+            .flatten()
+            .map(|size| *size as u32)
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = product_images)]
+pub struct NewProductImage {
+    /// Chosen up front, since the blobs are written under it before the row exists.
+    pub id: Uuid,
+    pub product_id: Uuid,
+    pub content_type: String,
+    pub width: i32,
+    pub height: i32,
+    pub byte_size: i64,
+    pub thumbnail_sizes: Vec<Option<i32>>,
+}
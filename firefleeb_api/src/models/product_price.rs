@@ -0,0 +1,46 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::product::Product;
+use crate::schema::product_prices;
+
+/// Set through `POST`/`PUT /products`.
+pub const PRICE_SOURCE_MANUAL: &str = "manual";
+/// Planned ahead by an admin and applied by the price scheduler.
+pub const PRICE_SOURCE_SCHEDULED: &str = "scheduled";
+
+/// A price and the window it is valid in. Where windows overlap, the one that started last wins.
+#[derive(
+    Debug, Clone, Queryable, Selectable, Identifiable, Associations, Serialize, Deserialize,
+)]
+#[diesel(belongs_to(Product))]
+#[diesel(table_name = product_prices)]
+pub struct ProductPrice {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    pub price: BigDecimal,
+    pub valid_from: DateTime<Utc>,
+    /// `None` while the window is open-ended.
+    pub valid_until: Option<DateTime<Utc>>,
+    pub source: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ProductPrice {
+    pub fn covers(&self, at: DateTime<Utc>) -> bool {
+        self.valid_from <= at && self.valid_until.is_none_or(|until| until > at)
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = product_prices)]
+pub struct NewProductPrice {
+    pub product_id: Uuid,
+    pub price: BigDecimal,
+    pub valid_from: DateTime<Utc>,
+    pub valid_until: Option<DateTime<Utc>>,
+    pub source: String,
+}
@@ -0,0 +1,14 @@
+use diesel::prelude::*;
+
+use crate::models::product::Product;
+
+/// A product bought together with the ones a recommendation was asked for.
+#[derive(Debug, QueryableByName)]
+pub struct RecommendedProduct {
+    #[diesel(embed)]
+    pub product: Product,
+    /// Sum over the requested products of the share of their checked-out carts that also
+    /// contained this one.
+    #[diesel(sql_type = diesel::sql_types::Double)]
+    pub score: f64,
+}
@@ -0,0 +1,50 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::product::Product;
+use crate::models::user::User;
+use crate::schema::product_reviews;
+
+/// Waiting for staff to moderate it.
+pub const REVIEW_PENDING: &str = "pending";
+/// Shown on the product and counted in its rating.
+pub const REVIEW_APPROVED: &str = "approved";
+/// Hidden from shoppers.
+pub const REVIEW_REJECTED: &str = "rejected";
+
+pub const REVIEW_STATUSES: [&str; 3] = [REVIEW_PENDING, REVIEW_APPROVED, REVIEW_REJECTED];
+
+/// A customer's review of a product; each customer reviews a product at most once.
+#[derive(
+    Debug, Clone, Queryable, Selectable, Identifiable, Associations, Serialize, Deserialize,
+)]
+#[diesel(belongs_to(Product))]
+#[diesel(belongs_to(User))]
+#[diesel(table_name = product_reviews)]
+pub struct ProductReview {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    pub user_id: Uuid,
+    /// From 1 to 5 stars.
+    pub rating: i32,
+    pub title: String,
+    pub body: String,
+    /// The reviewer had checked out a cart with the product when writing the review.
+    pub verified_purchase: bool,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = product_reviews)]
+pub struct NewProductReview {
+    pub product_id: Uuid,
+    pub user_id: Uuid,
+    pub rating: i32,
+    pub title: String,
+    pub body: String,
+    pub verified_purchase: bool,
+}
@@ -0,0 +1,57 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::models::product::Product;
+use crate::schema::product_variants;
+
+/// A sellable version of a product, e.g. one size and colour of a shirt. Every product has
+/// exactly one default variant; products without real options only ever have that one.
+#[derive(
+    Debug, Clone, Queryable, Selectable, Identifiable, Associations, Serialize, Deserialize,
+)]
+#[diesel(belongs_to(Product))]
+#[diesel(table_name = product_variants)]
+pub struct ProductVariant {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    pub sku: String,
+    /// Option values as a flat JSON object, e.g. `{"size": "M", "colour": "red"}`.
+    pub options: Value,
+    /// Overrides the product's price when set.
+    pub price: Option<BigDecimal>,
+    pub stock: i32,
+    pub is_default: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ProductVariant {
+    /// The variant's own price, or the product's when it has none.
+    pub fn effective_price(&self, product_price: &BigDecimal) -> BigDecimal {
+        self.price.clone().unwrap_or_else(|| product_price.clone())
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = product_variants)]
+pub struct NewProductVariant {
+    pub product_id: Uuid,
+    pub sku: String,
+    pub options: Value,
+    pub price: Option<BigDecimal>,
+    pub stock: i32,
+    pub is_default: bool,
+}
+
+#[derive(Debug, Default, AsChangeset)]
+#[diesel(table_name = product_variants)]
+pub struct UpdateProductVariant {
+    pub sku: Option<String>,
+    pub options: Option<Value>,
+    /// `Some(None)` clears the override so the product's price applies again.
+    pub price: Option<Option<BigDecimal>>,
+    pub stock: Option<i32>,
+}
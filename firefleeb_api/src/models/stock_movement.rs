@@ -0,0 +1,65 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::product::Product;
+use crate::schema::stock_movements;
+
+/// Goods received into a warehouse.
+pub const MOVEMENT_RECEIPT: &str = "receipt";
+/// A correction after a count, damage or loss; the only type that may go either way.
+pub const MOVEMENT_ADJUSTMENT: &str = "adjustment";
+/// Goods sold, recorded for each line when a cart is checked out.
+pub const MOVEMENT_SALE: &str = "sale";
+/// Goods a customer sent back.
+pub const MOVEMENT_RETURN: &str = "return";
+
+/// Reason logged for the stock a product or variant is created with.
+pub const OPENING_STOCK_REASON: &str = "Opening stock";
+
+/// Reservations are not movements: they hold stock in `stock_reservations` without moving it.
+pub const MOVEMENT_TYPES: [&str; 4] = [
+    MOVEMENT_RECEIPT,
+    MOVEMENT_ADJUSTMENT,
+    MOVEMENT_SALE,
+    MOVEMENT_RETURN,
+];
+
+/// One change to a variant's on-hand stock.
+#[derive(
+    Debug, Clone, Queryable, Selectable, Identifiable, Associations, Serialize, Deserialize,
+)]
+#[diesel(belongs_to(Product))]
+#[diesel(table_name = stock_movements)]
+pub struct StockMovement {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    /// `None` once the variant has been deleted.
+    pub variant_id: Option<Uuid>,
+    pub movement_type: String,
+    /// Signed change to the stock: positive adds, negative takes away.
+    pub quantity: i32,
+    /// The variant's stock across all warehouses right after this movement.
+    pub stock_after: i32,
+    pub reason: String,
+    pub actor_id: Option<Uuid>,
+    /// The checked-out cart behind a sale.
+    pub cart_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    /// The warehouse whose stock moved.
+    pub warehouse_id: Uuid,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = stock_movements)]
+pub struct NewStockMovement {
+    pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
+    pub warehouse_id: Uuid,
+    pub movement_type: String,
+    pub quantity: i32,
+    pub reason: String,
+    pub actor_id: Option<Uuid>,
+    pub cart_id: Option<Uuid>,
+}
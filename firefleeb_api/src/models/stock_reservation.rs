@@ -0,0 +1,33 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::cart_item::CartItem;
+use crate::models::product_variant::ProductVariant;
+use crate::schema::stock_reservations;
+
+/// Stock of a variant held for a cart line until `expires_at`.
+#[derive(
+    Debug, Clone, Queryable, Selectable, Identifiable, Associations, Serialize, Deserialize,
+)]
+#[diesel(belongs_to(CartItem))]
+#[diesel(belongs_to(ProductVariant, foreign_key = variant_id))]
+#[diesel(table_name = stock_reservations)]
+pub struct StockReservation {
+    pub id: Uuid,
+    pub cart_item_id: Uuid,
+    pub variant_id: Uuid,
+    pub quantity: i32,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Insertable, AsChangeset)]
+#[diesel(table_name = stock_reservations)]
+pub struct NewStockReservation {
+    pub cart_item_id: Uuid,
+    pub variant_id: Uuid,
+    pub quantity: i32,
+    pub expires_at: DateTime<Utc>,
+}
@@ -6,6 +6,7 @@ use uuid::Uuid;
 
 use crate::schema::users;
 use crate::types::email::Email;
+use crate::types::role::Role;
 
 #[derive(Debug, Queryable, Selectable, Identifiable, Serialize, Deserialize)]
 #[diesel(check_for_backend(Pg))]
@@ -15,6 +16,8 @@ pub struct User {
     pub email: Email,
     pub password_hash: String,
     pub created_at: Option<DateTime<Utc>>,
+    pub role: Role,
+    pub email_verified_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Insertable, Serialize, Deserialize)]
@@ -35,5 +38,7 @@ pub struct UpdateUser {
 pub struct UserResponse {
     pub id: Uuid,
     pub email: Email,
+    pub role: Role,
+    pub email_verified_at: Option<DateTime<Utc>>,
     pub created_at: Option<DateTime<Utc>>,
 }
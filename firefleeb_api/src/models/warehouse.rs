@@ -0,0 +1,78 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::schema::{warehouse_stock, warehouses};
+
+/// A location stock is kept and shipped from.
+#[derive(Debug, Clone, Queryable, Selectable, Identifiable, Serialize, Deserialize)]
+#[diesel(table_name = warehouses)]
+pub struct Warehouse {
+    pub id: Uuid,
+    pub code: String,
+    pub name: String,
+    /// `None` when the location is unknown; such warehouses come last in nearest-first allocation.
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// Receives stock entered without naming a warehouse.
+    pub is_default: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Warehouse {
+    pub fn location(&self) -> Option<GeoPoint> {
+        Some(GeoPoint {
+            latitude: self.latitude?,
+            longitude: self.longitude?,
+        })
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = warehouses)]
+pub struct NewWarehouse {
+    pub code: String,
+    pub name: String,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+#[derive(Debug, Default, AsChangeset)]
+#[diesel(table_name = warehouses)]
+pub struct UpdateWarehouse {
+    pub code: Option<String>,
+    pub name: Option<String>,
+    /// `Some(None)` forgets the location.
+    pub latitude: Option<Option<f64>>,
+    pub longitude: Option<Option<f64>>,
+}
+
+/// On-hand stock of one variant in one warehouse.
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = warehouse_stock)]
+pub struct WarehouseStock {
+    pub warehouse_id: Uuid,
+    pub variant_id: Uuid,
+    pub stock: i32,
+}
+
+/// A point on the globe, in degrees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoPoint {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl GeoPoint {
+    const EARTH_RADIUS_KM: f64 = 6371.0;
+
+    /// Great-circle distance in kilometres.
+    pub fn distance_km(&self, other: &GeoPoint) -> f64 {
+        let (lat1, lat2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let d_lat = lat2 - lat1;
+        let d_lon = (other.longitude - self.longitude).to_radians();
+        let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+        2.0 * Self::EARTH_RADIUS_KM * a.sqrt().asin()
+    }
+}
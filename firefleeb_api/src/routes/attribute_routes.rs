@@ -0,0 +1,73 @@
+use std::sync::Arc;
+
+use uuid::Uuid;
+use warp::{Filter, Rejection, Reply};
+
+use crate::auth::require_role;
+use crate::config::AppConfig;
+use crate::db::PgPool;
+use crate::handlers::attribute_handlers;
+use crate::handlers::dtos::{CreateAttributeRequest, UpdateAttributeRequest};
+use crate::routes::{json_body, with_pool};
+use crate::types::role::Role;
+
+pub fn attribute_routes(
+    pool: PgPool,
+    config: Arc<AppConfig>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    let auth = config.auth.clone();
+    let body_limit = config.server.body_limit_bytes;
+
+    // POST /attributes (admin)
+    let create = warp::post()
+        .and(warp::path("attributes"))
+        .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Admin))
+        .and(with_pool(pool.clone()))
+        .and(json_body::<CreateAttributeRequest>(body_limit))
+        .and_then(|_caller, pool, req| async move {
+            attribute_handlers::create(pool, req)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // GET /attributes
+    let list = warp::get()
+        .and(warp::path("attributes"))
+        .and(warp::path::end())
+        .and(with_pool(pool.clone()))
+        .and_then(|pool| async move {
+            attribute_handlers::list(pool)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // PUT /attributes/:id (admin)
+    let update = warp::put()
+        .and(warp::path("attributes"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Admin))
+        .and(with_pool(pool.clone()))
+        .and(json_body::<UpdateAttributeRequest>(body_limit))
+        .and_then(|id, _caller, pool, req| async move {
+            attribute_handlers::update(pool, id, req)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // DELETE /attributes/:id (admin), also removes the products' values
+    let delete = warp::delete()
+        .and(warp::path("attributes"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path::end())
+        .and(require_role(auth, Role::Admin))
+        .and(with_pool(pool))
+        .and_then(|id, _caller, pool| async move {
+            attribute_handlers::delete(pool, id)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    create.or(list).or(update).or(delete)
+}
@@ -1,26 +1,39 @@
+use std::sync::Arc;
+
 use uuid::Uuid;
 use warp::{Filter, Rejection, Reply};
 
+use crate::auth::require_role;
+use crate::config::AppConfig;
 use crate::db::PgPool;
 use crate::handlers::dtos::{
-    CreateCartItemRequest, CreateCartRequest, UpdateCartItemRequest, UpdateCartRequest,
+    CheckoutQuery, CreateCartItemRequest, CreateCartRequest, UpdateCartItemRequest,
+    UpdateCartRequest,
 };
 use crate::handlers::{cart_handlers, cart_item_handlers};
-use crate::routes::{json_body, with_pool};
+use crate::routes::{json_body, with_auth_config, with_pool};
+use crate::types::role::Role;
 
 pub fn cart_routes(
     pool: PgPool,
+    config: Arc<AppConfig>,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    let auth = config.auth.clone();
+    let body_limit = config.server.body_limit_bytes;
+    let reservation_ttl = config.carts.reservation_ttl;
+    let allocation_strategy = config.carts.allocation_strategy;
+
     let base = warp::path("carts");
 
     // POST /carts
     let create = warp::post()
         .and(base)
         .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Customer))
         .and(with_pool(pool.clone()))
-        .and(json_body::<CreateCartRequest>())
-        .and_then(|pool, req| async move {
-            cart_handlers::create(pool, req)
+        .and(json_body::<CreateCartRequest>(body_limit))
+        .and_then(|caller, pool, req| async move {
+            cart_handlers::create(pool, caller, req)
                 .await
                 .map_err(warp::reject::custom)
         });
@@ -30,10 +43,27 @@ pub fn cart_routes(
         .and(base)
         .and(warp::path::param::<Uuid>())
         .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Customer))
+        .and(with_pool(pool.clone()))
+        .and(json_body::<UpdateCartRequest>(body_limit))
+        .and_then(|id, caller, pool, req| async move {
+            cart_handlers::update(pool, caller, id, req)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // POST /carts/:id/checkout?latitude=&longitude=
+    let checkout = warp::post()
+        .and(base)
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("checkout"))
+        .and(warp::path::end())
+        .and(warp::query::<CheckoutQuery>())
+        .and(require_role(auth.clone(), Role::Customer))
         .and(with_pool(pool.clone()))
-        .and(json_body::<UpdateCartRequest>())
-        .and_then(|id, pool, req| async move {
-            cart_handlers::update(pool, id, req)
+        .and(with_auth_config(auth.clone()))
+        .and_then(move |id, query, caller, pool, auth| async move {
+            cart_handlers::checkout(pool, auth, allocation_strategy, caller, id, query)
                 .await
                 .map_err(warp::reject::custom)
         });
@@ -41,9 +71,10 @@ pub fn cart_routes(
     // GET /carts/:id
     let get_one = warp::get()
         .and(base.and(warp::path::param::<Uuid>()).and(warp::path::end()))
+        .and(require_role(auth.clone(), Role::Customer))
         .and(with_pool(pool.clone()))
-        .and_then(|id, pool| async move {
-            cart_handlers::get(pool, id)
+        .and_then(|id, caller, pool| async move {
+            cart_handlers::get(pool, caller, id)
                 .await
                 .map_err(warp::reject::custom)
         });
@@ -51,9 +82,10 @@ pub fn cart_routes(
     // DELETE /carts/:id
     let delete = warp::delete()
         .and(base.and(warp::path::param::<Uuid>()).and(warp::path::end()))
+        .and(require_role(auth.clone(), Role::Customer))
         .and(with_pool(pool.clone()))
-        .and_then(|id, pool| async move {
-            cart_handlers::delete(pool, id)
+        .and_then(|id, caller, pool| async move {
+            cart_handlers::delete(pool, caller, id)
                 .await
                 .map_err(warp::reject::custom)
         });
@@ -67,9 +99,10 @@ pub fn cart_routes(
     let list_items = warp::get()
         .and(items_base)
         .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Customer))
         .and(with_pool(pool.clone()))
-        .and_then(|cart_id, pool| async move {
-            cart_item_handlers::list(pool, cart_id)
+        .and_then(|cart_id, caller, pool| async move {
+            cart_item_handlers::list(pool, caller, cart_id)
                 .await
                 .map_err(warp::reject::custom)
         });
@@ -78,10 +111,11 @@ pub fn cart_routes(
     let add_item = warp::post()
         .and(items_base)
         .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Customer))
         .and(with_pool(pool.clone()))
-        .and(json_body::<CreateCartItemRequest>())
-        .and_then(|cart_id, pool, req| async move {
-            cart_item_handlers::create(pool, cart_id, req)
+        .and(json_body::<CreateCartItemRequest>(body_limit))
+        .and_then(move |cart_id, caller, pool, req| async move {
+            cart_item_handlers::create(pool, reservation_ttl, caller, cart_id, req)
                 .await
                 .map_err(warp::reject::custom)
         });
@@ -91,10 +125,11 @@ pub fn cart_routes(
         .and(items_base)
         .and(warp::path::param::<Uuid>())
         .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Customer))
         .and(with_pool(pool.clone()))
-        .and(json_body::<UpdateCartItemRequest>())
-        .and_then(|cart_id, item_id, pool, req| async move {
-            cart_item_handlers::update(pool, cart_id, item_id, req)
+        .and(json_body::<UpdateCartItemRequest>(body_limit))
+        .and_then(move |cart_id, item_id, caller, pool, req| async move {
+            cart_item_handlers::update(pool, reservation_ttl, caller, cart_id, item_id, req)
                 .await
                 .map_err(warp::reject::custom)
         });
@@ -104,9 +139,10 @@ pub fn cart_routes(
         .and(items_base)
         .and(warp::path::param::<Uuid>())
         .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Customer))
         .and(with_pool(pool.clone()))
-        .and_then(|cart_id, item_id, pool| async move {
-            cart_item_handlers::delete(pool, cart_id, item_id)
+        .and_then(|cart_id, item_id, caller, pool| async move {
+            cart_item_handlers::delete(pool, caller, cart_id, item_id)
                 .await
                 .map_err(warp::reject::custom)
         });
@@ -115,9 +151,10 @@ pub fn cart_routes(
     let clear_items = warp::delete()
         .and(items_base)
         .and(warp::path::end())
+        .and(require_role(auth, Role::Customer))
         .and(with_pool(pool))
-        .and_then(|cart_id, pool| async move {
-            cart_item_handlers::clear(pool, cart_id)
+        .and_then(|cart_id, caller, pool| async move {
+            cart_item_handlers::clear(pool, caller, cart_id)
                 .await
                 .map_err(warp::reject::custom)
         });
@@ -125,6 +162,7 @@ pub fn cart_routes(
     create
         .or(get_one)
         .or(update)
+        .or(checkout)
         .or(delete)
         .or(list_items)
         .or(add_item)
@@ -0,0 +1,138 @@
+use std::sync::Arc;
+
+use uuid::Uuid;
+use warp::{Filter, Rejection, Reply};
+
+use crate::auth::require_role;
+use crate::config::AppConfig;
+use crate::db::PgPool;
+use crate::handlers::category_handlers;
+use crate::handlers::dtos::{
+    CreateCategoryRequest, ListCategoryProductsQuery, UpdateCategoryRequest,
+};
+use crate::routes::{json_body, with_pool};
+use crate::types::role::Role;
+
+pub fn category_routes(
+    pool: PgPool,
+    config: Arc<AppConfig>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    let auth = config.auth.clone();
+    let body_limit = config.server.body_limit_bytes;
+
+    // POST /categories (staff)
+    let create = warp::post()
+        .and(warp::path("categories"))
+        .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Staff))
+        .and(with_pool(pool.clone()))
+        .and(json_body::<CreateCategoryRequest>(body_limit))
+        .and_then(|_caller, pool, req| async move {
+            category_handlers::create(pool, req)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // GET /categories
+    let list = warp::get()
+        .and(warp::path("categories"))
+        .and(warp::path::end())
+        .and(with_pool(pool.clone()))
+        .and_then(|pool| async move {
+            category_handlers::list(pool)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // GET /categories/:id
+    let get_one = warp::get()
+        .and(warp::path("categories"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path::end())
+        .and(with_pool(pool.clone()))
+        .and_then(|id, pool| async move {
+            category_handlers::get(pool, id)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // PUT /categories/:id (staff)
+    let update = warp::put()
+        .and(warp::path("categories"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Staff))
+        .and(with_pool(pool.clone()))
+        .and(json_body::<UpdateCategoryRequest>(body_limit))
+        .and_then(|id, _caller, pool, req| async move {
+            category_handlers::update(pool, id, req)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // DELETE /categories/:id (admin)
+    let delete = warp::delete()
+        .and(warp::path("categories"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Admin))
+        .and(with_pool(pool.clone()))
+        .and_then(|id, _caller, pool| async move {
+            category_handlers::delete(pool, id)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // GET /categories/:id/products?include_descendants=
+    let list_products = warp::get()
+        .and(warp::path("categories"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("products"))
+        .and(warp::path::end())
+        .and(warp::query::<ListCategoryProductsQuery>())
+        .and(with_pool(pool.clone()))
+        .and_then(|id, query, pool| async move {
+            category_handlers::list_products(pool, id, query)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // PUT /categories/:id/products/:product_id (staff)
+    let add_product = warp::put()
+        .and(warp::path("categories"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("products"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Staff))
+        .and(with_pool(pool.clone()))
+        .and_then(|id, product_id, _caller, pool| async move {
+            category_handlers::add_product(pool, id, product_id)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // DELETE /categories/:id/products/:product_id (staff)
+    let remove_product = warp::delete()
+        .and(warp::path("categories"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("products"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path::end())
+        .and(require_role(auth, Role::Staff))
+        .and(with_pool(pool))
+        .and_then(|id, product_id, _caller, pool| async move {
+            category_handlers::remove_product(pool, id, product_id)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    create
+        .or(list)
+        .or(get_one)
+        .or(update)
+        .or(delete)
+        .or(list_products)
+        .or(add_product)
+        .or(remove_product)
+}
@@ -1,12 +1,35 @@
+use crate::auth::AuthConfig;
 use crate::db::PgPool;
+use crate::mail::SharedMailer;
+use crate::storage::SharedBlobStore;
 use std::convert::Infallible;
 use warp::{Filter, Rejection};
 
-pub fn json_body<T: serde::de::DeserializeOwned + Send>()
--> impl Filter<Extract = (T,), Error = Rejection> + Clone {
-    warp::body::content_length_limit(16 * 1024).and(warp::body::json())
+/// JSON request body of at most `limit_bytes` (see `ServerConfig::body_limit_bytes`).
+pub fn json_body<T: serde::de::DeserializeOwned + Send>(
+    limit_bytes: u64,
+) -> impl Filter<Extract = (T,), Error = Rejection> + Clone {
+    warp::body::content_length_limit(limit_bytes).and(warp::body::json())
 }
 
 pub fn with_pool(pool: PgPool) -> impl Filter<Extract = (PgPool,), Error = Infallible> + Clone {
     warp::any().map(move || pool.clone())
 }
+
+pub fn with_auth_config(
+    config: AuthConfig,
+) -> impl Filter<Extract = (AuthConfig,), Error = Infallible> + Clone {
+    warp::any().map(move || config.clone())
+}
+
+pub fn with_mailer(
+    mailer: SharedMailer,
+) -> impl Filter<Extract = (SharedMailer,), Error = Infallible> + Clone {
+    warp::any().map(move || mailer.clone())
+}
+
+pub fn with_blob_store(
+    blobs: SharedBlobStore,
+) -> impl Filter<Extract = (SharedBlobStore,), Error = Infallible> + Clone {
+    warp::any().map(move || blobs.clone())
+}
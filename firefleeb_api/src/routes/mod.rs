@@ -1,8 +1,13 @@
+pub mod attribute_routes;
 pub mod cart_routes;
+pub mod category_routes;
 pub mod filters;
 pub mod product_routes;
+pub mod recommendation_routes;
 pub mod rejections;
+pub mod review_routes;
 pub mod user_routes;
+pub mod warehouse_routes;
 
-pub use filters::{json_body, with_pool};
+pub use filters::{json_body, with_auth_config, with_blob_store, with_mailer, with_pool};
 pub use rejections::handle_rejection;
@@ -1,66 +1,419 @@
+use std::sync::Arc;
+
 use uuid::Uuid;
 use warp::{Filter, Rejection, Reply};
 
+use crate::auth::require_role;
+use crate::config::AppConfig;
 use crate::db::PgPool;
-use crate::handlers::dtos::{CreateProductRequest, UpdateProductRequest};
+use crate::handlers::dtos::{
+    CreateProductRequest, CreateProductVariantRequest, CreateStockMovementRequest,
+    ExportProductsQuery, GetProductQuery, ImportProductsQuery, ListProductsQuery,
+    ListStockMovementsQuery, SchedulePriceRequest, SearchProductsQuery, SuggestProductsQuery,
+    UpdateProductRequest, UpdateProductVariantRequest,
+};
 use crate::handlers::product_handlers;
-use crate::routes::{json_body, with_pool};
+use crate::routes::{json_body, with_blob_store, with_pool};
+use crate::services::product_image_service::ImageRendition;
+use crate::storage::SharedBlobStore;
+use crate::types::role::Role;
+
+const MULTIPART_OVERHEAD_BYTES: u64 = 16 * 1024;
 
 pub fn product_routes(
     pool: PgPool,
+    config: Arc<AppConfig>,
+    blobs: SharedBlobStore,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
-    // POST /products
+    let auth = config.auth.clone();
+    let body_limit = config.server.body_limit_bytes;
+    let suggestion_threshold = config.catalog.suggestion_threshold;
+    let images = config.images.clone();
+    // Room for the multipart boundaries and part headers around the file itself
+    let upload_limit = images.max_upload_bytes + MULTIPART_OVERHEAD_BYTES;
+    let import_limit = config.catalog.import_max_bytes;
+
+    // POST /products (staff)
     let create = warp::post()
         .and(warp::path("products"))
         .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Staff))
         .and(with_pool(pool.clone()))
-        .and(json_body::<CreateProductRequest>())
-        .and_then(|pool, req| async move {
-            product_handlers::create(pool, req)
+        .and(json_body::<CreateProductRequest>(body_limit))
+        .and_then(|caller, pool, req| async move {
+            product_handlers::create(pool, caller, req)
                 .await
                 .map_err(warp::reject::custom)
         });
 
-    // PUT /products/:id
+    // PUT /products/:id (staff)
     let update = warp::put()
         .and(warp::path("products"))
         .and(warp::path::param::<Uuid>())
         .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Staff))
+        .and(with_pool(pool.clone()))
+        .and(json_body::<UpdateProductRequest>(body_limit))
+        .and_then(|id, caller, pool, req| async move {
+            product_handlers::update(pool, caller, id, req)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // GET /products?attr.<code>=
+    let list = warp::get()
+        .and(warp::path("products"))
+        .and(warp::path::end())
+        .and(warp::query::<ListProductsQuery>())
+        .and(warp::query::<Vec<(String, String)>>())
         .and(with_pool(pool.clone()))
-        .and(json_body::<UpdateProductRequest>())
-        .and_then(|id, pool, req| async move {
-            product_handlers::update(pool, id, req)
+        .and_then(|query, params, pool| async move {
+            product_handlers::list(pool, query, params)
                 .await
                 .map_err(warp::reject::custom)
         });
 
-    // GET /products/:id
+    // GET /products/search?q=
+    let search = warp::get()
+        .and(warp::path("products"))
+        .and(warp::path("search"))
+        .and(warp::path::end())
+        .and(warp::query::<SearchProductsQuery>())
+        .and(with_pool(pool.clone()))
+        .and_then(|query, pool| async move {
+            product_handlers::search(pool, query)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // GET /products/suggest?prefix=
+    let suggest = warp::get()
+        .and(warp::path("products"))
+        .and(warp::path("suggest"))
+        .and(warp::path::end())
+        .and(warp::query::<SuggestProductsQuery>())
+        .and(with_pool(pool.clone()))
+        .and_then(move |query, pool| async move {
+            product_handlers::suggest(pool, suggestion_threshold, query)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // POST /products/import?dry_run= (staff), a CSV or NDJSON body
+    let import = warp::post()
+        .and(warp::path("products"))
+        .and(warp::path("import"))
+        .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Staff))
+        .and(warp::header::optional::<String>("content-type"))
+        .and(warp::query::<ImportProductsQuery>())
+        .and(warp::body::content_length_limit(import_limit))
+        .and(warp::body::bytes())
+        .and(with_pool(pool.clone()))
+        .and_then(|caller, content_type, query, body, pool| async move {
+            product_handlers::import_products(pool, caller, content_type, query, body)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // GET /products/export?format=csv|ndjson (staff)
+    let export = warp::get()
+        .and(warp::path("products"))
+        .and(warp::path("export"))
+        .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Staff))
+        .and(warp::query::<ExportProductsQuery>())
+        .and(with_pool(pool.clone()))
+        .and_then(|_caller, query, pool| async move {
+            product_handlers::export_products(pool, query)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // GET /products/:id?include_warehouses=
     let get_one = warp::get()
         .and(
             warp::path("products")
                 .and(warp::path::param::<Uuid>())
                 .and(warp::path::end()),
         )
+        .and(warp::query::<GetProductQuery>())
         .and(with_pool(pool.clone()))
-        .and_then(|id, pool| async move {
-            product_handlers::get(pool, id)
+        .and_then(|id, query, pool| async move {
+            product_handlers::get(pool, id, query)
                 .await
                 .map_err(warp::reject::custom)
         });
 
-    // DELETE /products/:id
+    // DELETE /products/:id (admin), archives the product
     let delete = warp::delete()
         .and(
             warp::path("products")
                 .and(warp::path::param::<Uuid>())
                 .and(warp::path::end()),
         )
-        .and(with_pool(pool))
-        .and_then(|id, pool| async move {
+        .and(require_role(auth.clone(), Role::Admin))
+        .and(with_pool(pool.clone()))
+        .and_then(|id, _caller, pool| async move {
             product_handlers::delete(pool, id)
                 .await
                 .map_err(warp::reject::custom)
         });
 
-    create.or(get_one).or(update).or(delete)
+    // POST /products/:id/restore (admin)
+    let restore = warp::post()
+        .and(warp::path("products"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("restore"))
+        .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Admin))
+        .and(with_pool(pool.clone()))
+        .and_then(|id, _caller, pool| async move {
+            product_handlers::restore(pool, id)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // POST /products/:id/purge (admin), only for archived products
+    let purge = warp::post()
+        .and(warp::path("products"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("purge"))
+        .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Admin))
+        .and(with_pool(pool.clone()))
+        .and(with_blob_store(blobs.clone()))
+        .and_then(|id, _caller, pool, blobs| async move {
+            product_handlers::purge(pool, blobs, id)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // POST /products/:id/variants (staff)
+    let create_variant = warp::post()
+        .and(warp::path("products"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("variants"))
+        .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Staff))
+        .and(with_pool(pool.clone()))
+        .and(json_body::<CreateProductVariantRequest>(body_limit))
+        .and_then(|id, caller, pool, req| async move {
+            product_handlers::create_variant(pool, caller, id, req)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // PUT /products/:id/variants/:variant_id (staff)
+    let update_variant = warp::put()
+        .and(warp::path("products"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("variants"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Staff))
+        .and(with_pool(pool.clone()))
+        .and(json_body::<UpdateProductVariantRequest>(body_limit))
+        .and_then(|id, variant_id, caller, pool, req| async move {
+            product_handlers::update_variant(pool, caller, id, variant_id, req)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // DELETE /products/:id/variants/:variant_id (admin)
+    let delete_variant = warp::delete()
+        .and(warp::path("products"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("variants"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Admin))
+        .and(with_pool(pool.clone()))
+        .and_then(|id, variant_id, caller, pool| async move {
+            product_handlers::delete_variant(pool, caller, id, variant_id)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // POST /products/:id/images (staff), multipart with an `image` file field
+    let upload_image = warp::post()
+        .and(warp::path("products"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("images"))
+        .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Staff))
+        .and(with_pool(pool.clone()))
+        .and(with_blob_store(blobs.clone()))
+        .and(warp::multipart::form().max_length(upload_limit))
+        .and_then(move |id, _caller, pool, blobs, form| {
+            let images = images.clone();
+            async move {
+                product_handlers::upload_image(pool, blobs, images, id, form)
+                    .await
+                    .map_err(warp::reject::custom)
+            }
+        });
+
+    // GET /products/:id/images/:image_id
+    let get_image = warp::get()
+        .and(warp::path("products"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("images"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path::end())
+        .and(warp::header::optional::<String>("if-none-match"))
+        .and(with_pool(pool.clone()))
+        .and(with_blob_store(blobs.clone()))
+        .and_then(|id, image_id, if_none_match, pool, blobs| async move {
+            product_handlers::get_image(
+                pool,
+                blobs,
+                id,
+                image_id,
+                ImageRendition::Original,
+                if_none_match,
+            )
+            .await
+            .map_err(warp::reject::custom)
+        });
+
+    // GET /products/:id/images/:image_id/thumbnails/:size
+    let get_thumbnail = warp::get()
+        .and(warp::path("products"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("images"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("thumbnails"))
+        .and(warp::path::param::<u32>())
+        .and(warp::path::end())
+        .and(warp::header::optional::<String>("if-none-match"))
+        .and(with_pool(pool.clone()))
+        .and(with_blob_store(blobs.clone()))
+        .and_then(
+            |id, image_id, size, if_none_match, pool, blobs| async move {
+                product_handlers::get_image(
+                    pool,
+                    blobs,
+                    id,
+                    image_id,
+                    ImageRendition::Thumbnail(size),
+                    if_none_match,
+                )
+                .await
+                .map_err(warp::reject::custom)
+            },
+        );
+
+    // DELETE /products/:id/images/:image_id (staff)
+    let delete_image = warp::delete()
+        .and(warp::path("products"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("images"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Staff))
+        .and(with_pool(pool.clone()))
+        .and(with_blob_store(blobs))
+        .and_then(|id, image_id, _caller, pool, blobs| async move {
+            product_handlers::delete_image(pool, blobs, id, image_id)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // GET /products/:id/price-history
+    let price_history = warp::get()
+        .and(warp::path("products"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("price-history"))
+        .and(warp::path::end())
+        .and(with_pool(pool.clone()))
+        .and_then(|id, pool| async move {
+            product_handlers::price_history(pool, id)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // POST /products/:id/prices (admin), schedules a future price
+    let schedule_price = warp::post()
+        .and(warp::path("products"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("prices"))
+        .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Admin))
+        .and(with_pool(pool.clone()))
+        .and(json_body::<SchedulePriceRequest>(body_limit))
+        .and_then(|id, _caller, pool, req| async move {
+            product_handlers::schedule_price(pool, id, req)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // DELETE /products/:id/prices/:price_id (admin), only before the price starts
+    let cancel_price = warp::delete()
+        .and(warp::path("products"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("prices"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Admin))
+        .and(with_pool(pool.clone()))
+        .and_then(|id, price_id, _caller, pool| async move {
+            product_handlers::cancel_scheduled_price(pool, id, price_id)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // POST /products/:id/stock-movements (staff)
+    let record_stock_movement = warp::post()
+        .and(warp::path("products"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("stock-movements"))
+        .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Staff))
+        .and(with_pool(pool.clone()))
+        .and(json_body::<CreateStockMovementRequest>(body_limit))
+        .and_then(|id, caller, pool, req| async move {
+            product_handlers::record_stock_movement(pool, caller, id, req)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // GET /products/:id/stock-movements (staff)
+    let list_stock_movements = warp::get()
+        .and(warp::path("products"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("stock-movements"))
+        .and(warp::path::end())
+        .and(require_role(auth, Role::Staff))
+        .and(warp::query::<ListStockMovementsQuery>())
+        .and(with_pool(pool))
+        .and_then(|id, _caller, query, pool| async move {
+            product_handlers::list_stock_movements(pool, id, query)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    create
+        .or(list)
+        .or(search)
+        .or(suggest)
+        .or(import)
+        .or(export)
+        .or(get_one)
+        .or(update)
+        .or(delete)
+        .or(restore)
+        .or(purge)
+        .or(create_variant)
+        .or(update_variant)
+        .or(delete_variant)
+        .or(upload_image)
+        .or(get_image)
+        .or(get_thumbnail)
+        .or(delete_image)
+        .or(price_history)
+        .or(schedule_price)
+        .or(cancel_price)
+        .or(record_stock_movement)
+        .or(list_stock_movements)
 }
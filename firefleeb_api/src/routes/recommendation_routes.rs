@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use uuid::Uuid;
+use warp::{Filter, Rejection, Reply};
+
+use crate::auth::require_role;
+use crate::config::AppConfig;
+use crate::db::PgPool;
+use crate::handlers::dtos::RecommendationsQuery;
+use crate::handlers::recommendation_handlers;
+use crate::routes::with_pool;
+use crate::types::role::Role;
+
+pub fn recommendation_routes(
+    pool: PgPool,
+    config: Arc<AppConfig>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    let auth = config.auth.clone();
+
+    // GET /products/:id/recommendations?limit=
+    let for_product = warp::get()
+        .and(warp::path("products"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("recommendations"))
+        .and(warp::path::end())
+        .and(warp::query::<RecommendationsQuery>())
+        .and(with_pool(pool.clone()))
+        .and_then(|id, query, pool| async move {
+            recommendation_handlers::for_product(pool, id, query)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // GET /carts/:id/recommendations?limit= (the cart's owner)
+    let for_cart = warp::get()
+        .and(warp::path("carts"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("recommendations"))
+        .and(warp::path::end())
+        .and(require_role(auth, Role::Customer))
+        .and(warp::query::<RecommendationsQuery>())
+        .and(with_pool(pool))
+        .and_then(|id, caller, query, pool| async move {
+            recommendation_handlers::for_cart(pool, caller, id, query)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    for_product.or(for_cart)
+}
@@ -1,5 +1,6 @@
 use crate::errors::AppError;
 use std::convert::Infallible;
+use warp::reject::{InvalidHeader, InvalidQuery, LengthRequired, MissingHeader, PayloadTooLarge};
 use warp::{Rejection, Reply, filters::body::BodyDeserializeError, http::StatusCode};
 
 pub async fn handle_rejection(err: Rejection) -> Result<impl Reply, Infallible> {
@@ -11,6 +12,38 @@ pub async fn handle_rejection(err: Rejection) -> Result<impl Reply, Infallible>
             "status": 400
         });
         warp::reply::with_status(warp::reply::json(&body), StatusCode::BAD_REQUEST).into_response()
+    } else if let Some(query_err) = err.find::<InvalidQuery>() {
+        let body = serde_json::json!({
+            "error": format!("invalid query string: {query_err}"),
+            "status": 400
+        });
+        warp::reply::with_status(warp::reply::json(&body), StatusCode::BAD_REQUEST).into_response()
+    } else if let Some(header_err) = err.find::<InvalidHeader>() {
+        let body = serde_json::json!({
+            "error": header_err.to_string(),
+            "status": 400
+        });
+        warp::reply::with_status(warp::reply::json(&body), StatusCode::BAD_REQUEST).into_response()
+    } else if let Some(header_err) = err.find::<MissingHeader>() {
+        let body = serde_json::json!({
+            "error": header_err.to_string(),
+            "status": 400
+        });
+        warp::reply::with_status(warp::reply::json(&body), StatusCode::BAD_REQUEST).into_response()
+    } else if err.find::<PayloadTooLarge>().is_some() {
+        let body = serde_json::json!({
+            "error": "request body too large",
+            "status": 413
+        });
+        warp::reply::with_status(warp::reply::json(&body), StatusCode::PAYLOAD_TOO_LARGE)
+            .into_response()
+    } else if err.find::<LengthRequired>().is_some() {
+        let body = serde_json::json!({
+            "error": "content-length header required",
+            "status": 411
+        });
+        warp::reply::with_status(warp::reply::json(&body), StatusCode::LENGTH_REQUIRED)
+            .into_response()
     } else {
         let body = serde_json::json!({
             "error": "internal server error",
@@ -0,0 +1,80 @@
+use std::sync::Arc;
+
+use uuid::Uuid;
+use warp::{Filter, Rejection, Reply};
+
+use crate::auth::require_role;
+use crate::config::AppConfig;
+use crate::db::PgPool;
+use crate::handlers::dtos::{
+    CreateReviewRequest, ListProductReviewsQuery, ListReviewsQuery, ModerateReviewRequest,
+};
+use crate::handlers::review_handlers;
+use crate::routes::{json_body, with_pool};
+use crate::types::role::Role;
+
+pub fn review_routes(
+    pool: PgPool,
+    config: Arc<AppConfig>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    let auth = config.auth.clone();
+    let body_limit = config.server.body_limit_bytes;
+
+    // POST /products/:id/reviews (any signed-in user)
+    let create = warp::post()
+        .and(warp::path("products"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("reviews"))
+        .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Customer))
+        .and(with_pool(pool.clone()))
+        .and(json_body::<CreateReviewRequest>(body_limit))
+        .and_then(|id, caller, pool, req| async move {
+            review_handlers::create(pool, caller, id, req)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // GET /products/:id/reviews, approved reviews only
+    let list_for_product = warp::get()
+        .and(warp::path("products"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("reviews"))
+        .and(warp::path::end())
+        .and(warp::query::<ListProductReviewsQuery>())
+        .and(with_pool(pool.clone()))
+        .and_then(|id, query, pool| async move {
+            review_handlers::list_for_product(pool, id, query)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // GET /reviews?status= (staff), the moderation queue
+    let list = warp::get()
+        .and(warp::path("reviews"))
+        .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Staff))
+        .and(warp::query::<ListReviewsQuery>())
+        .and(with_pool(pool.clone()))
+        .and_then(|_caller, query, pool| async move {
+            review_handlers::list(pool, query)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // PUT /reviews/:id (staff), approves or rejects a review
+    let moderate = warp::put()
+        .and(warp::path("reviews"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path::end())
+        .and(require_role(auth, Role::Staff))
+        .and(with_pool(pool))
+        .and(json_body::<ModerateReviewRequest>(body_limit))
+        .and_then(|id, _caller, pool, req| async move {
+            review_handlers::moderate(pool, id, req)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    create.or(list_for_product).or(list).or(moderate)
+}
@@ -1,24 +1,39 @@
+use std::sync::Arc;
+
 use uuid::Uuid;
 use warp::{Filter, Rejection, Reply};
 
+use crate::auth::require_role;
+use crate::config::AppConfig;
 use crate::db::PgPool;
 use crate::handlers::dtos::{
-    CreateUserRequest, LoginRequest, UpdatePasswordRequest, UpdateUserRequest,
+    CreateUserRequest, ForgotPasswordRequest, LoginRequest, RefreshTokenRequest,
+    ResendVerificationRequest, ResetPasswordRequest, UpdatePasswordRequest, UpdateRoleRequest,
+    UpdateUserRequest, VerifyEmailRequest,
 };
 use crate::handlers::user_handlers;
-use crate::routes::{json_body, with_pool};
+use crate::mail::SharedMailer;
+use crate::routes::{json_body, with_auth_config, with_mailer, with_pool};
+use crate::types::role::Role;
 
 pub fn user_routes(
     pool: PgPool,
+    config: Arc<AppConfig>,
+    mailer: SharedMailer,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    let auth = config.auth.clone();
+    let body_limit = config.server.body_limit_bytes;
+
     // POST /users
     let create = warp::post()
         .and(warp::path("users"))
         .and(warp::path::end())
         .and(with_pool(pool.clone()))
-        .and(json_body::<CreateUserRequest>())
-        .and_then(|pool, req| async move {
-            user_handlers::register(pool, req)
+        .and(with_auth_config(auth.clone()))
+        .and(with_mailer(mailer.clone()))
+        .and(json_body::<CreateUserRequest>(body_limit))
+        .and_then(|pool, auth, mailer, req| async move {
+            user_handlers::register(pool, auth, mailer, req)
                 .await
                 .map_err(warp::reject::custom)
         });
@@ -28,24 +43,106 @@ pub fn user_routes(
         .and(warp::path("users"))
         .and(warp::path::param::<Uuid>())
         .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Customer))
         .and(with_pool(pool.clone()))
-        .and(json_body::<UpdateUserRequest>())
-        .and_then(|id, pool, req| async move {
-            user_handlers::update_user(pool, id, req)
+        .and(with_auth_config(auth.clone()))
+        .and(with_mailer(mailer.clone()))
+        .and(json_body::<UpdateUserRequest>(body_limit))
+        .and_then(|id, caller, pool, auth, mailer, req| async move {
+            user_handlers::update_user(pool, auth, mailer, caller, id, req)
                 .await
                 .map_err(warp::reject::custom)
         });
 
-    // PUT /users/:id/password
+    // PUT /users/password-reset/:id
     let update_password = warp::put()
         .and(warp::path("users"))
         .and(warp::path("password-reset"))
         .and(warp::path::param::<Uuid>())
         .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Customer))
+        .and(with_pool(pool.clone()))
+        .and(with_auth_config(auth.clone()))
+        .and(json_body::<UpdatePasswordRequest>(body_limit))
+        .and_then(|id, caller, pool, auth, req| async move {
+            user_handlers::change_password(pool, auth, caller, id, req)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // POST /users/password/forgot
+    let forgot_password = warp::post()
+        .and(warp::path("users"))
+        .and(warp::path("password"))
+        .and(warp::path("forgot"))
+        .and(warp::path::end())
         .and(with_pool(pool.clone()))
-        .and(json_body::<UpdatePasswordRequest>())
-        .and_then(|id, pool, req| async move {
-            user_handlers::change_password(pool, id, req)
+        .and(with_auth_config(auth.clone()))
+        .and(with_mailer(mailer.clone()))
+        .and(warp::addr::remote())
+        .and(json_body::<ForgotPasswordRequest>(body_limit))
+        .and_then(|pool, auth, mailer, remote, req| async move {
+            user_handlers::forgot_password(pool, auth, mailer, remote, req)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // POST /users/password/reset
+    let reset_password = warp::post()
+        .and(warp::path("users"))
+        .and(warp::path("password"))
+        .and(warp::path("reset"))
+        .and(warp::path::end())
+        .and(with_pool(pool.clone()))
+        .and(with_auth_config(auth.clone()))
+        .and(json_body::<ResetPasswordRequest>(body_limit))
+        .and_then(|pool, auth, req| async move {
+            user_handlers::reset_password(pool, auth, req)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // POST /users/verify-email
+    let verify_email = warp::post()
+        .and(warp::path("users"))
+        .and(warp::path("verify-email"))
+        .and(warp::path::end())
+        .and(with_pool(pool.clone()))
+        .and(json_body::<VerifyEmailRequest>(body_limit))
+        .and_then(|pool, req| async move {
+            user_handlers::verify_email(pool, req)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // POST /users/verify-email/resend
+    let resend_verification = warp::post()
+        .and(warp::path("users"))
+        .and(warp::path("verify-email"))
+        .and(warp::path("resend"))
+        .and(warp::path::end())
+        .and(with_pool(pool.clone()))
+        .and(with_auth_config(auth.clone()))
+        .and(with_mailer(mailer))
+        .and(warp::addr::remote())
+        .and(json_body::<ResendVerificationRequest>(body_limit))
+        .and_then(|pool, auth, mailer, remote, req| async move {
+            user_handlers::resend_verification(pool, auth, mailer, remote, req)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // PUT /users/:id/role (admin)
+    let update_role = warp::put()
+        .and(warp::path("users"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("role"))
+        .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Admin))
+        .and(with_pool(pool.clone()))
+        .and(json_body::<UpdateRoleRequest>(body_limit))
+        .and_then(|id, _caller, pool, req| async move {
+            user_handlers::update_role(pool, id, req)
                 .await
                 .map_err(warp::reject::custom)
         });
@@ -56,12 +153,52 @@ pub fn user_routes(
         .and(warp::path("login"))
         .and(warp::path::end())
         .and(with_pool(pool.clone()))
-        .and(json_body::<LoginRequest>())
+        .and(with_auth_config(auth.clone()))
+        .and(warp::addr::remote())
+        .and(json_body::<LoginRequest>(body_limit))
+        .and_then(|pool, auth, remote, req| async move {
+            user_handlers::login(pool, auth, remote, req)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // POST /users/token/refresh
+    let refresh = warp::post()
+        .and(warp::path("users"))
+        .and(warp::path("token"))
+        .and(warp::path("refresh"))
+        .and(warp::path::end())
+        .and(with_pool(pool.clone()))
+        .and(with_auth_config(auth))
+        .and(json_body::<RefreshTokenRequest>(body_limit))
+        .and_then(|pool, auth, req| async move {
+            user_handlers::refresh(pool, auth, req)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // POST /users/logout
+    let logout = warp::post()
+        .and(warp::path("users"))
+        .and(warp::path("logout"))
+        .and(warp::path::end())
+        .and(with_pool(pool))
+        .and(json_body::<RefreshTokenRequest>(body_limit))
         .and_then(|pool, req| async move {
-            user_handlers::login(pool, req)
+            user_handlers::logout(pool, req)
                 .await
                 .map_err(warp::reject::custom)
         });
 
-    create.or(update).or(update_password).or(login)
+    create
+        .or(update)
+        .or(update_password)
+        .or(forgot_password)
+        .or(reset_password)
+        .or(verify_email)
+        .or(resend_verification)
+        .or(update_role)
+        .or(login)
+        .or(refresh)
+        .or(logout)
 }
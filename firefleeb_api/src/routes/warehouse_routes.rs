@@ -0,0 +1,61 @@
+use std::sync::Arc;
+
+use uuid::Uuid;
+use warp::{Filter, Rejection, Reply};
+
+use crate::auth::require_role;
+use crate::config::AppConfig;
+use crate::db::PgPool;
+use crate::handlers::dtos::{CreateWarehouseRequest, UpdateWarehouseRequest};
+use crate::handlers::warehouse_handlers;
+use crate::routes::{json_body, with_pool};
+use crate::types::role::Role;
+
+pub fn warehouse_routes(
+    pool: PgPool,
+    config: Arc<AppConfig>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    let auth = config.auth.clone();
+    let body_limit = config.server.body_limit_bytes;
+
+    // POST /warehouses (admin)
+    let create = warp::post()
+        .and(warp::path("warehouses"))
+        .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Admin))
+        .and(with_pool(pool.clone()))
+        .and(json_body::<CreateWarehouseRequest>(body_limit))
+        .and_then(|_caller, pool, req| async move {
+            warehouse_handlers::create(pool, req)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // GET /warehouses (staff)
+    let list = warp::get()
+        .and(warp::path("warehouses"))
+        .and(warp::path::end())
+        .and(require_role(auth.clone(), Role::Staff))
+        .and(with_pool(pool.clone()))
+        .and_then(|_caller, pool| async move {
+            warehouse_handlers::list(pool)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    // PUT /warehouses/:id (admin)
+    let update = warp::put()
+        .and(warp::path("warehouses"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path::end())
+        .and(require_role(auth, Role::Admin))
+        .and(with_pool(pool))
+        .and(json_body::<UpdateWarehouseRequest>(body_limit))
+        .and_then(|id, _caller, pool, req| async move {
+            warehouse_handlers::update(pool, id, req)
+                .await
+                .map_err(warp::reject::custom)
+        });
+
+    create.or(list).or(update)
+}
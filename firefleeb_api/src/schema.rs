@@ -1,5 +1,16 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    attribute_definitions (id) {
+        id -> Uuid,
+        code -> Text,
+        name -> Text,
+        value_type -> Text,
+        allowed_values -> Nullable<Jsonb>,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     cart_items (id) {
         id -> Uuid,
@@ -8,6 +19,7 @@ diesel::table! {
         quantity -> Int4,
         unit_price -> Numeric,
         created_at -> Nullable<Timestamptz>,
+        variant_id -> Uuid,
     }
 }
 
@@ -21,6 +33,126 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    categories (id) {
+        id -> Uuid,
+        parent_id -> Nullable<Uuid>,
+        name -> Text,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    email_verification_tokens (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        email -> Text,
+        token_hash -> Text,
+        expires_at -> Timestamptz,
+        used_at -> Nullable<Timestamptz>,
+        created_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    login_throttles (scope, subject) {
+        scope -> Text,
+        subject -> Text,
+        failed_count -> Int4,
+        locked_until -> Nullable<Timestamptz>,
+        last_failed_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    password_reset_tokens (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        token_hash -> Text,
+        expires_at -> Timestamptz,
+        used_at -> Nullable<Timestamptz>,
+        created_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    product_attributes (product_id, attribute_id) {
+        product_id -> Uuid,
+        attribute_id -> Uuid,
+        value -> Jsonb,
+    }
+}
+
+diesel::table! {
+    product_categories (product_id, category_id) {
+        product_id -> Uuid,
+        category_id -> Uuid,
+    }
+}
+
+diesel::table! {
+    product_images (id) {
+        id -> Uuid,
+        product_id -> Uuid,
+        content_type -> Text,
+        width -> Int4,
+        height -> Int4,
+        byte_size -> Int8,
+        thumbnail_sizes -> Array<Nullable<Int4>>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    product_prices (id) {
+        id -> Uuid,
+        product_id -> Uuid,
+        price -> Numeric,
+        valid_from -> Timestamptz,
+        valid_until -> Nullable<Timestamptz>,
+        source -> Text,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    product_recommendations (product_id, recommended_id) {
+        product_id -> Uuid,
+        recommended_id -> Uuid,
+        carts_together -> Int4,
+        score -> Float8,
+        computed_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    product_reviews (id) {
+        id -> Uuid,
+        product_id -> Uuid,
+        user_id -> Uuid,
+        rating -> Int4,
+        title -> Text,
+        body -> Text,
+        verified_purchase -> Bool,
+        status -> Text,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    product_variants (id) {
+        id -> Uuid,
+        product_id -> Uuid,
+        sku -> Text,
+        options -> Jsonb,
+        price -> Nullable<Numeric>,
+        stock -> Int4,
+        is_default -> Bool,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     products (id) {
         id -> Uuid,
@@ -28,21 +160,129 @@ diesel::table! {
         product_description -> Nullable<Text>,
         price -> Numeric,
         stock -> Int4,
+        created_at -> Timestamptz,
+        archived_at -> Nullable<Timestamptz>,
+        rating_count -> Int4,
+        rating_average -> Nullable<Numeric>,
+    }
+}
+
+diesel::table! {
+    refresh_tokens (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        family_id -> Uuid,
+        token_hash -> Text,
+        expires_at -> Timestamptz,
+        revoked_at -> Nullable<Timestamptz>,
+        replaced_by -> Nullable<Uuid>,
         created_at -> Nullable<Timestamptz>,
     }
 }
 
+diesel::table! {
+    stock_movements (id) {
+        id -> Uuid,
+        product_id -> Uuid,
+        variant_id -> Nullable<Uuid>,
+        movement_type -> Text,
+        quantity -> Int4,
+        stock_after -> Int4,
+        reason -> Text,
+        actor_id -> Nullable<Uuid>,
+        cart_id -> Nullable<Uuid>,
+        created_at -> Timestamptz,
+        warehouse_id -> Uuid,
+    }
+}
+
+diesel::table! {
+    stock_reservations (id) {
+        id -> Uuid,
+        cart_item_id -> Uuid,
+        variant_id -> Uuid,
+        quantity -> Int4,
+        expires_at -> Timestamptz,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     users (id) {
         id -> Uuid,
         email -> Text,
         password_hash -> Text,
         created_at -> Nullable<Timestamptz>,
+        role -> Text,
+        email_verified_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    warehouse_stock (warehouse_id, variant_id) {
+        warehouse_id -> Uuid,
+        variant_id -> Uuid,
+        stock -> Int4,
+    }
+}
+
+diesel::table! {
+    warehouses (id) {
+        id -> Uuid,
+        code -> Text,
+        name -> Text,
+        latitude -> Nullable<Float8>,
+        longitude -> Nullable<Float8>,
+        is_default -> Bool,
+        created_at -> Timestamptz,
     }
 }
 
 diesel::joinable!(cart_items -> carts (cart_id));
+diesel::joinable!(cart_items -> product_variants (variant_id));
 diesel::joinable!(cart_items -> products (item_id));
 diesel::joinable!(carts -> users (user_id));
+diesel::joinable!(email_verification_tokens -> users (user_id));
+diesel::joinable!(password_reset_tokens -> users (user_id));
+diesel::joinable!(product_attributes -> attribute_definitions (attribute_id));
+diesel::joinable!(product_attributes -> products (product_id));
+diesel::joinable!(product_categories -> categories (category_id));
+diesel::joinable!(product_categories -> products (product_id));
+diesel::joinable!(product_images -> products (product_id));
+diesel::joinable!(product_prices -> products (product_id));
+diesel::joinable!(product_reviews -> products (product_id));
+diesel::joinable!(product_reviews -> users (user_id));
+diesel::joinable!(product_variants -> products (product_id));
+diesel::joinable!(refresh_tokens -> users (user_id));
+diesel::joinable!(stock_movements -> carts (cart_id));
+diesel::joinable!(stock_movements -> product_variants (variant_id));
+diesel::joinable!(stock_movements -> products (product_id));
+diesel::joinable!(stock_movements -> warehouses (warehouse_id));
+diesel::joinable!(stock_reservations -> cart_items (cart_item_id));
+diesel::joinable!(stock_reservations -> product_variants (variant_id));
+diesel::joinable!(warehouse_stock -> product_variants (variant_id));
+diesel::joinable!(warehouse_stock -> warehouses (warehouse_id));
 
-diesel::allow_tables_to_appear_in_same_query!(cart_items, carts, products, users,);
+diesel::allow_tables_to_appear_in_same_query!(
+    attribute_definitions,
+    cart_items,
+    carts,
+    categories,
+    email_verification_tokens,
+    login_throttles,
+    password_reset_tokens,
+    product_attributes,
+    product_categories,
+    product_images,
+    product_prices,
+    product_recommendations,
+    product_reviews,
+    product_variants,
+    products,
+    refresh_tokens,
+    stock_movements,
+    stock_reservations,
+    users,
+    warehouse_stock,
+    warehouses,
+);
@@ -0,0 +1,386 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+
+use diesel::Connection;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use serde_json::{Number, Value};
+use uuid::Uuid;
+
+use crate::db::{PgPool, attribute_repository, with_conn};
+use crate::errors::{AppError, map_diesel_error};
+use crate::models::attribute::{
+    ATTRIBUTE_BOOLEAN, ATTRIBUTE_NUMBER, ATTRIBUTE_TEXT, ATTRIBUTE_TYPES, AttributeDefinition,
+    NewAttributeDefinition, UpdateAttributeDefinition,
+};
+
+const MAX_CODE_CHARS: usize = 40;
+const MAX_NAME_CHARS: usize = 100;
+const MAX_TEXT_VALUE_CHARS: usize = 200;
+const MAX_ALLOWED_VALUES: usize = 200;
+
+enum UpdateOutcome {
+    Updated(AttributeDefinition),
+    NotFound,
+    InvalidValues(String),
+    InUse(i64),
+}
+
+/// How many matching products have one value of an attribute.
+#[derive(Debug, Clone)]
+pub struct FacetValue {
+    pub value: Value,
+    pub count: i64,
+}
+
+/// The values of one attribute across a set of products, most common first.
+#[derive(Debug, Clone)]
+pub struct AttributeFacet {
+    pub attribute: AttributeDefinition,
+    pub values: Vec<FacetValue>,
+}
+
+fn validate_code(code: &str) -> Result<String, AppError> {
+    let code = code.trim();
+    if code.is_empty() || code.chars().count() > MAX_CODE_CHARS {
+        return Err(AppError::Validation(format!(
+            "Attribute code must be between 1 and {MAX_CODE_CHARS} characters"
+        )));
+    }
+    if !code
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    {
+        return Err(AppError::Validation(
+            "Attribute code may only contain lowercase letters, digits and '_'".into(),
+        ));
+    }
+    Ok(code.to_string())
+}
+
+fn validate_name(name: &str) -> Result<String, AppError> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_CHARS {
+        return Err(AppError::Validation(format!(
+            "Attribute name must be between 1 and {MAX_NAME_CHARS} characters"
+        )));
+    }
+    Ok(name.to_string())
+}
+
+fn validate_value_type(value_type: &str) -> Result<(), AppError> {
+    if ATTRIBUTE_TYPES.contains(&value_type) {
+        Ok(())
+    } else {
+        Err(AppError::Validation(format!(
+            "Unknown attribute type (expected one of {}): {value_type}",
+            ATTRIBUTE_TYPES.join(", ")
+        )))
+    }
+}
+
+/// Whether `value` is a JSON value of the attribute type.
+fn has_type(value_type: &str, value: &Value) -> bool {
+    match value_type {
+        ATTRIBUTE_TEXT => value.as_str().is_some_and(|text| {
+            !text.trim().is_empty() && text.chars().count() <= MAX_TEXT_VALUE_CHARS
+        }),
+        ATTRIBUTE_NUMBER => value.is_number(),
+        ATTRIBUTE_BOOLEAN => value.is_boolean(),
+        _ => false,
+    }
+}
+
+/// Numbers compare by value, so `1200` and `1200.0` are the same wattage.
+fn same_value(a: &Value, b: &Value) -> bool {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// A non-empty JSON array of distinct values of the type. Booleans take no list.
+fn validate_allowed_values(value_type: &str, allowed: &Value) -> Result<(), String> {
+    if value_type == ATTRIBUTE_BOOLEAN {
+        return Err("Boolean attributes cannot restrict their values".into());
+    }
+    let Some(values) = allowed.as_array().filter(|values| !values.is_empty()) else {
+        return Err("allowed_values must be a non-empty array".into());
+    };
+    if values.len() > MAX_ALLOWED_VALUES {
+        return Err(format!(
+            "An attribute allows at most {MAX_ALLOWED_VALUES} values"
+        ));
+    }
+    for (index, value) in values.iter().enumerate() {
+        if !has_type(value_type, value) {
+            return Err(format!(
+                "Every allowed value must be a {value_type} value; got {value}"
+            ));
+        }
+        if values[..index].iter().any(|other| same_value(other, value)) {
+            return Err(format!("Allowed value {value} is listed twice"));
+        }
+    }
+    Ok(())
+}
+
+/// The value to store for the attribute, or why it is not accepted.
+fn check_value(definition: &AttributeDefinition, value: &Value) -> Result<Value, AppError> {
+    if !has_type(&definition.value_type, value) {
+        return Err(AppError::Validation(format!(
+            "Attribute {} takes a {} value",
+            definition.code, definition.value_type
+        )));
+    }
+    if let Some(allowed) = definition.allowed_values()
+        && !allowed.iter().any(|option| same_value(option, value))
+    {
+        return Err(AppError::Validation(format!(
+            "{value} is not an allowed value of attribute {}",
+            definition.code
+        )));
+    }
+    match value {
+        Value::String(text) => Ok(Value::String(text.trim().to_string())),
+        other => Ok(other.clone()),
+    }
+}
+
+/// A filter value from the query string, read as the attribute type.
+fn parse_filter_value(definition: &AttributeDefinition, raw: &str) -> Result<Value, AppError> {
+    let invalid = || {
+        AppError::Validation(format!(
+            "Attribute {} takes a {} value; got {raw}",
+            definition.code, definition.value_type
+        ))
+    };
+    match definition.value_type.as_str() {
+        ATTRIBUTE_NUMBER => raw
+            .parse::<i64>()
+            .map(Value::from)
+            .or_else(|_| {
+                raw.parse::<f64>()
+                    .ok()
+                    .and_then(Number::from_f64)
+                    .map(Value::Number)
+                    .ok_or(())
+            })
+            .map_err(|_| invalid()),
+        ATTRIBUTE_BOOLEAN => raw.parse::<bool>().map(Value::Bool).map_err(|_| invalid()),
+        _ => Ok(Value::String(raw.trim().to_string())),
+    }
+}
+
+/// Codes are unique, so a unique violation means a clash.
+fn map_attribute_error(err: DieselError) -> AppError {
+    match err {
+        DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+            AppError::Conflict("An attribute with this code already exists".into())
+        }
+        other => map_diesel_error(other),
+    }
+}
+
+pub async fn create_definition(
+    pool: PgPool,
+    mut new_definition: NewAttributeDefinition,
+) -> Result<AttributeDefinition, AppError> {
+    new_definition.code = validate_code(&new_definition.code)?;
+    new_definition.name = validate_name(&new_definition.name)?;
+    validate_value_type(&new_definition.value_type)?;
+    if let Some(allowed) = &new_definition.allowed_values {
+        validate_allowed_values(&new_definition.value_type, allowed)
+            .map_err(AppError::Validation)?;
+    }
+
+    with_conn(pool, move |conn| {
+        attribute_repository::create_definition(conn, &new_definition)
+    })
+    .await
+    .map_err(map_attribute_error)
+}
+
+/// Every attribute definition, by code.
+pub async fn list_definitions(pool: PgPool) -> Result<Vec<AttributeDefinition>, AppError> {
+    with_conn(pool, attribute_repository::list_definitions)
+        .await
+        .map_err(map_diesel_error)
+}
+
+/// Rename an attribute or change the values it allows. Narrowing the allowed values fails while
+/// products still use a value that would no longer be allowed.
+pub async fn update_definition(
+    pool: PgPool,
+    attribute_id: Uuid,
+    mut update: UpdateAttributeDefinition,
+) -> Result<AttributeDefinition, AppError> {
+    if let Some(name) = &update.name {
+        update.name = Some(validate_name(name)?);
+    }
+
+    let outcome = with_conn(pool, move |conn| {
+        conn.transaction(|conn| {
+            let Some(definition) = attribute_repository::get_definition_by_id(conn, attribute_id)?
+            else {
+                return Ok(UpdateOutcome::NotFound);
+            };
+            if let Some(Some(allowed)) = &update.allowed_values {
+                if let Err(message) = validate_allowed_values(&definition.value_type, allowed) {
+                    return Ok(UpdateOutcome::InvalidValues(message));
+                }
+                let values = allowed.as_array().cloned().unwrap_or_default();
+                let in_use =
+                    attribute_repository::count_disallowed_values(conn, attribute_id, &values)?;
+                if in_use > 0 {
+                    return Ok(UpdateOutcome::InUse(in_use));
+                }
+            }
+            // Diesel rejects empty changesets, so an empty update returns the row it read
+            if update.name.is_none() && update.allowed_values.is_none() {
+                return Ok(UpdateOutcome::Updated(definition));
+            }
+            let updated = attribute_repository::update_definition(conn, attribute_id, &update)?;
+            Ok(updated.map_or(UpdateOutcome::NotFound, UpdateOutcome::Updated))
+        })
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    match outcome {
+        UpdateOutcome::Updated(definition) => Ok(definition),
+        UpdateOutcome::NotFound => Err(AppError::NotFound("Attribute not found".into())),
+        UpdateOutcome::InvalidValues(message) => Err(AppError::Validation(message)),
+        UpdateOutcome::InUse(products) => Err(AppError::Conflict(format!(
+            "{products} products have a value that would no longer be allowed"
+        ))),
+    }
+}
+
+/// Delete an attribute; products lose their value for it.
+pub async fn delete_definition(pool: PgPool, attribute_id: Uuid) -> Result<(), AppError> {
+    let deleted = with_conn(pool, move |conn| {
+        attribute_repository::delete_definition(conn, attribute_id)
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    if deleted == 0 {
+        return Err(AppError::NotFound("Attribute not found".into()));
+    }
+    Ok(())
+}
+
+/// Check product attributes, keyed by code, against their definitions and return the values to
+/// store by attribute id.
+pub async fn resolve_values(
+    pool: PgPool,
+    attributes: BTreeMap<String, Value>,
+) -> Result<Vec<(Uuid, Value)>, AppError> {
+    if attributes.is_empty() {
+        return Ok(Vec::new());
+    }
+    let definitions = definitions_by_code(pool).await?;
+    attributes
+        .into_iter()
+        .map(|(code, value)| {
+            let definition = definitions
+                .get(&code)
+                .ok_or_else(|| AppError::Validation(format!("Unknown attribute: {code}")))?;
+            Ok((definition.id, check_value(definition, &value)?))
+        })
+        .collect()
+}
+
+/// Turn `(code, value)` listing filters into the values accepted per attribute id. Several values
+/// for one code match any of them.
+pub async fn resolve_filters(
+    pool: PgPool,
+    filters: Vec<(String, String)>,
+) -> Result<Vec<(Uuid, Vec<Value>)>, AppError> {
+    if filters.is_empty() {
+        return Ok(Vec::new());
+    }
+    let definitions = definitions_by_code(pool).await?;
+    let mut resolved: Vec<(Uuid, Vec<Value>)> = Vec::new();
+    for (code, raw) in filters {
+        let definition = definitions
+            .get(&code)
+            .ok_or_else(|| AppError::Validation(format!("Unknown attribute: {code}")))?;
+        let value = parse_filter_value(definition, &raw)?;
+        match resolved.iter_mut().find(|(id, _)| *id == definition.id) {
+            Some((_, values)) => values.push(value),
+            None => resolved.push((definition.id, vec![value])),
+        }
+    }
+    Ok(resolved)
+}
+
+async fn definitions_by_code(
+    pool: PgPool,
+) -> Result<HashMap<String, AttributeDefinition>, AppError> {
+    Ok(list_definitions(pool)
+        .await?
+        .into_iter()
+        .map(|definition| (definition.code.clone(), definition))
+        .collect())
+}
+
+/// Attribute values of each product, keyed by code.
+pub async fn attributes_for_products(
+    pool: PgPool,
+    product_ids: Vec<Uuid>,
+) -> Result<HashMap<Uuid, BTreeMap<String, Value>>, AppError> {
+    let rows = with_conn(pool, move |conn| {
+        attribute_repository::attributes_for_products(conn, &product_ids)
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    let mut by_product: HashMap<Uuid, BTreeMap<String, Value>> = HashMap::new();
+    for (product_id, code, value) in rows {
+        by_product
+            .entry(product_id)
+            .or_default()
+            .insert(code, value);
+    }
+    Ok(by_product)
+}
+
+/// Group `(attribute_id, value, count)` rows into facets, by attribute code. Values come most
+/// common first, then in ascending order.
+pub(crate) fn build_facets(
+    definitions: Vec<AttributeDefinition>,
+    counts: Vec<(Uuid, Value, i64)>,
+) -> Vec<AttributeFacet> {
+    let mut values: HashMap<Uuid, Vec<FacetValue>> = HashMap::new();
+    for (attribute_id, value, count) in counts {
+        values
+            .entry(attribute_id)
+            .or_default()
+            .push(FacetValue { value, count });
+    }
+    definitions
+        .into_iter()
+        .filter_map(|attribute| {
+            let mut values = values.remove(&attribute.id)?;
+            values.sort_by(|a, b| {
+                b.count
+                    .cmp(&a.count)
+                    .then_with(|| compare_values(&a.value, &b.value))
+            });
+            Some(AttributeFacet { attribute, values })
+        })
+        .collect()
+}
+
+fn compare_values(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a
+            .as_f64()
+            .unwrap_or_default()
+            .total_cmp(&b.as_f64().unwrap_or_default()),
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        (a, b) => a.to_string().cmp(&b.to_string()),
+    }
+}
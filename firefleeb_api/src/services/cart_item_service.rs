@@ -1,43 +1,153 @@
+use std::time::Duration;
+
 use bigdecimal::{BigDecimal, Zero};
+use chrono::Utc;
+use diesel::Connection;
 use uuid::Uuid;
 
-use crate::db::{PgPool, cart_item_repository, cart_repository, with_conn};
+use crate::auth::AuthUser;
+use crate::db::{
+    PgPool, cart_item_repository, cart_repository, product_repository, product_variant_repository,
+    with_conn,
+};
 use crate::errors::{AppError, map_diesel_error};
+use crate::models::cart::CART_STATUS_ACTIVE;
 use crate::models::cart_item::{CartItem, NewCartItem, UpdateCartItem};
+use crate::services::{cart_service, stock_reservation_service};
+
+/// A cart item and whether its product is still on sale.
+#[derive(Debug)]
+pub struct CartLine {
+    pub item: CartItem,
+    pub available: bool,
+}
+
+enum ItemOutcome {
+    Saved(CartItem),
+    NotFound,
+    /// A product id matched several of the cart's lines, one per variant.
+    Ambiguous,
+    Unavailable,
+    /// The cart was checked out or closed, so its lines are fixed.
+    CartNotActive,
+    /// Too little stock left; carries what is available.
+    OutOfStock(i64),
+}
+
+const UNAVAILABLE: &str = "This product is no longer available";
+const CART_NOT_ACTIVE: &str = "Only active carts can be changed";
+const AMBIGUOUS: &str =
+    "The cart holds several variants of this product; address the line by its variant id";
 
-pub async fn list_items(pool: PgPool, cart_id: Uuid) -> Result<Vec<CartItem>, AppError> {
-    with_conn(pool, move |conn| {
-        cart_item_repository::get_items_by_cart_id(conn, cart_id)
+pub async fn list_items(
+    pool: PgPool,
+    principal: &AuthUser,
+    cart_id: Uuid,
+) -> Result<Vec<CartLine>, AppError> {
+    cart_service::authorize_cart(pool.clone(), principal, cart_id).await?;
+
+    let (items, unavailable) = with_conn(pool, move |conn| {
+        let items = cart_item_repository::get_items_by_cart_id(conn, cart_id)?;
+        let unavailable = cart_item_repository::unavailable_item_ids(conn, cart_id)?;
+        Ok((items, unavailable))
     })
     .await
-    .map_err(map_diesel_error)
+    .map_err(map_diesel_error)?;
+
+    Ok(items
+        .into_iter()
+        .map(|item| CartLine {
+            available: !unavailable.contains(&item.id),
+            item,
+        })
+        .collect())
 }
 
-pub async fn add_item(pool: PgPool, new_item: NewCartItem) -> Result<CartItem, AppError> {
+/// Add a product to a cart. `item_id` names either a product, meaning its default variant,
+/// or one specific variant. Archived products cannot be added. The quantity is reserved for
+/// `reservation_ttl`, and refused when other carts' reservations leave too little stock.
+pub async fn add_item(
+    pool: PgPool,
+    principal: &AuthUser,
+    mut new_item: NewCartItem,
+    reservation_ttl: Duration,
+) -> Result<CartItem, AppError> {
     if let Err(msg) = new_item.validate() {
         return Err(AppError::Validation(msg));
     }
+    cart_service::authorize_cart(pool.clone(), principal, new_item.cart_id).await?;
 
-    with_conn(pool, move |conn| {
-        let item = cart_item_repository::create_cart_item(conn, &new_item)?;
-        recalc_cart_total(conn, new_item.cart_id)?;
-        Ok(item)
+    let outcome = with_conn(pool, move |conn| {
+        conn.transaction(|conn| {
+            let variant =
+                match product_variant_repository::get_variant_by_id(conn, new_item.item_id)? {
+                    Some(variant) => Some(variant),
+                    None => {
+                        product_variant_repository::get_default_variant(conn, new_item.item_id)?
+                    }
+                };
+            let Some(variant) = variant else {
+                return Ok(ItemOutcome::NotFound);
+            };
+            if !cart_is_active(conn, new_item.cart_id)? {
+                return Ok(ItemOutcome::CartNotActive);
+            }
+            if product_is_archived(conn, variant.product_id)? {
+                return Ok(ItemOutcome::Unavailable);
+            }
+            let now = Utc::now();
+            let available =
+                stock_reservation_service::available_stock(conn, variant.id, None, now)?;
+            if i64::from(new_item.quantity) > available {
+                return Ok(ItemOutcome::OutOfStock(available));
+            }
+            new_item.item_id = variant.product_id;
+            new_item.variant_id = Some(variant.id);
+
+            let item = cart_item_repository::create_cart_item(conn, &new_item)?;
+            stock_reservation_service::reserve(
+                conn,
+                item.id,
+                variant.id,
+                item.quantity,
+                reservation_ttl,
+                now,
+            )?;
+            recalc_cart_total(conn, new_item.cart_id)?;
+            Ok(ItemOutcome::Saved(item))
+        })
     })
     .await
-    .map_err(map_diesel_error)
+    .map_err(map_diesel_error)?;
+
+    match outcome {
+        ItemOutcome::Saved(item) => Ok(item),
+        ItemOutcome::NotFound => Err(AppError::NotFound("Product not found".into())),
+        ItemOutcome::Ambiguous => Err(AppError::Conflict(AMBIGUOUS.into())),
+        ItemOutcome::Unavailable => Err(AppError::Conflict(UNAVAILABLE.into())),
+        ItemOutcome::CartNotActive => Err(AppError::Conflict(CART_NOT_ACTIVE.into())),
+        ItemOutcome::OutOfStock(available) => {
+            Err(stock_reservation_service::insufficient_stock(available))
+        }
+    }
 }
 
+/// Change a cart line, see [`resolve_line`]. A new quantity renews the line's reservation for
+/// `reservation_ttl` and is refused when the line's variant has too little free stock for it.
 pub async fn update_item(
     pool: PgPool,
+    principal: &AuthUser,
     cart_id: Uuid,
     item_id: Uuid,
     mut updates: UpdateCartItem,
+    reservation_ttl: Duration,
 ) -> Result<Option<CartItem>, AppError> {
     if updates.quantity.is_none() && updates.unit_price.is_none() {
         return Err(AppError::Validation(
             "At least one field must be provided".into(),
         ));
     }
+    cart_service::authorize_cart(pool.clone(), principal, cart_id).await?;
 
     if let Some(qty) = updates.quantity {
         if qty < 0 {
@@ -46,7 +156,7 @@ pub async fn update_item(
             ));
         }
         if qty == 0 {
-            remove_item(pool, cart_id, item_id).await?;
+            remove_item(pool, principal, cart_id, item_id).await?;
             return Ok(None);
         }
         updates.quantity = Some(qty);
@@ -60,42 +170,149 @@ pub async fn update_item(
         ));
     }
 
-    with_conn(pool, move |conn| {
-        let item = cart_item_repository::update_cart_item(conn, cart_id, item_id, &updates)?;
-        recalc_cart_total(conn, cart_id)?;
-        Ok(item)
+    let outcome = with_conn(pool, move |conn| {
+        conn.transaction(|conn| {
+            let current = match resolve_line(conn, cart_id, item_id)? {
+                Ok(line) => line,
+                Err(outcome) => return Ok(outcome),
+            };
+            if !cart_is_active(conn, cart_id)? {
+                return Ok(ItemOutcome::CartNotActive);
+            }
+            if product_is_archived(conn, current.item_id)? {
+                return Ok(ItemOutcome::Unavailable);
+            }
+            let now = Utc::now();
+            // The line's own reservation may have expired and its stock gone to other carts,
+            // so every renewal is checked, not just increases
+            if let Some(qty) = updates.quantity {
+                let available = stock_reservation_service::available_stock(
+                    conn,
+                    current.variant_id,
+                    Some(current.id),
+                    now,
+                )?;
+                if i64::from(qty) > available {
+                    return Ok(ItemOutcome::OutOfStock(available));
+                }
+            }
+            let item = cart_item_repository::update_cart_item(conn, current.id, &updates)?;
+            if updates.quantity.is_some() {
+                stock_reservation_service::reserve(
+                    conn,
+                    current.id,
+                    current.variant_id,
+                    item.quantity,
+                    reservation_ttl,
+                    now,
+                )?;
+            }
+            recalc_cart_total(conn, cart_id)?;
+            Ok(ItemOutcome::Saved(item))
+        })
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    match outcome {
+        ItemOutcome::Saved(item) => Ok(Some(item)),
+        ItemOutcome::NotFound => Err(AppError::NotFound("Cart item not found".into())),
+        ItemOutcome::Ambiguous => Err(AppError::Conflict(AMBIGUOUS.into())),
+        ItemOutcome::Unavailable => Err(AppError::Conflict(format!(
+            "{UNAVAILABLE}; remove it from the cart instead"
+        ))),
+        ItemOutcome::CartNotActive => Err(AppError::Conflict(CART_NOT_ACTIVE.into())),
+        ItemOutcome::OutOfStock(available) => {
+            Err(stock_reservation_service::insufficient_stock(available))
+        }
+    }
+}
+
+/// Remove a cart line, see [`resolve_line`]; its stock reservation goes with it.
+pub async fn remove_item(
+    pool: PgPool,
+    principal: &AuthUser,
+    cart_id: Uuid,
+    item_id: Uuid,
+) -> Result<(), AppError> {
+    cart_service::authorize_cart(pool.clone(), principal, cart_id).await?;
+
+    let outcome = with_conn(pool, move |conn| {
+        conn.transaction(|conn| {
+            let line = match resolve_line(conn, cart_id, item_id)? {
+                Ok(line) => line,
+                Err(outcome) => return Ok(outcome),
+            };
+            if !cart_is_active(conn, cart_id)? {
+                return Ok(ItemOutcome::CartNotActive);
+            }
+            cart_item_repository::delete_item(conn, line.id)?;
+            recalc_cart_total(conn, cart_id)?;
+            Ok(ItemOutcome::Saved(line))
+        })
     })
     .await
-    .map(Some)
-    .map_err(map_diesel_error)
+    .map_err(map_diesel_error)?;
+
+    match outcome {
+        ItemOutcome::Saved(_) => Ok(()),
+        ItemOutcome::Ambiguous => Err(AppError::Conflict(AMBIGUOUS.into())),
+        ItemOutcome::CartNotActive => Err(AppError::Conflict(CART_NOT_ACTIVE.into())),
+        _ => Err(AppError::NotFound("Cart item not found".into())),
+    }
 }
 
-pub async fn remove_item(pool: PgPool, cart_id: Uuid, item_id: Uuid) -> Result<(), AppError> {
-    let deleted = with_conn(pool, move |conn| {
-        let deleted = cart_item_repository::delete_item(conn, cart_id, item_id)?;
-        if deleted > 0 {
+/// Remove every line of an active cart, releasing their stock reservations.
+pub async fn clear_cart(pool: PgPool, principal: &AuthUser, cart_id: Uuid) -> Result<(), AppError> {
+    cart_service::authorize_cart(pool.clone(), principal, cart_id).await?;
+
+    let active = with_conn(pool, move |conn| {
+        conn.transaction(|conn| {
+            if !cart_is_active(conn, cart_id)? {
+                return Ok(false);
+            }
+            cart_item_repository::delete_all_for_cart(conn, cart_id)?;
             recalc_cart_total(conn, cart_id)?;
-        }
-        Ok(deleted)
+            Ok(true)
+        })
     })
     .await
     .map_err(map_diesel_error)?;
 
-    if deleted == 0 {
-        Err(AppError::NotFound("Cart item not found".into()))
-    } else {
+    if active {
         Ok(())
+    } else {
+        Err(AppError::Conflict(CART_NOT_ACTIVE.into()))
     }
 }
 
-pub async fn clear_cart(pool: PgPool, cart_id: Uuid) -> Result<(), AppError> {
-    with_conn(pool, move |conn| {
-        cart_item_repository::delete_all_for_cart(conn, cart_id)?;
-        recalc_cart_total(conn, cart_id)?;
-        Ok(())
+/// The one line of the cart that `item_id` addresses: the line of a variant, or the line of a
+/// product as long as the cart holds only one of its variants.
+fn resolve_line(
+    conn: &mut diesel::PgConnection,
+    cart_id: Uuid,
+    item_id: Uuid,
+) -> diesel::QueryResult<Result<CartItem, ItemOutcome>> {
+    let mut lines = cart_item_repository::find_lines(conn, cart_id, item_id)?;
+    Ok(match lines.len() {
+        0 => Err(ItemOutcome::NotFound),
+        1 => Ok(lines.remove(0)),
+        _ => Err(ItemOutcome::Ambiguous),
     })
-    .await
-    .map_err(map_diesel_error)
+}
+
+/// Locks the cart until the transaction ends, so its lines cannot change while it is checked out.
+fn cart_is_active(conn: &mut diesel::PgConnection, cart_id: Uuid) -> diesel::QueryResult<bool> {
+    Ok(cart_repository::get_cart_for_update(conn, cart_id)?
+        .is_some_and(|cart| cart.cart_status == CART_STATUS_ACTIVE))
+}
+
+fn product_is_archived(
+    conn: &mut diesel::PgConnection,
+    product_id: Uuid,
+) -> diesel::QueryResult<bool> {
+    Ok(product_repository::get_product_by_id(conn, product_id)?
+        .is_some_and(|product| product.is_archived()))
 }
 
 fn recalc_cart_total(conn: &mut diesel::PgConnection, cart_id: Uuid) -> diesel::QueryResult<()> {
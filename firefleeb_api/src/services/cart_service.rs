@@ -1,12 +1,51 @@
+use chrono::Utc;
+use diesel::Connection;
 use uuid::Uuid;
 
-use crate::db::cart_repository;
+use crate::auth::{AuthConfig, AuthUser, ensure_email_verified, ensure_owner_or_admin};
 use crate::db::{PgPool, with_conn};
+use crate::db::{cart_item_repository, cart_repository, stock_reservation_repository};
 use crate::errors::AppError;
 use crate::errors::map_diesel_error;
-use crate::models::cart::{Cart, UpdateCart};
+use crate::models::cart::{CART_STATUS_ACTIVE, CART_STATUS_CHECKED_OUT, Cart, UpdateCart};
+use crate::models::stock_movement::{MOVEMENT_SALE, NewStockMovement};
+use crate::models::warehouse::GeoPoint;
+use crate::services::stock_allocation_service::{self, Allocation};
+use crate::services::{stock_movement_service, stock_reservation_service, user_service};
+use crate::types::AllocationStrategy;
+
+enum CheckoutOutcome {
+    CheckedOut(Cart, Vec<Allocation>),
+    NotActive,
+    Empty,
+    HasUnavailableItems,
+    OutOfStock,
+}
+
+/// Load a cart and make sure the caller owns it (or is an admin).
+pub async fn authorize_cart(
+    pool: PgPool,
+    principal: &AuthUser,
+    cart_id: Uuid,
+) -> Result<Cart, AppError> {
+    let maybe_cart = with_conn(pool, move |conn| {
+        cart_repository::get_cart_by_id(conn, cart_id)
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    let cart = maybe_cart.ok_or_else(|| AppError::NotFound("Cart not found".into()))?;
+    ensure_owner_or_admin(principal, cart.user_id)?;
+    Ok(cart)
+}
+
+pub async fn create_default_cart(
+    pool: PgPool,
+    principal: &AuthUser,
+    user_id: Uuid,
+) -> Result<Cart, AppError> {
+    ensure_owner_or_admin(principal, user_id)?;
 
-pub async fn create_default_cart(pool: PgPool, user_id: Uuid) -> Result<Cart, AppError> {
     with_conn(pool, move |conn| {
         cart_repository::create_default_cart(conn, user_id)
     })
@@ -14,7 +53,13 @@ pub async fn create_default_cart(pool: PgPool, user_id: Uuid) -> Result<Cart, Ap
     .map_err(map_diesel_error)
 }
 
-pub async fn get_active_by_user_id(pool: PgPool, user_id: Uuid) -> Result<Cart, AppError> {
+pub async fn get_active_by_user_id(
+    pool: PgPool,
+    principal: &AuthUser,
+    user_id: Uuid,
+) -> Result<Cart, AppError> {
+    ensure_owner_or_admin(principal, user_id)?;
+
     let maybe_cart = with_conn(pool, move |conn| {
         cart_repository::get_active_by_user_id(conn, user_id)
     })
@@ -26,9 +71,17 @@ pub async fn get_active_by_user_id(pool: PgPool, user_id: Uuid) -> Result<Cart,
 
 pub async fn update_cart(
     pool: PgPool,
+    principal: &AuthUser,
     cart_id: Uuid,
     updated: UpdateCart,
 ) -> Result<Cart, AppError> {
+    if updated.cart_status.as_deref() == Some(CART_STATUS_CHECKED_OUT) {
+        return Err(AppError::Validation(
+            "Carts are checked out through POST /carts/:id/checkout".into(),
+        ));
+    }
+    authorize_cart(pool.clone(), principal, cart_id).await?;
+
     with_conn(pool, move |conn| {
         cart_repository::update_cart(conn, cart_id, &updated)
     })
@@ -36,7 +89,109 @@ pub async fn update_cart(
     .map_err(map_diesel_error)
 }
 
-pub async fn delete_cart(pool: PgPool, cart_id: Uuid) -> Result<(), AppError> {
+/// Close an active, non-empty cart whose products are all still on sale.
+///
+/// Depending on configuration the cart owner must have verified their email address first.
+/// `strategy` picks the warehouses that ship each line, ranked by distance from `destination`
+/// when it is given. Each allocation is logged as a sale in the stock ledger, which takes it out
+/// of that warehouse's stock, and the cart's reservations are released.
+pub async fn checkout(
+    pool: PgPool,
+    auth: &AuthConfig,
+    strategy: AllocationStrategy,
+    principal: &AuthUser,
+    cart_id: Uuid,
+    destination: Option<GeoPoint>,
+) -> Result<(Cart, Vec<Allocation>), AppError> {
+    let cart = authorize_cart(pool.clone(), principal, cart_id).await?;
+    if auth.require_verified_email.blocks_checkout() {
+        let owner = user_service::get_user_by_id(pool.clone(), cart.user_id).await?;
+        ensure_email_verified(&owner)?;
+    }
+    let actor_id = principal.user_id;
+
+    let outcome = with_conn(pool, move |conn| {
+        conn.transaction(|conn| {
+            let Some(cart) = cart_repository::get_cart_for_update(conn, cart_id)? else {
+                return Ok(CheckoutOutcome::NotActive);
+            };
+            if cart.cart_status != CART_STATUS_ACTIVE {
+                return Ok(CheckoutOutcome::NotActive);
+            }
+            let mut items = cart_item_repository::get_items_by_cart_id(conn, cart_id)?;
+            if items.is_empty() {
+                return Ok(CheckoutOutcome::Empty);
+            }
+            if !cart_item_repository::unavailable_item_ids(conn, cart_id)?.is_empty() {
+                return Ok(CheckoutOutcome::HasUnavailableItems);
+            }
+
+            // Lock variants in a fixed order so concurrent checkouts cannot deadlock
+            items.sort_by_key(|item| item.variant_id);
+            let now = Utc::now();
+            for item in &items {
+                let available = stock_reservation_service::available_stock(
+                    conn,
+                    item.variant_id,
+                    Some(item.id),
+                    now,
+                )?;
+                if i64::from(item.quantity) > available {
+                    return Ok(CheckoutOutcome::OutOfStock);
+                }
+            }
+            let Some(allocations) =
+                stock_allocation_service::allocate_cart(conn, strategy, destination, &items)?
+            else {
+                return Ok(CheckoutOutcome::OutOfStock);
+            };
+            for allocation in &allocations {
+                let sale = NewStockMovement {
+                    product_id: allocation.product_id,
+                    variant_id: Some(allocation.variant_id),
+                    warehouse_id: allocation.warehouse_id,
+                    movement_type: MOVEMENT_SALE.into(),
+                    quantity: -allocation.quantity,
+                    reason: "Checkout".into(),
+                    actor_id: Some(actor_id),
+                    cart_id: Some(cart_id),
+                };
+                stock_movement_service::apply(conn, allocation.variant_id, &sale)?;
+            }
+            stock_reservation_repository::delete_for_cart(conn, cart_id)?;
+
+            let cart = cart_repository::set_cart_status(conn, cart_id, CART_STATUS_CHECKED_OUT)?;
+            Ok(CheckoutOutcome::CheckedOut(cart, allocations))
+        })
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    match outcome {
+        CheckoutOutcome::CheckedOut(cart, allocations) => Ok((cart, allocations)),
+        CheckoutOutcome::NotActive => Err(AppError::Conflict(
+            "Only active carts can be checked out".into(),
+        )),
+        CheckoutOutcome::Empty => Err(AppError::Validation(
+            "Cannot check out an empty cart".into(),
+        )),
+        CheckoutOutcome::HasUnavailableItems => Err(AppError::Conflict(
+            "Remove the products that are no longer available before checking out".into(),
+        )),
+        CheckoutOutcome::OutOfStock => Err(AppError::Conflict(
+            "Some items are no longer in stock; lower their quantity or remove them before checking out"
+                .into(),
+        )),
+    }
+}
+
+pub async fn delete_cart(
+    pool: PgPool,
+    principal: &AuthUser,
+    cart_id: Uuid,
+) -> Result<(), AppError> {
+    authorize_cart(pool.clone(), principal, cart_id).await?;
+
     with_conn(pool, move |conn| {
         cart_repository::delete_cart(conn, cart_id)
     })
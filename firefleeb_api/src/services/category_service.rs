@@ -0,0 +1,347 @@
+use std::collections::HashMap;
+
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use diesel::{Connection, PgConnection, QueryResult};
+use uuid::Uuid;
+
+use crate::db::{PgPool, with_conn};
+use crate::db::{category_repository, product_repository};
+use crate::errors::AppError;
+use crate::errors::map_diesel_error;
+use crate::models::category::{
+    Category, CategoryCrumb, CategoryPathStep, NewCategory, NewProductCategory, UpdateCategory,
+};
+use crate::models::product::Product;
+
+const MAX_CATEGORY_NAME_CHARS: usize = 100;
+
+enum MoveOutcome {
+    Updated(Category, CategoryPath),
+    NotFound,
+    ParentNotFound,
+    Cycle,
+}
+
+enum DeleteOutcome {
+    Deleted,
+    NotFound,
+    HasChildren,
+}
+
+enum LinkOutcome {
+    Linked,
+    CategoryNotFound,
+    ProductNotFound,
+}
+
+/// Root-first path of categories leading to (and including) a category.
+pub type CategoryPath = Vec<CategoryCrumb>;
+
+fn validate_name(name: &str) -> Result<String, AppError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::Validation(
+            "Category name must not be empty".into(),
+        ));
+    }
+    if name.chars().count() > MAX_CATEGORY_NAME_CHARS {
+        return Err(AppError::Validation(format!(
+            "Category name must be at most {MAX_CATEGORY_NAME_CHARS} characters"
+        )));
+    }
+    Ok(name.to_string())
+}
+
+/// Sibling names are unique, so a unique violation means a name clash.
+fn map_category_error(err: DieselError) -> AppError {
+    match err {
+        DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => AppError::Conflict(
+            "A category with this name already exists under the same parent".into(),
+        ),
+        other => map_diesel_error(other),
+    }
+}
+
+pub async fn create_category(
+    pool: PgPool,
+    name: String,
+    parent_id: Option<Uuid>,
+) -> Result<(Category, CategoryPath), AppError> {
+    let new_category = NewCategory {
+        parent_id,
+        name: validate_name(&name)?,
+    };
+
+    let created = with_conn(pool, move |conn| {
+        conn.transaction(|conn| {
+            category_repository::lock_tree(conn)?;
+            if let Some(parent_id) = new_category.parent_id
+                && category_repository::get_category_by_id(conn, parent_id)?.is_none()
+            {
+                return Ok(None);
+            }
+            let category = category_repository::create_category(conn, &new_category)?;
+            let path = path_of(conn, category.id)?;
+            Ok(Some((category, path)))
+        })
+    })
+    .await
+    .map_err(map_category_error)?;
+
+    created.ok_or_else(|| AppError::NotFound("Parent category not found".into()))
+}
+
+pub async fn list_categories(pool: PgPool) -> Result<Vec<(Category, CategoryPath)>, AppError> {
+    let categories = with_conn(pool, category_repository::list_categories)
+        .await
+        .map_err(map_diesel_error)?;
+
+    // The whole tree is at hand, so paths are walked in memory
+    let by_id: HashMap<Uuid, &Category> = categories.iter().map(|c| (c.id, c)).collect();
+    let paths: Vec<CategoryPath> = categories
+        .iter()
+        .map(|category| {
+            let mut path = Vec::new();
+            let mut current = Some(category);
+            while let Some(step) = current {
+                path.push(CategoryCrumb {
+                    id: step.id,
+                    name: step.name.clone(),
+                });
+                current = step
+                    .parent_id
+                    .and_then(|parent| by_id.get(&parent).copied());
+            }
+            path.reverse();
+            path
+        })
+        .collect();
+
+    Ok(categories.into_iter().zip(paths).collect())
+}
+
+pub async fn get_category(
+    pool: PgPool,
+    category_id: Uuid,
+) -> Result<(Category, CategoryPath), AppError> {
+    let found = with_conn(pool, move |conn| {
+        let Some(category) = category_repository::get_category_by_id(conn, category_id)? else {
+            return Ok(None);
+        };
+        let path = path_of(conn, category_id)?;
+        Ok(Some((category, path)))
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    found.ok_or_else(|| AppError::NotFound("Category not found".into()))
+}
+
+/// Rename and/or move a category. A category cannot move below itself.
+pub async fn update_category(
+    pool: PgPool,
+    category_id: Uuid,
+    mut update: UpdateCategory,
+) -> Result<(Category, CategoryPath), AppError> {
+    if let Some(name) = &update.name {
+        update.name = Some(validate_name(name)?);
+    }
+
+    let outcome = with_conn(pool, move |conn| {
+        conn.transaction(|conn| {
+            category_repository::lock_tree(conn)?;
+            if category_repository::get_category_by_id(conn, category_id)?.is_none() {
+                return Ok(MoveOutcome::NotFound);
+            }
+            if let Some(Some(parent_id)) = update.parent_id {
+                if category_repository::get_category_by_id(conn, parent_id)?.is_none() {
+                    return Ok(MoveOutcome::ParentNotFound);
+                }
+                if category_repository::is_descendant_or_self(conn, category_id, parent_id)? {
+                    return Ok(MoveOutcome::Cycle);
+                }
+            }
+            // Diesel rejects empty changesets, so an empty update just reads the category back
+            let category = if update.name.is_none() && update.parent_id.is_none() {
+                category_repository::get_category_by_id(conn, category_id)?
+                    .ok_or(DieselError::NotFound)?
+            } else {
+                category_repository::update_category(conn, category_id, &update)?
+            };
+            let path = path_of(conn, category_id)?;
+            Ok(MoveOutcome::Updated(category, path))
+        })
+    })
+    .await
+    .map_err(map_category_error)?;
+
+    match outcome {
+        MoveOutcome::Updated(category, path) => Ok((category, path)),
+        MoveOutcome::NotFound => Err(AppError::NotFound("Category not found".into())),
+        MoveOutcome::ParentNotFound => Err(AppError::NotFound("Parent category not found".into())),
+        MoveOutcome::Cycle => Err(AppError::Validation(
+            "A category cannot be moved below itself".into(),
+        )),
+    }
+}
+
+/// Delete a leaf category. Its product links go with it; the products stay.
+pub async fn delete_category(pool: PgPool, category_id: Uuid) -> Result<(), AppError> {
+    let outcome = with_conn(pool, move |conn| {
+        conn.transaction(|conn| {
+            category_repository::lock_tree(conn)?;
+            if category_repository::has_children(conn, category_id)? {
+                return Ok(DeleteOutcome::HasChildren);
+            }
+            match category_repository::delete_category(conn, category_id)? {
+                0 => Ok(DeleteOutcome::NotFound),
+                _ => Ok(DeleteOutcome::Deleted),
+            }
+        })
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    match outcome {
+        DeleteOutcome::Deleted => Ok(()),
+        DeleteOutcome::NotFound => Err(AppError::NotFound("Category not found".into())),
+        DeleteOutcome::HasChildren => Err(AppError::Conflict(
+            "Move or delete the subcategories first".into(),
+        )),
+    }
+}
+
+pub async fn add_product(
+    pool: PgPool,
+    category_id: Uuid,
+    product_id: Uuid,
+) -> Result<(), AppError> {
+    let outcome = with_conn(pool, move |conn| {
+        conn.transaction(|conn| {
+            if category_repository::get_category_by_id(conn, category_id)?.is_none() {
+                return Ok(LinkOutcome::CategoryNotFound);
+            }
+            if product_repository::get_product_by_id(conn, product_id)?.is_none() {
+                return Ok(LinkOutcome::ProductNotFound);
+            }
+            let link = NewProductCategory {
+                product_id,
+                category_id,
+            };
+            category_repository::add_product(conn, &link).map(|_| LinkOutcome::Linked)
+        })
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    match outcome {
+        LinkOutcome::Linked => Ok(()),
+        LinkOutcome::CategoryNotFound => Err(AppError::NotFound("Category not found".into())),
+        LinkOutcome::ProductNotFound => Err(AppError::NotFound("Product not found".into())),
+    }
+}
+
+pub async fn remove_product(
+    pool: PgPool,
+    category_id: Uuid,
+    product_id: Uuid,
+) -> Result<(), AppError> {
+    let removed = with_conn(pool, move |conn| {
+        category_repository::remove_product(conn, category_id, product_id)
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    if removed == 0 {
+        return Err(AppError::NotFound("Product is not in this category".into()));
+    }
+    Ok(())
+}
+
+/// One page of the category's products, plus how many there are in total.
+pub async fn list_products(
+    pool: PgPool,
+    category_id: Uuid,
+    include_descendants: bool,
+    offset: i64,
+    limit: i64,
+) -> Result<(Vec<Product>, i64), AppError> {
+    let page = with_conn(pool, move |conn| {
+        conn.build_transaction()
+            .read_only()
+            .repeatable_read()
+            .run(|conn| {
+                if category_repository::get_category_by_id(conn, category_id)?.is_none() {
+                    return Ok(None);
+                }
+                let total =
+                    category_repository::count_products_in(conn, category_id, include_descendants)?;
+                let products = category_repository::list_products_in(
+                    conn,
+                    category_id,
+                    include_descendants,
+                    offset,
+                    limit,
+                )?;
+                Ok(Some((products, total)))
+            })
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    page.ok_or_else(|| AppError::NotFound("Category not found".into()))
+}
+
+/// Breadcrumb trails of every category each product belongs to, keyed by product id.
+/// Products without categories are absent from the map.
+pub async fn product_breadcrumbs(
+    pool: PgPool,
+    product_ids: Vec<Uuid>,
+) -> Result<HashMap<Uuid, Vec<CategoryPath>>, AppError> {
+    if product_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let steps = with_conn(pool, move |conn| {
+        category_repository::product_category_paths(conn, &product_ids)
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    let mut breadcrumbs: HashMap<Uuid, Vec<CategoryPath>> = HashMap::new();
+    for ((product_id, _), path) in assemble_paths(steps) {
+        if let Some(product_id) = product_id {
+            breadcrumbs.entry(product_id).or_default().push(path);
+        }
+    }
+    for paths in breadcrumbs.values_mut() {
+        paths.sort_by(|a, b| {
+            let names =
+                |path: &CategoryPath| path.iter().map(|c| c.name.clone()).collect::<Vec<_>>();
+            names(a).cmp(&names(b))
+        });
+    }
+    Ok(breadcrumbs)
+}
+
+fn path_of(conn: &mut PgConnection, category_id: Uuid) -> QueryResult<CategoryPath> {
+    let steps = category_repository::category_paths(conn, &[category_id])?;
+    Ok(assemble_paths(steps)
+        .remove(&(None, category_id))
+        .unwrap_or_default())
+}
+
+/// Group path steps into root-first paths keyed by (product, leaf category).
+fn assemble_paths(mut steps: Vec<CategoryPathStep>) -> HashMap<(Option<Uuid>, Uuid), CategoryPath> {
+    steps.sort_by_key(|step| std::cmp::Reverse(step.depth));
+    let mut paths: HashMap<(Option<Uuid>, Uuid), CategoryPath> = HashMap::new();
+    for step in steps {
+        paths
+            .entry((step.product_id, step.leaf_id))
+            .or_default()
+            .push(CategoryCrumb {
+                id: step.id,
+                name: step.name,
+            });
+    }
+    paths
+}
@@ -0,0 +1,128 @@
+use chrono::{DateTime, Duration, Utc};
+use diesel::Connection;
+
+use crate::auth::{LoginThrottlePolicy, ThrottleScope};
+use crate::db::login_throttle_repository;
+use crate::db::{PgPool, with_conn};
+use crate::errors::AppError;
+use crate::errors::map_diesel_error;
+
+/// A throttled subject: the scope it is counted in plus its identifier (email or IP).
+pub type ThrottleSubject = (ThrottleScope, String);
+
+/// Count an attempt against every subject before it is made, rejecting it with 429 while any
+/// of them is locked out. All subject rows are locked before any is checked, so concurrent
+/// attempts are counted one after another and cannot all pass the check before the first one
+/// is recorded. Give back attempts that succeed with [`refund_attempt`].
+pub async fn reserve_attempt(
+    pool: PgPool,
+    policy: &LoginThrottlePolicy,
+    subjects: Vec<ThrottleSubject>,
+) -> Result<(), AppError> {
+    let policy = policy.clone();
+
+    let retry_after = with_conn(pool, move |conn| {
+        conn.transaction(|conn| {
+            let now = Utc::now();
+            let mut current = Vec::with_capacity(subjects.len());
+            for (scope, subject) in &subjects {
+                current.push(login_throttle_repository::get_or_create_for_update(
+                    conn,
+                    scope.as_str(),
+                    subject,
+                )?);
+            }
+            let longest = current
+                .iter()
+                .filter_map(|throttle| throttle.retry_after_secs(now))
+                .max();
+            if longest.is_some() {
+                return Ok(longest);
+            }
+
+            for ((scope, subject), throttle) in subjects.iter().zip(&current) {
+                let updated = policy.after_failure(*scope, throttle, now);
+                if updated.locked_until.is_some() {
+                    tracing::warn!(
+                        scope = scope.as_str(),
+                        attempts = updated.failed_count,
+                        "locked out after repeated attempts"
+                    );
+                }
+                login_throttle_repository::update_throttle(
+                    conn,
+                    scope.as_str(),
+                    subject,
+                    &updated,
+                )?;
+            }
+            Ok(None)
+        })
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    match retry_after {
+        Some(secs) => Err(AppError::TooManyRequests(
+            "Too many attempts, try again later".into(),
+            secs,
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Give back an attempt reserved with [`reserve_attempt`] that turned out to succeed.
+pub async fn refund_attempt(
+    pool: PgPool,
+    policy: &LoginThrottlePolicy,
+    subjects: Vec<ThrottleSubject>,
+) -> Result<(), AppError> {
+    let policy = policy.clone();
+
+    with_conn(pool, move |conn| {
+        conn.transaction(|conn| {
+            for (scope, subject) in &subjects {
+                let current = login_throttle_repository::get_or_create_for_update(
+                    conn,
+                    scope.as_str(),
+                    subject,
+                )?;
+                login_throttle_repository::update_throttle(
+                    conn,
+                    scope.as_str(),
+                    subject,
+                    &policy.after_refund(*scope, &current),
+                )?;
+            }
+            Ok(())
+        })
+    })
+    .await
+    .map_err(map_diesel_error)
+}
+
+/// Forget earlier failures for a subject after a successful login.
+pub async fn clear(pool: PgPool, subject: ThrottleSubject) -> Result<(), AppError> {
+    with_conn(pool, move |conn| {
+        login_throttle_repository::clear_throttle(conn, subject.0.as_str(), &subject.1)
+    })
+    .await
+    .map_err(map_diesel_error)
+    .map(|_| ())
+}
+
+/// Delete throttle rows that no longer affect anything at `now`, such as those left behind by
+/// made-up emails. Returns how many were deleted.
+pub async fn purge_stale(
+    pool: PgPool,
+    policy: &LoginThrottlePolicy,
+    now: DateTime<Utc>,
+) -> Result<usize, AppError> {
+    let window_start = now - Duration::seconds(policy.failure_window_secs);
+
+    with_conn(pool, move |conn| {
+        login_throttle_repository::delete_stale(conn, now, window_start)
+    })
+    .await
+    .map_err(map_diesel_error)
+}
@@ -1,4 +1,17 @@
+pub mod attribute_service;
 pub mod cart_item_service;
 pub mod cart_service;
+pub mod category_service;
+pub mod login_throttle_service;
+pub mod product_image_service;
+pub mod product_price_service;
+pub mod product_recommendation_service;
+pub mod product_review_service;
 pub mod product_service;
+pub mod product_transfer_service;
+pub mod product_variant_service;
+pub mod stock_allocation_service;
+pub mod stock_movement_service;
+pub mod stock_reservation_service;
 pub mod user_service;
+pub mod warehouse_service;
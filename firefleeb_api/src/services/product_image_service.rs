@@ -0,0 +1,356 @@
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use image::{DynamicImage, ImageFormat, ImageReader, Limits};
+use uuid::Uuid;
+
+use crate::config::ImageConfig;
+use crate::db::{PgPool, product_image_repository, product_repository, with_conn};
+use crate::errors::{AppError, map_diesel_error};
+use crate::models::product_image::{NewProductImage, ProductImage};
+use crate::storage::{BlobError, SharedBlobStore, run_blocking};
+
+/// Widest or tallest image accepted, so decoding cannot be made to allocate without bound.
+const MAX_IMAGE_DIMENSION: u32 = 10_000;
+
+/// Cached for a year: an image's files never change once uploaded.
+pub const IMAGE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+/// Which file of an image to serve.
+#[derive(Debug, Clone, Copy)]
+pub enum ImageRendition {
+    Original,
+    Thumbnail(u32),
+}
+
+/// An image file ready to send, or `None` bytes when the client's copy is current.
+#[derive(Debug)]
+pub struct ServedImage {
+    pub etag: String,
+    pub content_type: String,
+    pub bytes: Option<Vec<u8>>,
+}
+
+/// Thumbnails of one upload, encoded and ready to store.
+struct ProcessedImage {
+    content_type: &'static str,
+    width: u32,
+    height: u32,
+    thumbnails: Vec<(u32, Vec<u8>)>,
+}
+
+fn product_prefix(product_id: Uuid) -> String {
+    format!("products/{product_id}")
+}
+
+fn image_prefix(product_id: Uuid, image_id: Uuid) -> String {
+    format!("products/{product_id}/images/{image_id}")
+}
+
+fn blob_key(product_id: Uuid, image_id: Uuid, rendition: ImageRendition) -> String {
+    let prefix = image_prefix(product_id, image_id);
+    match rendition {
+        ImageRendition::Original => format!("{prefix}/original"),
+        ImageRendition::Thumbnail(size) => format!("{prefix}/{size}"),
+    }
+}
+
+fn map_blob_error(err: BlobError) -> AppError {
+    AppError::Internal(format!("Image storage failed: {err}"))
+}
+
+/// The accepted upload formats and the MIME type each is served with.
+fn accepted_mime_type(format: ImageFormat) -> Option<&'static str> {
+    match format {
+        ImageFormat::Jpeg => Some("image/jpeg"),
+        ImageFormat::Png => Some("image/png"),
+        ImageFormat::WebP => Some("image/webp"),
+        ImageFormat::Gif => Some("image/gif"),
+        _ => None,
+    }
+}
+
+/// Thumbnails of JPEG photos stay JPEG; everything else becomes PNG to keep transparency.
+fn thumbnail_format(content_type: &str) -> ImageFormat {
+    if content_type == "image/jpeg" {
+        ImageFormat::Jpeg
+    } else {
+        ImageFormat::Png
+    }
+}
+
+/// Sniff the format from the bytes themselves and check it against what the client declared.
+fn validate_image(declared: Option<&str>, bytes: &[u8]) -> Result<&'static str, AppError> {
+    let unsupported =
+        || AppError::UnsupportedMediaType("Images must be JPEG, PNG, WebP or GIF".into());
+    let content_type = image::guess_format(bytes)
+        .ok()
+        .and_then(accepted_mime_type)
+        .ok_or_else(unsupported)?;
+    let declared = declared
+        .map(|declared| declared.split(';').next().unwrap_or_default().trim())
+        .filter(|declared| !declared.is_empty() && *declared != "application/octet-stream");
+    if let Some(declared) = declared
+        && !declared.eq_ignore_ascii_case(content_type)
+    {
+        return Err(AppError::UnsupportedMediaType(format!(
+            "File is {content_type}, not {declared}"
+        )));
+    }
+    Ok(content_type)
+}
+
+fn encode(image: &DynamicImage, format: ImageFormat) -> Result<Vec<u8>, image::ImageError> {
+    let mut out = Cursor::new(Vec::new());
+    match format {
+        // The JPEG encoder has no alpha channel
+        ImageFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8()).write_to(&mut out, format)?,
+        _ => image.write_to(&mut out, format)?,
+    }
+    Ok(out.into_inner())
+}
+
+/// Decode the upload and render a thumbnail for every configured size. Images already
+/// within a size are re-encoded as they are rather than scaled up.
+fn process_image(
+    bytes: &[u8],
+    content_type: &'static str,
+    sizes: &[u32],
+) -> Result<ProcessedImage, AppError> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_IMAGE_DIMENSION);
+    limits.max_image_height = Some(MAX_IMAGE_DIMENSION);
+    let mut reader = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|e| AppError::Validation(format!("Image could not be read: {e}")))?;
+    reader.limits(limits);
+    let original = reader
+        .decode()
+        .map_err(|e| AppError::Validation(format!("Image could not be decoded: {e}")))?;
+
+    let format = thumbnail_format(content_type);
+    let thumbnails = sizes
+        .iter()
+        .map(|&size| {
+            let thumbnail = if original.width() <= size && original.height() <= size {
+                encode(&original, format)
+            } else {
+                encode(&original.thumbnail(size, size), format)
+            };
+            thumbnail
+                .map(|bytes| (size, bytes))
+                .map_err(|e| AppError::Internal(format!("Thumbnail could not be encoded: {e}")))
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(ProcessedImage {
+        content_type,
+        width: original.width(),
+        height: original.height(),
+        thumbnails,
+    })
+}
+
+/// Store an uploaded image and its thumbnails. The files are written before the row, and
+/// removed again if the row cannot be inserted.
+pub async fn upload_image(
+    pool: PgPool,
+    blobs: SharedBlobStore,
+    config: &ImageConfig,
+    product_id: Uuid,
+    declared_type: Option<String>,
+    bytes: Vec<u8>,
+) -> Result<ProductImage, AppError> {
+    if bytes.is_empty() {
+        return Err(AppError::Validation("Image file is empty".into()));
+    }
+    if bytes.len() as u64 > config.max_upload_bytes {
+        return Err(AppError::PayloadTooLarge(format!(
+            "Images must be at most {} bytes",
+            config.max_upload_bytes
+        )));
+    }
+    let content_type = validate_image(declared_type.as_deref(), &bytes)?;
+
+    let exists = with_conn(pool.clone(), move |conn| {
+        product_repository::get_product_by_id(conn, product_id)
+    })
+    .await
+    .map_err(map_diesel_error)?
+    .is_some();
+    if !exists {
+        return Err(AppError::NotFound("Product not found".into()));
+    }
+
+    let sizes = config.thumbnail_sizes.clone();
+    let (bytes, processed) = tokio::task::spawn_blocking(move || {
+        let processed = process_image(&bytes, content_type, &sizes);
+        (bytes, processed)
+    })
+    .await
+    .map_err(|e| AppError::Internal(format!("Image processing panicked: {e}")))?;
+    let processed = processed?;
+
+    let image_id = Uuid::new_v4();
+    let new_image = NewProductImage {
+        id: image_id,
+        product_id,
+        content_type: processed.content_type.to_string(),
+        width: processed.width as i32,
+        height: processed.height as i32,
+        byte_size: bytes.len() as i64,
+        thumbnail_sizes: processed
+            .thumbnails
+            .iter()
+            .map(|(size, _)| Some(*size as i32))
+            .collect(),
+    };
+
+    run_blocking(blobs.clone(), move |store| {
+        store.put(
+            &blob_key(product_id, image_id, ImageRendition::Original),
+            &bytes,
+        )?;
+        for (size, thumbnail) in &processed.thumbnails {
+            store.put(
+                &blob_key(product_id, image_id, ImageRendition::Thumbnail(*size)),
+                thumbnail,
+            )?;
+        }
+        Ok(())
+    })
+    .await
+    .map_err(map_blob_error)?;
+
+    let created = with_conn(pool, move |conn| {
+        product_image_repository::create_image(conn, &new_image)
+    })
+    .await;
+    match created {
+        Ok(image) => Ok(image),
+        Err(err) => {
+            delete_image_blobs(blobs, product_id, image_id).await;
+            Err(match err {
+                // The product was deleted while the files were being written
+                DieselError::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _) => {
+                    AppError::NotFound("Product not found".into())
+                }
+                other => map_diesel_error(other),
+            })
+        }
+    }
+}
+
+/// Images of the given products, oldest first, keyed by product id.
+pub async fn images_for_products(
+    pool: PgPool,
+    product_ids: Vec<Uuid>,
+) -> Result<HashMap<Uuid, Vec<ProductImage>>, AppError> {
+    if product_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let images = with_conn(pool, move |conn| {
+        product_image_repository::list_images_for_products(conn, &product_ids)
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    let mut by_product: HashMap<Uuid, Vec<ProductImage>> = HashMap::new();
+    for image in images {
+        by_product.entry(image.product_id).or_default().push(image);
+    }
+    Ok(by_product)
+}
+
+/// Load an image file for serving. When `if_none_match` names the current ETag the bytes
+/// are not read, and the caller should answer `304 Not Modified`.
+pub async fn serve_image(
+    pool: PgPool,
+    blobs: SharedBlobStore,
+    product_id: Uuid,
+    image_id: Uuid,
+    rendition: ImageRendition,
+    if_none_match: Option<String>,
+) -> Result<ServedImage, AppError> {
+    let image = with_conn(pool, move |conn| {
+        product_image_repository::get_image(conn, product_id, image_id)
+    })
+    .await
+    .map_err(map_diesel_error)?
+    .ok_or_else(|| AppError::NotFound("Image not found".into()))?;
+
+    let content_type = match rendition {
+        ImageRendition::Original => image.content_type.clone(),
+        ImageRendition::Thumbnail(size) => {
+            if !image.thumbnail_sizes().any(|s| s == size) {
+                return Err(AppError::NotFound("Thumbnail not found".into()));
+            }
+            let format = thumbnail_format(&image.content_type);
+            format.to_mime_type().to_string()
+        }
+    };
+    let etag = match rendition {
+        ImageRendition::Original => format!("\"{image_id}\""),
+        ImageRendition::Thumbnail(size) => format!("\"{image_id}-{size}\""),
+    };
+    let matches = if_none_match.is_some_and(|header| {
+        header
+            .split(',')
+            .map(str::trim)
+            .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+    });
+    if matches {
+        return Ok(ServedImage {
+            etag,
+            content_type,
+            bytes: None,
+        });
+    }
+
+    let key = blob_key(product_id, image_id, rendition);
+    let bytes = run_blocking(blobs, move |store| store.get(&key))
+        .await
+        .map_err(map_blob_error)?
+        .ok_or_else(|| AppError::Internal("Image file is missing from storage".into()))?;
+    Ok(ServedImage {
+        etag,
+        content_type,
+        bytes: Some(bytes),
+    })
+}
+
+pub async fn delete_image(
+    pool: PgPool,
+    blobs: SharedBlobStore,
+    product_id: Uuid,
+    image_id: Uuid,
+) -> Result<(), AppError> {
+    let deleted = with_conn(pool, move |conn| {
+        product_image_repository::delete_image(conn, product_id, image_id)
+    })
+    .await
+    .map_err(map_diesel_error)?;
+    if deleted == 0 {
+        return Err(AppError::NotFound("Image not found".into()));
+    }
+    delete_image_blobs(blobs, product_id, image_id).await;
+    Ok(())
+}
+
+/// Remove every file stored for a product. The rows are already gone, so a storage failure
+/// only leaves orphaned files behind; it is logged rather than reported.
+pub(crate) async fn delete_product_blobs(blobs: SharedBlobStore, product_id: Uuid) {
+    delete_prefix_logged(blobs, product_prefix(product_id)).await;
+}
+
+async fn delete_image_blobs(blobs: SharedBlobStore, product_id: Uuid, image_id: Uuid) {
+    delete_prefix_logged(blobs, image_prefix(product_id, image_id)).await;
+}
+
+async fn delete_prefix_logged(blobs: SharedBlobStore, prefix: String) {
+    let key = prefix.clone();
+    if let Err(err) = run_blocking(blobs, move |store| store.delete_prefix(&key)).await {
+        tracing::warn!(prefix = %prefix, error = %err, "failed to delete image files");
+    }
+}
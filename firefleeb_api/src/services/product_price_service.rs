@@ -0,0 +1,165 @@
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{DateTime, Utc};
+use diesel::Connection;
+use uuid::Uuid;
+
+use crate::db::{PgPool, product_price_repository, product_repository, with_conn};
+use crate::errors::{AppError, map_diesel_error};
+use crate::models::product_price::{NewProductPrice, PRICE_SOURCE_SCHEDULED, ProductPrice};
+
+enum CancelOutcome {
+    Cancelled,
+    NotFound,
+    NotUpcoming,
+}
+
+/// Where a price stands relative to the moment the history was read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriceStatus {
+    /// Starts later.
+    Upcoming,
+    /// The price the product sells at.
+    Current,
+    /// Still in its window, but a window that started later takes precedence.
+    Overridden,
+    /// Its window has ended.
+    Past,
+}
+
+impl PriceStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PriceStatus::Upcoming => "upcoming",
+            PriceStatus::Current => "current",
+            PriceStatus::Overridden => "overridden",
+            PriceStatus::Past => "past",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PriceHistoryEntry {
+    pub price: ProductPrice,
+    pub status: PriceStatus,
+}
+
+/// Every price the product has had or is scheduled to have, latest start first.
+pub async fn price_history(
+    pool: PgPool,
+    product_id: Uuid,
+) -> Result<Vec<PriceHistoryEntry>, AppError> {
+    let prices = with_conn(pool, move |conn| {
+        if product_repository::get_product_by_id(conn, product_id)?.is_none() {
+            return Ok(None);
+        }
+        product_price_repository::list_prices(conn, product_id).map(Some)
+    })
+    .await
+    .map_err(map_diesel_error)?
+    .ok_or_else(|| AppError::NotFound("Product not found".into()))?;
+
+    let now = Utc::now();
+    let mut found_current = false;
+    let history = prices
+        .into_iter()
+        .map(|price| {
+            // Prices are ordered by start, so the first window covering now is the one in effect
+            let status = if price.valid_from > now {
+                PriceStatus::Upcoming
+            } else if !price.covers(now) {
+                PriceStatus::Past
+            } else if found_current {
+                PriceStatus::Overridden
+            } else {
+                found_current = true;
+                PriceStatus::Current
+            };
+            PriceHistoryEntry { price, status }
+        })
+        .collect();
+    Ok(history)
+}
+
+/// Plan a price for a future window. It takes over from `valid_from`, and the product falls back
+/// to the price underneath once `valid_until` passes.
+pub async fn schedule_price(
+    pool: PgPool,
+    product_id: Uuid,
+    price: BigDecimal,
+    valid_from: DateTime<Utc>,
+    valid_until: Option<DateTime<Utc>>,
+) -> Result<ProductPrice, AppError> {
+    if price < BigDecimal::zero() {
+        return Err(AppError::Validation("Price must not be negative".into()));
+    }
+    if valid_from <= Utc::now() {
+        return Err(AppError::Validation(
+            "valid_from must be in the future".into(),
+        ));
+    }
+    if valid_until.is_some_and(|until| until <= valid_from) {
+        return Err(AppError::Validation(
+            "valid_until must be after valid_from".into(),
+        ));
+    }
+
+    let new_price = NewProductPrice {
+        product_id,
+        price,
+        valid_from,
+        valid_until,
+        source: PRICE_SOURCE_SCHEDULED.to_string(),
+    };
+    let created = with_conn(pool, move |conn| {
+        conn.transaction(|conn| {
+            if product_repository::get_product_by_id(conn, product_id)?.is_none() {
+                return Ok(None);
+            }
+            product_price_repository::create_price(conn, &new_price).map(Some)
+        })
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    created.ok_or_else(|| AppError::NotFound("Product not found".into()))
+}
+
+/// Drop a scheduled price before it starts. Prices that took effect stay in the history.
+pub async fn cancel_scheduled_price(
+    pool: PgPool,
+    product_id: Uuid,
+    price_id: Uuid,
+) -> Result<(), AppError> {
+    let outcome = with_conn(pool, move |conn| {
+        conn.transaction(|conn| {
+            let Some(price) = product_price_repository::get_price(conn, product_id, price_id)?
+            else {
+                return Ok(CancelOutcome::NotFound);
+            };
+            if price.source != PRICE_SOURCE_SCHEDULED || price.valid_from <= Utc::now() {
+                return Ok(CancelOutcome::NotUpcoming);
+            }
+            product_price_repository::delete_price(conn, product_id, price_id)?;
+            Ok(CancelOutcome::Cancelled)
+        })
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    match outcome {
+        CancelOutcome::Cancelled => Ok(()),
+        CancelOutcome::NotFound => Err(AppError::NotFound("Price not found".into())),
+        CancelOutcome::NotUpcoming => Err(AppError::Conflict(
+            "Only scheduled prices that have not started can be cancelled".into(),
+        )),
+    }
+}
+
+/// Bring every product's price in line with its history as of `at`. Returns how many changed.
+pub async fn apply_scheduled_prices(pool: PgPool, at: DateTime<Utc>) -> Result<usize, AppError> {
+    with_conn(pool, move |conn| {
+        product_price_repository::apply_prices_at(conn, at)
+    })
+    .await
+    .map_err(map_diesel_error)
+}
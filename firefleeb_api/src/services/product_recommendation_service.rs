@@ -0,0 +1,76 @@
+use chrono::{DateTime, Utc};
+use diesel::Connection;
+use uuid::Uuid;
+
+use crate::auth::AuthUser;
+use crate::db::{
+    PgPool, cart_item_repository, product_recommendation_repository, product_repository, with_conn,
+};
+use crate::errors::{AppError, map_diesel_error};
+use crate::models::product_recommendation::RecommendedProduct;
+use crate::services::cart_service;
+
+/// Recompute every recommendation from the checked-out carts as of `at`. Returns how many
+/// product pairs were stored.
+pub async fn rebuild(pool: PgPool, at: DateTime<Utc>) -> Result<usize, AppError> {
+    with_conn(pool, move |conn| {
+        conn.transaction(|conn| {
+            product_recommendation_repository::rebuild_recommendations(conn, at)
+        })
+    })
+    .await
+    .map_err(map_diesel_error)
+}
+
+/// Products frequently bought together with the product that are still available, best first.
+pub async fn recommend_for_product(
+    pool: PgPool,
+    product_id: Uuid,
+    limit: i64,
+) -> Result<Vec<RecommendedProduct>, AppError> {
+    let recommended = with_conn(pool, move |conn| {
+        if product_repository::get_product_by_id(conn, product_id)?.is_none() {
+            return Ok(None);
+        }
+        product_recommendation_repository::recommended_products(
+            conn,
+            &[product_id],
+            limit,
+            Utc::now(),
+        )
+        .map(Some)
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    recommended.ok_or_else(|| AppError::NotFound("Product not found".into()))
+}
+
+/// Products frequently bought together with what is in the cart, best first, leaving out the
+/// products already in it.
+pub async fn recommend_for_cart(
+    pool: PgPool,
+    principal: &AuthUser,
+    cart_id: Uuid,
+    limit: i64,
+) -> Result<Vec<RecommendedProduct>, AppError> {
+    cart_service::authorize_cart(pool.clone(), principal, cart_id).await?;
+
+    with_conn(pool, move |conn| {
+        let product_ids: Vec<Uuid> = cart_item_repository::get_items_by_cart_id(conn, cart_id)?
+            .into_iter()
+            .map(|item| item.item_id)
+            .collect();
+        if product_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        product_recommendation_repository::recommended_products(
+            conn,
+            &product_ids,
+            limit,
+            Utc::now(),
+        )
+    })
+    .await
+    .map_err(map_diesel_error)
+}
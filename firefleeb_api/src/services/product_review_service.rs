@@ -0,0 +1,176 @@
+use diesel::Connection;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use uuid::Uuid;
+
+use crate::db::product_review_repository::{self, ReviewFilter};
+use crate::db::{PgPool, product_repository, with_conn};
+use crate::errors::{AppError, map_diesel_error};
+use crate::models::product_review::{
+    NewProductReview, ProductReview, REVIEW_APPROVED, REVIEW_STATUSES,
+};
+
+const MAX_TITLE_CHARS: usize = 120;
+const MAX_BODY_CHARS: usize = 5000;
+
+fn validate_rating(rating: i32) -> Result<i32, AppError> {
+    if (1..=5).contains(&rating) {
+        Ok(rating)
+    } else {
+        Err(AppError::Validation(
+            "Rating must be between 1 and 5".into(),
+        ))
+    }
+}
+
+fn validate_text(field: &str, value: &str, max_chars: usize) -> Result<String, AppError> {
+    let value = value.trim();
+    if value.is_empty() || value.chars().count() > max_chars {
+        return Err(AppError::Validation(format!(
+            "Review {field} must be between 1 and {max_chars} characters"
+        )));
+    }
+    Ok(value.to_string())
+}
+
+fn validate_status(status: &str) -> Result<(), AppError> {
+    if REVIEW_STATUSES.contains(&status) {
+        Ok(())
+    } else {
+        Err(AppError::Validation(format!(
+            "Unknown review status (expected one of {}): {status}",
+            REVIEW_STATUSES.join(", ")
+        )))
+    }
+}
+
+/// One review per user and product, so a unique violation means a second review; the user is
+/// the only reference not checked up front, so a foreign key violation means it is gone.
+fn map_review_error(err: DieselError) -> AppError {
+    match err {
+        DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+            AppError::Conflict("You have already reviewed this product".into())
+        }
+        DieselError::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _) => {
+            AppError::NotFound("User not found".into())
+        }
+        other => map_diesel_error(other),
+    }
+}
+
+/// Review a product as `user_id`. The review is flagged as a verified purchase when one of the
+/// user's checkouts sold them the product, and waits for moderation before it is shown.
+pub async fn create_review(
+    pool: PgPool,
+    product_id: Uuid,
+    user_id: Uuid,
+    rating: i32,
+    title: String,
+    body: String,
+) -> Result<ProductReview, AppError> {
+    let rating = validate_rating(rating)?;
+    let title = validate_text("title", &title, MAX_TITLE_CHARS)?;
+    let body = validate_text("body", &body, MAX_BODY_CHARS)?;
+
+    let created = with_conn(pool, move |conn| {
+        conn.transaction(|conn| {
+            if product_repository::get_product_for_update(conn, product_id)?.is_none() {
+                return Ok(None);
+            }
+            let new_review = NewProductReview {
+                product_id,
+                user_id,
+                rating,
+                title,
+                body,
+                verified_purchase: product_review_repository::has_purchased(
+                    conn, user_id, product_id,
+                )?,
+            };
+            product_review_repository::create_review(conn, &new_review).map(Some)
+        })
+    })
+    .await
+    .map_err(map_review_error)?;
+
+    created.ok_or_else(|| AppError::NotFound("Product not found".into()))
+}
+
+/// A page of the product's approved reviews, newest first, with how many there are.
+pub async fn list_product_reviews(
+    pool: PgPool,
+    product_id: Uuid,
+    verified_only: bool,
+    offset: i64,
+    limit: i64,
+) -> Result<(Vec<ProductReview>, i64), AppError> {
+    let filter = ReviewFilter {
+        product_id: Some(product_id),
+        status: Some(REVIEW_APPROVED.to_string()),
+        verified_only,
+    };
+
+    let page = with_conn(pool, move |conn| {
+        if product_repository::get_product_by_id(conn, product_id)?.is_none() {
+            return Ok(None);
+        }
+        let reviews = product_review_repository::list_reviews(conn, &filter, offset, limit)?;
+        let total = product_review_repository::count_reviews(conn, &filter)?;
+        Ok(Some((reviews, total)))
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    page.ok_or_else(|| AppError::NotFound("Product not found".into()))
+}
+
+/// A page of reviews across all products in the given moderation state, newest first.
+pub async fn list_reviews(
+    pool: PgPool,
+    status: String,
+    offset: i64,
+    limit: i64,
+) -> Result<(Vec<ProductReview>, i64), AppError> {
+    validate_status(&status)?;
+    let filter = ReviewFilter {
+        status: Some(status),
+        ..ReviewFilter::default()
+    };
+
+    with_conn(pool, move |conn| {
+        let reviews = product_review_repository::list_reviews(conn, &filter, offset, limit)?;
+        let total = product_review_repository::count_reviews(conn, &filter)?;
+        Ok((reviews, total))
+    })
+    .await
+    .map_err(map_diesel_error)
+}
+
+/// Move a review to another moderation state and bring its product's rating up to date.
+pub async fn moderate_review(
+    pool: PgPool,
+    review_id: Uuid,
+    status: String,
+) -> Result<ProductReview, AppError> {
+    validate_status(&status)?;
+
+    let moderated = with_conn(pool, move |conn| {
+        conn.transaction(|conn| {
+            let Some(review) = product_review_repository::get_review(conn, review_id)? else {
+                return Ok(None);
+            };
+            // Lock the product first, so concurrent moderations of its reviews sync one at a time
+            product_repository::get_product_for_update(conn, review.product_id)?;
+            let Some(review) =
+                product_review_repository::set_review_status(conn, review.id, &status)?
+            else {
+                return Ok(None);
+            };
+            product_review_repository::sync_product_rating(conn, review.product_id)?;
+            Ok(Some(review))
+        })
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    moderated.ok_or_else(|| AppError::NotFound("Review not found".into()))
+}
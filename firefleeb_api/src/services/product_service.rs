@@ -1,17 +1,117 @@
+use std::collections::BTreeMap;
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{DateTime, SecondsFormat, Utc};
+use diesel::{Connection, PgConnection, QueryResult};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use uuid::Uuid;
 
-use crate::db::product_repository;
-use crate::db::{PgPool, with_conn};
+use crate::db::pagination::SortOrder;
+use crate::db::product_repository::{
+    self, ProductCursor, ProductFilter, ProductSort, ProductSortKey,
+};
+use crate::db::{
+    PgPool, attribute_repository, cart_item_repository, product_price_repository,
+    product_variant_repository, with_conn,
+};
 
 use crate::errors::AppError;
 
 use crate::errors::map_diesel_error;
 
-use crate::models::product::{NewProduct, Product, UpdateProduct};
+use crate::models::attribute::ProductAttribute;
+use crate::models::product::{
+    NewProduct, Product, ProductSearchHit, ProductSuggestion, UpdateProduct,
+};
+use crate::models::product_variant::UpdateProductVariant;
+use crate::services::attribute_service::{self, AttributeFacet};
+use crate::services::{product_image_service, product_variant_service, stock_movement_service};
+use crate::storage::SharedBlobStore;
+
+enum PurgeOutcome {
+    Purged,
+    NotFound,
+    NotArchived,
+}
+
+/// Where a listing page starts: a row offset, or an opaque cursor from a previous page.
+#[derive(Debug, Clone)]
+pub enum ProductPagination {
+    Offset(i64),
+    Cursor(String),
+}
+
+#[derive(Debug)]
+pub struct ProductListing {
+    pub products: Vec<Product>,
+    /// Products matching the filter, across all pages.
+    pub total: i64,
+    /// Continues after the last product of this page; `None` on the last page.
+    pub next_cursor: Option<String>,
+    /// Attribute values across all matching products.
+    pub facets: Vec<AttributeFacet>,
+}
+
+/// Longest search text accepted, and the most words taken from it.
+const MAX_SEARCH_CHARS: usize = 200;
+const MAX_SEARCH_TERMS: usize = 16;
+
+#[derive(Debug)]
+pub struct ProductSearchResults {
+    pub hits: Vec<ProductSearchHit>,
+    pub total: i64,
+}
+
+/// Decoded form of a listing cursor. It records the sort it was issued for, so that it cannot
+/// be replayed against a differently ordered listing.
+#[derive(Debug, Serialize, Deserialize)]
+struct CursorToken {
+    sort: String,
+    order: String,
+    key: String,
+    id: Uuid,
+}
+
+/// Create a product and its default variant, which gets `sku` or a generated one. Opening
+/// stock is logged as a receipt by `actor_id`. `attributes` are checked against their
+/// definitions.
+pub async fn create_product(
+    pool: PgPool,
+    new_product: NewProduct,
+    sku: Option<String>,
+    attributes: BTreeMap<String, Value>,
+    actor_id: Uuid,
+) -> Result<Product, AppError> {
+    product_variant_service::validate_stock(new_product.stock)?;
+    let sku = sku
+        .as_deref()
+        .map(product_variant_service::validate_sku)
+        .transpose()?;
+    let attributes = attribute_service::resolve_values(pool.clone(), attributes).await?;
 
-pub async fn create_product(pool: PgPool, new_product: NewProduct) -> Result<Product, AppError> {
     with_conn(pool, move |conn| {
-        product_repository::create_product(conn, &new_product)
+        conn.transaction(|conn| {
+            let product = product_repository::create_product(conn, &new_product, Some(actor_id))?;
+            set_attributes(conn, product.id, attributes)?;
+            if let Some(sku) = sku
+                && let Some(default_variant) =
+                    product_variant_repository::get_default_variant(conn, product.id)?
+            {
+                let update = UpdateProductVariant {
+                    sku: Some(sku),
+                    ..Default::default()
+                };
+                product_variant_repository::update_variant(
+                    conn,
+                    product.id,
+                    default_variant.id,
+                    &update,
+                )?;
+            }
+            Ok(product)
+        })
     })
     .await
     .map_err(map_diesel_error)
@@ -27,29 +127,332 @@ pub async fn get_product_by_id(pool: PgPool, product_id: Uuid) -> Result<Product
     maybe_product.ok_or_else(|| AppError::NotFound("Product not found".into()))
 }
 
+/// Update a product. `stock` becomes the default variant's stock through an adjustment in the
+/// stock ledger, made by `actor_id`. A new price is recorded in the price history. `attributes`,
+/// when given, replace all of the product's attributes.
 pub async fn update_product(
     pool: PgPool,
     product_id: Uuid,
-    updated: UpdateProduct,
+    mut updated: UpdateProduct,
+    attributes: Option<BTreeMap<String, Value>>,
+    actor_id: Uuid,
 ) -> Result<Product, AppError> {
+    if let Some(stock) = updated.stock {
+        product_variant_service::validate_stock(stock)?;
+    }
+    let attributes = match attributes {
+        Some(attributes) => {
+            Some(attribute_service::resolve_values(pool.clone(), attributes).await?)
+        }
+        None => None,
+    };
+
     with_conn(pool, move |conn| {
-        product_repository::update_product(conn, product_id, &updated)
+        conn.transaction(|conn| {
+            let product = apply_update(conn, product_id, &mut updated, Some(actor_id))?;
+            if let Some(attributes) = attributes {
+                set_attributes(conn, product_id, attributes)?;
+            }
+            Ok(product)
+        })
     })
     .await
     .map_err(map_diesel_error)
 }
 
-pub async fn delete_product(pool: PgPool, product_id: Uuid) -> Result<(), AppError> {
-    with_conn(pool, move |conn| {
-        product_repository::delete_product(conn, product_id)
+fn set_attributes(
+    conn: &mut PgConnection,
+    product_id: Uuid,
+    attributes: Vec<(Uuid, Value)>,
+) -> QueryResult<usize> {
+    let attributes: Vec<ProductAttribute> = attributes
+        .into_iter()
+        .map(|(attribute_id, value)| ProductAttribute {
+            product_id,
+            attribute_id,
+            value,
+        })
+        .collect();
+    attribute_repository::set_product_attributes(conn, product_id, &attributes)
+}
+
+/// Body of [`update_product`], for callers that already hold a transaction.
+pub(crate) fn apply_update(
+    conn: &mut PgConnection,
+    product_id: Uuid,
+    updated: &mut UpdateProduct,
+    actor_id: Option<Uuid>,
+) -> QueryResult<Product> {
+    if let Some(stock) = updated.stock.take()
+        && let Some(default_variant) =
+            product_variant_repository::get_default_variant(conn, product_id)?
+    {
+        stock_movement_service::set_stock(
+            conn,
+            &default_variant,
+            stock,
+            "Stock set through the product",
+            actor_id,
+        )?;
+    }
+    let previous = product_repository::get_product_by_id(conn, product_id)?
+        .ok_or(diesel::result::Error::NotFound)?;
+    // Diesel rejects empty changesets, so a stock-only update just returns the row it read
+    if updated.product_name.is_none()
+        && updated.product_description.is_none()
+        && updated.price.is_none()
+    {
+        return Ok(previous);
+    }
+    let product = product_repository::update_product(conn, product_id, updated)?;
+    if product.price != previous.price {
+        product_price_repository::record_manual_price(conn, product_id, &product.price)?;
+    }
+    Ok(product)
+}
+
+/// Withdraw a product from sale. It disappears from the catalog, but cart lines keep
+/// pointing at it. Archiving an archived product changes nothing.
+pub async fn archive_product(pool: PgPool, product_id: Uuid) -> Result<(), AppError> {
+    let found = with_conn(pool, move |conn| {
+        conn.transaction(|conn| {
+            if product_repository::archive_product(conn, product_id)? > 0 {
+                return Ok(true);
+            }
+            product_repository::get_product_by_id(conn, product_id).map(|p| p.is_some())
+        })
     })
     .await
-    .map_err(map_diesel_error)
-    .and_then(|rows_deleted| {
-        if rows_deleted == 0 {
-            Err(AppError::NotFound("Product not found".into()))
-        } else {
+    .map_err(map_diesel_error)?;
+
+    if !found {
+        return Err(AppError::NotFound("Product not found".into()));
+    }
+    Ok(())
+}
+
+/// Put an archived product back on sale.
+pub async fn restore_product(pool: PgPool, product_id: Uuid) -> Result<Product, AppError> {
+    let restored = with_conn(pool, move |conn| {
+        conn.transaction(|conn| {
+            if product_repository::restore_product(conn, product_id)? == 0 {
+                return Ok(None);
+            }
+            product_repository::get_product_by_id(conn, product_id)
+        })
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    restored.ok_or_else(|| AppError::NotFound("Product not found".into()))
+}
+
+/// Delete an archived product for good, then the image files stored for it. Cart lines
+/// still holding the product are removed first, as its variants cannot go while carts hold them.
+pub async fn purge_product(
+    pool: PgPool,
+    blobs: SharedBlobStore,
+    product_id: Uuid,
+) -> Result<(), AppError> {
+    let outcome = with_conn(pool, move |conn| {
+        conn.transaction(
+            |conn| match product_repository::get_product_by_id(conn, product_id)? {
+                None => Ok(PurgeOutcome::NotFound),
+                Some(product) if !product.is_archived() => Ok(PurgeOutcome::NotArchived),
+                Some(_) => {
+                    cart_item_repository::delete_all_for_product(conn, product_id)?;
+                    product_repository::delete_product(conn, product_id)?;
+                    Ok(PurgeOutcome::Purged)
+                }
+            },
+        )
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    match outcome {
+        PurgeOutcome::Purged => {
+            product_image_service::delete_product_blobs(blobs, product_id).await;
             Ok(())
         }
+        PurgeOutcome::NotFound => Err(AppError::NotFound("Product not found".into())),
+        PurgeOutcome::NotArchived => Err(AppError::Conflict(
+            "Archive the product before purging it".into(),
+        )),
+    }
+}
+
+pub async fn list_products(
+    pool: PgPool,
+    filter: ProductFilter,
+    sort: ProductSort,
+    order: SortOrder,
+    pagination: ProductPagination,
+    limit: i64,
+) -> Result<ProductListing, AppError> {
+    let (offset, after) = match &pagination {
+        ProductPagination::Offset(offset) => (*offset, None),
+        ProductPagination::Cursor(cursor) => (0, Some(decode_cursor(cursor, sort, order)?)),
+    };
+
+    let (mut products, total, facets) = with_conn(pool, move |conn| {
+        // Count and page from one snapshot so `total` agrees with the rows returned
+        conn.build_transaction()
+            .read_only()
+            .repeatable_read()
+            .run(|conn| {
+                let total = product_repository::count_products(conn, &filter)?;
+                let facets = attribute_service::build_facets(
+                    attribute_repository::list_definitions(conn)?,
+                    product_repository::attribute_facets(conn, &filter)?,
+                );
+                // One extra row tells whether another page follows
+                let products = product_repository::list_products(
+                    conn,
+                    &filter,
+                    sort,
+                    order,
+                    after.as_ref(),
+                    offset,
+                    limit + 1,
+                )?;
+                Ok((products, total, facets))
+            })
     })
+    .await
+    .map_err(map_diesel_error)?;
+
+    let next_cursor = if products.len() as i64 > limit {
+        products.truncate(limit as usize);
+        products.last().map(|last| encode_cursor(sort, order, last))
+    } else {
+        None
+    };
+
+    Ok(ProductListing {
+        products,
+        total,
+        next_cursor,
+        facets,
+    })
+}
+
+fn encode_cursor(sort: ProductSort, order: SortOrder, last: &Product) -> String {
+    let key = match ProductSortKey::of(sort, last) {
+        ProductSortKey::Price(price) => price.to_string(),
+        ProductSortKey::Name(name) => name,
+        ProductSortKey::CreatedAt(at) => at.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+    };
+    let token = CursorToken {
+        sort: sort.as_str().into(),
+        order: order.as_str().into(),
+        key,
+        id: last.id,
+    };
+    let json = serde_json::to_vec(&token).expect("cursor token serializes");
+    URL_SAFE_NO_PAD.encode(json)
+}
+
+fn decode_cursor(
+    cursor: &str,
+    sort: ProductSort,
+    order: SortOrder,
+) -> Result<ProductCursor, AppError> {
+    let invalid = || AppError::Validation("Invalid cursor".into());
+    let bytes = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+    let token: CursorToken = serde_json::from_slice(&bytes).map_err(|_| invalid())?;
+
+    if token.sort != sort.as_str() || token.order != order.as_str() {
+        return Err(AppError::Validation(
+            "Cursor was issued for a different sort; repeat the sort and order it came with".into(),
+        ));
+    }
+    let key = match sort {
+        ProductSort::Price => ProductSortKey::Price(token.key.parse().map_err(|_| invalid())?),
+        ProductSort::Name => ProductSortKey::Name(token.key),
+        ProductSort::CreatedAt => ProductSortKey::CreatedAt(
+            DateTime::parse_from_rfc3339(&token.key)
+                .map_err(|_| invalid())?
+                .with_timezone(&Utc),
+        ),
+    };
+    Ok(ProductCursor { key, id: token.id })
+}
+
+/// Full-text search over product names and descriptions. With `prefix`, the last word also
+/// matches longer words, so results can update while the user is typing.
+pub async fn search_products(
+    pool: PgPool,
+    text: &str,
+    prefix: bool,
+    offset: i64,
+    limit: i64,
+) -> Result<ProductSearchResults, AppError> {
+    if text.chars().count() > MAX_SEARCH_CHARS {
+        return Err(AppError::Validation(format!(
+            "q must be at most {MAX_SEARCH_CHARS} characters"
+        )));
+    }
+    let tsquery = build_tsquery(text, prefix)
+        .ok_or_else(|| AppError::Validation("q must contain at least one word".into()))?;
+
+    with_conn(pool, move |conn| {
+        conn.build_transaction()
+            .read_only()
+            .repeatable_read()
+            .run(|conn| {
+                let total = product_repository::count_search_matches(conn, &tsquery)?;
+                let hits = product_repository::search_products(conn, &tsquery, offset, limit)?;
+                Ok(ProductSearchResults { hits, total })
+            })
+    })
+    .await
+    .map_err(map_diesel_error)
+}
+
+/// Free text to `to_tsquery` syntax: every word must match. Only letters and digits survive, so
+/// user input can never inject tsquery operators.
+fn build_tsquery(text: &str, prefix: bool) -> Option<String> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .take(MAX_SEARCH_TERMS)
+        .map(str::to_lowercase)
+        .collect();
+    if words.is_empty() {
+        return None;
+    }
+
+    let mut tsquery = words.join(" & ");
+    if prefix {
+        tsquery.push_str(":*");
+    }
+    Some(tsquery)
+}
+
+/// Product names resembling `input`, tolerant of typos. See
+/// [`product_repository::suggest_product_names`].
+pub async fn suggest_products(
+    pool: PgPool,
+    input: String,
+    threshold: f32,
+    limit: i64,
+) -> Result<Vec<ProductSuggestion>, AppError> {
+    let input = input.trim().to_string();
+    if input.is_empty() {
+        return Err(AppError::Validation("prefix must not be empty".into()));
+    }
+    if input.chars().count() > MAX_SEARCH_CHARS {
+        return Err(AppError::Validation(format!(
+            "prefix must be at most {MAX_SEARCH_CHARS} characters"
+        )));
+    }
+
+    with_conn(pool, move |conn| {
+        conn.build_transaction()
+            .read_only()
+            .run(|conn| product_repository::suggest_product_names(conn, &input, threshold, limit))
+    })
+    .await
+    .map_err(map_diesel_error)
 }
@@ -0,0 +1,516 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use bigdecimal::{BigDecimal, Zero};
+use bytes::Bytes;
+use diesel::Connection;
+use diesel::result::Error as DieselError;
+use futures_util::Stream;
+use futures_util::stream;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::db::pagination::SortOrder;
+use crate::db::product_repository::{
+    self, ProductCursor, ProductFilter, ProductSort, ProductSortKey,
+};
+use crate::db::{PgPool, with_conn};
+use crate::errors::{AppError, map_diesel_error};
+use crate::models::product::{NewProduct, Product, UpdateProduct};
+use crate::services::product_service;
+
+/// Products read per query while exporting.
+const EXPORT_BATCH_SIZE: i64 = 500;
+/// `NUMERIC(10, 2)`: at most eight digits before the decimal point and two after it.
+const MAX_PRICE: i64 = 100_000_000;
+const PRICE_SCALE: i64 = 2;
+
+/// File formats the catalog is imported from and exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CatalogFormat {
+    Csv,
+    /// One JSON object per line.
+    Ndjson,
+}
+
+impl CatalogFormat {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw {
+            "csv" => Some(CatalogFormat::Csv),
+            "ndjson" => Some(CatalogFormat::Ndjson),
+            _ => None,
+        }
+    }
+
+    /// The format of a request body, from its `Content-Type` header.
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let essence = content_type.split(';').next().unwrap_or_default().trim();
+        match essence.to_ascii_lowercase().as_str() {
+            "text/csv" => Some(CatalogFormat::Csv),
+            "application/x-ndjson" | "application/ndjson" | "application/jsonl" => {
+                Some(CatalogFormat::Ndjson)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            CatalogFormat::Csv => "text/csv; charset=utf-8",
+            CatalogFormat::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            CatalogFormat::Csv => "csv",
+            CatalogFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportStatus {
+    Created,
+    Updated,
+    Rejected,
+}
+
+impl ImportStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportStatus::Created => "created",
+            ImportStatus::Updated => "updated",
+            ImportStatus::Rejected => "rejected",
+        }
+    }
+}
+
+/// What became of one row of an import.
+#[derive(Debug)]
+pub struct ImportRowResult {
+    /// Line of the row in the file, counting from 1 (the CSV header is line 1).
+    pub line: u64,
+    pub product_name: Option<String>,
+    pub status: ImportStatus,
+    /// `None` for rejected rows, and for created rows of a dry run.
+    pub product_id: Option<Uuid>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub rows: Vec<ImportRowResult>,
+}
+
+impl ImportReport {
+    pub fn count(&self, status: ImportStatus) -> usize {
+        self.rows.iter().filter(|row| row.status == status).count()
+    }
+}
+
+/// A row that passed validation. `None` fields leave an existing product's value as it is.
+#[derive(Debug)]
+struct ImportRow {
+    product_name: String,
+    product_description: Option<String>,
+    price: BigDecimal,
+    stock: Option<i32>,
+}
+
+/// A row as read from the file, before validation.
+struct ParsedRow {
+    line: u64,
+    product_name: Option<String>,
+    row: Result<ImportRow, String>,
+}
+
+#[derive(Deserialize)]
+struct CsvRecord {
+    product_name: Option<String>,
+    product_description: Option<String>,
+    price: Option<String>,
+    stock: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct JsonRecord {
+    product_name: Option<String>,
+    product_description: Option<String>,
+    price: Option<JsonPrice>,
+    stock: Option<i32>,
+}
+
+/// A price given as a JSON string or number. Numbers are parsed from their decimal text rather
+/// than through `f64`, so `19.99` keeps its two decimal places.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonPrice {
+    Number(serde_json::Number),
+    Text(String),
+}
+
+impl JsonPrice {
+    fn parse(&self) -> Result<BigDecimal, String> {
+        let text = match self {
+            JsonPrice::Number(number) => number.to_string(),
+            JsonPrice::Text(text) => text.clone(),
+        };
+        BigDecimal::from_str(&text).map_err(|_| "price is not a number".to_string())
+    }
+}
+
+/// One product as exported. The columns match what an import reads, so an export can be edited
+/// and imported again; the `id` column is ignored on import.
+#[derive(Serialize)]
+struct ExportRecord<'a> {
+    id: Uuid,
+    product_name: &'a str,
+    product_description: Option<&'a str>,
+    price: &'a BigDecimal,
+    stock: i32,
+}
+
+fn validate_row(
+    product_name: Option<String>,
+    product_description: Option<String>,
+    price: Option<BigDecimal>,
+    stock: Option<i32>,
+) -> Result<ImportRow, String> {
+    let product_name = product_name
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .ok_or("product_name is required")?;
+    let price = price.ok_or("price is required")?;
+    if price < BigDecimal::zero() {
+        return Err("price must not be negative".into());
+    }
+    if price >= BigDecimal::from(MAX_PRICE) || price.fractional_digit_count() > PRICE_SCALE {
+        return Err("price must be below 100000000 with at most two decimal places".into());
+    }
+    if stock.is_some_and(|stock| stock < 0) {
+        return Err("stock must not be negative".into());
+    }
+    Ok(ImportRow {
+        product_name,
+        product_description: product_description.filter(|text| !text.trim().is_empty()),
+        price,
+        stock,
+    })
+}
+
+fn parse_csv(body: &[u8]) -> Result<Vec<ParsedRow>, AppError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(body);
+    let headers = reader
+        .headers()
+        .map_err(|e| AppError::Validation(format!("Cannot read the CSV header: {e}")))?
+        .clone();
+    for required in ["product_name", "price"] {
+        if !headers.iter().any(|header| header == required) {
+            return Err(AppError::Validation(format!(
+                "The CSV header must have a {required} column"
+            )));
+        }
+    }
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| AppError::Validation(format!("Malformed CSV: {e}")))?;
+        let line = record.position().map_or(0, |position| position.line());
+        let parsed = record
+            .deserialize::<CsvRecord>(Some(&headers))
+            .map_err(|e| e.to_string())
+            .and_then(|raw| {
+                let price = raw
+                    .price
+                    .map(|price| BigDecimal::from_str(&price))
+                    .transpose()
+                    .map_err(|_| "price is not a number".to_string())?;
+                let stock = raw
+                    .stock
+                    .map(|stock| stock.parse::<i32>())
+                    .transpose()
+                    .map_err(|_| "stock is not a whole number".to_string())?;
+                Ok((raw.product_name, raw.product_description, price, stock))
+            });
+        rows.push(match parsed {
+            Ok((name, description, price, stock)) => ParsedRow {
+                line,
+                product_name: name.clone(),
+                row: validate_row(name, description, price, stock),
+            },
+            Err(error) => ParsedRow {
+                line,
+                product_name: None,
+                row: Err(error),
+            },
+        });
+    }
+    Ok(rows)
+}
+
+fn parse_ndjson(body: &[u8]) -> Result<Vec<ParsedRow>, AppError> {
+    let body = std::str::from_utf8(body)
+        .map_err(|_| AppError::Validation("The import must be UTF-8 text".into()))?;
+    let rows = body
+        .lines()
+        .enumerate()
+        .filter(|(_, text)| !text.trim().is_empty())
+        .map(|(index, text)| {
+            let line = index as u64 + 1;
+            match serde_json::from_str::<JsonRecord>(text) {
+                Ok(raw) => ParsedRow {
+                    line,
+                    product_name: raw.product_name.clone(),
+                    row: raw
+                        .price
+                        .as_ref()
+                        .map(JsonPrice::parse)
+                        .transpose()
+                        .and_then(|price| {
+                            validate_row(
+                                raw.product_name,
+                                raw.product_description,
+                                price,
+                                raw.stock,
+                            )
+                        }),
+                },
+                Err(e) => ParsedRow {
+                    line,
+                    product_name: None,
+                    row: Err(format!("invalid JSON: {e}")),
+                },
+            }
+        })
+        .collect();
+    Ok(rows)
+}
+
+/// Create or update a product from one row. `Err` carries the reason the row was rejected.
+fn upsert_row(
+    conn: &mut diesel::PgConnection,
+    row: &ImportRow,
+    actor_id: Uuid,
+) -> Result<(ImportStatus, Product), String> {
+    let existing = product_repository::get_product_by_name(conn, &row.product_name)
+        .map_err(|e| e.to_string())?;
+    let saved = match existing {
+        Some(product) if product.is_archived() => {
+            return Err("product is archived; restore it before importing it".into());
+        }
+        Some(product) => {
+            let mut update = UpdateProduct {
+                product_name: None,
+                product_description: row.product_description.clone(),
+                price: Some(row.price.clone()),
+                stock: row.stock,
+            };
+            conn.transaction(|conn| {
+                product_service::apply_update(conn, product.id, &mut update, Some(actor_id))
+            })
+            .map(|product| (ImportStatus::Updated, product))
+        }
+        None => {
+            let new_product = NewProduct {
+                product_name: row.product_name.clone(),
+                product_description: row.product_description.clone(),
+                price: row.price.clone(),
+                stock: row.stock.unwrap_or(0),
+            };
+            conn.transaction(|conn| {
+                product_repository::create_product(conn, &new_product, Some(actor_id))
+            })
+            .map(|product| (ImportStatus::Created, product))
+        }
+    };
+    saved.map_err(|e| {
+        tracing::warn!(product_name = %row.product_name, error = %e, "import row failed");
+        "row could not be saved".to_string()
+    })
+}
+
+/// Upsert products by `product_name` from a CSV or NDJSON file. Invalid rows are reported and
+/// skipped; the others are applied together. A dry run reports the same outcome without saving.
+/// Stock changes are logged in the stock ledger on behalf of `actor_id`.
+pub async fn import_products(
+    pool: PgPool,
+    format: CatalogFormat,
+    body: Bytes,
+    dry_run: bool,
+    actor_id: Uuid,
+) -> Result<ImportReport, AppError> {
+    let parsed = match format {
+        CatalogFormat::Csv => parse_csv(&body)?,
+        CatalogFormat::Ndjson => parse_ndjson(&body)?,
+    };
+
+    let rows = with_conn(pool, move |conn| {
+        let mut results = Vec::with_capacity(parsed.len());
+        let mut seen: HashMap<String, u64> = HashMap::new();
+        let outcome = conn.transaction(|conn| {
+            for parsed_row in parsed {
+                let ParsedRow {
+                    line,
+                    product_name,
+                    row,
+                } = parsed_row;
+                let row = row.and_then(|row| match seen.get(&row.product_name) {
+                    Some(first) => Err(format!("product_name repeats line {first}")),
+                    None => {
+                        seen.insert(row.product_name.clone(), line);
+                        Ok(row)
+                    }
+                });
+                let result = match row.and_then(|row| upsert_row(conn, &row, actor_id)) {
+                    Ok((status, product)) => ImportRowResult {
+                        line,
+                        product_name: Some(product.product_name),
+                        status,
+                        product_id: (!dry_run || status == ImportStatus::Updated)
+                            .then_some(product.id),
+                        error: None,
+                    },
+                    Err(error) => ImportRowResult {
+                        line,
+                        product_name,
+                        status: ImportStatus::Rejected,
+                        product_id: None,
+                        error: Some(error),
+                    },
+                };
+                results.push(result);
+            }
+            if dry_run {
+                Err(DieselError::RollbackTransaction)
+            } else {
+                Ok(())
+            }
+        });
+        match outcome {
+            Ok(()) | Err(DieselError::RollbackTransaction) => Ok(results),
+            Err(e) => Err(e),
+        }
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    Ok(ImportReport { dry_run, rows })
+}
+
+fn encode_batch(
+    format: CatalogFormat,
+    products: &[Product],
+    with_header: bool,
+) -> Result<Bytes, AppError> {
+    let records = products.iter().map(|product| ExportRecord {
+        id: product.id,
+        product_name: &product.product_name,
+        product_description: product.product_description.as_deref(),
+        price: &product.price,
+        stock: product.stock,
+    });
+    let encode_failed =
+        |e: &dyn std::fmt::Display| AppError::Internal(format!("Export failed: {e}"));
+
+    match format {
+        CatalogFormat::Csv => {
+            let mut writer = csv::WriterBuilder::new()
+                .has_headers(false)
+                .from_writer(Vec::new());
+            if with_header {
+                writer
+                    .write_record([
+                        "id",
+                        "product_name",
+                        "product_description",
+                        "price",
+                        "stock",
+                    ])
+                    .map_err(|e| encode_failed(&e))?;
+            }
+            for record in records {
+                writer.serialize(record).map_err(|e| encode_failed(&e))?;
+            }
+            let out = writer.into_inner().map_err(|e| encode_failed(&e))?;
+            Ok(Bytes::from(out))
+        }
+        CatalogFormat::Ndjson => {
+            let mut out = Vec::new();
+            for record in records {
+                serde_json::to_writer(&mut out, &record).map_err(|e| encode_failed(&e))?;
+                out.push(b'\n');
+            }
+            Ok(Bytes::from(out))
+        }
+    }
+}
+
+/// Every product on sale, by name, encoded in batches so the catalog is never held in memory
+/// at once. The first chunk carries the CSV header even when there are no products.
+pub fn export_products(
+    pool: PgPool,
+    format: CatalogFormat,
+) -> impl Stream<Item = Result<Bytes, AppError>> + Send + 'static {
+    struct Position {
+        after: Option<ProductCursor>,
+        first: bool,
+        done: bool,
+    }
+
+    let start = Position {
+        after: None,
+        first: true,
+        done: false,
+    };
+    stream::unfold(start, move |position| {
+        let pool = pool.clone();
+        async move {
+            if position.done {
+                return None;
+            }
+            let after = position.after.clone();
+            let batch = with_conn(pool, move |conn| {
+                product_repository::list_products(
+                    conn,
+                    &ProductFilter::default(),
+                    ProductSort::Name,
+                    SortOrder::Asc,
+                    after.as_ref(),
+                    0,
+                    EXPORT_BATCH_SIZE,
+                )
+            })
+            .await
+            .map_err(map_diesel_error);
+
+            let products = match batch {
+                Ok(products) => products,
+                Err(e) => {
+                    let stop = Position {
+                        done: true,
+                        ..position
+                    };
+                    return Some((Err(e), stop));
+                }
+            };
+            if products.is_empty() && !position.first {
+                return None;
+            }
+            let chunk = encode_batch(format, &products, position.first);
+            let next = Position {
+                after: products.last().map(|last| ProductCursor {
+                    key: ProductSortKey::of(ProductSort::Name, last),
+                    id: last.id,
+                }),
+                first: false,
+                done: (products.len() as i64) < EXPORT_BATCH_SIZE || chunk.is_err(),
+            };
+            Some((chunk, next))
+        }
+    })
+}
@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+
+use diesel::Connection;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::db::{PgPool, with_conn};
+use crate::db::{
+    cart_item_repository, product_repository, product_variant_repository, stock_movement_repository,
+};
+use crate::errors::{AppError, map_diesel_error};
+use crate::models::product_variant::{NewProductVariant, ProductVariant, UpdateProductVariant};
+use crate::services::stock_movement_service;
+
+const MAX_SKU_CHARS: usize = 64;
+const MAX_OPTIONS: usize = 10;
+const MAX_OPTION_CHARS: usize = 50;
+
+enum VariantOutcome {
+    Saved(ProductVariant),
+    ProductNotFound,
+    VariantNotFound,
+}
+
+enum DeleteOutcome {
+    Deleted,
+    NotFound,
+    IsDefault,
+    InCarts,
+}
+
+pub(crate) fn validate_sku(sku: &str) -> Result<String, AppError> {
+    let sku = sku.trim();
+    if sku.is_empty() || sku.chars().count() > MAX_SKU_CHARS {
+        return Err(AppError::Validation(format!(
+            "SKU must be between 1 and {MAX_SKU_CHARS} characters"
+        )));
+    }
+    if sku.chars().any(char::is_whitespace) {
+        return Err(AppError::Validation(
+            "SKU must not contain whitespace".into(),
+        ));
+    }
+    Ok(sku.to_string())
+}
+
+/// Option values must be a flat object of short, non-empty strings, e.g. `{"size": "M"}`.
+fn validate_options(options: &Value) -> Result<(), AppError> {
+    let Some(options) = options.as_object() else {
+        return Err(AppError::Validation(
+            "options must be an object of option names to values".into(),
+        ));
+    };
+    if options.len() > MAX_OPTIONS {
+        return Err(AppError::Validation(format!(
+            "A variant has at most {MAX_OPTIONS} options"
+        )));
+    }
+    for (name, value) in options {
+        let valid =
+            |text: &str| !text.trim().is_empty() && text.chars().count() <= MAX_OPTION_CHARS;
+        if !valid(name) || !value.as_str().is_some_and(valid) {
+            return Err(AppError::Validation(format!(
+                "Option names and values must be non-empty strings of at most {MAX_OPTION_CHARS} characters"
+            )));
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_stock(stock: i32) -> Result<(), AppError> {
+    if stock < 0 {
+        return Err(AppError::Validation("Stock must not be negative".into()));
+    }
+    Ok(())
+}
+
+/// Tells the two ways a variant can clash with an existing one apart.
+fn map_variant_error(err: DieselError) -> AppError {
+    match &err {
+        DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, info) => {
+            match info.constraint_name() {
+                Some("product_variants_sku_key") => {
+                    AppError::Conflict("A variant with this SKU already exists".into())
+                }
+                Some("product_variants_options_key") => AppError::Conflict(
+                    "This product already has a variant with these options".into(),
+                ),
+                _ => map_diesel_error(err),
+            }
+        }
+        _ => map_diesel_error(err),
+    }
+}
+
+/// Add a variant to a product. Its opening stock is logged as a receipt by `actor_id`.
+pub async fn create_variant(
+    pool: PgPool,
+    mut new_variant: NewProductVariant,
+    actor_id: Uuid,
+) -> Result<ProductVariant, AppError> {
+    new_variant.sku = validate_sku(&new_variant.sku)?;
+    validate_options(&new_variant.options)?;
+    validate_stock(new_variant.stock)?;
+    // Only an update can move the default, so the product never has two
+    new_variant.is_default = false;
+
+    let outcome = with_conn(pool, move |conn| {
+        conn.transaction(|conn| {
+            if product_repository::get_product_by_id(conn, new_variant.product_id)?.is_none() {
+                return Ok(VariantOutcome::ProductNotFound);
+            }
+            let variant = product_variant_repository::create_variant(conn, &new_variant)?;
+            stock_movement_repository::log_opening_stock(conn, &variant, Some(actor_id))?;
+            product_variant_repository::sync_product_stock(conn, variant.product_id)?;
+            Ok(VariantOutcome::Saved(variant))
+        })
+    })
+    .await
+    .map_err(map_variant_error)?;
+
+    match outcome {
+        VariantOutcome::Saved(variant) => Ok(variant),
+        VariantOutcome::ProductNotFound | VariantOutcome::VariantNotFound => {
+            Err(AppError::NotFound("Product not found".into()))
+        }
+    }
+}
+
+/// Update a variant, and make it the product's default when `make_default` is set. A new
+/// `stock` is reached through an adjustment in the stock ledger, made by `actor_id`.
+pub async fn update_variant(
+    pool: PgPool,
+    product_id: Uuid,
+    variant_id: Uuid,
+    mut update: UpdateProductVariant,
+    make_default: bool,
+    actor_id: Uuid,
+) -> Result<ProductVariant, AppError> {
+    if let Some(sku) = &update.sku {
+        update.sku = Some(validate_sku(sku)?);
+    }
+    if let Some(options) = &update.options {
+        validate_options(options)?;
+    }
+    let stock = update.stock.take();
+    if let Some(stock) = stock {
+        validate_stock(stock)?;
+    }
+    let has_changes = update.sku.is_some() || update.options.is_some() || update.price.is_some();
+
+    let outcome = with_conn(pool, move |conn| {
+        conn.transaction(|conn| {
+            if product_repository::get_product_by_id(conn, product_id)?.is_none() {
+                return Ok(VariantOutcome::ProductNotFound);
+            }
+            let Some(variant) = product_variant_repository::get_variant_by_id(conn, variant_id)?
+                .filter(|variant| variant.product_id == product_id)
+            else {
+                return Ok(VariantOutcome::VariantNotFound);
+            };
+            if make_default && !variant.is_default {
+                product_variant_repository::set_default_variant(conn, product_id, variant_id)?;
+            }
+            if let Some(stock) = stock {
+                stock_movement_service::set_stock(
+                    conn,
+                    &variant,
+                    stock,
+                    "Stock set through the variant",
+                    Some(actor_id),
+                )?;
+            }
+            // Diesel rejects empty changesets, so re-read when only the default or stock changed
+            let variant = if has_changes {
+                product_variant_repository::update_variant(conn, product_id, variant_id, &update)?
+            } else {
+                product_variant_repository::get_variant_by_id(conn, variant_id)?
+            };
+            product_variant_repository::sync_product_stock(conn, product_id)?;
+            Ok(variant.map_or(VariantOutcome::VariantNotFound, VariantOutcome::Saved))
+        })
+    })
+    .await
+    .map_err(map_variant_error)?;
+
+    match outcome {
+        VariantOutcome::Saved(variant) => Ok(variant),
+        VariantOutcome::ProductNotFound => Err(AppError::NotFound("Product not found".into())),
+        VariantOutcome::VariantNotFound => Err(AppError::NotFound("Variant not found".into())),
+    }
+}
+
+/// Delete a variant other than the default that no cart holds. Its remaining stock is written
+/// off in the stock ledger with adjustments made by `actor_id`.
+pub async fn delete_variant(
+    pool: PgPool,
+    product_id: Uuid,
+    variant_id: Uuid,
+    actor_id: Uuid,
+) -> Result<(), AppError> {
+    let outcome = with_conn(pool, move |conn| {
+        conn.transaction(|conn| {
+            // The row lock keeps a cart line from being added between the check and the delete
+            let Some(variant) =
+                product_variant_repository::get_variant_for_update(conn, variant_id)?
+                    .filter(|variant| variant.product_id == product_id)
+            else {
+                return Ok(DeleteOutcome::NotFound);
+            };
+            if variant.is_default {
+                return Ok(DeleteOutcome::IsDefault);
+            }
+            // Checked-out lines are order history; open ones would lose what they point at
+            if cart_item_repository::variant_in_carts(conn, variant_id)? {
+                return Ok(DeleteOutcome::InCarts);
+            }
+            stock_movement_service::set_stock(
+                conn,
+                &variant,
+                0,
+                &format!("Variant {} deleted", variant.sku),
+                Some(actor_id),
+            )?;
+            product_variant_repository::delete_variant(conn, product_id, variant_id)?;
+            product_variant_repository::sync_product_stock(conn, product_id)?;
+            Ok(DeleteOutcome::Deleted)
+        })
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    match outcome {
+        DeleteOutcome::Deleted => Ok(()),
+        DeleteOutcome::NotFound => Err(AppError::NotFound("Variant not found".into())),
+        DeleteOutcome::IsDefault => Err(AppError::Conflict(
+            "The default variant cannot be deleted; make another variant the default first".into(),
+        )),
+        DeleteOutcome::InCarts => Err(AppError::Conflict(
+            "The variant is in customers' carts and cannot be deleted".into(),
+        )),
+    }
+}
+
+/// Variants of each product, default first. Every product has at least its default variant.
+pub async fn variants_for_products(
+    pool: PgPool,
+    product_ids: Vec<Uuid>,
+) -> Result<HashMap<Uuid, Vec<ProductVariant>>, AppError> {
+    if product_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let variants = with_conn(pool, move |conn| {
+        product_variant_repository::list_variants_for_products(conn, &product_ids)
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    let mut by_product: HashMap<Uuid, Vec<ProductVariant>> = HashMap::new();
+    for variant in variants {
+        by_product
+            .entry(variant.product_id)
+            .or_default()
+            .push(variant);
+    }
+    Ok(by_product)
+}
@@ -0,0 +1,156 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use diesel::{PgConnection, QueryResult};
+use uuid::Uuid;
+
+use crate::db::warehouse_repository;
+use crate::models::cart_item::CartItem;
+use crate::models::warehouse::{GeoPoint, Warehouse, WarehouseStock};
+use crate::types::AllocationStrategy;
+
+/// Part or all of a cart line, shipped from one warehouse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Allocation {
+    pub cart_item_id: Uuid,
+    pub product_id: Uuid,
+    pub variant_id: Uuid,
+    pub warehouse_id: Uuid,
+    pub quantity: i32,
+}
+
+/// Warehouses in the order allocation tries them. With a destination the nearest come first and
+/// those without a location last; otherwise, and between equals, the default comes first and the
+/// rest follow by code.
+pub fn rank_warehouses(mut warehouses: Vec<Warehouse>, destination: Option<GeoPoint>) -> Vec<Uuid> {
+    let distance = |warehouse: &Warehouse| {
+        destination
+            .zip(warehouse.location())
+            .map(|(to, from)| from.distance_km(&to))
+    };
+    warehouses.sort_by(|a, b| {
+        let by_distance = match (distance(a), distance(b)) {
+            (Some(a), Some(b)) => a.total_cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        };
+        by_distance
+            .then(b.is_default.cmp(&a.is_default))
+            .then_with(|| a.code.cmp(&b.code))
+    });
+    warehouses
+        .into_iter()
+        .map(|warehouse| warehouse.id)
+        .collect()
+}
+
+/// Stock left to allocate, per warehouse and variant.
+struct Levels(HashMap<(Uuid, Uuid), i32>);
+
+impl Levels {
+    fn available(&self, warehouse_id: Uuid, variant_id: Uuid) -> i32 {
+        self.0
+            .get(&(warehouse_id, variant_id))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    fn take(&mut self, warehouse_id: Uuid, item: &CartItem, quantity: i32) -> Allocation {
+        if let Some(level) = self.0.get_mut(&(warehouse_id, item.variant_id)) {
+            *level -= quantity;
+        }
+        Allocation {
+            cart_item_id: item.id,
+            product_id: item.item_id,
+            variant_id: item.variant_id,
+            warehouse_id,
+            quantity,
+        }
+    }
+}
+
+/// Split the cart's lines over the ranked warehouses. A line only ships from several warehouses
+/// when none holds all of it. `None` when the warehouses together cannot fill every line.
+pub fn allocate(
+    strategy: AllocationStrategy,
+    items: &[CartItem],
+    ranked: &[Uuid],
+    stock: Vec<WarehouseStock>,
+) -> Option<Vec<Allocation>> {
+    let mut levels = Levels(
+        stock
+            .into_iter()
+            .map(|level| ((level.warehouse_id, level.variant_id), level.stock))
+            .collect(),
+    );
+    let mut allocations = Vec::new();
+    let mut pending: Vec<&CartItem> = items.iter().collect();
+
+    if strategy == AllocationStrategy::SingleShipment {
+        // Greedily pick the warehouse that can ship the most remaining lines in full
+        loop {
+            let mut best: Option<(Uuid, usize)> = None;
+            for &warehouse_id in ranked {
+                let whole_lines = pending
+                    .iter()
+                    .filter(|item| levels.available(warehouse_id, item.variant_id) >= item.quantity)
+                    .count();
+                if whole_lines > best.map_or(0, |(_, lines)| lines) {
+                    best = Some((warehouse_id, whole_lines));
+                }
+            }
+            let Some((warehouse_id, _)) = best else {
+                break;
+            };
+            pending.retain(|item| {
+                if levels.available(warehouse_id, item.variant_id) >= item.quantity {
+                    allocations.push(levels.take(warehouse_id, item, item.quantity));
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+    }
+
+    for item in pending {
+        let whole = ranked
+            .iter()
+            .copied()
+            .find(|&warehouse_id| levels.available(warehouse_id, item.variant_id) >= item.quantity);
+        if let Some(warehouse_id) = whole {
+            allocations.push(levels.take(warehouse_id, item, item.quantity));
+            continue;
+        }
+
+        let mut remaining = item.quantity;
+        for &warehouse_id in ranked {
+            let taken = levels
+                .available(warehouse_id, item.variant_id)
+                .min(remaining);
+            if taken > 0 {
+                allocations.push(levels.take(warehouse_id, item, taken));
+                remaining -= taken;
+            }
+        }
+        if remaining > 0 {
+            return None;
+        }
+    }
+    Some(allocations)
+}
+
+/// Allocate the cart's lines from the stock currently in each warehouse, in the caller's
+/// transaction. The caller holds the variant locks.
+pub(crate) fn allocate_cart(
+    conn: &mut PgConnection,
+    strategy: AllocationStrategy,
+    destination: Option<GeoPoint>,
+    items: &[CartItem],
+) -> QueryResult<Option<Vec<Allocation>>> {
+    let ranked = rank_warehouses(warehouse_repository::list_warehouses(conn)?, destination);
+    let variant_ids: Vec<Uuid> = items.iter().map(|item| item.variant_id).collect();
+    let stock = warehouse_repository::stock_levels_for_variants(conn, &variant_ids)?;
+    Ok(allocate(strategy, items, &ranked, stock))
+}
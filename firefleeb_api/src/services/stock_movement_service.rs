@@ -0,0 +1,230 @@
+use diesel::{Connection, PgConnection, QueryResult};
+use uuid::Uuid;
+
+use crate::db::stock_movement_repository::{self, MovementFilter};
+use crate::db::{
+    PgPool, product_repository, product_variant_repository, warehouse_repository, with_conn,
+};
+use crate::errors::{AppError, map_diesel_error};
+use crate::models::product_variant::ProductVariant;
+use crate::models::stock_movement::{
+    MOVEMENT_ADJUSTMENT, MOVEMENT_RECEIPT, MOVEMENT_RETURN, MOVEMENT_SALE, MOVEMENT_TYPES,
+    NewStockMovement, StockMovement,
+};
+
+const MAX_REASON_CHARS: usize = 500;
+
+/// A movement entered by warehouse staff.
+#[derive(Debug)]
+pub struct MovementInput {
+    /// Defaults to the product's default variant.
+    pub variant_id: Option<Uuid>,
+    /// Defaults to the default warehouse.
+    pub warehouse_id: Option<Uuid>,
+    pub movement_type: String,
+    /// Signed change to the stock.
+    pub quantity: i32,
+    pub reason: String,
+}
+
+enum MovementOutcome {
+    Recorded(StockMovement),
+    ProductNotFound,
+    VariantNotFound,
+    WarehouseNotFound,
+    /// The movement would take the warehouse's stock below zero; carries the stock on hand there.
+    Insufficient(i32),
+}
+
+pub(crate) fn validate_movement_type(movement_type: &str) -> Result<(), AppError> {
+    if MOVEMENT_TYPES.contains(&movement_type) {
+        Ok(())
+    } else {
+        Err(AppError::Validation(format!(
+            "movement_type must be one of {}",
+            MOVEMENT_TYPES.join(", ")
+        )))
+    }
+}
+
+fn validate_input(input: &mut MovementInput) -> Result<(), AppError> {
+    validate_movement_type(&input.movement_type)?;
+    let sign_ok = match input.movement_type.as_str() {
+        MOVEMENT_RECEIPT | MOVEMENT_RETURN => input.quantity > 0,
+        MOVEMENT_SALE => input.quantity < 0,
+        _ => input.quantity != 0,
+    };
+    if !sign_ok {
+        return Err(AppError::Validation(
+            "quantity must be positive for receipts and returns, negative for sales and non-zero for adjustments"
+                .into(),
+        ));
+    }
+
+    input.reason = input.reason.trim().to_string();
+    if input.reason.is_empty() || input.reason.chars().count() > MAX_REASON_CHARS {
+        return Err(AppError::Validation(format!(
+            "reason must be between 1 and {MAX_REASON_CHARS} characters"
+        )));
+    }
+    Ok(())
+}
+
+/// Apply the movement's quantity to the variant's stock in its warehouse and log it, in the
+/// caller's transaction. The caller makes sure the warehouse's stock stays non-negative, normally
+/// after reading the variant with [`product_variant_repository::get_variant_for_update`].
+pub(crate) fn apply(
+    conn: &mut PgConnection,
+    variant_id: Uuid,
+    movement: &NewStockMovement,
+) -> QueryResult<StockMovement> {
+    warehouse_repository::adjust_stock(conn, movement.warehouse_id, variant_id, movement.quantity)?;
+    let stock_after =
+        product_variant_repository::adjust_stock(conn, variant_id, movement.quantity)?;
+    let logged = stock_movement_repository::create_movement(conn, movement, stock_after)?;
+    product_variant_repository::sync_product_stock(conn, movement.product_id)?;
+    Ok(logged)
+}
+
+/// Bring the variant's total stock to `target` with adjustments; nothing is logged when it is
+/// already there. Stock is added to the default warehouse. Stock taken away comes out of the
+/// default warehouse first and then the others by code, with one adjustment per warehouse.
+pub(crate) fn set_stock(
+    conn: &mut PgConnection,
+    variant: &ProductVariant,
+    target: i32,
+    reason: &str,
+    actor_id: Option<Uuid>,
+) -> QueryResult<Vec<StockMovement>> {
+    let Some(locked) = product_variant_repository::get_variant_for_update(conn, variant.id)? else {
+        return Ok(Vec::new());
+    };
+    let adjustment = |warehouse_id: Uuid, quantity: i32| NewStockMovement {
+        product_id: locked.product_id,
+        variant_id: Some(locked.id),
+        warehouse_id,
+        movement_type: MOVEMENT_ADJUSTMENT.into(),
+        quantity,
+        reason: reason.into(),
+        actor_id,
+        cart_id: None,
+    };
+
+    let delta = target - locked.stock;
+    if delta > 0 {
+        let warehouse = warehouse_repository::get_default_warehouse(conn)?;
+        return apply(conn, locked.id, &adjustment(warehouse.id, delta)).map(|m| vec![m]);
+    }
+
+    let mut to_remove = -delta;
+    let mut logged = Vec::new();
+    for warehouse in warehouse_repository::list_warehouses(conn)? {
+        if to_remove == 0 {
+            break;
+        }
+        let level = warehouse_repository::stock_level(conn, warehouse.id, locked.id)?;
+        let taken = level.min(to_remove);
+        if taken > 0 {
+            logged.push(apply(conn, locked.id, &adjustment(warehouse.id, -taken))?);
+            to_remove -= taken;
+        }
+    }
+    Ok(logged)
+}
+
+/// Record a receipt, adjustment, sale or return against one of the product's variants in one
+/// warehouse.
+pub async fn record_movement(
+    pool: PgPool,
+    product_id: Uuid,
+    mut input: MovementInput,
+    actor_id: Uuid,
+) -> Result<StockMovement, AppError> {
+    validate_input(&mut input)?;
+
+    let outcome = with_conn(pool, move |conn| {
+        conn.transaction(|conn| {
+            if product_repository::get_product_by_id(conn, product_id)?.is_none() {
+                return Ok(MovementOutcome::ProductNotFound);
+            }
+            let variant_id = match input.variant_id {
+                Some(variant_id) => Some(variant_id),
+                None => product_variant_repository::get_default_variant(conn, product_id)?
+                    .map(|variant| variant.id),
+            };
+            let variant = match variant_id {
+                Some(variant_id) => {
+                    product_variant_repository::get_variant_for_update(conn, variant_id)?
+                }
+                None => None,
+            };
+            let Some(variant) = variant.filter(|variant| variant.product_id == product_id) else {
+                return Ok(MovementOutcome::VariantNotFound);
+            };
+            let warehouse = match input.warehouse_id {
+                Some(warehouse_id) => {
+                    warehouse_repository::get_warehouse_by_id(conn, warehouse_id)?
+                }
+                None => Some(warehouse_repository::get_default_warehouse(conn)?),
+            };
+            let Some(warehouse) = warehouse else {
+                return Ok(MovementOutcome::WarehouseNotFound);
+            };
+            let on_hand = warehouse_repository::stock_level(conn, warehouse.id, variant.id)?;
+            if i64::from(on_hand) + i64::from(input.quantity) < 0 {
+                return Ok(MovementOutcome::Insufficient(on_hand));
+            }
+
+            let movement = NewStockMovement {
+                product_id,
+                variant_id: Some(variant.id),
+                warehouse_id: warehouse.id,
+                movement_type: input.movement_type,
+                quantity: input.quantity,
+                reason: input.reason,
+                actor_id: Some(actor_id),
+                cart_id: None,
+            };
+            apply(conn, variant.id, &movement).map(MovementOutcome::Recorded)
+        })
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    match outcome {
+        MovementOutcome::Recorded(movement) => Ok(movement),
+        MovementOutcome::ProductNotFound => Err(AppError::NotFound("Product not found".into())),
+        MovementOutcome::VariantNotFound => Err(AppError::NotFound("Variant not found".into())),
+        MovementOutcome::WarehouseNotFound => Err(AppError::NotFound("Warehouse not found".into())),
+        MovementOutcome::Insufficient(on_hand) => Err(AppError::Conflict(format!(
+            "Stock cannot go below zero; {on_hand} on hand in this warehouse"
+        ))),
+    }
+}
+
+/// A page of the product's movements, newest first, with the number matching `filter`.
+pub async fn list_movements(
+    pool: PgPool,
+    product_id: Uuid,
+    filter: MovementFilter,
+    offset: i64,
+    limit: i64,
+) -> Result<(Vec<StockMovement>, i64), AppError> {
+    if let Some(movement_type) = &filter.movement_type {
+        validate_movement_type(movement_type)?;
+    }
+
+    let page = with_conn(pool, move |conn| {
+        if product_repository::get_product_by_id(conn, product_id)?.is_none() {
+            return Ok(None);
+        }
+        let movements =
+            stock_movement_repository::list_movements(conn, product_id, &filter, offset, limit)?;
+        let total = stock_movement_repository::count_movements(conn, product_id, &filter)?;
+        Ok(Some((movements, total)))
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    page.ok_or_else(|| AppError::NotFound("Product not found".into()))
+}
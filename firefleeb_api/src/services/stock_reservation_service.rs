@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use diesel::{PgConnection, QueryResult};
+use uuid::Uuid;
+
+use crate::db::{PgPool, product_variant_repository, stock_reservation_repository, with_conn};
+use crate::errors::{AppError, map_diesel_error};
+use crate::models::stock_reservation::{NewStockReservation, StockReservation};
+
+/// Stock of the variant not held by other carts at `at`. Locks the variant until the caller's
+/// transaction ends, so check and reserve in the same transaction. `except_item` leaves out that
+/// cart line's own reservation.
+pub(crate) fn available_stock(
+    conn: &mut PgConnection,
+    variant_id: Uuid,
+    except_item: Option<Uuid>,
+    at: DateTime<Utc>,
+) -> QueryResult<i64> {
+    let variant = product_variant_repository::get_variant_for_update(conn, variant_id)?
+        .ok_or(diesel::result::Error::NotFound)?;
+    let reserved =
+        stock_reservation_repository::reserved_quantity(conn, variant_id, except_item, at)?;
+    Ok(i64::from(variant.stock) - reserved)
+}
+
+/// Hold `quantity` of the variant for the cart line for `ttl` from `at`, replacing whatever
+/// the line held before.
+pub(crate) fn reserve(
+    conn: &mut PgConnection,
+    cart_item_id: Uuid,
+    variant_id: Uuid,
+    quantity: i32,
+    ttl: Duration,
+    at: DateTime<Utc>,
+) -> QueryResult<StockReservation> {
+    let ttl = chrono::Duration::from_std(ttl).unwrap_or(chrono::Duration::MAX);
+    let expires_at = at
+        .checked_add_signed(ttl)
+        .unwrap_or(DateTime::<Utc>::MAX_UTC);
+    stock_reservation_repository::upsert_reservation(
+        conn,
+        &NewStockReservation {
+            cart_item_id,
+            variant_id,
+            quantity,
+            expires_at,
+        },
+    )
+}
+
+/// The conflict reported when a cart asks for more than is available.
+pub(crate) fn insufficient_stock(available: i64) -> AppError {
+    AppError::Conflict(match available {
+        n if n <= 0 => "This product is out of stock".into(),
+        n => format!("Only {n} left in stock"),
+    })
+}
+
+/// Quantity of each product held by carts at `at`; products without reservations are left out.
+pub async fn reserved_for_products(
+    pool: PgPool,
+    product_ids: Vec<Uuid>,
+    at: DateTime<Utc>,
+) -> Result<HashMap<Uuid, i64>, AppError> {
+    let reserved = with_conn(pool, move |conn| {
+        stock_reservation_repository::reserved_by_product(conn, &product_ids, at)
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    Ok(reserved
+        .into_iter()
+        .map(|(product_id, quantity)| (product_id, quantity.unwrap_or(0)))
+        .collect())
+}
+
+/// Delete reservations that expired by `at`. Returns how many were released.
+pub async fn release_expired(pool: PgPool, at: DateTime<Utc>) -> Result<usize, AppError> {
+    with_conn(pool, move |conn| {
+        stock_reservation_repository::delete_expired(conn, at)
+    })
+    .await
+    .map_err(map_diesel_error)
+}
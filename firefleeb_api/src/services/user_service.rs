@@ -1,55 +1,307 @@
-use bcrypt::{DEFAULT_COST, hash, verify};
+use std::net::IpAddr;
+
+use chrono::{Duration, Utc};
+use diesel::{Connection, OptionalExtension};
 use uuid::Uuid;
 
-use crate::db::user_repository;
+use crate::auth::{
+    AccessToken, AuthConfig, AuthUser, ThrottleScope, ensure_email_verified, ensure_owner_or_admin,
+    generate_opaque_token, hash_token, issue_access_token, run_hasher,
+};
 use crate::db::{PgPool, with_conn};
+use crate::db::{
+    email_verification_repository, password_reset_repository, product_repository,
+    product_review_repository, refresh_token_repository, user_repository,
+};
 use crate::errors::AppError;
 use crate::errors::map_diesel_error;
+use crate::mail::{OutgoingMail, SharedMailer, deliver};
+use crate::models::email_verification_token::NewEmailVerificationToken;
+use crate::models::password_reset_token::NewPasswordResetToken;
+use crate::models::refresh_token::NewRefreshToken;
 use crate::models::user::{NewUser, UpdateUser, User};
+use crate::services::login_throttle_service::{self, ThrottleSubject};
 use crate::types::email::Email;
+use crate::types::role::Role;
+
+/// Credentials handed to a client after a successful login or refresh.
+#[derive(Debug)]
+pub struct Session {
+    pub user: User,
+    pub access: AccessToken,
+    pub refresh_token: String,
+    pub refresh_expires_in: i64,
+}
 
+enum ResetOutcome {
+    Reset,
+    InvalidToken,
+}
+
+enum VerifyOutcome {
+    Verified(User),
+    InvalidToken,
+}
+
+enum RefreshOutcome {
+    Rotated {
+        user_id: Uuid,
+        refresh_token: String,
+    },
+    Reused,
+    Invalid,
+}
+
+/// Create an account and email it a verification token. The account starts out unverified.
 pub async fn register_user(
     pool: PgPool,
+    auth: &AuthConfig,
+    mailer: SharedMailer,
     email: Email,
     password_plain: String,
 ) -> Result<User, AppError> {
-    validate_password(&password_plain)?;
+    auth.password_policy.check(&password_plain, Some(&email))?;
 
-    let password_hash = hash(&password_plain, DEFAULT_COST)
-        .map_err(|_| AppError::Internal("Failed to hash password".into()))?;
+    let password_hash = run_hasher(auth.password_hasher.clone(), move |hasher| {
+        hasher.hash(&password_plain)
+    })
+    .await?;
 
     let new_user = NewUser {
         email,
         password_hash,
     };
 
-    with_conn(pool, move |conn| {
+    let user = with_conn(pool.clone(), move |conn| {
         user_repository::create_user(conn, &new_user)
     })
     .await
-    .map_err(map_diesel_error)
+    .map_err(map_diesel_error)?;
+
+    send_email_verification(pool, auth, mailer, user.id, user.email.clone()).await?;
+    Ok(user)
 }
 
+/// Check a login attempt. Attempts are counted per account and per client IP before the
+/// password is checked and given back when it is right; subjects that keep failing are locked
+/// out with increasing backoff and get 429 until the lockout ends.
 pub async fn authenticate_user(
     pool: PgPool,
+    auth: &AuthConfig,
     email: Email,
     password_plain: String,
+    client_ip: Option<IpAddr>,
 ) -> Result<User, AppError> {
+    let account: ThrottleSubject = (ThrottleScope::Account, email.as_str().to_string());
+    let client: Option<ThrottleSubject> =
+        client_ip.map(|ip| (ThrottleScope::ClientIp, ip.to_string()));
+    let subjects = [Some(account.clone()), client.clone()]
+        .into_iter()
+        .flatten()
+        .collect();
+    login_throttle_service::reserve_attempt(pool.clone(), &auth.login_throttle, subjects).await?;
+    // Unknown emails are verified against a dummy hash so they cost as much as a wrong password.
+    // It is built before the lookup so its one-off cost never hints at an unknown email.
+    let hasher = auth.password_hasher.clone();
+    run_hasher(hasher.clone(), |hasher| {
+        hasher.dummy_hash();
+        Ok(())
+    })
+    .await?;
+
     let maybe_user = with_conn(pool.clone(), move |conn| {
         user_repository::get_user_by_email(conn, &email)
     })
     .await
     .map_err(map_diesel_error)?;
 
-    let user = maybe_user.ok_or_else(|| AppError::Unauthorized("Invalid credentials".into()))?;
+    let stored_hash = maybe_user.as_ref().map(|user| user.password_hash.clone());
+    let attempt = password_plain.clone();
+    let ok = run_hasher(hasher.clone(), move |hasher| {
+        let stored_hash = stored_hash.as_deref().unwrap_or(hasher.dummy_hash());
+        hasher.verify(&attempt, stored_hash)
+    })
+    .await?;
 
-    let ok = verify(&password_plain, &user.password_hash)
-        .map_err(|_| AppError::Internal("Failed to verify password".into()))?;
-    if !ok {
-        return Err(AppError::Unauthorized("Invalid credentials".into()));
+    match maybe_user {
+        Some(user) if ok => {
+            // The account starts over, while the client IP only gets this attempt back
+            login_throttle_service::clear(pool.clone(), account).await?;
+            if let Some(client) = client {
+                let policy = &auth.login_throttle;
+                login_throttle_service::refund_attempt(pool.clone(), policy, vec![client]).await?;
+            }
+            if hasher.needs_rehash(&user.password_hash) {
+                return Ok(upgrade_password_hash(pool, auth, user, password_plain).await);
+            }
+            Ok(user)
+        }
+        _ => Err(AppError::Unauthorized("Invalid credentials".into())),
     }
+}
 
-    Ok(user)
+/// Re-hash a just-verified password with the current scheme and parameters. A failure only
+/// delays the upgrade to a later login, so it is logged rather than returned.
+async fn upgrade_password_hash(
+    pool: PgPool,
+    auth: &AuthConfig,
+    user: User,
+    password_plain: String,
+) -> User {
+    let hashed = run_hasher(auth.password_hasher.clone(), move |hasher| {
+        hasher.hash(&password_plain)
+    })
+    .await;
+    let password_hash = match hashed {
+        Ok(hash) => hash,
+        Err(err) => {
+            tracing::warn!("failed to rehash password on login: {err}");
+            return user;
+        }
+    };
+    let update = UpdateUser {
+        email: None,
+        password_hash: Some(password_hash),
+    };
+    let user_id = user.id;
+
+    match with_conn(pool, move |conn| {
+        user_repository::update_user(conn, user_id, &update)
+    })
+    .await
+    {
+        Ok(updated) => updated,
+        Err(err) => {
+            tracing::warn!("failed to store upgraded password hash: {err}");
+            user
+        }
+    }
+}
+
+/// Start a new refresh-token family for a freshly authenticated user.
+pub async fn start_session(
+    pool: PgPool,
+    auth: &AuthConfig,
+    user: User,
+) -> Result<Session, AppError> {
+    if auth.require_verified_email.blocks_login() {
+        ensure_email_verified(&user)?;
+    }
+
+    let refresh_token = generate_opaque_token();
+    let new_token = NewRefreshToken {
+        user_id: user.id,
+        family_id: Uuid::new_v4(),
+        token_hash: hash_token(&refresh_token),
+        expires_at: Utc::now() + Duration::seconds(auth.refresh_token_ttl_secs),
+    };
+
+    with_conn(pool, move |conn| {
+        refresh_token_repository::create_refresh_token(conn, &new_token)
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    let access = issue_access_token(auth, user.id, user.role)?;
+    Ok(Session {
+        user,
+        access,
+        refresh_token,
+        refresh_expires_in: auth.refresh_token_ttl_secs,
+    })
+}
+
+/// Exchange a refresh token for a new access/refresh pair. Each refresh token is single-use:
+/// presenting one that was already rotated revokes the whole family.
+pub async fn refresh_session(
+    pool: PgPool,
+    auth: &AuthConfig,
+    refresh_token: String,
+) -> Result<Session, AppError> {
+    let token_hash = hash_token(&refresh_token);
+    let ttl = auth.refresh_token_ttl_secs;
+
+    let outcome = with_conn(pool.clone(), move |conn| {
+        conn.transaction(|conn| {
+            let Some(current) =
+                refresh_token_repository::get_by_hash_for_update(conn, &token_hash)?
+            else {
+                return Ok(RefreshOutcome::Invalid);
+            };
+            if current.revoked_at.is_some() {
+                refresh_token_repository::revoke_family(conn, current.family_id)?;
+                return Ok(RefreshOutcome::Reused);
+            }
+            if current.is_expired() {
+                return Ok(RefreshOutcome::Invalid);
+            }
+
+            let next_token = generate_opaque_token();
+            let next = refresh_token_repository::create_refresh_token(
+                conn,
+                &NewRefreshToken {
+                    user_id: current.user_id,
+                    family_id: current.family_id,
+                    token_hash: hash_token(&next_token),
+                    expires_at: Utc::now() + Duration::seconds(ttl),
+                },
+            )?;
+            refresh_token_repository::revoke_token(conn, current.id, Some(next.id))?;
+
+            Ok(RefreshOutcome::Rotated {
+                user_id: current.user_id,
+                refresh_token: next_token,
+            })
+        })
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    match outcome {
+        RefreshOutcome::Rotated {
+            user_id,
+            refresh_token,
+        } => {
+            let user = get_user_by_id(pool, user_id).await?;
+            if auth.require_verified_email.blocks_login() {
+                ensure_email_verified(&user)?;
+            }
+            let access = issue_access_token(auth, user.id, user.role)?;
+            Ok(Session {
+                user,
+                access,
+                refresh_token,
+                refresh_expires_in: ttl,
+            })
+        }
+        RefreshOutcome::Reused => {
+            tracing::warn!("refresh token reuse detected; token family revoked");
+            Err(AppError::Unauthorized(
+                "Refresh token has already been used".into(),
+            ))
+        }
+        RefreshOutcome::Invalid => Err(AppError::Unauthorized(
+            "Invalid or expired refresh token".into(),
+        )),
+    }
+}
+
+/// Revoke the refresh-token family that the given token belongs to. Unknown tokens are ignored.
+pub async fn revoke_session(pool: PgPool, refresh_token: String) -> Result<(), AppError> {
+    let token_hash = hash_token(&refresh_token);
+
+    with_conn(pool, move |conn| {
+        conn.transaction(|conn| {
+            if let Some(token) =
+                refresh_token_repository::get_by_hash_for_update(conn, &token_hash)?
+            {
+                refresh_token_repository::revoke_family(conn, token.family_id)?;
+            }
+            Ok(())
+        })
+    })
+    .await
+    .map_err(map_diesel_error)
 }
 
 pub async fn get_user_by_id(pool: PgPool, user_id: Uuid) -> Result<User, AppError> {
@@ -62,39 +314,75 @@ pub async fn get_user_by_id(pool: PgPool, user_id: Uuid) -> Result<User, AppErro
     maybe_user.ok_or_else(|| AppError::NotFound("User not found".into()))
 }
 
+/// Apply profile changes. A new email address is unverified until its token is redeemed.
 pub async fn update_user(
     pool: PgPool,
+    auth: &AuthConfig,
+    mailer: SharedMailer,
+    principal: &AuthUser,
     user_id: Uuid,
     update: UpdateUser,
 ) -> Result<User, AppError> {
-    let updated_user = with_conn(pool, move |conn| {
-        user_repository::update_user(conn, user_id, &update)
+    ensure_owner_or_admin(principal, user_id)?;
+
+    let updated = with_conn(pool.clone(), move |conn| {
+        conn.transaction(|conn| {
+            let Some(current) = user_repository::get_user_by_id(conn, user_id)? else {
+                return Ok(None);
+            };
+            let email_changed = update
+                .email
+                .as_ref()
+                .is_some_and(|email| *email != current.email);
+
+            let mut user = user_repository::update_user(conn, user_id, &update)?;
+            if email_changed {
+                user = user_repository::set_email_verified_at(conn, user_id, None)?;
+                email_verification_repository::invalidate_outstanding_for_user(conn, user_id)?;
+            }
+            Ok(Some((user, email_changed)))
+        })
     })
     .await
     .map_err(map_diesel_error)?;
 
-    Ok(updated_user)
+    let (user, email_changed) =
+        updated.ok_or_else(|| AppError::NotFound("User not found".into()))?;
+    if email_changed {
+        send_email_verification(pool, auth, mailer, user.id, user.email.clone()).await?;
+    }
+    Ok(user)
 }
 
-// Reset Password
+/// Change the password of a signed-in user. Existing sessions are revoked along with the old
+/// password.
 pub async fn update_user_password(
     pool: PgPool,
+    auth: &AuthConfig,
+    principal: &AuthUser,
     user_id: Uuid,
     old_password_plain: String,
     new_password_plain: String,
 ) -> Result<User, AppError> {
-    validate_password(&new_password_plain)?;
+    ensure_owner_or_admin(principal, user_id)?;
 
     let user = get_user_by_id(pool.clone(), user_id).await?;
+    auth.password_policy
+        .check(&new_password_plain, Some(&user.email))?;
 
-    let ok = verify(&old_password_plain, &user.password_hash)
-        .map_err(|_| AppError::Internal("Failed to verify current password".into()))?;
+    let stored_hash = user.password_hash;
+    let ok = run_hasher(auth.password_hasher.clone(), move |hasher| {
+        hasher.verify(&old_password_plain, &stored_hash)
+    })
+    .await?;
     if !ok {
         return Err(AppError::Unauthorized("Invalid current password".into()));
     }
 
-    let new_password_hash = hash(&new_password_plain, DEFAULT_COST)
-        .map_err(|_| AppError::Internal("Failed to hash new password".into()))?;
+    let new_password_hash = run_hasher(auth.password_hasher.clone(), move |hasher| {
+        hasher.hash(&new_password_plain)
+    })
+    .await?;
 
     let update = UpdateUser {
         email: None,
@@ -102,15 +390,215 @@ pub async fn update_user_password(
     };
 
     with_conn(pool, move |conn| {
-        user_repository::update_user(conn, user_id, &update)
+        conn.transaction(|conn| {
+            let user = user_repository::update_user(conn, user_id, &update)?;
+            refresh_token_repository::revoke_all_for_user(conn, user_id)?;
+            Ok(user)
+        })
     })
     .await
     .map_err(map_diesel_error)
 }
 
+pub async fn set_user_role(pool: PgPool, user_id: Uuid, role: Role) -> Result<User, AppError> {
+    let maybe_user = with_conn(pool, move |conn| {
+        user_repository::set_user_role(conn, user_id, role).optional()
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    maybe_user.ok_or_else(|| AppError::NotFound("User not found".into()))
+}
+
+/// Email a single-use reset token to the account, if there is one. Always succeeds for unknown
+/// addresses so the endpoint cannot be used to discover which emails are registered: the token
+/// is issued and mailed in the background, so both cases answer after the same work. Requests
+/// are throttled per address and client IP whether or not the address is registered.
+pub async fn request_password_reset(
+    pool: PgPool,
+    auth: &AuthConfig,
+    mailer: SharedMailer,
+    email: Email,
+    client_ip: Option<IpAddr>,
+) -> Result<(), AppError> {
+    let subjects = mail_throttle_subjects(&email, client_ip);
+    login_throttle_service::reserve_attempt(pool.clone(), &auth.login_throttle, subjects).await?;
+
+    let maybe_user = with_conn(pool.clone(), move |conn| {
+        user_repository::get_user_by_email(conn, &email)
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    match maybe_user {
+        Some(user) => {
+            let auth = auth.clone();
+            tokio::spawn(async move {
+                if let Err(err) =
+                    send_password_reset(pool, &auth, mailer, user.id, user.email).await
+                {
+                    tracing::error!("failed to issue password reset token: {err}");
+                }
+            });
+        }
+        None => tracing::debug!("password reset requested for unknown email"),
+    }
+    Ok(())
+}
+
+/// Throttle subjects for a mail requested for `email` from `client_ip`.
+fn mail_throttle_subjects(email: &Email, client_ip: Option<IpAddr>) -> Vec<ThrottleSubject> {
+    let mut subjects = vec![(ThrottleScope::MailRecipient, email.as_str().to_string())];
+    if let Some(ip) = client_ip {
+        subjects.push((ThrottleScope::MailClientIp, ip.to_string()));
+    }
+    subjects
+}
+
+/// Redeem a reset token and set a new password. Existing sessions are revoked.
+pub async fn reset_password_with_token(
+    pool: PgPool,
+    auth: &AuthConfig,
+    token: String,
+    new_password_plain: String,
+) -> Result<(), AppError> {
+    let token_hash = hash_token(&token);
+
+    // The policy needs the account's email, so find the owner before locking anything
+    let lookup_hash = token_hash.clone();
+    let owner = with_conn(
+        pool.clone(),
+        move |conn| match password_reset_repository::get_by_hash(conn, &lookup_hash)? {
+            Some(token) if token.is_redeemable() => {
+                user_repository::get_user_by_id(conn, token.user_id)
+            }
+            _ => Ok(None),
+        },
+    )
+    .await
+    .map_err(map_diesel_error)?;
+    let owner = owner.ok_or_else(invalid_reset_token)?;
+    auth.password_policy
+        .check(&new_password_plain, Some(&owner.email))?;
+
+    let new_password_hash = run_hasher(auth.password_hasher.clone(), move |hasher| {
+        hasher.hash(&new_password_plain)
+    })
+    .await?;
+
+    let outcome = with_conn(pool, move |conn| {
+        conn.transaction(|conn| {
+            let reset_token =
+                match password_reset_repository::get_by_hash_for_update(conn, &token_hash)? {
+                    Some(token) if token.is_redeemable() => token,
+                    _ => return Ok(ResetOutcome::InvalidToken),
+                };
+
+            let update = UpdateUser {
+                email: None,
+                password_hash: Some(new_password_hash),
+            };
+            user_repository::update_user(conn, reset_token.user_id, &update)?;
+            password_reset_repository::invalidate_outstanding_for_user(conn, reset_token.user_id)?;
+            refresh_token_repository::revoke_all_for_user(conn, reset_token.user_id)?;
+            Ok(ResetOutcome::Reset)
+        })
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    match outcome {
+        ResetOutcome::Reset => Ok(()),
+        ResetOutcome::InvalidToken => Err(invalid_reset_token()),
+    }
+}
+
+fn invalid_reset_token() -> AppError {
+    AppError::Validation("Invalid or expired password reset token".into())
+}
+
+/// Confirm the address a verification token was sent to. Tokens issued for an address the
+/// account no longer uses are rejected.
+pub async fn verify_email(pool: PgPool, token: String) -> Result<User, AppError> {
+    let token_hash = hash_token(&token);
+
+    let outcome = with_conn(pool, move |conn| {
+        conn.transaction(|conn| {
+            let Some(verification) =
+                email_verification_repository::get_by_hash_for_update(conn, &token_hash)?
+            else {
+                return Ok(VerifyOutcome::InvalidToken);
+            };
+            let Some(user) = user_repository::get_user_by_id(conn, verification.user_id)? else {
+                return Ok(VerifyOutcome::InvalidToken);
+            };
+            if !verification.is_redeemable_for(&user.email) {
+                return Ok(VerifyOutcome::InvalidToken);
+            }
+
+            email_verification_repository::invalidate_outstanding_for_user(conn, user.id)?;
+            let user = user_repository::set_email_verified_at(conn, user.id, Some(Utc::now()))?;
+            Ok(VerifyOutcome::Verified(user))
+        })
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    match outcome {
+        VerifyOutcome::Verified(user) => Ok(user),
+        VerifyOutcome::InvalidToken => Err(AppError::Validation(
+            "Invalid or expired email verification token".into(),
+        )),
+    }
+}
+
+/// Send a fresh verification token to an unverified account. Like password resets, this
+/// succeeds silently for unknown or already verified addresses, mails in the background and
+/// shares their throttle.
+pub async fn resend_email_verification(
+    pool: PgPool,
+    auth: &AuthConfig,
+    mailer: SharedMailer,
+    email: Email,
+    client_ip: Option<IpAddr>,
+) -> Result<(), AppError> {
+    let subjects = mail_throttle_subjects(&email, client_ip);
+    login_throttle_service::reserve_attempt(pool.clone(), &auth.login_throttle, subjects).await?;
+
+    let maybe_user = with_conn(pool.clone(), move |conn| {
+        user_repository::get_user_by_email(conn, &email)
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    if let Some(user) = maybe_user.filter(|user| user.email_verified_at.is_none()) {
+        let auth = auth.clone();
+        tokio::spawn(async move {
+            if let Err(err) =
+                send_email_verification(pool, &auth, mailer, user.id, user.email).await
+            {
+                tracing::error!("failed to issue email verification token: {err}");
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Delete the user. Their reviews go with them, so the ratings of the products they reviewed
+/// are brought up to date.
 pub async fn delete_user(pool: PgPool, user_id: Uuid) -> Result<(), AppError> {
     let rows = with_conn(pool, move |conn| {
-        user_repository::delete_user(conn, user_id)
+        conn.transaction(|conn| {
+            let product_ids = product_review_repository::reviewed_product_ids(conn, user_id)?;
+            for &product_id in &product_ids {
+                product_repository::get_product_for_update(conn, product_id)?;
+            }
+            let rows = user_repository::delete_user(conn, user_id)?;
+            for product_id in product_ids {
+                product_review_repository::sync_product_rating(conn, product_id)?;
+            }
+            Ok(rows)
+        })
     })
     .await
     .map_err(map_diesel_error)?;
@@ -122,11 +610,120 @@ pub async fn delete_user(pool: PgPool, user_id: Uuid) -> Result<(), AppError> {
     Ok(())
 }
 
-fn validate_password(password: &str) -> Result<(), AppError> {
-    if password.len() < 8 {
-        return Err(AppError::Validation(
-            "Password must be at least 8 characters long".into(),
-        ));
+/// Replace any outstanding verification token for the user and mail the new one to `email`.
+async fn send_email_verification(
+    pool: PgPool,
+    auth: &AuthConfig,
+    mailer: SharedMailer,
+    user_id: Uuid,
+    email: Email,
+) -> Result<(), AppError> {
+    let token = generate_opaque_token();
+    let new_token = NewEmailVerificationToken {
+        user_id,
+        email: email.clone(),
+        token_hash: hash_token(&token),
+        expires_at: Utc::now() + Duration::seconds(auth.email_verification_ttl_secs),
+    };
+
+    with_conn(pool, move |conn| {
+        conn.transaction(|conn| {
+            email_verification_repository::invalidate_outstanding_for_user(conn, user_id)?;
+            email_verification_repository::create_verification_token(conn, &new_token)
+        })
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    let mail = email_verification_mail(auth, email, &token);
+    if let Err(err) = deliver(mailer, mail).await {
+        tracing::error!("failed to send email verification: {err}");
     }
     Ok(())
 }
+
+/// Replace any outstanding reset token for the user and mail the new one to `email`.
+async fn send_password_reset(
+    pool: PgPool,
+    auth: &AuthConfig,
+    mailer: SharedMailer,
+    user_id: Uuid,
+    email: Email,
+) -> Result<(), AppError> {
+    let token = generate_opaque_token();
+    let new_token = NewPasswordResetToken {
+        user_id,
+        token_hash: hash_token(&token),
+        expires_at: Utc::now() + Duration::seconds(auth.password_reset_ttl_secs),
+    };
+
+    with_conn(pool, move |conn| {
+        conn.transaction(|conn| {
+            password_reset_repository::invalidate_outstanding_for_user(conn, user_id)?;
+            password_reset_repository::create_reset_token(conn, &new_token)
+        })
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    let mail = password_reset_mail(auth, email, &token);
+    if let Err(err) = deliver(mailer, mail).await {
+        tracing::error!("failed to send password reset email: {err}");
+    }
+    Ok(())
+}
+
+/// A token lifetime as mail text: whole hours, or minutes when it is under an hour.
+fn describe_ttl(ttl_secs: i64) -> String {
+    let plural = |n: i64, unit: &str| match n {
+        1 => format!("1 {unit}"),
+        n => format!("{n} {unit}s"),
+    };
+    if ttl_secs < 3600 {
+        plural((ttl_secs / 60).max(1), "minute")
+    } else {
+        plural(ttl_secs / 3600, "hour")
+    }
+}
+
+fn email_verification_mail(auth: &AuthConfig, to: Email, token: &str) -> OutgoingMail {
+    let ttl = describe_ttl(auth.email_verification_ttl_secs);
+    let instructions = match &auth.email_verification_url {
+        Some(url) => {
+            format!("Open this link to confirm your email address:\n{url}?token={token}\n")
+        }
+        None => String::from("Use the token below to confirm your email address.\n"),
+    };
+
+    OutgoingMail {
+        to,
+        subject: "Confirm your FireFleeb email address".into(),
+        body: format!(
+            "Please confirm that this address belongs to your FireFleeb account.\n\n\
+             {instructions}\n\
+             Verification token: {token}\n\n\
+             The token expires in {ttl}. If you did not create an account or change \
+             your email, you can ignore this email.\n"
+        ),
+    }
+}
+
+fn password_reset_mail(auth: &AuthConfig, to: Email, token: &str) -> OutgoingMail {
+    let ttl = describe_ttl(auth.password_reset_ttl_secs);
+    let instructions = match &auth.password_reset_url {
+        Some(url) => format!("Open this link to choose a new password:\n{url}?token={token}\n"),
+        None => String::from("Use the token below to choose a new password.\n"),
+    };
+
+    OutgoingMail {
+        to,
+        subject: "Reset your FireFleeb password".into(),
+        body: format!(
+            "Someone asked to reset the password for your FireFleeb account.\n\n\
+             {instructions}\n\
+             Reset token: {token}\n\n\
+             The token expires in {ttl} and can only be used once. \
+             If you did not ask for a reset, you can ignore this email.\n"
+        ),
+    }
+}
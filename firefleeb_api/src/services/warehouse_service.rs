@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+use diesel::Connection;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use uuid::Uuid;
+
+use crate::db::{PgPool, warehouse_repository, with_conn};
+use crate::errors::{AppError, map_diesel_error};
+use crate::models::warehouse::{GeoPoint, NewWarehouse, UpdateWarehouse, Warehouse};
+
+const MAX_CODE_CHARS: usize = 32;
+const MAX_NAME_CHARS: usize = 100;
+
+/// Stock of a product held in one warehouse.
+#[derive(Debug, Clone)]
+pub struct WarehouseLevel {
+    pub warehouse: Warehouse,
+    pub stock: i64,
+}
+
+fn validate_code(code: &str) -> Result<String, AppError> {
+    let code = code.trim();
+    if code.is_empty() || code.chars().count() > MAX_CODE_CHARS {
+        return Err(AppError::Validation(format!(
+            "Warehouse code must be between 1 and {MAX_CODE_CHARS} characters"
+        )));
+    }
+    if !code
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(AppError::Validation(
+            "Warehouse code may only contain letters, digits, '-' and '_'".into(),
+        ));
+    }
+    Ok(code.to_string())
+}
+
+fn validate_name(name: &str) -> Result<String, AppError> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_CHARS {
+        return Err(AppError::Validation(format!(
+            "Warehouse name must be between 1 and {MAX_NAME_CHARS} characters"
+        )));
+    }
+    Ok(name.to_string())
+}
+
+/// A location needs both coordinates, each within range; `None` when neither is given.
+pub(crate) fn validate_location(
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+) -> Result<Option<GeoPoint>, AppError> {
+    match (latitude, longitude) {
+        (None, None) => Ok(None),
+        (Some(latitude), Some(longitude)) => {
+            if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+                return Err(AppError::Validation(
+                    "latitude must be between -90 and 90 and longitude between -180 and 180".into(),
+                ));
+            }
+            Ok(Some(GeoPoint {
+                latitude,
+                longitude,
+            }))
+        }
+        _ => Err(AppError::Validation(
+            "latitude and longitude must be given together".into(),
+        )),
+    }
+}
+
+/// Codes are unique, so a unique violation means a clash.
+fn map_warehouse_error(err: DieselError) -> AppError {
+    match err {
+        DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+            AppError::Conflict("A warehouse with this code already exists".into())
+        }
+        other => map_diesel_error(other),
+    }
+}
+
+pub async fn create_warehouse(
+    pool: PgPool,
+    mut new_warehouse: NewWarehouse,
+) -> Result<Warehouse, AppError> {
+    new_warehouse.code = validate_code(&new_warehouse.code)?;
+    new_warehouse.name = validate_name(&new_warehouse.name)?;
+    validate_location(new_warehouse.latitude, new_warehouse.longitude)?;
+
+    with_conn(pool, move |conn| {
+        warehouse_repository::create_warehouse(conn, &new_warehouse)
+    })
+    .await
+    .map_err(map_warehouse_error)
+}
+
+/// Every warehouse, the default first and the rest by code.
+pub async fn list_warehouses(pool: PgPool) -> Result<Vec<Warehouse>, AppError> {
+    with_conn(pool, warehouse_repository::list_warehouses)
+        .await
+        .map_err(map_diesel_error)
+}
+
+/// Update a warehouse, and make it the default when `make_default` is set. The location is
+/// replaced as a whole, so `latitude` and `longitude` are changed together.
+pub async fn update_warehouse(
+    pool: PgPool,
+    warehouse_id: Uuid,
+    mut update: UpdateWarehouse,
+    make_default: bool,
+) -> Result<Warehouse, AppError> {
+    if let Some(code) = &update.code {
+        update.code = Some(validate_code(code)?);
+    }
+    if let Some(name) = &update.name {
+        update.name = Some(validate_name(name)?);
+    }
+    match (update.latitude, update.longitude) {
+        (None, None) => {}
+        (Some(latitude), Some(longitude)) => {
+            validate_location(latitude, longitude)?;
+        }
+        _ => {
+            return Err(AppError::Validation(
+                "latitude and longitude must be given together".into(),
+            ));
+        }
+    }
+    let has_changes = update.code.is_some() || update.name.is_some() || update.latitude.is_some();
+
+    let updated = with_conn(pool, move |conn| {
+        conn.transaction(|conn| {
+            if warehouse_repository::get_warehouse_by_id(conn, warehouse_id)?.is_none() {
+                return Ok(None);
+            }
+            if make_default {
+                warehouse_repository::set_default_warehouse(conn, warehouse_id)?;
+            }
+            // Diesel rejects empty changesets, so re-read when only the default changed
+            if has_changes {
+                warehouse_repository::update_warehouse(conn, warehouse_id, &update)
+            } else {
+                warehouse_repository::get_warehouse_by_id(conn, warehouse_id)
+            }
+        })
+    })
+    .await
+    .map_err(map_warehouse_error)?;
+
+    updated.ok_or_else(|| AppError::NotFound("Warehouse not found".into()))
+}
+
+/// Stock of each product per warehouse, summed over its variants, in the order of
+/// [`list_warehouses`]. Warehouses without stock of a product are left out of its list.
+pub async fn stock_by_warehouse(
+    pool: PgPool,
+    product_ids: Vec<Uuid>,
+) -> Result<HashMap<Uuid, Vec<WarehouseLevel>>, AppError> {
+    let (warehouses, levels) = with_conn(pool, move |conn| {
+        let warehouses = warehouse_repository::list_warehouses(conn)?;
+        let levels = warehouse_repository::stock_levels_for_products(conn, &product_ids)?;
+        Ok((warehouses, levels))
+    })
+    .await
+    .map_err(map_diesel_error)?;
+
+    let mut totals: HashMap<(Uuid, Uuid), i64> = HashMap::new();
+    for (product_id, level) in levels {
+        *totals.entry((product_id, level.warehouse_id)).or_default() += i64::from(level.stock);
+    }
+    let rank: HashMap<Uuid, usize> = warehouses
+        .iter()
+        .enumerate()
+        .map(|(index, warehouse)| (warehouse.id, index))
+        .collect();
+    let mut totals: Vec<((Uuid, Uuid), i64)> = totals.into_iter().collect();
+    totals.sort_by_key(|((_, warehouse_id), _)| rank.get(warehouse_id).copied());
+
+    let mut by_product: HashMap<Uuid, Vec<WarehouseLevel>> = HashMap::new();
+    for ((product_id, warehouse_id), stock) in totals {
+        if let Some(&index) = rank.get(&warehouse_id) {
+            by_product
+                .entry(product_id)
+                .or_default()
+                .push(WarehouseLevel {
+                    warehouse: warehouses[index].clone(),
+                    stock,
+                });
+        }
+    }
+    Ok(by_product)
+}
@@ -0,0 +1,76 @@
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use uuid::Uuid;
+
+use super::{BlobError, BlobStore};
+
+/// Keeps blobs as files below a root directory, one file per key.
+#[derive(Debug, Clone)]
+pub struct LocalBlobStore {
+    root: PathBuf,
+}
+
+impl LocalBlobStore {
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Map a key to a path below the root, refusing anything that could escape it.
+    fn path_for(&self, key: &str) -> Result<PathBuf, BlobError> {
+        let mut path = self.root.clone();
+        for segment in key.split('/') {
+            let valid = !segment.is_empty()
+                && segment != "."
+                && segment != ".."
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+            if !valid {
+                return Err(BlobError::InvalidKey(key.to_string()));
+            }
+            path.push(segment);
+        }
+        Ok(path)
+    }
+}
+
+impl BlobStore for LocalBlobStore {
+    fn put(&self, key: &str, bytes: &[u8]) -> Result<(), BlobError> {
+        let path = self.path_for(key)?;
+        let dir = path
+            .parent()
+            .ok_or_else(|| BlobError::InvalidKey(key.to_string()))?;
+        fs::create_dir_all(dir)?;
+
+        // Write to a sibling temp file and rename it, so readers never see a partial blob
+        let temp = dir.join(format!(".{}.tmp", Uuid::new_v4().simple()));
+        let written = fs::File::create(&temp).and_then(|mut file| {
+            file.write_all(bytes)?;
+            file.sync_all()
+        });
+        if let Err(err) = written.and_then(|_| fs::rename(&temp, &path)) {
+            let _ = fs::remove_file(&temp);
+            return Err(err.into());
+        }
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, BlobError> {
+        match fs::read(self.path_for(key)?) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn delete_prefix(&self, prefix: &str) -> Result<(), BlobError> {
+        match fs::remove_dir_all(self.path_for(prefix)?) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
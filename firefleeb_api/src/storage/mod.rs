@@ -0,0 +1,48 @@
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use crate::config::StorageConfig;
+
+pub mod local;
+
+pub use local::LocalBlobStore;
+
+#[derive(Debug, Error)]
+pub enum BlobError {
+    #[error("invalid blob key: {0}")]
+    InvalidKey(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Storage for uploaded files, addressed by `/`-separated keys such as
+/// `products/<id>/images/<id>/original`. Implementations may block.
+pub trait BlobStore: Send + Sync {
+    /// Store `bytes` under `key`, replacing whatever was there.
+    fn put(&self, key: &str, bytes: &[u8]) -> Result<(), BlobError>;
+
+    /// The blob under `key`, or `None` if there is none.
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, BlobError>;
+
+    /// Remove every blob whose key starts with `prefix/`. Missing blobs are not an error.
+    fn delete_prefix(&self, prefix: &str) -> Result<(), BlobError>;
+}
+
+pub type SharedBlobStore = Arc<dyn BlobStore>;
+
+/// Run a blob operation on a blocking thread so slow disks never stall the async runtime.
+pub async fn run_blocking<T, F>(blobs: SharedBlobStore, op: F) -> Result<T, BlobError>
+where
+    F: FnOnce(&dyn BlobStore) -> Result<T, BlobError> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(move || op(blobs.as_ref()))
+        .await
+        .map_err(|e| BlobError::Io(std::io::Error::other(format!("blob task panicked: {e}"))))?
+}
+
+/// Build the blob store described by the storage config.
+pub fn blob_store_from_config(config: &StorageConfig) -> SharedBlobStore {
+    Arc::new(LocalBlobStore::new(&config.local_root))
+}
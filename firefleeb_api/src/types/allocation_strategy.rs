@@ -0,0 +1,23 @@
+/// How checkout picks the warehouses that ship a cart's lines.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AllocationStrategy {
+    /// Each line ships from the nearest warehouse that holds all of it.
+    #[default]
+    NearestFirst,
+    /// Lines go to the warehouse that can ship the most remaining lines in full, the nearest
+    /// breaking ties, until no warehouse can ship a whole line. This is a greedy choice and does
+    /// not always find the fewest warehouses.
+    SingleShipment,
+}
+
+impl AllocationStrategy {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "nearest_first" => Ok(Self::NearestFirst),
+            "single_shipment" => Ok(Self::SingleShipment),
+            other => Err(format!(
+                "Unknown allocation strategy (expected nearest_first or single_shipment): {other}"
+            )),
+        }
+    }
+}
@@ -1,3 +1,7 @@
+pub mod allocation_strategy;
 pub mod email;
+pub mod role;
 
+pub use allocation_strategy::AllocationStrategy;
 pub use email::Email;
+pub use role::Role;
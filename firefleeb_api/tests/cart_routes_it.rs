@@ -1,21 +1,50 @@
-mod common;
+mod common {
+    pub mod auth;
+    pub mod config;
+    pub mod db;
+    pub mod users;
+}
 
 use std::str::FromStr;
+use std::sync::Arc;
 
 use bigdecimal::BigDecimal;
-use common::setup_postgres;
-use firefleeb_api::db::{PgPool, get_conn, product_repository, user_repository};
+use common::auth::{bearer_token, role_token, test_auth_config};
+use common::config::app_config;
+use common::db::setup_postgres;
+use common::users::insert_user;
+use firefleeb_api::auth::{AuthConfig, EmailVerificationRule};
+use firefleeb_api::config::AppConfig;
+use firefleeb_api::db::stock_movement_repository::{self, MovementFilter};
+use firefleeb_api::db::{
+    PgPool, get_conn, product_repository, product_variant_repository, user_repository,
+    warehouse_repository,
+};
+use firefleeb_api::errors::AppError;
 use firefleeb_api::handlers::dtos::CartResponse;
-use firefleeb_api::models::{CartItemResponse, NewProduct, NewUser, Product, User};
+use firefleeb_api::models::{
+    CartItemResponse, NewProduct, NewProductVariant, NewWarehouse, Product, Warehouse,
+};
 use firefleeb_api::routes::{cart_routes::cart_routes, handle_rejection};
-use firefleeb_api::types::email::Email;
+use firefleeb_api::services::stock_movement_service::{self, MovementInput};
+use firefleeb_api::services::{product_variant_service, stock_reservation_service};
+use firefleeb_api::types::AllocationStrategy;
+use firefleeb_api::types::role::Role;
 use serde_json::json;
+use uuid::Uuid;
 use warp::Filter;
 
 fn cart_filter(
     pool: PgPool,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = std::convert::Infallible> + Clone {
-    cart_routes(pool).recover(handle_rejection)
+    cart_filter_with(pool, test_auth_config())
+}
+
+fn cart_filter_with(
+    pool: PgPool,
+    auth: AuthConfig,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = std::convert::Infallible> + Clone {
+    cart_routes(pool, app_config(auth)).recover(handle_rejection)
 }
 
 #[tokio::test]
@@ -29,6 +58,7 @@ async fn create_cart_defaults_active_zero_total() {
     let resp = warp::test::request()
         .method("POST")
         .path("/carts")
+        .header("authorization", bearer_token(user.id, Role::Customer))
         .json(&json!({ "user_id": user.id }))
         .reply(&filter)
         .await;
@@ -53,6 +83,7 @@ async fn cart_item_crud_updates_cart_total() {
     let cart_resp = warp::test::request()
         .method("POST")
         .path("/carts")
+        .header("authorization", bearer_token(user.id, Role::Customer))
         .json(&json!({ "user_id": user.id }))
         .reply(&filter)
         .await;
@@ -67,6 +98,7 @@ async fn cart_item_crud_updates_cart_total() {
     let add_resp = warp::test::request()
         .method("POST")
         .path(&format!("/carts/{}/items", cart.cart_id))
+        .header("authorization", bearer_token(user.id, Role::Customer))
         .json(&add_payload)
         .reply(&filter)
         .await;
@@ -83,6 +115,7 @@ async fn cart_item_crud_updates_cart_total() {
     let fetched_resp = warp::test::request()
         .method("GET")
         .path(&format!("/carts/{}", user.id))
+        .header("authorization", bearer_token(user.id, Role::Customer))
         .reply(&filter)
         .await;
     assert_eq!(fetched_resp.status(), 200);
@@ -96,6 +129,7 @@ async fn cart_item_crud_updates_cart_total() {
     let update_resp = warp::test::request()
         .method("PUT")
         .path(&format!("/carts/{}/items/{}", cart.cart_id, product.id))
+        .header("authorization", bearer_token(user.id, Role::Customer))
         .json(&update_payload)
         .reply(&filter)
         .await;
@@ -110,6 +144,7 @@ async fn cart_item_crud_updates_cart_total() {
     let fetched_after_update_resp = warp::test::request()
         .method("GET")
         .path(&format!("/carts/{}", user.id))
+        .header("authorization", bearer_token(user.id, Role::Customer))
         .reply(&filter)
         .await;
     assert_eq!(fetched_after_update_resp.status(), 200);
@@ -123,6 +158,7 @@ async fn cart_item_crud_updates_cart_total() {
     let delete_resp = warp::test::request()
         .method("DELETE")
         .path(&format!("/carts/{}/items/{}", cart.cart_id, product.id))
+        .header("authorization", bearer_token(user.id, Role::Customer))
         .reply(&filter)
         .await;
     assert_eq!(delete_resp.status(), 204);
@@ -130,6 +166,7 @@ async fn cart_item_crud_updates_cart_total() {
     let cleared_resp = warp::test::request()
         .method("GET")
         .path(&format!("/carts/{}", user.id))
+        .header("authorization", bearer_token(user.id, Role::Customer))
         .reply(&filter)
         .await;
     assert_eq!(cleared_resp.status(), 200);
@@ -137,27 +174,983 @@ async fn cart_item_crud_updates_cart_total() {
     assert_eq!(cleared.cart_total, BigDecimal::from(0));
 }
 
-fn insert_user(pool: &PgPool, email: &str) -> User {
-    let mut conn = get_conn(pool).expect("conn");
-    let new_user = NewUser {
-        email: Email::parse(email).expect("email"),
-        password_hash: "test-hash".into(),
-    };
-    user_repository::create_user(&mut conn, &new_user).expect("create user")
+fn insert_product(pool: &PgPool, name: &str, price: &str) -> Product {
+    insert_product_with_stock(pool, name, price, 100)
 }
 
-fn insert_product(pool: &PgPool, name: &str, price: &str) -> Product {
+fn insert_product_with_stock(pool: &PgPool, name: &str, price: &str, stock: i32) -> Product {
     let mut conn = get_conn(pool).expect("conn");
     let new_product = NewProduct {
         product_name: name.into(),
         product_description: Some("cart test product".into()),
         price: BigDecimal::from_str(price).expect("price"),
-        stock: 100,
+        stock,
     };
     let created =
-        product_repository::create_product(&mut conn, &new_product).expect("create product");
+        product_repository::create_product(&mut conn, &new_product, None).expect("create product");
     product_repository::get_product_by_id(&mut conn, created.id)
         .expect("db lookup")
         .expect("product should exist");
     created
 }
+
+#[tokio::test]
+async fn cart_routes_require_authentication() {
+    let test_db = setup_postgres();
+    let pool = test_db.pool.clone();
+    let filter = cart_filter(pool.clone());
+
+    let user = insert_user(&pool, "cart-anonymous@example.com");
+
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/carts")
+        .json(&json!({ "user_id": user.id }))
+        .reply(&filter)
+        .await;
+    assert_eq!(resp.status(), 401);
+
+    let resp = warp::test::request()
+        .method("GET")
+        .path(&format!("/carts/{}", user.id))
+        .header("authorization", "Bearer not-a-jwt")
+        .reply(&filter)
+        .await;
+    assert_eq!(resp.status(), 401);
+}
+
+#[tokio::test]
+async fn other_customers_are_forbidden_on_every_cart_route() {
+    let test_db = setup_postgres();
+    let pool = test_db.pool.clone();
+    let filter = cart_filter(pool.clone());
+
+    let owner = insert_user(&pool, "cart-owner@example.com");
+    let intruder = insert_user(&pool, "cart-intruder@example.com");
+    let product = insert_product(&pool, "Owned Beans", "2.00");
+    let owner_auth = bearer_token(owner.id, Role::Customer);
+    let intruder_auth = bearer_token(intruder.id, Role::Customer);
+
+    // Creating a cart on behalf of somebody else is rejected
+    let foreign_create = warp::test::request()
+        .method("POST")
+        .path("/carts")
+        .header("authorization", &intruder_auth)
+        .json(&json!({ "user_id": owner.id }))
+        .reply(&filter)
+        .await;
+    assert_eq!(foreign_create.status(), 403);
+
+    let cart_resp = warp::test::request()
+        .method("POST")
+        .path("/carts")
+        .header("authorization", &owner_auth)
+        .json(&json!({ "user_id": owner.id }))
+        .reply(&filter)
+        .await;
+    assert_eq!(cart_resp.status(), 200);
+    let cart: CartResponse = serde_json::from_slice(cart_resp.body()).expect("cart");
+
+    let add_resp = warp::test::request()
+        .method("POST")
+        .path(&format!("/carts/{}/items", cart.cart_id))
+        .header("authorization", &owner_auth)
+        .json(&json!({ "item_id": product.id, "quantity": 1, "unit_price": "2.00" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(add_resp.status(), 201);
+
+    let cart_path = format!("/carts/{}", cart.cart_id);
+    let items_path = format!("/carts/{}/items", cart.cart_id);
+    let item_path = format!("/carts/{}/items/{}", cart.cart_id, product.id);
+    let attempts = [
+        ("GET", format!("/carts/{}", owner.id), None),
+        (
+            "PUT",
+            cart_path.clone(),
+            Some(json!({ "cart_status": "abandoned" })),
+        ),
+        ("GET", items_path.clone(), None),
+        (
+            "POST",
+            items_path.clone(),
+            Some(json!({ "item_id": product.id, "quantity": 5, "unit_price": "0.01" })),
+        ),
+        ("PUT", item_path.clone(), Some(json!({ "quantity": 9 }))),
+        ("DELETE", item_path, None),
+        ("POST", format!("/carts/{}/checkout", cart.cart_id), None),
+        ("DELETE", items_path, None),
+        ("DELETE", cart_path, None),
+    ];
+
+    for (method, path, body) in attempts {
+        let mut request = warp::test::request()
+            .method(method)
+            .path(&path)
+            .header("authorization", &intruder_auth);
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        let resp = request.reply(&filter).await;
+        assert_eq!(resp.status(), 403, "{method} {path}");
+    }
+
+    // Nothing changed for the owner
+    let items_resp = warp::test::request()
+        .method("GET")
+        .path(&format!("/carts/{}/items", cart.cart_id))
+        .header("authorization", &owner_auth)
+        .reply(&filter)
+        .await;
+    assert_eq!(items_resp.status(), 200);
+    let items: Vec<CartItemResponse> = serde_json::from_slice(items_resp.body()).expect("items");
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].quantity, 1);
+}
+
+#[tokio::test]
+async fn admins_can_manage_any_cart() {
+    let test_db = setup_postgres();
+    let pool = test_db.pool.clone();
+    let filter = cart_filter(pool.clone());
+
+    let owner = insert_user(&pool, "cart-managed@example.com");
+    let product = insert_product(&pool, "Managed Beans", "4.00");
+    let admin_auth = role_token(Role::Admin);
+
+    let cart_resp = warp::test::request()
+        .method("POST")
+        .path("/carts")
+        .header("authorization", &admin_auth)
+        .json(&json!({ "user_id": owner.id }))
+        .reply(&filter)
+        .await;
+    assert_eq!(cart_resp.status(), 200);
+    let cart: CartResponse = serde_json::from_slice(cart_resp.body()).expect("cart");
+    assert_eq!(cart.user_id, owner.id);
+
+    let add_resp = warp::test::request()
+        .method("POST")
+        .path(&format!("/carts/{}/items", cart.cart_id))
+        .header("authorization", &admin_auth)
+        .json(&json!({ "item_id": product.id, "quantity": 2, "unit_price": "4.00" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(add_resp.status(), 201);
+
+    let clear_resp = warp::test::request()
+        .method("DELETE")
+        .path(&format!("/carts/{}/items", cart.cart_id))
+        .header("authorization", &admin_auth)
+        .reply(&filter)
+        .await;
+    assert_eq!(clear_resp.status(), 204);
+
+    let delete_resp = warp::test::request()
+        .method("DELETE")
+        .path(&format!("/carts/{}", cart.cart_id))
+        .header("authorization", &admin_auth)
+        .reply(&filter)
+        .await;
+    assert_eq!(delete_resp.status(), 204);
+
+    let missing_resp = warp::test::request()
+        .method("DELETE")
+        .path(&format!("/carts/{}", cart.cart_id))
+        .header("authorization", &admin_auth)
+        .reply(&filter)
+        .await;
+    assert_eq!(missing_resp.status(), 404);
+}
+
+#[tokio::test]
+async fn checkout_closes_the_cart_once_the_owner_is_verified() {
+    let test_db = setup_postgres();
+    let pool = test_db.pool.clone();
+    let mut auth = test_auth_config();
+    auth.require_verified_email = EmailVerificationRule::Checkout;
+    let filter = cart_filter_with(pool.clone(), auth);
+
+    let user = insert_user(&pool, "checkout@example.com");
+    let product = insert_product(&pool, "Checkout Beans", "3.00");
+    let user_auth = bearer_token(user.id, Role::Customer);
+
+    let cart_resp = warp::test::request()
+        .method("POST")
+        .path("/carts")
+        .header("authorization", &user_auth)
+        .json(&json!({ "user_id": user.id }))
+        .reply(&filter)
+        .await;
+    assert_eq!(cart_resp.status(), 200);
+    let cart: CartResponse = serde_json::from_slice(cart_resp.body()).expect("cart");
+    let checkout_path = format!("/carts/{}/checkout", cart.cart_id);
+
+    let unverified = warp::test::request()
+        .method("POST")
+        .path(&checkout_path)
+        .header("authorization", &user_auth)
+        .reply(&filter)
+        .await;
+    assert_eq!(unverified.status(), 403);
+
+    {
+        let mut conn = get_conn(&pool).expect("conn");
+        user_repository::set_email_verified_at(&mut conn, user.id, Some(chrono::Utc::now()))
+            .expect("verify user");
+    }
+
+    let empty = warp::test::request()
+        .method("POST")
+        .path(&checkout_path)
+        .header("authorization", &user_auth)
+        .reply(&filter)
+        .await;
+    assert_eq!(empty.status(), 400);
+
+    let add_resp = warp::test::request()
+        .method("POST")
+        .path(&format!("/carts/{}/items", cart.cart_id))
+        .header("authorization", &user_auth)
+        .json(&json!({ "item_id": product.id, "quantity": 1, "unit_price": "3.00" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(add_resp.status(), 201);
+
+    // The status cannot be forced through the generic update
+    let forced = warp::test::request()
+        .method("PUT")
+        .path(&format!("/carts/{}", cart.cart_id))
+        .header("authorization", &user_auth)
+        .json(&json!({ "cart_status": "checked_out" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(forced.status(), 400);
+
+    let checked_out = warp::test::request()
+        .method("POST")
+        .path(&checkout_path)
+        .header("authorization", &user_auth)
+        .reply(&filter)
+        .await;
+    assert_eq!(checked_out.status(), 200);
+    let closed: CartResponse = serde_json::from_slice(checked_out.body()).expect("cart");
+    assert_eq!(closed.cart_status, "checked_out");
+
+    // The sale leaves the stock ledger and takes the line out of stock
+    {
+        let mut conn = get_conn(&pool).expect("conn");
+        let sold = product_repository::get_product_by_id(&mut conn, product.id)
+            .expect("db lookup")
+            .expect("product");
+        assert_eq!(sold.stock, 99);
+        let filter = MovementFilter {
+            movement_type: Some("sale".into()),
+            ..Default::default()
+        };
+        let sales =
+            stock_movement_repository::list_movements(&mut conn, product.id, &filter, 0, 10)
+                .expect("movements");
+        assert_eq!(sales.len(), 1);
+        assert_eq!(
+            (sales[0].quantity, sales[0].cart_id),
+            (-1, Some(cart.cart_id))
+        );
+    }
+
+    let again = warp::test::request()
+        .method("POST")
+        .path(&checkout_path)
+        .header("authorization", &user_auth)
+        .reply(&filter)
+        .await;
+    assert_eq!(again.status(), 409);
+
+    // The lines of a checked-out cart are fixed
+    let items_path = format!("/carts/{}/items", cart.cart_id);
+    let line_path = format!("{items_path}/{}", product.id);
+    let attempts = [
+        warp::test::request()
+            .method("POST")
+            .path(&items_path)
+            .json(&json!({ "item_id": product.id, "quantity": 1, "unit_price": "3.00" })),
+        warp::test::request()
+            .method("PUT")
+            .path(&line_path)
+            .json(&json!({ "quantity": 2 })),
+        warp::test::request().method("DELETE").path(&line_path),
+        warp::test::request().method("DELETE").path(&items_path),
+    ];
+    for attempt in attempts {
+        let resp = attempt
+            .header("authorization", &user_auth)
+            .reply(&filter)
+            .await;
+        assert_eq!(resp.status(), 409, "{:?}", resp.body());
+    }
+    let items_resp = warp::test::request()
+        .method("GET")
+        .path(&items_path)
+        .header("authorization", &user_auth)
+        .reply(&filter)
+        .await;
+    let items: Vec<CartItemResponse> = serde_json::from_slice(items_resp.body()).expect("items");
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].quantity, 1);
+}
+
+#[tokio::test]
+async fn cart_holds_each_variant_of_a_product_separately() {
+    let test_db = setup_postgres();
+    let pool = test_db.pool.clone();
+    let filter = cart_filter(pool.clone());
+
+    let user = insert_user(&pool, "cart-variants@example.com");
+    let auth = bearer_token(user.id, Role::Customer);
+    let product = insert_product(&pool, "Variant Socks", "4.00");
+    let large = {
+        let mut conn = get_conn(&pool).expect("conn");
+        product_variant_repository::create_variant(
+            &mut conn,
+            &NewProductVariant {
+                product_id: product.id,
+                sku: "SOCKS-L".into(),
+                options: json!({ "size": "L" }),
+                price: None,
+                stock: 5,
+                is_default: false,
+            },
+        )
+        .expect("create variant")
+    };
+
+    let cart_resp = warp::test::request()
+        .method("POST")
+        .path("/carts")
+        .header("authorization", &auth)
+        .json(&json!({ "user_id": user.id }))
+        .reply(&filter)
+        .await;
+    let cart: CartResponse = serde_json::from_slice(cart_resp.body()).expect("cart");
+    let items_path = format!("/carts/{}/items", cart.cart_id);
+
+    // item_id may name the product (its default variant) or one variant
+    let mut added = Vec::new();
+    for item_id in [product.id, large.id] {
+        let resp = warp::test::request()
+            .method("POST")
+            .path(&items_path)
+            .header("authorization", &auth)
+            .json(&json!({ "item_id": item_id, "quantity": 1, "unit_price": "4.00" }))
+            .reply(&filter)
+            .await;
+        assert_eq!(resp.status(), 201, "{:?}", resp.body());
+        let item: CartItemResponse = serde_json::from_slice(resp.body()).expect("item");
+        assert_eq!(item.item_id, product.id);
+        added.push(item);
+    }
+    assert_ne!(added[0].variant_id, added[1].variant_id);
+    assert_eq!(added[1].variant_id, large.id);
+
+    let again = warp::test::request()
+        .method("POST")
+        .path(&items_path)
+        .header("authorization", &auth)
+        .json(&json!({ "item_id": large.id, "quantity": 1, "unit_price": "4.00" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(again.status(), 409);
+
+    let unknown = warp::test::request()
+        .method("POST")
+        .path(&items_path)
+        .header("authorization", &auth)
+        .json(&json!({ "item_id": uuid::Uuid::new_v4(), "quantity": 1, "unit_price": "4.00" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(unknown.status(), 404);
+
+    // The product id now matches both lines, so it no longer addresses one
+    let by_product = format!("{items_path}/{}", product.id);
+    let update = |path: String, quantity: i32| {
+        warp::test::request()
+            .method("PUT")
+            .path(&path)
+            .header("authorization", &auth)
+            .json(&json!({ "quantity": quantity }))
+            .reply(&filter)
+    };
+    assert_eq!(update(by_product.clone(), 2).await.status(), 409);
+    let ambiguous = warp::test::request()
+        .method("DELETE")
+        .path(&by_product)
+        .header("authorization", &auth)
+        .reply(&filter)
+        .await;
+    assert_eq!(ambiguous.status(), 409);
+    let resized = update(format!("{items_path}/{}", large.id), 3).await;
+    assert_eq!(resized.status(), 200, "{:?}", resized.body());
+    let resized: CartItemResponse = serde_json::from_slice(resized.body()).expect("item");
+    assert_eq!((resized.variant_id, resized.quantity), (large.id, 3));
+
+    // A variant stays while any cart holds it
+    let in_cart =
+        product_variant_service::delete_variant(pool.clone(), product.id, large.id, user.id).await;
+    assert!(matches!(in_cart, Err(AppError::Conflict(_))), "{in_cart:?}");
+
+    let removed = warp::test::request()
+        .method("DELETE")
+        .path(&format!("{items_path}/{}", large.id))
+        .header("authorization", &auth)
+        .reply(&filter)
+        .await;
+    assert_eq!(removed.status(), 204);
+    product_variant_service::delete_variant(pool.clone(), product.id, large.id, user.id)
+        .await
+        .expect("delete variant");
+
+    let listed = warp::test::request()
+        .method("GET")
+        .path(&items_path)
+        .header("authorization", &auth)
+        .reply(&filter)
+        .await;
+    let items: Vec<CartItemResponse> = serde_json::from_slice(listed.body()).expect("items");
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].variant_id, added[0].variant_id);
+    assert_eq!(items[0].quantity, 1);
+
+    // With one line left the product id addresses it again
+    assert_eq!(update(by_product, 2).await.status(), 200);
+}
+
+#[tokio::test]
+async fn archived_products_stay_in_carts_but_cannot_be_bought() {
+    let test_db = setup_postgres();
+    let pool = test_db.pool.clone();
+    let filter = cart_filter(pool.clone());
+
+    let user = insert_user(&pool, "cart-archived@example.com");
+    let auth = bearer_token(user.id, Role::Customer);
+    let retired = insert_product(&pool, "Retired Mug", "6.00");
+    let current = insert_product(&pool, "Current Mug", "7.00");
+
+    let cart_resp = warp::test::request()
+        .method("POST")
+        .path("/carts")
+        .header("authorization", &auth)
+        .json(&json!({ "user_id": user.id }))
+        .reply(&filter)
+        .await;
+    let cart: CartResponse = serde_json::from_slice(cart_resp.body()).expect("cart");
+    let items_path = format!("/carts/{}/items", cart.cart_id);
+
+    let added = warp::test::request()
+        .method("POST")
+        .path(&items_path)
+        .header("authorization", &auth)
+        .json(&json!({ "item_id": retired.id, "quantity": 1, "unit_price": "6.00" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(added.status(), 201);
+    let added: CartItemResponse = serde_json::from_slice(added.body()).expect("item");
+    assert!(added.available);
+
+    {
+        let mut conn = get_conn(&pool).expect("conn");
+        product_repository::archive_product(&mut conn, retired.id).expect("archive");
+    }
+
+    let listed = warp::test::request()
+        .method("GET")
+        .path(&items_path)
+        .header("authorization", &auth)
+        .reply(&filter)
+        .await;
+    let items: Vec<CartItemResponse> = serde_json::from_slice(listed.body()).expect("items");
+    assert_eq!(items.len(), 1);
+    assert!(!items[0].available);
+
+    let increased = warp::test::request()
+        .method("PUT")
+        .path(&format!("{items_path}/{}", retired.id))
+        .header("authorization", &auth)
+        .json(&json!({ "quantity": 2 }))
+        .reply(&filter)
+        .await;
+    assert_eq!(increased.status(), 409);
+
+    let add_current = warp::test::request()
+        .method("POST")
+        .path(&items_path)
+        .header("authorization", &auth)
+        .json(&json!({ "item_id": current.id, "quantity": 1, "unit_price": "7.00" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(add_current.status(), 201);
+
+    let checkout_path = format!("/carts/{}/checkout", cart.cart_id);
+    let blocked = warp::test::request()
+        .method("POST")
+        .path(&checkout_path)
+        .header("authorization", &auth)
+        .reply(&filter)
+        .await;
+    assert_eq!(blocked.status(), 409);
+
+    let removed = warp::test::request()
+        .method("DELETE")
+        .path(&format!("{items_path}/{}", retired.id))
+        .header("authorization", &auth)
+        .reply(&filter)
+        .await;
+    assert_eq!(removed.status(), 204);
+
+    let re_added = warp::test::request()
+        .method("POST")
+        .path(&items_path)
+        .header("authorization", &auth)
+        .json(&json!({ "item_id": retired.id, "quantity": 1, "unit_price": "6.00" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(re_added.status(), 409);
+
+    let checked_out = warp::test::request()
+        .method("POST")
+        .path(&checkout_path)
+        .header("authorization", &auth)
+        .reply(&filter)
+        .await;
+    assert_eq!(checked_out.status(), 200);
+}
+
+#[tokio::test]
+async fn cart_lines_reserve_stock_until_released() {
+    let test_db = setup_postgres();
+    let pool = test_db.pool.clone();
+    let filter = cart_filter(pool.clone());
+
+    let product = insert_product_with_stock(&pool, "Limited Print", "30.00", 3);
+
+    let mut carts = Vec::new();
+    for email in ["reserve-a@example.com", "reserve-b@example.com"] {
+        let user = insert_user(&pool, email);
+        let auth = bearer_token(user.id, Role::Customer);
+        let cart_resp = warp::test::request()
+            .method("POST")
+            .path("/carts")
+            .header("authorization", &auth)
+            .json(&json!({ "user_id": user.id }))
+            .reply(&filter)
+            .await;
+        let cart: CartResponse = serde_json::from_slice(cart_resp.body()).expect("cart");
+        carts.push((auth, format!("/carts/{}/items", cart.cart_id)));
+    }
+    let (auth_a, items_a) = &carts[0];
+    let (auth_b, items_b) = &carts[1];
+
+    let add = |auth: &str, path: &str, quantity: i32| {
+        warp::test::request()
+            .method("POST")
+            .path(path)
+            .header("authorization", auth)
+            .json(&json!({ "item_id": product.id, "quantity": quantity, "unit_price": "30.00" }))
+    };
+
+    let held = add(auth_a, items_a, 2).reply(&filter).await;
+    assert_eq!(held.status(), 201);
+
+    let too_many = add(auth_b, items_b, 2).reply(&filter).await;
+    assert_eq!(too_many.status(), 409);
+    let body: serde_json::Value = serde_json::from_slice(too_many.body()).expect("error body");
+    assert!(body.to_string().contains("Only 1 left in stock"), "{body}");
+
+    let last_one = add(auth_b, items_b, 1).reply(&filter).await;
+    assert_eq!(last_one.status(), 201);
+
+    // Raising a line needs free stock; lowering it fits in what the line already holds
+    let increased = warp::test::request()
+        .method("PUT")
+        .path(&format!("{items_a}/{}", product.id))
+        .header("authorization", auth_a)
+        .json(&json!({ "quantity": 3 }))
+        .reply(&filter)
+        .await;
+    assert_eq!(increased.status(), 409);
+    let lowered = warp::test::request()
+        .method("PUT")
+        .path(&format!("{items_a}/{}", product.id))
+        .header("authorization", auth_a)
+        .json(&json!({ "quantity": 1 }))
+        .reply(&filter)
+        .await;
+    assert_eq!(lowered.status(), 200);
+
+    // Removing a line releases its reservation
+    let removed = warp::test::request()
+        .method("DELETE")
+        .path(&format!("{items_a}/{}", product.id))
+        .header("authorization", auth_a)
+        .reply(&filter)
+        .await;
+    assert_eq!(removed.status(), 204);
+    let raised = warp::test::request()
+        .method("PUT")
+        .path(&format!("{items_b}/{}", product.id))
+        .header("authorization", auth_b)
+        .json(&json!({ "quantity": 3 }))
+        .reply(&filter)
+        .await;
+    assert_eq!(raised.status(), 200);
+    assert_eq!(add(auth_a, items_a, 1).reply(&filter).await.status(), 409);
+
+    // Expired reservations no longer hold stock and are swept away
+    let released = stock_reservation_service::release_expired(
+        pool.clone(),
+        chrono::Utc::now() + chrono::Duration::days(1),
+    )
+    .await
+    .expect("sweep");
+    assert_eq!(released, 1);
+    assert_eq!(add(auth_a, items_a, 3).reply(&filter).await.status(), 201);
+
+    // Clearing a cart releases every line
+    let set_b = |quantity: i32| {
+        warp::test::request()
+            .method("PUT")
+            .path(&format!("{items_b}/{}", product.id))
+            .header("authorization", auth_b)
+            .json(&json!({ "quantity": quantity }))
+    };
+    assert_eq!(set_b(1).reply(&filter).await.status(), 409);
+    let cleared = warp::test::request()
+        .method("DELETE")
+        .path(items_a)
+        .header("authorization", auth_a)
+        .reply(&filter)
+        .await;
+    assert_eq!(cleared.status(), 204);
+    assert_eq!(set_b(2).reply(&filter).await.status(), 200);
+}
+
+#[tokio::test]
+async fn raising_a_line_checks_the_stock_of_its_own_variant() {
+    let test_db = setup_postgres();
+    let pool = test_db.pool.clone();
+    let filter = cart_filter(pool.clone());
+
+    let user = insert_user(&pool, "reserve-variant@example.com");
+    let auth = bearer_token(user.id, Role::Customer);
+    let product = insert_product_with_stock(&pool, "Framed Print", "30.00", 10);
+    let large = {
+        let mut conn = get_conn(&pool).expect("conn");
+        product_variant_repository::create_variant(
+            &mut conn,
+            &NewProductVariant {
+                product_id: product.id,
+                sku: "PRINT-XL".into(),
+                options: json!({ "size": "XL" }),
+                price: None,
+                stock: 2,
+                is_default: false,
+            },
+        )
+        .expect("create variant")
+    };
+
+    let cart_resp = warp::test::request()
+        .method("POST")
+        .path("/carts")
+        .header("authorization", &auth)
+        .json(&json!({ "user_id": user.id }))
+        .reply(&filter)
+        .await;
+    let cart: CartResponse = serde_json::from_slice(cart_resp.body()).expect("cart");
+    let items_path = format!("/carts/{}/items", cart.cart_id);
+    for item_id in [product.id, large.id] {
+        let resp = warp::test::request()
+            .method("POST")
+            .path(&items_path)
+            .header("authorization", &auth)
+            .json(&json!({ "item_id": item_id, "quantity": 1, "unit_price": "30.00" }))
+            .reply(&filter)
+            .await;
+        assert_eq!(resp.status(), 201, "{:?}", resp.body());
+    }
+
+    // The default variant has stock to spare, the large one does not
+    let set_large = |quantity: i32| {
+        warp::test::request()
+            .method("PUT")
+            .path(&format!("{items_path}/{}", large.id))
+            .header("authorization", &auth)
+            .json(&json!({ "quantity": quantity }))
+            .reply(&filter)
+    };
+    let too_many = set_large(3).await;
+    assert_eq!(too_many.status(), 409);
+    let body: serde_json::Value = serde_json::from_slice(too_many.body()).expect("error body");
+    assert!(body.to_string().contains("Only 2 left in stock"), "{body}");
+    assert_eq!(set_large(2).await.status(), 200);
+
+    let reserved = stock_reservation_service::reserved_for_products(
+        pool.clone(),
+        vec![product.id],
+        chrono::Utc::now(),
+    )
+    .await
+    .expect("reserved");
+    assert_eq!(reserved.get(&product.id), Some(&3));
+}
+
+#[tokio::test]
+async fn renewing_an_expired_line_checks_free_stock() {
+    let test_db = setup_postgres();
+    let pool = test_db.pool.clone();
+    let filter = cart_filter(pool.clone());
+
+    let product = insert_product_with_stock(&pool, "Signed Print", "30.00", 2);
+
+    let mut carts = Vec::new();
+    for email in ["expired-a@example.com", "expired-b@example.com"] {
+        let user = insert_user(&pool, email);
+        let auth = bearer_token(user.id, Role::Customer);
+        let cart_resp = warp::test::request()
+            .method("POST")
+            .path("/carts")
+            .header("authorization", &auth)
+            .json(&json!({ "user_id": user.id }))
+            .reply(&filter)
+            .await;
+        let cart: CartResponse = serde_json::from_slice(cart_resp.body()).expect("cart");
+        carts.push((auth, format!("/carts/{}/items", cart.cart_id)));
+    }
+    let (auth_a, items_a) = &carts[0];
+    let (auth_b, items_b) = &carts[1];
+
+    let add = |auth: &str, path: &str| {
+        warp::test::request()
+            .method("POST")
+            .path(path)
+            .header("authorization", auth)
+            .json(&json!({ "item_id": product.id, "quantity": 2, "unit_price": "30.00" }))
+    };
+    let set_a = |quantity: i32| {
+        warp::test::request()
+            .method("PUT")
+            .path(&format!("{items_a}/{}", product.id))
+            .header("authorization", auth_a)
+            .json(&json!({ "quantity": quantity }))
+    };
+
+    assert_eq!(add(auth_a, items_a).reply(&filter).await.status(), 201);
+    // A's reservation runs out and another cart takes the stock
+    let released = stock_reservation_service::release_expired(
+        pool.clone(),
+        chrono::Utc::now() + chrono::Duration::days(1),
+    )
+    .await
+    .expect("sweep");
+    assert_eq!(released, 1);
+    assert_eq!(add(auth_b, items_b).reply(&filter).await.status(), 201);
+
+    // Renewing A's line, even at a lower quantity, must not reserve stock B holds
+    for quantity in [2, 1] {
+        let resp = set_a(quantity).reply(&filter).await;
+        assert_eq!(resp.status(), 409, "quantity {quantity}");
+        let body: serde_json::Value = serde_json::from_slice(resp.body()).expect("error body");
+        assert!(body.to_string().contains("out of stock"), "{body}");
+    }
+    let reserved = stock_reservation_service::reserved_for_products(
+        pool.clone(),
+        vec![product.id],
+        chrono::Utc::now(),
+    )
+    .await
+    .expect("reserved");
+    assert_eq!(reserved.get(&product.id), Some(&2));
+
+    let removed = warp::test::request()
+        .method("DELETE")
+        .path(&format!("{items_b}/{}", product.id))
+        .header("authorization", auth_b)
+        .reply(&filter)
+        .await;
+    assert_eq!(removed.status(), 204);
+    assert_eq!(set_a(1).reply(&filter).await.status(), 200);
+}
+
+fn cart_filter_allocating(
+    pool: PgPool,
+    strategy: AllocationStrategy,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = std::convert::Infallible> + Clone {
+    let mut config = AppConfig::new(test_auth_config());
+    config.carts.allocation_strategy = strategy;
+    cart_routes(pool, Arc::new(config)).recover(handle_rejection)
+}
+
+fn insert_warehouse(pool: &PgPool, code: &str, latitude: f64, longitude: f64) -> Warehouse {
+    let mut conn = get_conn(pool).expect("conn");
+    let new_warehouse = NewWarehouse {
+        code: code.into(),
+        name: format!("{code} warehouse"),
+        latitude: Some(latitude),
+        longitude: Some(longitude),
+    };
+    warehouse_repository::create_warehouse(&mut conn, &new_warehouse).expect("create warehouse")
+}
+
+async fn receive(pool: &PgPool, product: &Product, warehouse: &Warehouse, quantity: i32) {
+    let receipt = MovementInput {
+        variant_id: None,
+        warehouse_id: Some(warehouse.id),
+        movement_type: "receipt".into(),
+        quantity,
+        reason: "Delivery".into(),
+    };
+    stock_movement_service::record_movement(pool.clone(), product.id, receipt, Uuid::new_v4())
+        .await
+        .expect("receipt");
+}
+
+async fn fill_cart<F>(
+    filter: &F,
+    pool: &PgPool,
+    email: &str,
+    lines: &[(&Product, i32)],
+) -> (String, String)
+where
+    F: Filter + Clone + 'static,
+    F::Extract: warp::Reply + Send,
+{
+    let user = insert_user(pool, email);
+    let auth = bearer_token(user.id, Role::Customer);
+    let cart_resp = warp::test::request()
+        .method("POST")
+        .path("/carts")
+        .header("authorization", &auth)
+        .json(&json!({ "user_id": user.id }))
+        .reply(filter)
+        .await;
+    let cart: CartResponse = serde_json::from_slice(cart_resp.body()).expect("cart");
+    for (product, quantity) in lines {
+        let added = warp::test::request()
+            .method("POST")
+            .path(&format!("/carts/{}/items", cart.cart_id))
+            .header("authorization", &auth)
+            .json(&json!({ "item_id": product.id, "quantity": quantity, "unit_price": product.price }))
+            .reply(filter)
+            .await;
+        assert_eq!(added.status(), 201, "{:?}", added.body());
+    }
+    (auth, format!("/carts/{}/checkout", cart.cart_id))
+}
+
+/// `(product, warehouse, quantity)` of each allocation in a checkout response, sorted.
+fn shipped_from(body: &[u8]) -> Vec<(Uuid, Uuid, i32)> {
+    let cart: CartResponse = serde_json::from_slice(body).expect("cart");
+    let mut allocations: Vec<(Uuid, Uuid, i32)> = cart
+        .allocations
+        .iter()
+        .map(|a| (a.product_id, a.warehouse_id, a.quantity))
+        .collect();
+    allocations.sort();
+    allocations
+}
+
+fn sorted<T: Ord>(mut items: Vec<T>) -> Vec<T> {
+    items.sort();
+    items
+}
+
+#[tokio::test]
+async fn checkout_allocates_lines_to_warehouses() {
+    let test_db = setup_postgres();
+    let pool = test_db.pool.clone();
+    let nearest = cart_filter_allocating(pool.clone(), AllocationStrategy::NearestFirst);
+    let single = cart_filter_allocating(pool.clone(), AllocationStrategy::SingleShipment);
+
+    let amsterdam = insert_warehouse(&pool, "AMS", 52.37, 4.90);
+    let new_york = insert_warehouse(&pool, "NYC", 40.71, -74.01);
+    let lamp = insert_product_with_stock(&pool, "Harbour Lamp", "20.00", 0);
+    let rug = insert_product_with_stock(&pool, "Harbour Rug", "60.00", 0);
+    let vase = insert_product_with_stock(&pool, "Harbour Vase", "15.00", 0);
+    receive(&pool, &lamp, &amsterdam, 1).await;
+    receive(&pool, &lamp, &new_york, 5).await;
+    receive(&pool, &rug, &new_york, 2).await;
+    receive(&pool, &vase, &amsterdam, 1).await;
+    receive(&pool, &vase, &new_york, 1).await;
+
+    // Rotterdam, a short drive from the Amsterdam warehouse
+    let destination = "?latitude=51.92&longitude=4.48";
+
+    // One shipment from New York beats splitting the order, even though Amsterdam is nearer
+    let (auth, checkout) = fill_cart(
+        &single,
+        &pool,
+        "single-shipment@example.com",
+        &[(&lamp, 1), (&rug, 1)],
+    )
+    .await;
+    let resp = warp::test::request()
+        .method("POST")
+        .path(&format!("{checkout}{destination}"))
+        .header("authorization", &auth)
+        .reply(&single)
+        .await;
+    assert_eq!(resp.status(), 200, "{:?}", resp.body());
+    assert_eq!(
+        shipped_from(resp.body()),
+        sorted(vec![(lamp.id, new_york.id, 1), (rug.id, new_york.id, 1)])
+    );
+
+    // Nearest-first ships each line from the closest warehouse holding all of it, and splits a
+    // line only when no single warehouse can fill it
+    let (auth, checkout) = fill_cart(
+        &nearest,
+        &pool,
+        "nearest-first@example.com",
+        &[(&lamp, 1), (&rug, 1), (&vase, 2)],
+    )
+    .await;
+    let half_located = warp::test::request()
+        .method("POST")
+        .path(&format!("{checkout}?latitude=51.92"))
+        .header("authorization", &auth)
+        .reply(&nearest)
+        .await;
+    assert_eq!(half_located.status(), 400);
+    let resp = warp::test::request()
+        .method("POST")
+        .path(&format!("{checkout}{destination}"))
+        .header("authorization", &auth)
+        .reply(&nearest)
+        .await;
+    assert_eq!(resp.status(), 200, "{:?}", resp.body());
+    assert_eq!(
+        shipped_from(resp.body()),
+        sorted(vec![
+            (lamp.id, amsterdam.id, 1),
+            (rug.id, new_york.id, 1),
+            (vase.id, amsterdam.id, 1),
+            (vase.id, new_york.id, 1),
+        ])
+    );
+
+    // Each allocation left the ledger a sale in its warehouse
+    let mut conn = get_conn(&pool).expect("conn");
+    let filter = MovementFilter {
+        warehouse_id: Some(amsterdam.id),
+        movement_type: Some("sale".into()),
+        ..Default::default()
+    };
+    let amsterdam_sales =
+        stock_movement_repository::list_movements(&mut conn, vase.id, &filter, 0, 10)
+            .expect("movements");
+    assert_eq!(amsterdam_sales.len(), 1);
+    assert_eq!(amsterdam_sales[0].quantity, -1);
+    let vase_left = product_repository::get_product_by_id(&mut conn, vase.id)
+        .expect("db lookup")
+        .expect("product");
+    assert_eq!(vase_left.stock, 0);
+}
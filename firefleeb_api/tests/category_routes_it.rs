@@ -0,0 +1,342 @@
+mod common {
+    pub mod auth;
+    pub mod blobs;
+    pub mod config;
+    pub mod db;
+}
+
+use bigdecimal::BigDecimal;
+use common::auth::{role_token, test_auth_config};
+use common::blobs::test_blob_store;
+use common::config::app_config;
+use common::db::setup_postgres;
+use firefleeb_api::db::{PgPool, get_conn, product_repository};
+use firefleeb_api::handlers::dtos::{
+    CategoriesResponse, CategoryResponse, PageResponse, ProductResponse,
+};
+use firefleeb_api::models::NewProduct;
+use firefleeb_api::routes::{
+    category_routes::category_routes, handle_rejection, product_routes::product_routes,
+};
+use firefleeb_api::types::role::Role;
+use serde_json::{Value, json};
+use std::str::FromStr;
+use uuid::Uuid;
+use warp::Filter;
+
+fn catalog_filter(
+    pool: PgPool,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = std::convert::Infallible> + Clone {
+    let config = app_config(test_auth_config());
+    category_routes(pool.clone(), config.clone())
+        .or(product_routes(
+            pool,
+            config,
+            test_blob_store().store.clone(),
+        ))
+        .recover(handle_rejection)
+}
+
+fn seed_product(pool: &PgPool, name: &str) -> Uuid {
+    let mut conn = get_conn(pool).expect("conn");
+    let new_product = NewProduct {
+        product_name: name.to_string(),
+        product_description: None,
+        price: BigDecimal::from_str("5.00").expect("price"),
+        stock: 10,
+    };
+    product_repository::create_product(&mut conn, &new_product, None)
+        .expect("insert product")
+        .id
+}
+
+async fn send<F>(
+    filter: &F,
+    method: &str,
+    path: &str,
+    token: Option<String>,
+    body: Option<Value>,
+) -> warp::http::Response<warp::hyper::body::Bytes>
+where
+    F: Filter + Clone + 'static,
+    F::Extract: warp::Reply + Send,
+{
+    let mut request = warp::test::request().method(method).path(path);
+    if let Some(token) = token {
+        request = request.header("authorization", token);
+    }
+    if let Some(body) = body {
+        request = request.json(&body);
+    }
+    request.reply(filter).await
+}
+
+async fn create_category<F>(filter: &F, name: &str, parent_id: Option<Uuid>) -> CategoryResponse
+where
+    F: Filter + Clone + 'static,
+    F::Extract: warp::Reply + Send,
+{
+    let resp = send(
+        filter,
+        "POST",
+        "/categories",
+        Some(role_token(Role::Staff)),
+        Some(json!({ "name": name, "parent_id": parent_id })),
+    )
+    .await;
+    assert_eq!(resp.status(), 201, "{name}: {:?}", resp.body());
+    serde_json::from_slice(resp.body()).expect("category")
+}
+
+fn crumb_names(path: &[firefleeb_api::models::category::CategoryCrumb]) -> Vec<&str> {
+    path.iter().map(|crumb| crumb.name.as_str()).collect()
+}
+
+#[tokio::test]
+async fn categories_nest_rename_and_move() {
+    let test_db = setup_postgres();
+    let filter = catalog_filter(test_db.pool.clone());
+
+    let drinks = create_category(&filter, "Drinks", None).await;
+    let hot = create_category(&filter, "Hot", Some(drinks.id)).await;
+    let coffee = create_category(&filter, "  Coffee ", Some(hot.id)).await;
+    assert_eq!(coffee.name, "Coffee");
+    assert_eq!(crumb_names(&coffee.path), ["Drinks", "Hot", "Coffee"]);
+
+    let duplicate = send(
+        &filter,
+        "POST",
+        "/categories",
+        Some(role_token(Role::Staff)),
+        Some(json!({ "name": "hot", "parent_id": drinks.id })),
+    )
+    .await;
+    assert_eq!(duplicate.status(), 409);
+
+    let renamed = send(
+        &filter,
+        "PUT",
+        &format!("/categories/{}", hot.id),
+        Some(role_token(Role::Staff)),
+        Some(json!({ "name": "Warm" })),
+    )
+    .await;
+    assert_eq!(renamed.status(), 200);
+
+    let fetched = send(
+        &filter,
+        "GET",
+        &format!("/categories/{}", coffee.id),
+        None,
+        None,
+    )
+    .await;
+    let fetched: CategoryResponse = serde_json::from_slice(fetched.body()).expect("category");
+    assert_eq!(crumb_names(&fetched.path), ["Drinks", "Warm", "Coffee"]);
+
+    // null moves the category to the root; leaving parent_id out keeps it
+    let moved = send(
+        &filter,
+        "PUT",
+        &format!("/categories/{}", coffee.id),
+        Some(role_token(Role::Staff)),
+        Some(json!({ "parent_id": null })),
+    )
+    .await;
+    assert_eq!(moved.status(), 200);
+    let moved: CategoryResponse = serde_json::from_slice(moved.body()).expect("category");
+    assert_eq!(moved.parent_id, None);
+    assert_eq!(crumb_names(&moved.path), ["Coffee"]);
+
+    let listed = send(&filter, "GET", "/categories", None, None).await;
+    let listed: CategoriesResponse = serde_json::from_slice(listed.body()).expect("categories");
+    let warm = listed
+        .categories
+        .iter()
+        .find(|category| category.id == hot.id)
+        .expect("warm listed");
+    assert_eq!(crumb_names(&warm.path), ["Drinks", "Warm"]);
+    assert_eq!(listed.categories.len(), 3);
+}
+
+#[tokio::test]
+async fn category_cannot_move_below_itself() {
+    let test_db = setup_postgres();
+    let filter = catalog_filter(test_db.pool.clone());
+
+    let root = create_category(&filter, "Root", None).await;
+    let child = create_category(&filter, "Child", Some(root.id)).await;
+    let grandchild = create_category(&filter, "Grandchild", Some(child.id)).await;
+
+    for parent in [root.id, grandchild.id] {
+        let resp = send(
+            &filter,
+            "PUT",
+            &format!("/categories/{}", root.id),
+            Some(role_token(Role::Staff)),
+            Some(json!({ "parent_id": parent })),
+        )
+        .await;
+        assert_eq!(resp.status(), 400, "{:?}", resp.body());
+    }
+
+    let missing_parent = send(
+        &filter,
+        "PUT",
+        &format!("/categories/{}", child.id),
+        Some(role_token(Role::Staff)),
+        Some(json!({ "parent_id": Uuid::new_v4() })),
+    )
+    .await;
+    assert_eq!(missing_parent.status(), 404);
+}
+
+#[tokio::test]
+async fn deleting_category_requires_admin_and_no_children() {
+    let test_db = setup_postgres();
+    let filter = catalog_filter(test_db.pool.clone());
+
+    let parent = create_category(&filter, "Parent", None).await;
+    let child = create_category(&filter, "Child", Some(parent.id)).await;
+    let product_id = seed_product(&test_db.pool, "Linked Product");
+    let linked = send(
+        &filter,
+        "PUT",
+        &format!("/categories/{}/products/{product_id}", child.id),
+        Some(role_token(Role::Staff)),
+        None,
+    )
+    .await;
+    assert_eq!(linked.status(), 204);
+
+    let as_staff = send(
+        &filter,
+        "DELETE",
+        &format!("/categories/{}", child.id),
+        Some(role_token(Role::Staff)),
+        None,
+    )
+    .await;
+    assert_eq!(as_staff.status(), 403);
+
+    let with_children = send(
+        &filter,
+        "DELETE",
+        &format!("/categories/{}", parent.id),
+        Some(role_token(Role::Admin)),
+        None,
+    )
+    .await;
+    assert_eq!(with_children.status(), 409);
+
+    let leaf = send(
+        &filter,
+        "DELETE",
+        &format!("/categories/{}", child.id),
+        Some(role_token(Role::Admin)),
+        None,
+    )
+    .await;
+    assert_eq!(leaf.status(), 204);
+
+    // The product outlives its category
+    let product = send(
+        &filter,
+        "GET",
+        &format!("/products/{product_id}"),
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(product.status(), 200);
+    let product: ProductResponse = serde_json::from_slice(product.body()).expect("product");
+    assert!(product.breadcrumbs.is_empty());
+}
+
+#[tokio::test]
+async fn category_products_include_descendants_and_breadcrumbs() {
+    let test_db = setup_postgres();
+    let filter = catalog_filter(test_db.pool.clone());
+
+    let drinks = create_category(&filter, "Drinks", None).await;
+    let coffee = create_category(&filter, "Coffee", Some(drinks.id)).await;
+    let gifts = create_category(&filter, "Gifts", None).await;
+
+    let water = seed_product(&test_db.pool, "Water");
+    let espresso = seed_product(&test_db.pool, "Espresso");
+    for (category_id, product_id) in [
+        (drinks.id, water),
+        (coffee.id, espresso),
+        (gifts.id, espresso),
+    ] {
+        let resp = send(
+            &filter,
+            "PUT",
+            &format!("/categories/{category_id}/products/{product_id}"),
+            Some(role_token(Role::Staff)),
+            None,
+        )
+        .await;
+        assert_eq!(resp.status(), 204);
+    }
+
+    let direct = send(
+        &filter,
+        "GET",
+        &format!("/categories/{}/products", drinks.id),
+        None,
+        None,
+    )
+    .await;
+    let direct: PageResponse<ProductResponse> =
+        serde_json::from_slice(direct.body()).expect("page");
+    assert_eq!(direct.total, 1);
+    assert_eq!(direct.items[0].id, water);
+
+    let nested = send(
+        &filter,
+        "GET",
+        &format!(
+            "/categories/{}/products?include_descendants=true&limit=1",
+            drinks.id
+        ),
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(nested.status(), 200);
+    let nested: PageResponse<ProductResponse> =
+        serde_json::from_slice(nested.body()).expect("page");
+    assert_eq!(nested.total, 2);
+    assert_eq!(nested.items.len(), 1);
+    let next = nested.links.next.expect("next link");
+    assert!(next.contains("include_descendants=true"), "{next}");
+
+    let product = send(&filter, "GET", &format!("/products/{espresso}"), None, None).await;
+    let product: ProductResponse = serde_json::from_slice(product.body()).expect("product");
+    let trails: Vec<Vec<&str>> = product
+        .breadcrumbs
+        .iter()
+        .map(|path| crumb_names(path))
+        .collect();
+    assert_eq!(trails, [vec!["Drinks", "Coffee"], vec!["Gifts"]]);
+
+    let unlinked = send(
+        &filter,
+        "DELETE",
+        &format!("/categories/{}/products/{espresso}", gifts.id),
+        Some(role_token(Role::Staff)),
+        None,
+    )
+    .await;
+    assert_eq!(unlinked.status(), 204);
+    let again = send(
+        &filter,
+        "DELETE",
+        &format!("/categories/{}/products/{espresso}", gifts.id),
+        Some(role_token(Role::Staff)),
+        None,
+    )
+    .await;
+    assert_eq!(again.status(), 404);
+}
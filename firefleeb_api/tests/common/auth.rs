@@ -0,0 +1,18 @@
+use firefleeb_api::auth::{AuthConfig, issue_access_token};
+use firefleeb_api::types::role::Role;
+use uuid::Uuid;
+
+pub fn test_auth_config() -> AuthConfig {
+    AuthConfig::new("integration-test-secret")
+}
+
+/// `Authorization` header value for a token signed with the test auth config.
+pub fn bearer_token(user_id: Uuid, role: Role) -> String {
+    let token = issue_access_token(&test_auth_config(), user_id, role).expect("access token");
+    format!("Bearer {}", token.token)
+}
+
+/// Bearer token of a fresh user with `role`.
+pub fn role_token(role: Role) -> String {
+    bearer_token(Uuid::new_v4(), role)
+}
@@ -0,0 +1,25 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use firefleeb_api::storage::{LocalBlobStore, SharedBlobStore};
+use uuid::Uuid;
+
+/// A local blob store in a fresh temporary directory, removed again on drop.
+pub struct TestBlobs {
+    pub store: SharedBlobStore,
+    pub root: PathBuf,
+}
+
+impl Drop for TestBlobs {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
+
+pub fn test_blob_store() -> TestBlobs {
+    let root = std::env::temp_dir().join(format!("firefleeb-blobs-{}", Uuid::new_v4()));
+    TestBlobs {
+        store: Arc::new(LocalBlobStore::new(&root)),
+        root,
+    }
+}
@@ -0,0 +1,9 @@
+use std::sync::Arc;
+
+use firefleeb_api::auth::AuthConfig;
+use firefleeb_api::config::AppConfig;
+
+/// Default app config around `auth`, as passed to the route builders.
+pub fn app_config(auth: AuthConfig) -> Arc<AppConfig> {
+    Arc::new(AppConfig::new(auth))
+}
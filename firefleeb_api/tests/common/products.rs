@@ -0,0 +1,26 @@
+use firefleeb_api::handlers::dtos::ProductResponse;
+use firefleeb_api::types::role::Role;
+use serde_json::json;
+use uuid::Uuid;
+use warp::Filter;
+
+use super::auth::role_token;
+
+/// Create a product through `POST /products` as staff and return its id.
+pub async fn create_product<F>(filter: &F, name: &str, price: &str, stock: i32) -> Uuid
+where
+    F: Filter + Clone + 'static,
+    F::Extract: warp::Reply + Send,
+{
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/products")
+        .header("authorization", role_token(Role::Staff))
+        .json(&json!({ "product_name": name, "price": price, "stock": stock }))
+        .reply(filter)
+        .await;
+    assert_eq!(resp.status(), 200, "{:?}", resp.body());
+    serde_json::from_slice::<ProductResponse>(resp.body())
+        .expect("product")
+        .id
+}
@@ -0,0 +1,13 @@
+use firefleeb_api::db::{PgPool, get_conn, user_repository};
+use firefleeb_api::models::{NewUser, User};
+use firefleeb_api::types::email::Email;
+
+/// Insert a customer with a placeholder password hash.
+pub fn insert_user(pool: &PgPool, email: &str) -> User {
+    let mut conn = get_conn(pool).expect("conn");
+    let new_user = NewUser {
+        email: Email::parse(email).expect("email"),
+        password_hash: "test-hash".into(),
+    };
+    user_repository::create_user(&mut conn, &new_user).expect("create user")
+}
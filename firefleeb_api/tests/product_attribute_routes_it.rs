@@ -0,0 +1,338 @@
+mod common {
+    pub mod auth;
+    pub mod blobs;
+    pub mod config;
+    pub mod db;
+}
+
+use common::auth::{role_token, test_auth_config};
+use common::blobs::test_blob_store;
+use common::config::app_config;
+use common::db::setup_postgres;
+use firefleeb_api::db::PgPool;
+use firefleeb_api::handlers::dtos::{
+    AttributeResponse, AttributesResponse, ProductPageResponse, ProductResponse,
+};
+use firefleeb_api::routes::{
+    attribute_routes::attribute_routes, handle_rejection, product_routes::product_routes,
+};
+use firefleeb_api::types::role::Role;
+use serde_json::{Value, json};
+use uuid::Uuid;
+use warp::Filter;
+
+fn attribute_filter(
+    pool: PgPool,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = std::convert::Infallible> + Clone {
+    let config = app_config(test_auth_config());
+    attribute_routes(pool.clone(), config.clone())
+        .or(product_routes(
+            pool,
+            config,
+            test_blob_store().store.clone(),
+        ))
+        .recover(handle_rejection)
+}
+
+async fn define<F>(filter: &F, body: Value) -> AttributeResponse
+where
+    F: Filter + Clone + 'static,
+    F::Extract: warp::Reply + Send,
+{
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/attributes")
+        .header("authorization", role_token(Role::Admin))
+        .json(&body)
+        .reply(filter)
+        .await;
+    assert_eq!(resp.status(), 201, "{:?}", resp.body());
+    serde_json::from_slice(resp.body()).expect("attribute")
+}
+
+async fn create_product<F>(filter: &F, name: &str, attributes: Value) -> ProductResponse
+where
+    F: Filter + Clone + 'static,
+    F::Extract: warp::Reply + Send,
+{
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/products")
+        .header("authorization", role_token(Role::Staff))
+        .json(&json!({ "product_name": name, "price": "20.00", "stock": 3, "attributes": attributes }))
+        .reply(filter)
+        .await;
+    assert_eq!(resp.status(), 200, "{:?}", resp.body());
+    serde_json::from_slice(resp.body()).expect("product")
+}
+
+async fn list<F>(filter: &F, query: &str) -> ProductPageResponse
+where
+    F: Filter + Clone + 'static,
+    F::Extract: warp::Reply + Send,
+{
+    let resp = warp::test::request()
+        .method("GET")
+        .path(&format!("/products{query}"))
+        .reply(filter)
+        .await;
+    assert_eq!(resp.status(), 200, "{:?}", resp.body());
+    serde_json::from_slice(resp.body()).expect("page")
+}
+
+fn names(page: &ProductPageResponse) -> Vec<&str> {
+    let mut names: Vec<&str> = page
+        .page
+        .items
+        .iter()
+        .map(|product| product.product_name.as_str())
+        .collect();
+    names.sort();
+    names
+}
+
+/// `(code, [(value, count)])` for every facet of the page.
+fn facets(page: &ProductPageResponse) -> Vec<(&str, Vec<(Value, i64)>)> {
+    page.facets
+        .iter()
+        .map(|facet| {
+            let values = facet
+                .values
+                .iter()
+                .map(|value| (value.value.clone(), value.count))
+                .collect();
+            (facet.code.as_str(), values)
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn admins_define_attributes() {
+    let test_db = setup_postgres();
+    let filter = attribute_filter(test_db.pool.clone());
+
+    let create = |token: String, body: Value| {
+        warp::test::request()
+            .method("POST")
+            .path("/attributes")
+            .header("authorization", token)
+            .json(&body)
+            .reply(&filter)
+    };
+    let material = json!({
+        "code": "material", "name": "Material", "value_type": "text",
+        "allowed_values": ["steel", "oak"]
+    });
+    assert_eq!(
+        create(role_token(Role::Staff), material.clone())
+            .await
+            .status(),
+        403
+    );
+    for bad in [
+        json!({ "code": "Material", "name": "Material", "value_type": "text" }),
+        json!({ "code": "colour", "name": "Colour", "value_type": "colour" }),
+        json!({ "code": "wattage", "name": "Wattage", "value_type": "number", "allowed_values": ["high"] }),
+        json!({ "code": "size", "name": "Size", "value_type": "text", "allowed_values": ["S", "S"] }),
+        json!({ "code": "cordless", "name": "Cordless", "value_type": "boolean", "allowed_values": [true] }),
+    ] {
+        assert_eq!(create(role_token(Role::Admin), bad).await.status(), 400);
+    }
+    let created = create(role_token(Role::Admin), material.clone()).await;
+    assert_eq!(created.status(), 201, "{:?}", created.body());
+    let material: AttributeResponse = serde_json::from_slice(created.body()).expect("attribute");
+    assert_eq!(
+        material.allowed_values,
+        Some(vec![json!("steel"), json!("oak")])
+    );
+    let duplicate = json!({ "code": "material", "name": "Another", "value_type": "text" });
+    assert_eq!(
+        create(role_token(Role::Admin), duplicate).await.status(),
+        409
+    );
+
+    let listed = warp::test::request()
+        .method("GET")
+        .path("/attributes")
+        .reply(&filter)
+        .await;
+    assert_eq!(listed.status(), 200);
+    let listed: AttributesResponse = serde_json::from_slice(listed.body()).expect("attributes");
+    assert_eq!(listed.attributes.len(), 1);
+
+    let product = create_product(&filter, "Oak Stool", json!({ "material": "oak" })).await;
+    let update = |body: Value| {
+        warp::test::request()
+            .method("PUT")
+            .path(&format!("/attributes/{}", material.id))
+            .header("authorization", role_token(Role::Admin))
+            .json(&body)
+            .reply(&filter)
+    };
+    // The stool still uses oak
+    assert_eq!(
+        update(json!({ "allowed_values": ["steel"] }))
+            .await
+            .status(),
+        409
+    );
+    let widened =
+        update(json!({ "name": "Main material", "allowed_values": ["steel", "oak", "glass"] }))
+            .await;
+    assert_eq!(widened.status(), 200, "{:?}", widened.body());
+    let opened = update(json!({ "allowed_values": null })).await;
+    let opened: AttributeResponse = serde_json::from_slice(opened.body()).expect("attribute");
+    assert_eq!(
+        (opened.name.as_str(), opened.allowed_values),
+        ("Main material", None)
+    );
+
+    let delete = |id: Uuid| {
+        warp::test::request()
+            .method("DELETE")
+            .path(&format!("/attributes/{id}"))
+            .header("authorization", role_token(Role::Admin))
+            .reply(&filter)
+    };
+    assert_eq!(delete(material.id).await.status(), 204);
+    assert_eq!(delete(material.id).await.status(), 404);
+    let stool = warp::test::request()
+        .method("GET")
+        .path(&format!("/products/{}", product.id))
+        .reply(&filter)
+        .await;
+    let stool: ProductResponse = serde_json::from_slice(stool.body()).expect("product");
+    assert!(stool.attributes.is_empty());
+}
+
+#[tokio::test]
+async fn listings_filter_and_count_by_attribute() {
+    let test_db = setup_postgres();
+    let filter = attribute_filter(test_db.pool.clone());
+    define(
+        &filter,
+        json!({
+            "code": "material", "name": "Material", "value_type": "text",
+            "allowed_values": ["steel", "oak", "glass"]
+        }),
+    )
+    .await;
+    define(
+        &filter,
+        json!({ "code": "wattage", "name": "Wattage", "value_type": "number" }),
+    )
+    .await;
+    define(
+        &filter,
+        json!({ "code": "cordless", "name": "Cordless", "value_type": "boolean" }),
+    )
+    .await;
+
+    let rejected = |attributes: Value| {
+        warp::test::request()
+            .method("POST")
+            .path("/products")
+            .header("authorization", role_token(Role::Staff))
+            .json(&json!({ "product_name": "Nope", "price": "1.00", "stock": 1, "attributes": attributes }))
+            .reply(&filter)
+    };
+    assert_eq!(rejected(json!({ "colour": "red" })).await.status(), 400);
+    assert_eq!(
+        rejected(json!({ "material": "plastic" })).await.status(),
+        400
+    );
+    assert_eq!(rejected(json!({ "wattage": "1200" })).await.status(), 400);
+
+    let kettle = create_product(
+        &filter,
+        "Steel Kettle",
+        json!({ "material": "steel", "wattage": 2200, "cordless": true }),
+    )
+    .await;
+    assert_eq!(kettle.attributes["wattage"], json!(2200));
+    create_product(
+        &filter,
+        "Glass Kettle",
+        json!({ "material": "glass", "wattage": 2200, "cordless": true }),
+    )
+    .await;
+    create_product(
+        &filter,
+        "Steel Toaster",
+        json!({ "material": "steel", "wattage": 900 }),
+    )
+    .await;
+    create_product(&filter, "Oak Board", json!({ "material": "oak" })).await;
+
+    let everything = list(&filter, "").await;
+    assert_eq!(everything.page.total, 4);
+    assert_eq!(
+        facets(&everything),
+        [
+            ("cordless", vec![(json!(true), 2)]),
+            (
+                "material",
+                vec![(json!("steel"), 2), (json!("glass"), 1), (json!("oak"), 1)]
+            ),
+            ("wattage", vec![(json!(2200), 2), (json!(900), 1)]),
+        ]
+    );
+
+    // Facets describe only the products that match
+    let steel = list(&filter, "?attr.material=steel").await;
+    assert_eq!(names(&steel), ["Steel Kettle", "Steel Toaster"]);
+    assert_eq!(
+        facets(&steel),
+        [
+            ("cordless", vec![(json!(true), 1)]),
+            ("material", vec![(json!("steel"), 2)]),
+            ("wattage", vec![(json!(900), 1), (json!(2200), 1)]),
+        ]
+    );
+
+    // Values of one attribute widen the match, different attributes narrow it
+    let either = list(&filter, "?attr.material=steel&attr.material=glass").await;
+    assert_eq!(either.page.total, 3);
+    let strong = list(
+        &filter,
+        "?attr.material=steel&attr.material=glass&attr.wattage=2200&attr.cordless=true",
+    )
+    .await;
+    assert_eq!(names(&strong), ["Glass Kettle", "Steel Kettle"]);
+
+    let paged = list(&filter, "?limit=1&attr.material=steel").await;
+    assert_eq!(
+        paged.page.links.next.as_deref(),
+        Some("/products?limit=1&offset=1&attr.material=steel")
+    );
+
+    for bad in [
+        "?attr.colour=red",
+        "?attr.wattage=lots",
+        "?attr.cordless=maybe",
+    ] {
+        let resp = warp::test::request()
+            .method("GET")
+            .path(&format!("/products{bad}"))
+            .reply(&filter)
+            .await;
+        assert_eq!(resp.status(), 400, "{bad}");
+    }
+
+    // Updating replaces the whole set of attributes
+    let updated = warp::test::request()
+        .method("PUT")
+        .path(&format!("/products/{}", kettle.id))
+        .header("authorization", role_token(Role::Staff))
+        .json(&json!({ "attributes": { "material": "glass" } }))
+        .reply(&filter)
+        .await;
+    assert_eq!(updated.status(), 200, "{:?}", updated.body());
+    let updated: ProductResponse = serde_json::from_slice(updated.body()).expect("product");
+    assert_eq!(
+        updated.attributes.into_iter().collect::<Vec<_>>(),
+        [("material".to_string(), json!("glass"))]
+    );
+    let glass = list(&filter, "?attr.material=glass").await;
+    assert_eq!(glass.page.total, 2);
+}
@@ -0,0 +1,279 @@
+mod common {
+    pub mod auth;
+    pub mod blobs;
+    pub mod db;
+    pub mod products;
+}
+
+use std::io::Cursor;
+use std::sync::Arc;
+
+use common::auth::{role_token, test_auth_config};
+use common::blobs::test_blob_store;
+use common::db::setup_postgres;
+use common::products::create_product;
+use firefleeb_api::config::AppConfig;
+use firefleeb_api::db::PgPool;
+use firefleeb_api::handlers::dtos::{ProductImageResponse, ProductResponse};
+use firefleeb_api::routes::{handle_rejection, product_routes::product_routes};
+use firefleeb_api::storage::SharedBlobStore;
+use firefleeb_api::types::role::Role;
+use image::{ImageFormat, Rgba, RgbaImage};
+use serde_json::json;
+use uuid::Uuid;
+use warp::Filter;
+
+const BOUNDARY: &str = "firefleeb-test-boundary";
+
+fn image_filter(
+    pool: PgPool,
+    blobs: SharedBlobStore,
+    config: AppConfig,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = std::convert::Infallible> + Clone {
+    product_routes(pool, Arc::new(config), blobs).recover(handle_rejection)
+}
+
+fn png(width: u32, height: u32) -> Vec<u8> {
+    let image = RgbaImage::from_pixel(width, height, Rgba([200, 40, 40, 255]));
+    let mut out = Cursor::new(Vec::new());
+    image
+        .write_to(&mut out, ImageFormat::Png)
+        .expect("encode png");
+    out.into_inner()
+}
+
+/// A multipart body with a single `image` file field.
+fn multipart(content_type: &str, bytes: &[u8]) -> Vec<u8> {
+    let mut body = format!(
+        "--{BOUNDARY}\r\n\
+         Content-Disposition: form-data; name=\"image\"; filename=\"upload\"\r\n\
+         Content-Type: {content_type}\r\n\r\n"
+    )
+    .into_bytes();
+    body.extend_from_slice(bytes);
+    body.extend_from_slice(format!("\r\n--{BOUNDARY}--\r\n").as_bytes());
+    body
+}
+
+async fn upload<F>(
+    filter: &F,
+    product_id: Uuid,
+    content_type: &str,
+    bytes: &[u8],
+) -> warp::http::Response<warp::hyper::body::Bytes>
+where
+    F: Filter + Clone + 'static,
+    F::Extract: warp::Reply + Send,
+{
+    warp::test::request()
+        .method("POST")
+        .path(&format!("/products/{product_id}/images"))
+        .header("authorization", role_token(Role::Staff))
+        .header(
+            "content-type",
+            format!("multipart/form-data; boundary={BOUNDARY}"),
+        )
+        .body(multipart(content_type, bytes))
+        .reply(filter)
+        .await
+}
+
+#[tokio::test]
+async fn uploaded_image_is_served_with_thumbnails_and_cache_headers() {
+    let test_db = setup_postgres();
+    let blobs = test_blob_store();
+    let filter = image_filter(
+        test_db.pool.clone(),
+        blobs.store.clone(),
+        AppConfig::new(test_auth_config()),
+    );
+    let product_id = create_product(&filter, "Pictured Widget", "9.99", 1).await;
+
+    let original = png(600, 300);
+    let resp = upload(&filter, product_id, "image/png", &original).await;
+    assert_eq!(resp.status(), 201, "{:?}", resp.body());
+    let image: ProductImageResponse = serde_json::from_slice(resp.body()).expect("image");
+    assert_eq!((image.width, image.height), (600, 300));
+    assert_eq!(image.content_type, "image/png");
+    assert_eq!(
+        image.thumbnails.keys().copied().collect::<Vec<_>>(),
+        [128, 512]
+    );
+
+    let served = warp::test::request()
+        .method("GET")
+        .path(&image.url)
+        .reply(&filter)
+        .await;
+    assert_eq!(served.status(), 200);
+    assert_eq!(served.headers()["content-type"], "image/png");
+    assert!(
+        served.headers()["cache-control"]
+            .to_str()
+            .expect("header")
+            .contains("immutable")
+    );
+    assert_eq!(served.body().as_ref(), original.as_slice());
+
+    let etag = served.headers()["etag"].clone();
+    let revalidated = warp::test::request()
+        .method("GET")
+        .path(&image.url)
+        .header("if-none-match", etag)
+        .reply(&filter)
+        .await;
+    assert_eq!(revalidated.status(), 304);
+    assert!(revalidated.body().is_empty());
+
+    let thumbnail = warp::test::request()
+        .method("GET")
+        .path(&image.thumbnails[&128])
+        .reply(&filter)
+        .await;
+    assert_eq!(thumbnail.status(), 200);
+    let decoded = image::load_from_memory(thumbnail.body()).expect("thumbnail decodes");
+    assert_eq!((decoded.width(), decoded.height()), (128, 64));
+
+    let unknown_size = warp::test::request()
+        .method("GET")
+        .path(&format!("{}/thumbnails/64", image.url))
+        .reply(&filter)
+        .await;
+    assert_eq!(unknown_size.status(), 404);
+
+    let product = warp::test::request()
+        .method("GET")
+        .path(&format!("/products/{product_id}"))
+        .reply(&filter)
+        .await;
+    let product: ProductResponse = serde_json::from_slice(product.body()).expect("product");
+    assert_eq!(product.images.len(), 1);
+    assert_eq!(product.images[0].url, image.url);
+}
+
+#[tokio::test]
+async fn uploads_are_checked_for_type_and_size() {
+    let test_db = setup_postgres();
+    let blobs = test_blob_store();
+    let mut config = AppConfig::new(test_auth_config());
+    config.images.max_upload_bytes = 4 * 1024;
+    let filter = image_filter(test_db.pool.clone(), blobs.store.clone(), config);
+    let product_id = create_product(&filter, "Strict Widget", "9.99", 1).await;
+
+    let text = upload(&filter, product_id, "image/png", b"definitely not an image").await;
+    assert_eq!(text.status(), 415);
+
+    let mislabelled = upload(&filter, product_id, "image/jpeg", &png(8, 8)).await;
+    assert_eq!(mislabelled.status(), 415);
+
+    // Noise does not compress, so this PNG is well over the 4 KiB limit
+    let noisy = RgbaImage::from_fn(64, 64, |x, y| {
+        let v = (x * 7919 + y * 104729) as u8;
+        Rgba([v, v.wrapping_mul(31), v.wrapping_mul(17), 255])
+    });
+    let mut out = Cursor::new(Vec::new());
+    noisy.write_to(&mut out, ImageFormat::Png).expect("encode");
+    let too_big = upload(&filter, product_id, "image/png", out.get_ref()).await;
+    assert_eq!(too_big.status(), 413);
+
+    let missing_product = upload(&filter, Uuid::new_v4(), "image/png", &png(8, 8)).await;
+    assert_eq!(missing_product.status(), 404);
+
+    let not_multipart = warp::test::request()
+        .method("POST")
+        .path(&format!("/products/{product_id}/images"))
+        .header("authorization", role_token(Role::Staff))
+        .json(&json!({ "image": "nope" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(not_multipart.status(), 400);
+}
+
+#[tokio::test]
+async fn purging_a_product_removes_its_image_files() {
+    let test_db = setup_postgres();
+    let blobs = test_blob_store();
+    let filter = image_filter(
+        test_db.pool.clone(),
+        blobs.store.clone(),
+        AppConfig::new(test_auth_config()),
+    );
+    let product_id = create_product(&filter, "Short-lived Widget", "9.99", 1).await;
+    let kept_id = create_product(&filter, "Kept Widget", "9.99", 1).await;
+
+    for id in [product_id, kept_id] {
+        let resp = upload(&filter, id, "image/png", &png(32, 32)).await;
+        assert_eq!(resp.status(), 201, "{:?}", resp.body());
+    }
+    let product_dir = blobs.root.join("products").join(product_id.to_string());
+    assert!(product_dir.is_dir());
+
+    // Archiving keeps the files, since the product can still be restored
+    let archived = warp::test::request()
+        .method("DELETE")
+        .path(&format!("/products/{product_id}"))
+        .header("authorization", role_token(Role::Admin))
+        .reply(&filter)
+        .await;
+    assert_eq!(archived.status(), 204);
+    assert!(product_dir.is_dir());
+
+    let purged = warp::test::request()
+        .method("POST")
+        .path(&format!("/products/{product_id}/purge"))
+        .header("authorization", role_token(Role::Admin))
+        .reply(&filter)
+        .await;
+    assert_eq!(purged.status(), 204);
+    assert!(!product_dir.exists());
+    assert!(
+        blobs
+            .root
+            .join("products")
+            .join(kept_id.to_string())
+            .is_dir()
+    );
+}
+
+#[tokio::test]
+async fn deleting_an_image_removes_it() {
+    let test_db = setup_postgres();
+    let blobs = test_blob_store();
+    let filter = image_filter(
+        test_db.pool.clone(),
+        blobs.store.clone(),
+        AppConfig::new(test_auth_config()),
+    );
+    let product_id = create_product(&filter, "Retouched Widget", "9.99", 1).await;
+    let resp = upload(&filter, product_id, "image/png", &png(32, 32)).await;
+    let image: ProductImageResponse = serde_json::from_slice(resp.body()).expect("image");
+
+    let as_customer = warp::test::request()
+        .method("DELETE")
+        .path(&image.url)
+        .header("authorization", role_token(Role::Customer))
+        .reply(&filter)
+        .await;
+    assert_eq!(as_customer.status(), 403);
+
+    let deleted = warp::test::request()
+        .method("DELETE")
+        .path(&image.url)
+        .header("authorization", role_token(Role::Staff))
+        .reply(&filter)
+        .await;
+    assert_eq!(deleted.status(), 204);
+
+    let gone = warp::test::request()
+        .method("GET")
+        .path(&image.url)
+        .reply(&filter)
+        .await;
+    assert_eq!(gone.status(), 404);
+    assert!(
+        !blobs
+            .root
+            .join(format!("products/{product_id}/images/{}", image.id))
+            .exists()
+    );
+}
@@ -0,0 +1,227 @@
+mod common {
+    pub mod auth;
+    pub mod blobs;
+    pub mod config;
+    pub mod db;
+    pub mod products;
+}
+
+use bigdecimal::BigDecimal;
+use chrono::{Duration, Utc};
+use common::auth::{role_token, test_auth_config};
+use common::blobs::test_blob_store;
+use common::config::app_config;
+use common::db::setup_postgres;
+use common::products::create_product;
+use firefleeb_api::db::PgPool;
+use firefleeb_api::handlers::dtos::{ProductPriceResponse, ProductResponse};
+use firefleeb_api::routes::{handle_rejection, product_routes::product_routes};
+use firefleeb_api::services::product_price_service::apply_scheduled_prices;
+use firefleeb_api::types::role::Role;
+use serde_json::json;
+use std::str::FromStr;
+use uuid::Uuid;
+use warp::Filter;
+
+fn price_filter(
+    pool: PgPool,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = std::convert::Infallible> + Clone {
+    product_routes(
+        pool,
+        app_config(test_auth_config()),
+        test_blob_store().store.clone(),
+    )
+    .recover(handle_rejection)
+}
+
+fn decimal(raw: &str) -> BigDecimal {
+    BigDecimal::from_str(raw).expect("decimal")
+}
+
+async fn history<F>(filter: &F, product_id: Uuid) -> Vec<ProductPriceResponse>
+where
+    F: Filter + Clone + 'static,
+    F::Extract: warp::Reply + Send,
+{
+    let resp = warp::test::request()
+        .method("GET")
+        .path(&format!("/products/{product_id}/price-history"))
+        .reply(filter)
+        .await;
+    assert_eq!(resp.status(), 200, "{:?}", resp.body());
+    serde_json::from_slice(resp.body()).expect("history")
+}
+
+async fn current_price<F>(filter: &F, product_id: Uuid) -> BigDecimal
+where
+    F: Filter + Clone + 'static,
+    F::Extract: warp::Reply + Send,
+{
+    let resp = warp::test::request()
+        .method("GET")
+        .path(&format!("/products/{product_id}"))
+        .reply(filter)
+        .await;
+    serde_json::from_slice::<ProductResponse>(resp.body())
+        .expect("product")
+        .price
+}
+
+#[tokio::test]
+async fn price_changes_are_recorded_in_the_history() {
+    let test_db = setup_postgres();
+    let filter = price_filter(test_db.pool.clone());
+    let product_id = create_product(&filter, "Tracked Widget", "10.00", 1).await;
+
+    let opening = history(&filter, product_id).await;
+    assert_eq!(opening.len(), 1);
+    assert_eq!(opening[0].price, decimal("10.00"));
+    assert_eq!(
+        (opening[0].source.as_str(), opening[0].status.as_str()),
+        ("manual", "current")
+    );
+
+    for payload in [json!({ "price": "12.50" }), json!({ "stock": 4 })] {
+        let resp = warp::test::request()
+            .method("PUT")
+            .path(&format!("/products/{product_id}"))
+            .header("authorization", role_token(Role::Staff))
+            .json(&payload)
+            .reply(&filter)
+            .await;
+        assert_eq!(resp.status(), 200, "{:?}", resp.body());
+    }
+
+    // Only the price change opens a new window; the stock update leaves the history alone
+    let prices = history(&filter, product_id).await;
+    assert_eq!(prices.len(), 2);
+    assert_eq!(prices[0].price, decimal("12.50"));
+    assert_eq!(prices[0].status, "current");
+    assert!(prices[0].valid_until.is_none());
+    assert_eq!(prices[1].price, decimal("10.00"));
+    assert_eq!(prices[1].status, "past");
+    assert_eq!(prices[1].valid_until, Some(prices[0].valid_from));
+
+    let missing = warp::test::request()
+        .method("GET")
+        .path(&format!("/products/{}/price-history", Uuid::new_v4()))
+        .reply(&filter)
+        .await;
+    assert_eq!(missing.status(), 404);
+}
+
+#[tokio::test]
+async fn scheduled_prices_apply_during_their_window() {
+    let test_db = setup_postgres();
+    let pool = test_db.pool.clone();
+    let filter = price_filter(pool.clone());
+    let product_id = create_product(&filter, "Weekend Widget", "10.00", 1).await;
+
+    let now = Utc::now();
+    let sale = json!({
+        "price": "7.50",
+        "valid_from": now + Duration::days(1),
+        "valid_until": now + Duration::days(4),
+    });
+    let schedule = |token: String, body: serde_json::Value| {
+        warp::test::request()
+            .method("POST")
+            .path(&format!("/products/{product_id}/prices"))
+            .header("authorization", token)
+            .json(&body)
+            .reply(&filter)
+    };
+
+    assert_eq!(
+        schedule(role_token(Role::Staff), sale.clone())
+            .await
+            .status(),
+        403
+    );
+    let started = json!({ "price": "7.50", "valid_from": now - Duration::hours(1) });
+    assert_eq!(
+        schedule(role_token(Role::Admin), started).await.status(),
+        400
+    );
+    let backwards = json!({
+        "price": "7.50",
+        "valid_from": now + Duration::days(2),
+        "valid_until": now + Duration::days(1),
+    });
+    assert_eq!(
+        schedule(role_token(Role::Admin), backwards).await.status(),
+        400
+    );
+
+    let resp = schedule(role_token(Role::Admin), sale).await;
+    assert_eq!(resp.status(), 201, "{:?}", resp.body());
+    let scheduled: ProductPriceResponse = serde_json::from_slice(resp.body()).expect("price");
+    assert_eq!(
+        (scheduled.source.as_str(), scheduled.status.as_str()),
+        ("scheduled", "upcoming")
+    );
+
+    assert_eq!(
+        apply_scheduled_prices(pool.clone(), now)
+            .await
+            .expect("apply"),
+        0
+    );
+    assert_eq!(current_price(&filter, product_id).await, decimal("10.00"));
+
+    // The sale takes over once it starts, and the regular price returns when it ends
+    let during = now + Duration::days(2);
+    assert_eq!(
+        apply_scheduled_prices(pool.clone(), during)
+            .await
+            .expect("apply"),
+        1
+    );
+    assert_eq!(current_price(&filter, product_id).await, decimal("7.50"));
+
+    let after = now + Duration::days(5);
+    assert_eq!(
+        apply_scheduled_prices(pool.clone(), after)
+            .await
+            .expect("apply"),
+        1
+    );
+    assert_eq!(current_price(&filter, product_id).await, decimal("10.00"));
+}
+
+#[tokio::test]
+async fn only_upcoming_scheduled_prices_can_be_cancelled() {
+    let test_db = setup_postgres();
+    let filter = price_filter(test_db.pool.clone());
+    let product_id = create_product(&filter, "Fickle Widget", "10.00", 1).await;
+
+    let resp = warp::test::request()
+        .method("POST")
+        .path(&format!("/products/{product_id}/prices"))
+        .header("authorization", role_token(Role::Admin))
+        .json(&json!({ "price": "11.00", "valid_from": Utc::now() + Duration::days(7) }))
+        .reply(&filter)
+        .await;
+    let scheduled: ProductPriceResponse = serde_json::from_slice(resp.body()).expect("price");
+    let manual_id = history(&filter, product_id)
+        .await
+        .into_iter()
+        .find(|price| price.source == "manual")
+        .expect("manual price")
+        .id;
+
+    let cancel = |price_id: Uuid| {
+        warp::test::request()
+            .method("DELETE")
+            .path(&format!("/products/{product_id}/prices/{price_id}"))
+            .header("authorization", role_token(Role::Admin))
+            .reply(&filter)
+    };
+    assert_eq!(cancel(manual_id).await.status(), 409);
+    assert_eq!(cancel(Uuid::new_v4()).await.status(), 404);
+    assert_eq!(cancel(scheduled.id).await.status(), 204);
+
+    let prices = history(&filter, product_id).await;
+    assert_eq!(prices.len(), 1);
+    assert_eq!(prices[0].id, manual_id);
+}
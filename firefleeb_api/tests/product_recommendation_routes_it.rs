@@ -0,0 +1,238 @@
+mod common {
+    pub mod auth;
+    pub mod blobs;
+    pub mod config;
+    pub mod db;
+    pub mod products;
+    pub mod users;
+}
+
+use chrono::Utc;
+use common::auth::{bearer_token, role_token, test_auth_config};
+use common::blobs::test_blob_store;
+use common::config::app_config;
+use common::db::setup_postgres;
+use common::products::create_product;
+use common::users::insert_user;
+use firefleeb_api::db::PgPool;
+use firefleeb_api::handlers::dtos::{CartResponse, RecommendationsResponse};
+use firefleeb_api::models::User;
+use firefleeb_api::routes::{
+    cart_routes::cart_routes, handle_rejection, product_routes::product_routes,
+    recommendation_routes::recommendation_routes,
+};
+use firefleeb_api::services::product_recommendation_service;
+use firefleeb_api::types::role::Role;
+use serde_json::json;
+use uuid::Uuid;
+use warp::Filter;
+
+fn recommendation_filter(
+    pool: PgPool,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = std::convert::Infallible> + Clone {
+    let config = app_config(test_auth_config());
+    recommendation_routes(pool.clone(), config.clone())
+        .or(cart_routes(pool.clone(), config.clone()))
+        .or(product_routes(
+            pool,
+            config,
+            test_blob_store().store.clone(),
+        ))
+        .recover(handle_rejection)
+}
+
+/// A new cart for the user holding one of each product, checked out when `check_out` is set.
+async fn fill_cart<F>(filter: &F, user: &User, product_ids: &[Uuid], check_out: bool) -> Uuid
+where
+    F: Filter + Clone + 'static,
+    F::Extract: warp::Reply + Send,
+{
+    let token = bearer_token(user.id, Role::Customer);
+    let cart = warp::test::request()
+        .method("POST")
+        .path("/carts")
+        .header("authorization", &token)
+        .json(&json!({ "user_id": user.id }))
+        .reply(filter)
+        .await;
+    assert_eq!(cart.status(), 200, "{:?}", cart.body());
+    let cart_id = serde_json::from_slice::<CartResponse>(cart.body())
+        .expect("cart")
+        .cart_id;
+    for product_id in product_ids {
+        let added = warp::test::request()
+            .method("POST")
+            .path(&format!("/carts/{cart_id}/items"))
+            .header("authorization", &token)
+            .json(&json!({ "item_id": product_id, "quantity": 1, "unit_price": "5.00" }))
+            .reply(filter)
+            .await;
+        assert_eq!(added.status(), 201, "{:?}", added.body());
+    }
+    if check_out {
+        let checked_out = warp::test::request()
+            .method("POST")
+            .path(&format!("/carts/{cart_id}/checkout"))
+            .header("authorization", &token)
+            .reply(filter)
+            .await;
+        assert_eq!(checked_out.status(), 200, "{:?}", checked_out.body());
+    }
+    cart_id
+}
+
+/// `(product_id, score)` of each recommendation, scores rounded to three decimals.
+fn scored(resp: &RecommendationsResponse) -> Vec<(Uuid, f64)> {
+    resp.items
+        .iter()
+        .map(|item| (item.product.id, (item.score * 1000.0).round() / 1000.0))
+        .collect()
+}
+
+#[tokio::test]
+async fn products_recommend_what_was_bought_with_them() {
+    let test_db = setup_postgres();
+    let pool = test_db.pool.clone();
+    let filter = recommendation_filter(pool.clone());
+    let kettle = create_product(&filter, "Kettle", "5.00", 10).await;
+    let mug = create_product(&filter, "Mug", "5.00", 10).await;
+    let tea = create_product(&filter, "Tea", "5.00", 10).await;
+    let toaster = create_product(&filter, "Toaster", "5.00", 10).await;
+
+    let shoppers: Vec<User> = (0..4)
+        .map(|index| insert_user(&pool, &format!("shopper{index}@example.com")))
+        .collect();
+    fill_cart(&filter, &shoppers[0], &[kettle, mug, tea], true).await;
+    fill_cart(&filter, &shoppers[1], &[kettle, mug], true).await;
+    fill_cart(&filter, &shoppers[2], &[kettle, toaster], true).await;
+    // Carts that were never checked out do not count
+    fill_cart(&filter, &shoppers[3], &[kettle, tea], false).await;
+
+    let recommendations = |product_id: Uuid, query: &'static str| {
+        warp::test::request()
+            .method("GET")
+            .path(&format!("/products/{product_id}/recommendations{query}"))
+            .reply(&filter)
+    };
+    let before: RecommendationsResponse =
+        serde_json::from_slice(recommendations(kettle, "").await.body()).expect("recommendations");
+    assert!(before.items.is_empty(), "nothing until the job has run");
+
+    let pairs = product_recommendation_service::rebuild(pool.clone(), Utc::now())
+        .await
+        .expect("rebuild");
+    assert_eq!(pairs, 8);
+
+    let resp = recommendations(kettle, "").await;
+    assert_eq!(resp.status(), 200, "{:?}", resp.body());
+    let for_kettle: RecommendationsResponse =
+        serde_json::from_slice(resp.body()).expect("recommendations");
+    assert_eq!(
+        scored(&for_kettle),
+        [(mug, 0.667), (tea, 0.333), (toaster, 0.333)]
+    );
+    assert_eq!(for_kettle.items[0].product.product_name, "Mug");
+
+    let for_tea: RecommendationsResponse =
+        serde_json::from_slice(recommendations(tea, "?limit=1").await.body())
+            .expect("recommendations");
+    assert_eq!(scored(&for_tea), [(kettle, 1.0)]);
+
+    // Out of stock products are left out
+    let sold_out = warp::test::request()
+        .method("PUT")
+        .path(&format!("/products/{toaster}"))
+        .header("authorization", role_token(Role::Staff))
+        .json(&json!({ "stock": 0 }))
+        .reply(&filter)
+        .await;
+    assert_eq!(sold_out.status(), 200, "{:?}", sold_out.body());
+    let in_stock: RecommendationsResponse =
+        serde_json::from_slice(recommendations(kettle, "").await.body()).expect("recommendations");
+    assert_eq!(scored(&in_stock), [(mug, 0.667), (tea, 0.333)]);
+
+    // So are products whose remaining stock is held by other carts
+    let hoarder = insert_user(&pool, "hoarder@example.com");
+    let hoard = fill_cart(&filter, &hoarder, &[], false).await;
+    let held = warp::test::request()
+        .method("POST")
+        .path(&format!("/carts/{hoard}/items"))
+        .header("authorization", bearer_token(hoarder.id, Role::Customer))
+        .json(&json!({ "item_id": tea, "quantity": 8, "unit_price": "5.00" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(held.status(), 201, "{:?}", held.body());
+    let available: RecommendationsResponse =
+        serde_json::from_slice(recommendations(kettle, "").await.body()).expect("recommendations");
+    assert_eq!(scored(&available), [(mug, 0.667)]);
+
+    assert_eq!(recommendations(Uuid::new_v4(), "").await.status(), 404);
+    assert_eq!(recommendations(kettle, "?limit=0").await.status(), 400);
+    assert_eq!(recommendations(kettle, "?limit=21").await.status(), 400);
+}
+
+#[tokio::test]
+async fn carts_recommend_what_they_do_not_hold_yet() {
+    let test_db = setup_postgres();
+    let pool = test_db.pool.clone();
+    let filter = recommendation_filter(pool.clone());
+    let kettle = create_product(&filter, "Kettle", "5.00", 10).await;
+    let mug = create_product(&filter, "Mug", "5.00", 10).await;
+    let tea = create_product(&filter, "Tea", "5.00", 10).await;
+    let spoon = create_product(&filter, "Spoon", "5.00", 10).await;
+
+    let buyer = insert_user(&pool, "buyer@example.com");
+    let other = insert_user(&pool, "other@example.com");
+    fill_cart(&filter, &buyer, &[kettle, mug, tea], true).await;
+    fill_cart(&filter, &buyer, &[kettle, mug], true).await;
+    fill_cart(&filter, &buyer, &[mug, spoon], true).await;
+    product_recommendation_service::rebuild(pool.clone(), Utc::now())
+        .await
+        .expect("rebuild");
+
+    let shopper = insert_user(&pool, "shopper@example.com");
+    let cart_id = fill_cart(&filter, &shopper, &[kettle, mug], false).await;
+    let recommendations = |token: Option<String>| {
+        let request = warp::test::request()
+            .method("GET")
+            .path(&format!("/carts/{cart_id}/recommendations"));
+        match token {
+            Some(token) => request.header("authorization", token),
+            None => request,
+        }
+        .reply(&filter)
+    };
+
+    let resp = recommendations(Some(bearer_token(shopper.id, Role::Customer))).await;
+    assert_eq!(resp.status(), 200, "{:?}", resp.body());
+    let for_cart: RecommendationsResponse =
+        serde_json::from_slice(resp.body()).expect("recommendations");
+    // Tea: half of the kettle's carts plus a third of the mug's; spoon: a third of the mug's
+    assert_eq!(scored(&for_cart), [(tea, 0.833), (spoon, 0.333)]);
+
+    assert_eq!(recommendations(None).await.status(), 401);
+    assert_eq!(
+        recommendations(Some(bearer_token(other.id, Role::Customer)))
+            .await
+            .status(),
+        403
+    );
+    let missing = warp::test::request()
+        .method("GET")
+        .path(&format!("/carts/{}/recommendations", Uuid::new_v4()))
+        .header("authorization", bearer_token(shopper.id, Role::Customer))
+        .reply(&filter)
+        .await;
+    assert_eq!(missing.status(), 404);
+
+    let empty_cart = fill_cart(&filter, &other, &[], false).await;
+    let empty = warp::test::request()
+        .method("GET")
+        .path(&format!("/carts/{empty_cart}/recommendations"))
+        .header("authorization", bearer_token(other.id, Role::Customer))
+        .reply(&filter)
+        .await;
+    let empty: RecommendationsResponse =
+        serde_json::from_slice(empty.body()).expect("recommendations");
+    assert!(empty.items.is_empty());
+}
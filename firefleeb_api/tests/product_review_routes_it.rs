@@ -0,0 +1,351 @@
+mod common {
+    pub mod auth;
+    pub mod blobs;
+    pub mod config;
+    pub mod db;
+    pub mod products;
+    pub mod users;
+}
+
+use std::str::FromStr;
+
+use bigdecimal::BigDecimal;
+use common::auth::{bearer_token, role_token, test_auth_config};
+use common::blobs::test_blob_store;
+use common::config::app_config;
+use common::db::setup_postgres;
+use common::products::create_product;
+use common::users::insert_user;
+use firefleeb_api::db::{PgPool, cart_item_repository, get_conn, product_variant_repository};
+use firefleeb_api::handlers::dtos::{CartResponse, PageResponse, ProductResponse, ReviewResponse};
+use firefleeb_api::models::{NewCartItem, User};
+use firefleeb_api::routes::{
+    cart_routes::cart_routes, handle_rejection, product_routes::product_routes,
+    review_routes::review_routes,
+};
+use firefleeb_api::services::user_service;
+use firefleeb_api::types::role::Role;
+use serde_json::json;
+use uuid::Uuid;
+use warp::Filter;
+
+fn review_filter(
+    pool: PgPool,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = std::convert::Infallible> + Clone {
+    let config = app_config(test_auth_config());
+    review_routes(pool.clone(), config.clone())
+        .or(cart_routes(pool.clone(), config.clone()))
+        .or(product_routes(
+            pool,
+            config,
+            test_blob_store().store.clone(),
+        ))
+        .recover(handle_rejection)
+}
+
+/// Put one of the product in a new cart for the user and check it out. Returns the cart id.
+async fn buy<F>(filter: &F, user: &User, product_id: Uuid) -> Uuid
+where
+    F: Filter + Clone + 'static,
+    F::Extract: warp::Reply + Send,
+{
+    let token = bearer_token(user.id, Role::Customer);
+    let cart = warp::test::request()
+        .method("POST")
+        .path("/carts")
+        .header("authorization", &token)
+        .json(&json!({ "user_id": user.id }))
+        .reply(filter)
+        .await;
+    assert_eq!(cart.status(), 200, "{:?}", cart.body());
+    let cart_id = serde_json::from_slice::<CartResponse>(cart.body())
+        .expect("cart")
+        .cart_id;
+    let added = warp::test::request()
+        .method("POST")
+        .path(&format!("/carts/{cart_id}/items"))
+        .header("authorization", &token)
+        .json(&json!({ "item_id": product_id, "quantity": 1, "unit_price": "12.00" }))
+        .reply(filter)
+        .await;
+    assert_eq!(added.status(), 201, "{:?}", added.body());
+    let checked_out = warp::test::request()
+        .method("POST")
+        .path(&format!("/carts/{cart_id}/checkout"))
+        .header("authorization", &token)
+        .reply(filter)
+        .await;
+    assert_eq!(checked_out.status(), 200, "{:?}", checked_out.body());
+    cart_id
+}
+
+async fn moderate<F>(filter: &F, review_id: Uuid, status: &str) -> u16
+where
+    F: Filter + Clone + 'static,
+    F::Extract: warp::Reply + Send,
+{
+    warp::test::request()
+        .method("PUT")
+        .path(&format!("/reviews/{review_id}"))
+        .header("authorization", role_token(Role::Staff))
+        .json(&json!({ "status": status }))
+        .reply(filter)
+        .await
+        .status()
+        .as_u16()
+}
+
+async fn product<F>(filter: &F, product_id: Uuid) -> ProductResponse
+where
+    F: Filter + Clone + 'static,
+    F::Extract: warp::Reply + Send,
+{
+    let resp = warp::test::request()
+        .method("GET")
+        .path(&format!("/products/{product_id}"))
+        .reply(filter)
+        .await;
+    assert_eq!(resp.status(), 200, "{:?}", resp.body());
+    serde_json::from_slice(resp.body()).expect("product")
+}
+
+#[tokio::test]
+async fn customers_review_products_once() {
+    let test_db = setup_postgres();
+    let pool = test_db.pool.clone();
+    let filter = review_filter(pool.clone());
+    let product_id = create_product(&filter, "Review Kettle", "12.00", 10).await;
+    let buyer = insert_user(&pool, "buyer@example.com");
+    let browser = insert_user(&pool, "browser@example.com");
+    buy(&filter, &buyer, product_id).await;
+
+    let review = |user_id: Uuid, product_id: Uuid, body: serde_json::Value| {
+        warp::test::request()
+            .method("POST")
+            .path(&format!("/products/{product_id}/reviews"))
+            .header("authorization", bearer_token(user_id, Role::Customer))
+            .json(&body)
+            .reply(&filter)
+    };
+    let good = json!({ "rating": 5, "title": "Boils fast", "body": "Does what it says." });
+
+    let anonymous = warp::test::request()
+        .method("POST")
+        .path(&format!("/products/{product_id}/reviews"))
+        .json(&good)
+        .reply(&filter)
+        .await;
+    assert_eq!(anonymous.status(), 401);
+    for bad in [
+        json!({ "rating": 0, "title": "Hm", "body": "Hm" }),
+        json!({ "rating": 6, "title": "Hm", "body": "Hm" }),
+        json!({ "rating": 3, "title": "  ", "body": "Hm" }),
+        json!({ "rating": 3, "title": "Hm", "body": "" }),
+    ] {
+        assert_eq!(review(buyer.id, product_id, bad).await.status(), 400);
+    }
+    assert_eq!(
+        review(buyer.id, Uuid::new_v4(), good.clone())
+            .await
+            .status(),
+        404
+    );
+
+    let created = review(buyer.id, product_id, good.clone()).await;
+    assert_eq!(created.status(), 201, "{:?}", created.body());
+    let verified: ReviewResponse = serde_json::from_slice(created.body()).expect("review");
+    assert_eq!(
+        (verified.rating, verified.status.as_str(), verified.user_id),
+        (5, "pending", buyer.id)
+    );
+    assert!(verified.verified_purchase);
+    assert_eq!(review(buyer.id, product_id, good).await.status(), 409);
+
+    let unverified = review(
+        browser.id,
+        product_id,
+        json!({ "rating": 2, "title": "Looks flimsy", "body": "Saw it in a shop." }),
+    )
+    .await;
+    assert_eq!(unverified.status(), 201, "{:?}", unverified.body());
+    let unverified: ReviewResponse = serde_json::from_slice(unverified.body()).expect("review");
+    assert!(!unverified.verified_purchase);
+
+    // Nothing is shown or counted until staff approve it
+    let listed = warp::test::request()
+        .method("GET")
+        .path(&format!("/products/{product_id}/reviews"))
+        .reply(&filter)
+        .await;
+    assert_eq!(listed.status(), 200);
+    let page: PageResponse<ReviewResponse> = serde_json::from_slice(listed.body()).expect("page");
+    assert_eq!(page.total, 0);
+    let before = product(&filter, product_id).await;
+    assert_eq!((before.rating_count, before.rating_average), (0, None));
+
+    let missing = warp::test::request()
+        .method("GET")
+        .path(&format!("/products/{}/reviews", Uuid::new_v4()))
+        .reply(&filter)
+        .await;
+    assert_eq!(missing.status(), 404);
+}
+
+#[tokio::test]
+async fn cart_lines_without_a_sale_do_not_verify_a_purchase() {
+    let test_db = setup_postgres();
+    let pool = test_db.pool.clone();
+    let filter = review_filter(pool.clone());
+    let bought = create_product(&filter, "Sold Teapot", "12.00", 10).await;
+    let slipped = create_product(&filter, "Slipped Teapot", "12.00", 10).await;
+    let customer = insert_user(&pool, "slipped@example.com");
+    let cart_id = buy(&filter, &customer, bought).await;
+
+    // A line that reached the checked-out cart without going through checkout
+    {
+        let mut conn = get_conn(&pool).expect("conn");
+        let variant = product_variant_repository::get_default_variant(&mut conn, slipped)
+            .expect("db lookup")
+            .expect("default variant");
+        cart_item_repository::create_cart_item(
+            &mut conn,
+            &NewCartItem {
+                item_id: slipped,
+                cart_id,
+                quantity: 1,
+                unit_price: BigDecimal::from_str("12.00").expect("price"),
+                variant_id: Some(variant.id),
+            },
+        )
+        .expect("cart item");
+    }
+
+    let review = |product_id: Uuid| {
+        warp::test::request()
+            .method("POST")
+            .path(&format!("/products/{product_id}/reviews"))
+            .header("authorization", bearer_token(customer.id, Role::Customer))
+            .json(&json!({ "rating": 4, "title": "Pours well", "body": "No drips." }))
+            .reply(&filter)
+    };
+    for (product_id, verified) in [(bought, true), (slipped, false)] {
+        let resp = review(product_id).await;
+        assert_eq!(resp.status(), 201, "{:?}", resp.body());
+        let created: ReviewResponse = serde_json::from_slice(resp.body()).expect("review");
+        assert_eq!(created.verified_purchase, verified, "{product_id}");
+    }
+}
+
+#[tokio::test]
+async fn moderation_keeps_the_rating_up_to_date() {
+    let test_db = setup_postgres();
+    let pool = test_db.pool.clone();
+    let filter = review_filter(pool.clone());
+    let product_id = create_product(&filter, "Rated Lamp", "12.00", 10).await;
+
+    let mut reviews = Vec::new();
+    for (index, rating) in [5, 4, 2].into_iter().enumerate() {
+        let user = insert_user(&pool, &format!("reviewer{index}@example.com"));
+        if index == 0 {
+            buy(&filter, &user, product_id).await;
+        }
+        let resp = warp::test::request()
+            .method("POST")
+            .path(&format!("/products/{product_id}/reviews"))
+            .header("authorization", bearer_token(user.id, Role::Customer))
+            .json(&json!({ "rating": rating, "title": "Lamp", "body": "A lamp." }))
+            .reply(&filter)
+            .await;
+        assert_eq!(resp.status(), 201, "{:?}", resp.body());
+        reviews.push(serde_json::from_slice::<ReviewResponse>(resp.body()).expect("review"));
+    }
+
+    let queue = |token: String, query: &'static str| {
+        warp::test::request()
+            .method("GET")
+            .path(&format!("/reviews{query}"))
+            .header("authorization", token)
+            .reply(&filter)
+    };
+    assert_eq!(queue(role_token(Role::Customer), "").await.status(), 403);
+    assert_eq!(
+        queue(role_token(Role::Staff), "?status=hidden")
+            .await
+            .status(),
+        400
+    );
+    let pending: PageResponse<ReviewResponse> =
+        serde_json::from_slice(queue(role_token(Role::Staff), "").await.body()).expect("page");
+    assert_eq!(pending.total, 3);
+
+    let customer_moderates = warp::test::request()
+        .method("PUT")
+        .path(&format!("/reviews/{}", reviews[0].id))
+        .header(
+            "authorization",
+            bearer_token(reviews[0].user_id, Role::Customer),
+        )
+        .json(&json!({ "status": "approved" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(customer_moderates.status(), 403);
+    assert_eq!(moderate(&filter, reviews[0].id, "published").await, 400);
+    assert_eq!(moderate(&filter, Uuid::new_v4(), "approved").await, 404);
+
+    for review in &reviews {
+        assert_eq!(moderate(&filter, review.id, "approved").await, 200);
+    }
+    let rated = product(&filter, product_id).await;
+    assert_eq!(rated.rating_count, 3);
+    assert_eq!(
+        rated.rating_average,
+        Some(BigDecimal::from_str("3.67").expect("decimal"))
+    );
+
+    // Rejecting an approved review takes it back out of the rating
+    assert_eq!(moderate(&filter, reviews[2].id, "rejected").await, 200);
+    let rerated = product(&filter, product_id).await;
+    assert_eq!(rerated.rating_count, 2);
+    assert_eq!(
+        rerated.rating_average,
+        Some(BigDecimal::from_str("4.5").expect("decimal"))
+    );
+
+    let shown = |query: &'static str| {
+        warp::test::request()
+            .method("GET")
+            .path(&format!("/products/{product_id}/reviews{query}"))
+            .reply(&filter)
+    };
+    let first: PageResponse<ReviewResponse> =
+        serde_json::from_slice(shown("?limit=1").await.body()).expect("page");
+    assert_eq!(first.total, 2);
+    assert_eq!(first.items[0].id, reviews[1].id, "newest first");
+    assert_eq!(
+        first.links.next.as_deref(),
+        Some(format!("/products/{product_id}/reviews?limit=1&offset=1").as_str())
+    );
+    let verified: PageResponse<ReviewResponse> =
+        serde_json::from_slice(shown("?verified_only=true").await.body()).expect("page");
+    let ids: Vec<Uuid> = verified.items.iter().map(|review| review.id).collect();
+    assert_eq!(ids, [reviews[0].id]);
+
+    let rejected: PageResponse<ReviewResponse> = serde_json::from_slice(
+        queue(role_token(Role::Staff), "?status=rejected")
+            .await
+            .body(),
+    )
+    .expect("page");
+    assert_eq!(rejected.items.len(), 1);
+    assert_eq!(rejected.items[0].id, reviews[2].id);
+
+    // A deleted account takes its reviews out of the rating
+    user_service::delete_user(pool.clone(), reviews[1].user_id)
+        .await
+        .expect("delete user");
+    let remaining = product(&filter, product_id).await;
+    assert_eq!(
+        (remaining.rating_count, remaining.rating_average),
+        (1, Some(BigDecimal::from_str("5").expect("decimal")))
+    );
+}
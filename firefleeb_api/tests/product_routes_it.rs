@@ -1,19 +1,78 @@
-mod common;
+mod common {
+    pub mod auth;
+    pub mod blobs;
+    pub mod config;
+    pub mod db;
+    pub mod users;
+}
 
 use bigdecimal::BigDecimal;
-use common::setup_postgres;
-use firefleeb_api::db::{PgPool, get_conn, product_repository};
-use firefleeb_api::handlers::dtos::ProductResponse;
+use chrono::{Duration, Utc};
+use common::auth::{role_token, test_auth_config};
+use common::blobs::test_blob_store;
+use common::config::app_config;
+use common::db::setup_postgres;
+use common::users::insert_user;
+use firefleeb_api::config::AppConfig;
+use firefleeb_api::db::{
+    PgPool, cart_item_repository, cart_repository, get_conn, product_repository,
+    product_variant_repository, stock_reservation_repository,
+};
+use firefleeb_api::handlers::dtos::{
+    PageResponse, ProductResponse, ProductSearchResponse, ProductSuggestionsResponse,
+    ProductVariantResponse,
+};
+use firefleeb_api::models::{NewCartItem, NewProduct, NewStockReservation};
 use firefleeb_api::routes::{handle_rejection, product_routes::product_routes};
+use firefleeb_api::types::role::Role;
 use serde_json::json;
 use std::str::FromStr;
+use std::sync::Arc;
 use uuid::Uuid;
 use warp::Filter;
 
 fn product_filter(
     pool: PgPool,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = std::convert::Infallible> + Clone {
-    product_routes(pool).recover(handle_rejection)
+    product_filter_with(pool, app_config(test_auth_config()))
+}
+
+fn product_filter_with(
+    pool: PgPool,
+    config: Arc<AppConfig>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = std::convert::Infallible> + Clone {
+    product_routes(pool, config, test_blob_store().store.clone()).recover(handle_rejection)
+}
+
+fn seed_products(pool: &PgPool, products: &[(&str, &str, i32)]) {
+    let mut conn = get_conn(pool).expect("conn");
+    for (name, price, stock) in products {
+        let new_product = NewProduct {
+            product_name: name.to_string(),
+            product_description: None,
+            price: BigDecimal::from_str(price).expect("price"),
+            stock: *stock,
+        };
+        product_repository::create_product(&mut conn, &new_product, None).expect("insert product");
+    }
+}
+
+async fn list_page<F>(filter: &F, path: &str) -> PageResponse<ProductResponse>
+where
+    F: Filter + Clone + 'static,
+    F::Extract: warp::Reply + Send,
+{
+    let resp = warp::test::request()
+        .method("GET")
+        .path(path)
+        .reply(filter)
+        .await;
+    assert_eq!(resp.status(), 200, "{path}: {:?}", resp.body());
+    serde_json::from_slice(resp.body()).expect("page")
+}
+
+fn names(page: &PageResponse<ProductResponse>) -> Vec<&str> {
+    page.items.iter().map(|p| p.product_name.as_str()).collect()
 }
 
 #[tokio::test]
@@ -31,6 +90,7 @@ async fn create_and_get_product_round_trip() {
     let resp = warp::test::request()
         .method("POST")
         .path("/products")
+        .header("authorization", role_token(Role::Staff))
         .json(&payload)
         .reply(&filter)
         .await;
@@ -61,6 +121,7 @@ async fn update_product_via_route_overwrites_fields() {
     let create_resp = warp::test::request()
         .method("POST")
         .path("/products")
+        .header("authorization", role_token(Role::Staff))
         .json(&json!({
             "product_name": "Original Widget",
             "product_description": "First revision",
@@ -83,6 +144,7 @@ async fn update_product_via_route_overwrites_fields() {
     let update_resp = warp::test::request()
         .method("PUT")
         .path(&format!("/products/{id}", id = created.id))
+        .header("authorization", role_token(Role::Staff))
         .json(&update_payload)
         .reply(&filter)
         .await;
@@ -102,7 +164,7 @@ async fn update_product_via_route_overwrites_fields() {
 }
 
 #[tokio::test]
-async fn delete_product_removes_record() {
+async fn deleting_a_product_archives_it_until_purged() {
     let test_db = setup_postgres();
     let pool = test_db.pool.clone();
     let filter = product_filter(pool.clone());
@@ -110,6 +172,7 @@ async fn delete_product_removes_record() {
     let create_resp = warp::test::request()
         .method("POST")
         .path("/products")
+        .header("authorization", role_token(Role::Staff))
         .json(&json!({
             "product_name": "Disposable Widget",
             "product_description": null,
@@ -121,15 +184,73 @@ async fn delete_product_removes_record() {
 
     assert_eq!(create_resp.status(), 200);
     let created: ProductResponse = serde_json::from_slice(create_resp.body()).expect("created");
+    assert!(created.archived_at.is_none());
+
+    let premature_purge = warp::test::request()
+        .method("POST")
+        .path(&format!("/products/{id}/purge", id = created.id))
+        .header("authorization", role_token(Role::Admin))
+        .reply(&filter)
+        .await;
+    assert_eq!(premature_purge.status(), 409);
 
     let delete_resp = warp::test::request()
         .method("DELETE")
         .path(&format!("/products/{id}", id = created.id))
+        .header("authorization", role_token(Role::Admin))
         .reply(&filter)
         .await;
 
     assert_eq!(delete_resp.status(), 204);
 
+    // Archived products keep their row and stay readable by id, but leave the catalog
+    let fetched = warp::test::request()
+        .method("GET")
+        .path(&format!("/products/{id}", id = created.id))
+        .reply(&filter)
+        .await;
+    assert_eq!(fetched.status(), 200);
+    let fetched: ProductResponse = serde_json::from_slice(fetched.body()).expect("product");
+    assert!(fetched.archived_at.is_some());
+    assert_eq!(list_page(&filter, "/products").await.total, 0);
+    assert_eq!(
+        search(&filter, "/products/search?q=disposable").await.total,
+        0
+    );
+
+    let restored = warp::test::request()
+        .method("POST")
+        .path(&format!("/products/{id}/restore", id = created.id))
+        .header("authorization", role_token(Role::Admin))
+        .reply(&filter)
+        .await;
+    assert_eq!(restored.status(), 200);
+    assert_eq!(list_page(&filter, "/products").await.total, 1);
+
+    let archived_again = warp::test::request()
+        .method("DELETE")
+        .path(&format!("/products/{id}", id = created.id))
+        .header("authorization", role_token(Role::Admin))
+        .reply(&filter)
+        .await;
+    assert_eq!(archived_again.status(), 204);
+
+    let staff_purge = warp::test::request()
+        .method("POST")
+        .path(&format!("/products/{id}/purge", id = created.id))
+        .header("authorization", role_token(Role::Staff))
+        .reply(&filter)
+        .await;
+    assert_eq!(staff_purge.status(), 403);
+
+    let purge_resp = warp::test::request()
+        .method("POST")
+        .path(&format!("/products/{id}/purge", id = created.id))
+        .header("authorization", role_token(Role::Admin))
+        .reply(&filter)
+        .await;
+    assert_eq!(purge_resp.status(), 204);
+
     let mut conn = get_conn(&pool).expect("conn");
     let db_record =
         product_repository::get_product_by_id(&mut conn, created.id).expect("db lookup");
@@ -160,3 +281,574 @@ async fn getting_unknown_product_returns_404() {
     let body: serde_json::Value = serde_json::from_slice(resp.body()).expect("json");
     assert_eq!(body.get("status").and_then(|s| s.as_u64()), Some(404));
 }
+
+#[tokio::test]
+async fn catalog_writes_require_staff_or_admin_role() {
+    let test_db = setup_postgres();
+    let filter = product_filter(test_db.pool.clone());
+    let payload = json!({
+        "product_name": "Guarded Widget",
+        "product_description": null,
+        "price": "3.00",
+        "stock": 2
+    });
+
+    let anonymous = warp::test::request()
+        .method("POST")
+        .path("/products")
+        .json(&payload)
+        .reply(&filter)
+        .await;
+    assert_eq!(anonymous.status(), 401);
+
+    let customer = warp::test::request()
+        .method("POST")
+        .path("/products")
+        .header("authorization", role_token(Role::Customer))
+        .json(&payload)
+        .reply(&filter)
+        .await;
+    assert_eq!(customer.status(), 403);
+    let body: serde_json::Value = serde_json::from_slice(customer.body()).expect("json");
+    assert_eq!(body.get("status").and_then(|s| s.as_u64()), Some(403));
+
+    let created = warp::test::request()
+        .method("POST")
+        .path("/products")
+        .header("authorization", role_token(Role::Staff))
+        .json(&payload)
+        .reply(&filter)
+        .await;
+    assert_eq!(created.status(), 200);
+    let created: ProductResponse = serde_json::from_slice(created.body()).expect("created");
+
+    let staff_delete = warp::test::request()
+        .method("DELETE")
+        .path(&format!("/products/{id}", id = created.id))
+        .header("authorization", role_token(Role::Staff))
+        .reply(&filter)
+        .await;
+    assert_eq!(staff_delete.status(), 403);
+
+    let admin_delete = warp::test::request()
+        .method("DELETE")
+        .path(&format!("/products/{id}", id = created.id))
+        .header("authorization", role_token(Role::Admin))
+        .reply(&filter)
+        .await;
+    assert_eq!(admin_delete.status(), 204);
+}
+
+#[tokio::test]
+async fn bodies_over_the_configured_limit_get_413() {
+    let test_db = setup_postgres();
+    let mut config = AppConfig::new(test_auth_config());
+    config.server.body_limit_bytes = 64;
+    let filter = product_filter_with(test_db.pool.clone(), Arc::new(config));
+
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/products")
+        .header("authorization", role_token(Role::Staff))
+        .json(&json!({
+            "product_name": "Oversized",
+            "product_description": "x".repeat(128),
+            "price": "1.00"
+        }))
+        .reply(&filter)
+        .await;
+
+    assert_eq!(resp.status(), 413);
+    let body: serde_json::Value = serde_json::from_slice(resp.body()).expect("json");
+    assert_eq!(body.get("status").and_then(|s| s.as_u64()), Some(413));
+}
+
+#[tokio::test]
+async fn listing_pages_by_offset_with_filters_and_links() {
+    let test_db = setup_postgres();
+    let pool = test_db.pool.clone();
+    let filter = product_filter(pool.clone());
+    seed_products(
+        &pool,
+        &[
+            ("Coffee Beans", "12.00", 10),
+            ("Coffee Filter", "3.50", 0),
+            ("Coffee Grinder", "45.00", 2),
+            ("Coffee Mug", "8.00", 5),
+            ("Tea Leaves", "6.00", 7),
+        ],
+    );
+
+    let first = list_page(&filter, "/products?sort=price&limit=2&name_prefix=coffee").await;
+    assert_eq!(first.total, 4);
+    assert_eq!(first.offset, Some(0));
+    assert_eq!(names(&first), ["Coffee Filter", "Coffee Mug"]);
+    assert!(first.links.prev.is_none());
+    let next = first.links.next.expect("next link");
+    assert!(next.contains("offset=2") && next.contains("name_prefix=coffee"));
+
+    let second = list_page(&filter, &next).await;
+    assert_eq!(names(&second), ["Coffee Beans", "Coffee Grinder"]);
+    assert!(second.links.next.is_none());
+    assert_eq!(second.next_cursor, None);
+    assert!(second.links.prev.expect("prev link").contains("offset=0"));
+
+    let filtered = list_page(
+        &filter,
+        "/products?sort=price&order=desc&in_stock=true&min_price=6&max_price=20",
+    )
+    .await;
+    assert_eq!(filtered.total, 3);
+    assert_eq!(
+        names(&filtered),
+        ["Coffee Beans", "Coffee Mug", "Tea Leaves"]
+    );
+
+    let by_name = list_page(&filter, "/products?sort=name&order=desc&limit=1").await;
+    assert_eq!(names(&by_name), ["Tea Leaves"]);
+}
+
+#[tokio::test]
+async fn in_stock_listing_leaves_out_stock_held_by_carts() {
+    let test_db = setup_postgres();
+    let pool = test_db.pool.clone();
+    let filter = product_filter(pool.clone());
+    seed_products(
+        &pool,
+        &[
+            ("Held Print", "10.00", 2),
+            ("Partly Held Print", "10.00", 2),
+            ("Lapsed Print", "10.00", 2),
+        ],
+    );
+
+    {
+        let mut conn = get_conn(&pool).expect("conn");
+        let user = insert_user(&pool, "holder@example.com");
+        let cart = cart_repository::create_default_cart(&mut conn, user.id).expect("cart");
+        let now = Utc::now();
+        for (name, quantity, expires_at) in [
+            ("Held Print", 2, now + Duration::hours(1)),
+            ("Partly Held Print", 1, now + Duration::hours(1)),
+            ("Lapsed Print", 2, now - Duration::minutes(1)),
+        ] {
+            let product = product_repository::get_product_by_name(&mut conn, name)
+                .expect("db lookup")
+                .expect("product");
+            let variant = product_variant_repository::get_default_variant(&mut conn, product.id)
+                .expect("db lookup")
+                .expect("default variant");
+            let item = cart_item_repository::create_cart_item(
+                &mut conn,
+                &NewCartItem {
+                    item_id: product.id,
+                    cart_id: cart.id,
+                    quantity,
+                    unit_price: product.price.clone(),
+                    variant_id: Some(variant.id),
+                },
+            )
+            .expect("cart item");
+            stock_reservation_repository::upsert_reservation(
+                &mut conn,
+                &NewStockReservation {
+                    cart_item_id: item.id,
+                    variant_id: variant.id,
+                    quantity,
+                    expires_at,
+                },
+            )
+            .expect("reservation");
+        }
+    }
+
+    let listed = list_page(&filter, "/products?in_stock=true&sort=name").await;
+    assert_eq!(listed.total, 2);
+    assert_eq!(names(&listed), ["Lapsed Print", "Partly Held Print"]);
+}
+
+#[tokio::test]
+async fn listing_cursor_walks_every_product_once() {
+    let test_db = setup_postgres();
+    let pool = test_db.pool.clone();
+    let filter = product_filter(pool.clone());
+    // Equal prices make the id tie-breaker matter
+    seed_products(
+        &pool,
+        &[
+            ("A", "5.00", 1),
+            ("B", "5.00", 1),
+            ("C", "5.00", 1),
+            ("D", "7.00", 1),
+            ("E", "1.00", 1),
+        ],
+    );
+
+    let first = list_page(&filter, "/products?sort=price&limit=2").await;
+    let mut seen: Vec<String> = names(&first).iter().map(|n| n.to_string()).collect();
+    let mut cursor = first.next_cursor;
+    while let Some(current) = cursor {
+        let page = list_page(
+            &filter,
+            &format!("/products?sort=price&limit=2&cursor={current}"),
+        )
+        .await;
+        assert_eq!(page.offset, None);
+        assert_eq!(page.total, 5);
+        assert_eq!(page.links.next.is_some(), page.next_cursor.is_some());
+        seen.extend(names(&page).iter().map(|n| n.to_string()));
+        cursor = page.next_cursor;
+    }
+
+    assert_eq!(seen.len(), 5);
+    assert_eq!(seen[0], "E");
+    assert_eq!(seen[4], "D");
+    let mut unique = seen.clone();
+    unique.sort();
+    unique.dedup();
+    assert_eq!(unique.len(), 5);
+}
+
+#[tokio::test]
+async fn listing_rejects_bad_parameters() {
+    let test_db = setup_postgres();
+    let pool = test_db.pool.clone();
+    let filter = product_filter(pool.clone());
+    seed_products(&pool, &[("A", "1.00", 1), ("B", "2.00", 1)]);
+
+    let first = warp::test::request()
+        .method("GET")
+        .path("/products?sort=price&limit=1")
+        .reply(&filter)
+        .await;
+    let first: PageResponse<ProductResponse> = serde_json::from_slice(first.body()).expect("page");
+    let cursor = first.next_cursor.expect("cursor");
+
+    for path in [
+        "/products?limit=0".to_string(),
+        "/products?limit=101".to_string(),
+        "/products?sort=popularity".to_string(),
+        "/products?order=sideways".to_string(),
+        "/products?offset=-1".to_string(),
+        "/products?min_price=10&max_price=5".to_string(),
+        "/products?limit=ten".to_string(),
+        "/products?cursor=not-a-cursor".to_string(),
+        format!("/products?offset=1&cursor={cursor}"),
+        format!("/products?sort=name&cursor={cursor}"),
+    ] {
+        let resp = warp::test::request()
+            .method("GET")
+            .path(&path)
+            .reply(&filter)
+            .await;
+        assert_eq!(resp.status(), 400, "{path}");
+    }
+}
+
+fn seed_described_products(pool: &PgPool, products: &[(&str, &str)]) {
+    let mut conn = get_conn(pool).expect("conn");
+    for (name, description) in products {
+        let new_product = NewProduct {
+            product_name: name.to_string(),
+            product_description: Some(description.to_string()),
+            price: BigDecimal::from_str("9.99").expect("price"),
+            stock: 1,
+        };
+        product_repository::create_product(&mut conn, &new_product, None).expect("insert product");
+    }
+}
+
+async fn search<F>(filter: &F, path: &str) -> PageResponse<ProductSearchResponse>
+where
+    F: Filter + Clone + 'static,
+    F::Extract: warp::Reply + Send,
+{
+    let resp = warp::test::request()
+        .method("GET")
+        .path(path)
+        .reply(filter)
+        .await;
+    assert_eq!(resp.status(), 200, "{path}: {:?}", resp.body());
+    serde_json::from_slice(resp.body()).expect("search page")
+}
+
+#[tokio::test]
+async fn search_ranks_stems_and_highlights() {
+    let test_db = setup_postgres();
+    let pool = test_db.pool.clone();
+    let filter = product_filter(pool.clone());
+    seed_described_products(
+        &pool,
+        &[
+            ("Burr Grinder", "Grinds coffee beans evenly for espresso"),
+            ("Espresso Cup", "Pairs well with a good grinder"),
+            ("Tea Kettle", "Boils water quickly"),
+        ],
+    );
+
+    // "grinders" stems to the same lexeme as "Grinder"; name matches outrank description ones
+    let page = search(&filter, "/products/search?q=grinders&prefix=false").await;
+    assert_eq!(page.total, 2);
+    let names: Vec<&str> = page
+        .items
+        .iter()
+        .map(|hit| hit.product.product_name.as_str())
+        .collect();
+    assert_eq!(names, ["Burr Grinder", "Espresso Cup"]);
+    assert!(page.items[0].rank >= page.items[1].rank);
+    assert_eq!(page.items[0].name_highlight, "Burr <mark>Grinder</mark>");
+    let snippet = page.items[1]
+        .description_snippet
+        .as_deref()
+        .expect("snippet");
+    assert!(snippet.contains("<mark>grinder</mark>"), "{snippet}");
+
+    // Type-ahead: the last word is matched as a prefix
+    let typed = search(&filter, "/products/search?q=espresso%20gri").await;
+    assert_eq!(typed.total, 2);
+
+    let paged = search(&filter, "/products/search?q=espresso&limit=1").await;
+    assert_eq!(paged.total, 2);
+    assert_eq!(paged.items.len(), 1);
+    assert!(paged.links.next.expect("next").contains("offset=1"));
+}
+
+#[tokio::test]
+async fn search_validates_the_query_text() {
+    let test_db = setup_postgres();
+    let pool = test_db.pool.clone();
+    let filter = product_filter(pool.clone());
+    seed_described_products(&pool, &[("Tea Kettle", "Boils water quickly")]);
+
+    // tsquery operators in user input are dropped rather than interpreted
+    let page = search(&filter, "/products/search?q=kettle%20%26%20!%7C%20(").await;
+    assert_eq!(page.total, 1);
+
+    for path in [
+        "/products/search",
+        "/products/search?q=",
+        "/products/search?q=%26%7C!",
+        "/products/search?q=kettle&limit=0",
+    ] {
+        let resp = warp::test::request()
+            .method("GET")
+            .path(path)
+            .reply(&filter)
+            .await;
+        assert_eq!(resp.status(), 400, "{path}");
+    }
+}
+
+async fn suggest<F>(filter: &F, path: &str) -> Vec<String>
+where
+    F: Filter + Clone + 'static,
+    F::Extract: warp::Reply + Send,
+{
+    let resp = warp::test::request()
+        .method("GET")
+        .path(path)
+        .reply(filter)
+        .await;
+    assert_eq!(resp.status(), 200, "{path}: {:?}", resp.body());
+    let body: ProductSuggestionsResponse = serde_json::from_slice(resp.body()).expect("json");
+    body.suggestions
+        .into_iter()
+        .map(|s| s.product_name)
+        .collect()
+}
+
+#[tokio::test]
+async fn suggestions_tolerate_typos() {
+    let test_db = setup_postgres();
+    let pool = test_db.pool.clone();
+    let filter = product_filter(pool.clone());
+    seed_products(
+        &pool,
+        &[
+            ("FireFleeb Mug", "8.00", 1),
+            ("FireFleeb Hoodie", "40.00", 1),
+            ("Coffee Grinder", "45.00", 1),
+        ],
+    );
+
+    let misspelt = suggest(&filter, "/products/suggest?prefix=firfleeb").await;
+    assert_eq!(misspelt.len(), 2);
+    assert!(misspelt.iter().all(|name| name.starts_with("FireFleeb")));
+
+    let limited = suggest(&filter, "/products/suggest?prefix=firefleeb%20mug&limit=1").await;
+    assert_eq!(limited, ["FireFleeb Mug"]);
+
+    let partial = suggest(&filter, "/products/suggest?prefix=grind").await;
+    assert_eq!(partial, ["Coffee Grinder"]);
+
+    assert!(
+        suggest(&filter, "/products/suggest?prefix=zzzz")
+            .await
+            .is_empty()
+    );
+
+    for path in [
+        "/products/suggest",
+        "/products/suggest?prefix=%20",
+        "/products/suggest?prefix=mug&limit=21",
+    ] {
+        let resp = warp::test::request()
+            .method("GET")
+            .path(path)
+            .reply(&filter)
+            .await;
+        assert_eq!(resp.status(), 400, "{path}");
+    }
+}
+
+#[tokio::test]
+async fn suggestion_threshold_comes_from_config() {
+    let test_db = setup_postgres();
+    let pool = test_db.pool.clone();
+    let mut config = AppConfig::new(test_auth_config());
+    config.catalog.suggestion_threshold = 0.9;
+    let filter = product_filter_with(pool.clone(), Arc::new(config));
+    seed_products(&pool, &[("FireFleeb Mug", "8.00", 1)]);
+
+    assert!(
+        suggest(&filter, "/products/suggest?prefix=firfleeb")
+            .await
+            .is_empty()
+    );
+    assert_eq!(
+        suggest(&filter, "/products/suggest?prefix=firefleeb").await,
+        ["FireFleeb Mug"]
+    );
+}
+
+#[tokio::test]
+async fn product_variants_have_their_own_sku_price_and_stock() {
+    let test_db = setup_postgres();
+    let filter = product_filter(test_db.pool.clone());
+
+    let created = warp::test::request()
+        .method("POST")
+        .path("/products")
+        .header("authorization", role_token(Role::Staff))
+        .json(&json!({
+            "product_name": "Variant Tee",
+            "price": "20.00",
+            "stock": 3,
+            "sku": "TEE-BASE"
+        }))
+        .reply(&filter)
+        .await;
+    assert_eq!(created.status(), 200);
+    let created: ProductResponse = serde_json::from_slice(created.body()).expect("created");
+    assert_eq!(created.variants.len(), 1);
+    let base = &created.variants[0];
+    assert!(base.is_default);
+    assert_eq!(base.sku, "TEE-BASE");
+    assert_eq!(base.stock, 3);
+    assert_eq!(base.price_override, None);
+    let base_id = base.id;
+
+    let variants_path = format!("/products/{}/variants", created.id);
+    let red = warp::test::request()
+        .method("POST")
+        .path(&variants_path)
+        .header("authorization", role_token(Role::Staff))
+        .json(&json!({
+            "sku": "TEE-M-RED",
+            "options": { "size": "M", "colour": "red" },
+            "price": "25.00",
+            "stock": 4
+        }))
+        .reply(&filter)
+        .await;
+    assert_eq!(red.status(), 201, "{:?}", red.body());
+    let red: ProductVariantResponse = serde_json::from_slice(red.body()).expect("variant");
+    assert_eq!(red.price, BigDecimal::from_str("25.00").unwrap());
+
+    for clash in [
+        json!({ "sku": "tee-m-red", "options": { "size": "L" } }),
+        json!({ "sku": "TEE-M-RED-2", "options": { "colour": "red", "size": "M" } }),
+    ] {
+        let resp = warp::test::request()
+            .method("POST")
+            .path(&variants_path)
+            .header("authorization", role_token(Role::Staff))
+            .json(&clash)
+            .reply(&filter)
+            .await;
+        assert_eq!(resp.status(), 409, "{clash}");
+    }
+    let invalid = warp::test::request()
+        .method("POST")
+        .path(&variants_path)
+        .header("authorization", role_token(Role::Staff))
+        .json(&json!({ "sku": "TEE-XL", "options": { "size": 42 } }))
+        .reply(&filter)
+        .await;
+    assert_eq!(invalid.status(), 400);
+
+    // Clearing the override falls back to the product price; the variant becomes the default
+    let updated = warp::test::request()
+        .method("PUT")
+        .path(&format!("{variants_path}/{}", red.id))
+        .header("authorization", role_token(Role::Staff))
+        .json(&json!({ "price": null, "is_default": true }))
+        .reply(&filter)
+        .await;
+    assert_eq!(updated.status(), 200);
+    let updated: ProductVariantResponse = serde_json::from_slice(updated.body()).expect("variant");
+    assert_eq!(updated.price, BigDecimal::from_str("20.00").unwrap());
+    assert!(updated.is_default);
+
+    let default_delete = warp::test::request()
+        .method("DELETE")
+        .path(&format!("{variants_path}/{}", red.id))
+        .header("authorization", role_token(Role::Admin))
+        .reply(&filter)
+        .await;
+    assert_eq!(default_delete.status(), 409);
+
+    let fetched = warp::test::request()
+        .method("GET")
+        .path(&format!("/products/{}", created.id))
+        .reply(&filter)
+        .await;
+    let fetched: ProductResponse = serde_json::from_slice(fetched.body()).expect("product");
+    assert_eq!(fetched.stock, 7);
+    let skus: Vec<&str> = fetched.variants.iter().map(|v| v.sku.as_str()).collect();
+    assert_eq!(skus, ["TEE-M-RED", "TEE-BASE"]);
+
+    let deleted = warp::test::request()
+        .method("DELETE")
+        .path(&format!("{variants_path}/{base_id}"))
+        .header("authorization", role_token(Role::Admin))
+        .reply(&filter)
+        .await;
+    assert_eq!(deleted.status(), 204);
+
+    let fetched = warp::test::request()
+        .method("GET")
+        .path(&format!("/products/{}", created.id))
+        .reply(&filter)
+        .await;
+    let fetched: ProductResponse = serde_json::from_slice(fetched.body()).expect("product");
+    assert_eq!(fetched.stock, 4);
+    assert_eq!(fetched.variants.len(), 1);
+
+    // The product total saturates instead of overflowing
+    let plenty = warp::test::request()
+        .method("POST")
+        .path(&variants_path)
+        .header("authorization", role_token(Role::Staff))
+        .json(&json!({ "sku": "TEE-XL", "options": { "size": "XL" }, "stock": i32::MAX }))
+        .reply(&filter)
+        .await;
+    assert_eq!(plenty.status(), 201, "{:?}", plenty.body());
+    let fetched = warp::test::request()
+        .method("GET")
+        .path(&format!("/products/{}", created.id))
+        .reply(&filter)
+        .await;
+    let fetched: ProductResponse = serde_json::from_slice(fetched.body()).expect("product");
+    assert_eq!(fetched.stock, i32::MAX);
+}
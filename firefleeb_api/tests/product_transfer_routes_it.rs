@@ -0,0 +1,294 @@
+mod common {
+    pub mod auth;
+    pub mod blobs;
+    pub mod db;
+    pub mod products;
+}
+
+use std::sync::Arc;
+
+use bigdecimal::BigDecimal;
+use common::auth::{role_token, test_auth_config};
+use common::blobs::test_blob_store;
+use common::db::setup_postgres;
+use common::products::create_product;
+use diesel::RunQueryDsl;
+use firefleeb_api::config::AppConfig;
+use firefleeb_api::db::{PgPool, get_conn};
+use firefleeb_api::handlers::dtos::{ImportReportResponse, ProductResponse};
+use firefleeb_api::routes::{handle_rejection, product_routes::product_routes};
+use firefleeb_api::types::role::Role;
+use serde_json::Value;
+use std::str::FromStr;
+use uuid::Uuid;
+use warp::Filter;
+
+fn transfer_filter(
+    pool: PgPool,
+    config: AppConfig,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = std::convert::Infallible> + Clone {
+    product_routes(pool, Arc::new(config), test_blob_store().store.clone())
+        .recover(handle_rejection)
+}
+
+async fn import<F>(
+    filter: &F,
+    query: &str,
+    content_type: &str,
+    body: &str,
+) -> warp::http::Response<warp::hyper::body::Bytes>
+where
+    F: Filter + Clone + 'static,
+    F::Extract: warp::Reply + Send,
+{
+    warp::test::request()
+        .method("POST")
+        .path(&format!("/products/import{query}"))
+        .header("authorization", role_token(Role::Staff))
+        .header("content-type", content_type)
+        .body(body)
+        .reply(filter)
+        .await
+}
+
+async fn export<F>(filter: &F, format: &str) -> warp::http::Response<warp::hyper::body::Bytes>
+where
+    F: Filter + Clone + 'static,
+    F::Extract: warp::Reply + Send,
+{
+    warp::test::request()
+        .method("GET")
+        .path(&format!("/products/export?format={format}"))
+        .header("authorization", role_token(Role::Staff))
+        .reply(filter)
+        .await
+}
+
+async fn get_product<F>(filter: &F, product_id: Uuid) -> ProductResponse
+where
+    F: Filter + Clone + 'static,
+    F::Extract: warp::Reply + Send,
+{
+    let resp = warp::test::request()
+        .method("GET")
+        .path(&format!("/products/{product_id}"))
+        .reply(filter)
+        .await;
+    serde_json::from_slice(resp.body()).expect("product")
+}
+
+fn report(resp: &warp::http::Response<warp::hyper::body::Bytes>) -> ImportReportResponse {
+    assert_eq!(resp.status(), 200, "{:?}", resp.body());
+    serde_json::from_slice(resp.body()).expect("report")
+}
+
+#[tokio::test]
+async fn csv_import_upserts_by_name_and_reports_each_row() {
+    let test_db = setup_postgres();
+    let filter = transfer_filter(test_db.pool.clone(), AppConfig::new(test_auth_config()));
+    let mug_id = create_product(&filter, "Existing Mug", "5.00", 3).await;
+
+    let csv = "product_name,product_description,price,stock\n\
+               Existing Mug,,6.50,\n\
+               New Lamp,Bright and warm,19.99,4\n\
+               ,,1.00,1\n\
+               Broken Price,,abc,1\n\
+               New Lamp,Again,1.00,1\n\
+               Negative Widget,,-1,1\n";
+    let report = report(&import(&filter, "", "text/csv", csv).await);
+
+    assert!(!report.dry_run);
+    assert_eq!((report.created, report.updated, report.rejected), (1, 1, 4));
+    let statuses: Vec<(u64, &str)> = report
+        .rows
+        .iter()
+        .map(|row| (row.line, row.status.as_str()))
+        .collect();
+    assert_eq!(
+        statuses,
+        [
+            (2, "updated"),
+            (3, "created"),
+            (4, "rejected"),
+            (5, "rejected"),
+            (6, "rejected"),
+            (7, "rejected"),
+        ]
+    );
+    assert_eq!(report.rows[0].product_id, Some(mug_id));
+    assert_eq!(
+        report.rows[2].error.as_deref(),
+        Some("product_name is required")
+    );
+    assert_eq!(
+        report.rows[4].error.as_deref(),
+        Some("product_name repeats line 3")
+    );
+
+    // Empty cells leave the existing values alone
+    let mug = get_product(&filter, mug_id).await;
+    assert_eq!(mug.price, BigDecimal::from_str("6.50").expect("decimal"));
+    assert_eq!(mug.stock, 3);
+
+    let lamp = get_product(&filter, report.rows[1].product_id.expect("lamp id")).await;
+    assert_eq!(lamp.product_name, "New Lamp");
+    assert_eq!(lamp.product_description.as_deref(), Some("Bright and warm"));
+    assert_eq!(lamp.stock, 4);
+}
+
+#[tokio::test]
+async fn ndjson_dry_run_reports_without_saving() {
+    let test_db = setup_postgres();
+    let filter = transfer_filter(test_db.pool.clone(), AppConfig::new(test_auth_config()));
+
+    let ndjson = "{\"product_name\": \"Ghost Lamp\", \"price\": \"3.00\", \"stock\": 2}\n\
+                  \n\
+                  {\"product_name\": \"Half Row\"\n";
+    let dry = report(&import(&filter, "?dry_run=true", "application/x-ndjson", ndjson).await);
+    assert!(dry.dry_run);
+    assert_eq!((dry.created, dry.updated, dry.rejected), (1, 0, 1));
+    assert_eq!(dry.rows[0].product_id, None);
+    assert_eq!(dry.rows[1].line, 3);
+    assert!(
+        dry.rows[1]
+            .error
+            .as_deref()
+            .is_some_and(|error| error.starts_with("invalid JSON"))
+    );
+
+    let exported = export(&filter, "ndjson").await;
+    assert!(exported.body().is_empty(), "{:?}", exported.body());
+
+    let real = report(&import(&filter, "", "application/x-ndjson", ndjson).await);
+    assert_eq!((real.created, real.rejected), (1, 1));
+    let ghost = get_product(&filter, real.rows[0].product_id.expect("id")).await;
+    assert_eq!(ghost.product_name, "Ghost Lamp");
+    assert_eq!(ghost.stock, 2);
+}
+
+#[tokio::test]
+async fn ndjson_prices_may_be_numbers() {
+    let test_db = setup_postgres();
+    let filter = transfer_filter(test_db.pool.clone(), AppConfig::new(test_auth_config()));
+
+    let ndjson = "{\"product_name\": \"Desk Lamp\", \"price\": 19.99, \"stock\": 1}\n\
+                  {\"product_name\": \"Floor Lamp\", \"price\": 120}\n\
+                  {\"product_name\": \"Odd Lamp\", \"price\": 0.125}\n\
+                  {\"product_name\": \"Word Lamp\", \"price\": true}\n";
+    let report = report(&import(&filter, "", "application/x-ndjson", ndjson).await);
+    assert_eq!(
+        (report.created, report.rejected),
+        (2, 2),
+        "{:?}",
+        report.rows
+    );
+    assert_eq!(
+        report.rows[2].error.as_deref(),
+        Some("price must be below 100000000 with at most two decimal places")
+    );
+    assert!(
+        report.rows[3]
+            .error
+            .as_deref()
+            .is_some_and(|error| error.starts_with("invalid JSON"))
+    );
+
+    let desk = get_product(&filter, report.rows[0].product_id.expect("id")).await;
+    assert_eq!(desk.price, BigDecimal::from_str("19.99").expect("decimal"));
+    let floor = get_product(&filter, report.rows[1].product_id.expect("id")).await;
+    assert_eq!(floor.price, BigDecimal::from_str("120").expect("decimal"));
+}
+
+#[tokio::test]
+async fn import_checks_role_type_and_size() {
+    let test_db = setup_postgres();
+    let mut config = AppConfig::new(test_auth_config());
+    config.catalog.import_max_bytes = 128;
+    let filter = transfer_filter(test_db.pool.clone(), config);
+
+    let as_customer = warp::test::request()
+        .method("POST")
+        .path("/products/import")
+        .header("authorization", role_token(Role::Customer))
+        .header("content-type", "text/csv")
+        .body("product_name,price\nMug,1.00\n")
+        .reply(&filter)
+        .await;
+    assert_eq!(as_customer.status(), 403);
+
+    let json_body = import(&filter, "", "application/json", "[]").await;
+    assert_eq!(json_body.status(), 415);
+
+    let no_price_column = import(&filter, "", "text/csv", "product_name,cost\nMug,1.00\n").await;
+    assert_eq!(no_price_column.status(), 400);
+
+    let too_big = format!("product_name,price\n{}", "Mug,1.00\n".repeat(20));
+    assert_eq!(
+        import(&filter, "", "text/csv", &too_big).await.status(),
+        413
+    );
+}
+
+#[tokio::test]
+async fn export_streams_the_catalog_in_batches() {
+    let test_db = setup_postgres();
+    let pool = test_db.pool.clone();
+    let filter = transfer_filter(pool.clone(), AppConfig::new(test_auth_config()));
+
+    // More products than one export batch holds
+    let mut conn = get_conn(&pool).expect("conn");
+    diesel::sql_query(
+        "INSERT INTO products (product_name, price, stock) \
+         SELECT 'Bulk ' || lpad(i::text, 4, '0'), 1.50, i FROM generate_series(1, 501) i",
+    )
+    .execute(&mut conn)
+    .expect("seed products");
+    let archived_id = create_product(&filter, "Archived Anvil", "9.00", 1).await;
+    let archived = warp::test::request()
+        .method("DELETE")
+        .path(&format!("/products/{archived_id}"))
+        .header("authorization", role_token(Role::Admin))
+        .reply(&filter)
+        .await;
+    assert_eq!(archived.status(), 204);
+
+    let csv = export(&filter, "csv").await;
+    assert_eq!(csv.status(), 200);
+    assert_eq!(csv.headers()["content-type"], "text/csv; charset=utf-8");
+    assert!(
+        csv.headers()["content-disposition"]
+            .to_str()
+            .expect("header")
+            .contains("products.csv")
+    );
+    let text = std::str::from_utf8(csv.body()).expect("utf-8");
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 502);
+    assert_eq!(lines[0], "id,product_name,product_description,price,stock");
+    assert!(lines[1].contains(",Bulk 0001,,1.50,1"), "{}", lines[1]);
+    assert!(
+        lines[501].contains(",Bulk 0501,,1.50,501"),
+        "{}",
+        lines[501]
+    );
+    assert!(!text.contains("Archived Anvil"));
+
+    let ndjson = export(&filter, "ndjson").await;
+    assert_eq!(ndjson.headers()["content-type"], "application/x-ndjson");
+    let records: Vec<Value> = std::str::from_utf8(ndjson.body())
+        .expect("utf-8")
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("json line"))
+        .collect();
+    assert_eq!(records.len(), 501);
+    assert_eq!(records[0]["product_name"], "Bulk 0001");
+
+    assert_eq!(export(&filter, "xml").await.status(), 400);
+
+    // An export imports back cleanly, updating every product in place
+    let round_trip = report(&import(&filter, "", "text/csv", text).await);
+    assert_eq!(
+        (round_trip.created, round_trip.updated, round_trip.rejected),
+        (0, 501, 0)
+    );
+}
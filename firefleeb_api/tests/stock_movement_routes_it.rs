@@ -0,0 +1,257 @@
+mod common {
+    pub mod auth;
+    pub mod blobs;
+    pub mod config;
+    pub mod db;
+}
+
+use common::auth::{bearer_token, role_token, test_auth_config};
+use common::blobs::test_blob_store;
+use common::config::app_config;
+use common::db::setup_postgres;
+use firefleeb_api::db::PgPool;
+use firefleeb_api::handlers::dtos::{
+    PageResponse, ProductResponse, ProductVariantResponse, StockMovementResponse,
+};
+use firefleeb_api::routes::{handle_rejection, product_routes::product_routes};
+use firefleeb_api::types::role::Role;
+use serde_json::json;
+use uuid::Uuid;
+use warp::Filter;
+
+fn stock_filter(
+    pool: PgPool,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = std::convert::Infallible> + Clone {
+    product_routes(
+        pool,
+        app_config(test_auth_config()),
+        test_blob_store().store.clone(),
+    )
+    .recover(handle_rejection)
+}
+
+async fn product<F>(filter: &F, product_id: Uuid) -> ProductResponse
+where
+    F: Filter + Clone + 'static,
+    F::Extract: warp::Reply + Send,
+{
+    let resp = warp::test::request()
+        .method("GET")
+        .path(&format!("/products/{product_id}"))
+        .reply(filter)
+        .await;
+    assert_eq!(resp.status(), 200, "{:?}", resp.body());
+    serde_json::from_slice(resp.body()).expect("product")
+}
+
+async fn movements<F>(filter: &F, auth: &str, path: &str) -> PageResponse<StockMovementResponse>
+where
+    F: Filter + Clone + 'static,
+    F::Extract: warp::Reply + Send,
+{
+    let resp = warp::test::request()
+        .method("GET")
+        .path(path)
+        .header("authorization", auth)
+        .reply(filter)
+        .await;
+    assert_eq!(resp.status(), 200, "{:?}", resp.body());
+    serde_json::from_slice(resp.body()).expect("movements")
+}
+
+#[tokio::test]
+async fn stock_changes_are_kept_in_the_ledger() {
+    let test_db = setup_postgres();
+    let filter = stock_filter(test_db.pool.clone());
+    let clerk = Uuid::new_v4();
+    let auth = bearer_token(clerk, Role::Staff);
+
+    let created = warp::test::request()
+        .method("POST")
+        .path("/products")
+        .header("authorization", &auth)
+        .json(&json!({ "product_name": "Ledger Lamp", "price": "40.00", "stock": 5 }))
+        .reply(&filter)
+        .await;
+    assert_eq!(created.status(), 200, "{:?}", created.body());
+    let product_id = serde_json::from_slice::<ProductResponse>(created.body())
+        .expect("product")
+        .id;
+    let path = format!("/products/{product_id}/stock-movements");
+
+    let record = |body: serde_json::Value| {
+        warp::test::request()
+            .method("POST")
+            .path(&path)
+            .header("authorization", &auth)
+            .json(&body)
+    };
+
+    let receipt =
+        record(json!({ "movement_type": "receipt", "quantity": 10, "reason": "PO-1042" }))
+            .reply(&filter)
+            .await;
+    assert_eq!(receipt.status(), 201, "{:?}", receipt.body());
+    let receipt: StockMovementResponse = serde_json::from_slice(receipt.body()).expect("movement");
+    assert_eq!(receipt.stock_after, 15);
+    assert_eq!(receipt.actor_id, Some(clerk));
+
+    let shrinkage =
+        record(json!({ "movement_type": "adjustment", "quantity": -3, "reason": "Cycle count" }))
+            .reply(&filter)
+            .await;
+    assert_eq!(shrinkage.status(), 201, "{:?}", shrinkage.body());
+    assert_eq!(product(&filter, product_id).await.stock, 12);
+
+    // Setting stock through the product is logged as an adjustment of the difference
+    let set = warp::test::request()
+        .method("PUT")
+        .path(&format!("/products/{product_id}"))
+        .header("authorization", &auth)
+        .json(&json!({ "stock": 20 }))
+        .reply(&filter)
+        .await;
+    assert_eq!(set.status(), 200, "{:?}", set.body());
+
+    let page = movements(&filter, &auth, &path).await;
+    assert_eq!(page.total, 4);
+    let summary: Vec<(&str, i32, i32)> = page
+        .items
+        .iter()
+        .map(|m| (m.movement_type.as_str(), m.quantity, m.stock_after))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("adjustment", 8, 20),
+            ("adjustment", -3, 12),
+            ("receipt", 10, 15),
+            ("receipt", 5, 5),
+        ]
+    );
+    assert_eq!(page.items[3].reason, "Opening stock");
+
+    let receipts = movements(
+        &filter,
+        &auth,
+        &format!("{path}?movement_type=receipt&limit=1"),
+    )
+    .await;
+    assert_eq!(receipts.total, 2);
+    assert_eq!(receipts.items.len(), 1);
+    assert!(receipts.links.next.is_some());
+
+    // Stock never goes below zero, and each type has a fixed direction
+    let oversold =
+        record(json!({ "movement_type": "sale", "quantity": -21, "reason": "Market stall" }))
+            .reply(&filter)
+            .await;
+    assert_eq!(oversold.status(), 409);
+    for bad in [
+        json!({ "movement_type": "receipt", "quantity": -1, "reason": "x" }),
+        json!({ "movement_type": "adjustment", "quantity": 0, "reason": "x" }),
+        json!({ "movement_type": "theft", "quantity": -1, "reason": "x" }),
+        json!({ "movement_type": "return", "quantity": 1, "reason": "  " }),
+    ] {
+        assert_eq!(record(bad).reply(&filter).await.status(), 400);
+    }
+    let wrong_variant = record(json!({
+        "variant_id": Uuid::new_v4(),
+        "movement_type": "receipt",
+        "quantity": 1,
+        "reason": "x",
+    }))
+    .reply(&filter)
+    .await;
+    assert_eq!(wrong_variant.status(), 404);
+    assert_eq!(product(&filter, product_id).await.stock, 20);
+}
+
+#[tokio::test]
+async fn variant_movements_roll_up_to_the_product() {
+    let test_db = setup_postgres();
+    let filter = stock_filter(test_db.pool.clone());
+    let auth = role_token(Role::Staff);
+
+    let created = warp::test::request()
+        .method("POST")
+        .path("/products")
+        .header("authorization", &auth)
+        .json(&json!({ "product_name": "Ledger Tee", "price": "15.00", "stock": 2 }))
+        .reply(&filter)
+        .await;
+    let product_id = serde_json::from_slice::<ProductResponse>(created.body())
+        .expect("product")
+        .id;
+    let path = format!("/products/{product_id}/stock-movements");
+
+    let variant = warp::test::request()
+        .method("POST")
+        .path(&format!("/products/{product_id}/variants"))
+        .header("authorization", &auth)
+        .json(&json!({ "sku": "LEDGER-TEE-L", "options": { "size": "L" }, "stock": 3 }))
+        .reply(&filter)
+        .await;
+    assert_eq!(variant.status(), 201, "{:?}", variant.body());
+    let variant_id = serde_json::from_slice::<ProductVariantResponse>(variant.body())
+        .expect("variant")
+        .id;
+
+    let returned = warp::test::request()
+        .method("POST")
+        .path(&path)
+        .header("authorization", &auth)
+        .json(&json!({
+            "variant_id": variant_id,
+            "movement_type": "return",
+            "quantity": 1,
+            "reason": "RMA-7",
+        }))
+        .reply(&filter)
+        .await;
+    assert_eq!(returned.status(), 201, "{:?}", returned.body());
+    assert_eq!(product(&filter, product_id).await.stock, 6);
+
+    let large = movements(&filter, &auth, &format!("{path}?variant_id={variant_id}")).await;
+    let quantities: Vec<(i32, i32)> = large
+        .items
+        .iter()
+        .map(|m| (m.quantity, m.stock_after))
+        .collect();
+    assert_eq!(quantities, [(1, 4), (3, 3)]);
+
+    let customer = role_token(Role::Customer);
+    let forbidden = warp::test::request()
+        .method("GET")
+        .path(&path)
+        .header("authorization", &customer)
+        .reply(&filter)
+        .await;
+    assert_eq!(forbidden.status(), 403);
+
+    let missing = warp::test::request()
+        .method("GET")
+        .path(&format!("/products/{}/stock-movements", Uuid::new_v4()))
+        .header("authorization", &auth)
+        .reply(&filter)
+        .await;
+    assert_eq!(missing.status(), 404);
+
+    // Deleting the variant writes its stock off, and the history outlives it
+    let admin = role_token(Role::Admin);
+    let deleted = warp::test::request()
+        .method("DELETE")
+        .path(&format!("/products/{product_id}/variants/{variant_id}"))
+        .header("authorization", &admin)
+        .reply(&filter)
+        .await;
+    assert_eq!(deleted.status(), 204);
+    assert_eq!(product(&filter, product_id).await.stock, 2);
+    let written_off = movements(&filter, &auth, &format!("{path}?movement_type=adjustment")).await;
+    let written_off: Vec<(Option<Uuid>, i32, i32, &str)> = written_off
+        .items
+        .iter()
+        .map(|m| (m.variant_id, m.quantity, m.stock_after, m.reason.as_str()))
+        .collect();
+    assert_eq!(written_off, [(None, -4, 0, "Variant LEDGER-TEE-L deleted")]);
+}
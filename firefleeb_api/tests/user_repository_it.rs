@@ -1,5 +1,7 @@
-mod common;
-use common::setup_postgres;
+mod common {
+    pub mod db;
+}
+use common::db::setup_postgres;
 
 use bcrypt::{DEFAULT_COST, hash, verify};
 use firefleeb_api::db::get_conn;
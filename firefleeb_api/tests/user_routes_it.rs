@@ -1,16 +1,74 @@
-mod common;
+mod common {
+    pub mod auth;
+    pub mod config;
+    pub mod db;
+}
 
-use common::setup_postgres;
-use firefleeb_api::db::PgPool;
-use firefleeb_api::handlers::dtos::UserResponse;
+use chrono::Utc;
+use common::auth::{bearer_token, role_token, test_auth_config};
+use common::config::app_config;
+use common::db::setup_postgres;
+use firefleeb_api::auth::{
+    Argon2idHasher, AuthConfig, BreachedPasswordList, EmailVerificationRule, decode_access_token,
+    issue_access_token,
+};
+use firefleeb_api::db::{PgPool, get_conn, login_throttle_repository, user_repository};
+use firefleeb_api::handlers::dtos::{LoginResponse, UserResponse};
+use firefleeb_api::mail::{InMemoryMailer, OutgoingMail};
+use firefleeb_api::models::NewUser;
 use firefleeb_api::routes::{handle_rejection, user_routes::user_routes};
+use firefleeb_api::services::login_throttle_service;
+use firefleeb_api::types::email::Email;
+use firefleeb_api::types::role::Role;
+use futures_util::future::join_all;
 use serde_json::{Value, json};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
 use warp::Filter;
+use warp::http::Response;
+use warp::hyper::body::Bytes;
 
 fn user_filter(
     pool: PgPool,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = std::convert::Infallible> + Clone {
-    user_routes(pool).recover(handle_rejection)
+    user_filter_with(pool, test_auth_config(), InMemoryMailer::new())
+}
+
+fn user_filter_with(
+    pool: PgPool,
+    auth: AuthConfig,
+    mailer: InMemoryMailer,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = std::convert::Infallible> + Clone {
+    user_routes(pool, app_config(auth), Arc::new(mailer)).recover(handle_rejection)
+}
+
+/// Pull the token out of the most recent email sent to `to`.
+fn mailed_token(mailer: &InMemoryMailer, to: &str, prefix: &str) -> String {
+    mailer
+        .sent()
+        .iter()
+        .rev()
+        .filter(|mail| mail.to.as_str() == to)
+        .find_map(|mail| mail.body.lines().find_map(|line| line.strip_prefix(prefix)))
+        .expect("token in email")
+        .to_string()
+}
+
+/// Wait until mail sent in the background brings the outbox to `count` messages.
+async fn wait_for_mail(mailer: &InMemoryMailer, count: usize) -> Vec<OutgoingMail> {
+    for _ in 0..250 {
+        let sent = mailer.sent();
+        if sent.len() >= count {
+            return sent;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("expected {count} mails, found {}", mailer.sent().len());
+}
+
+fn bearer(user: &UserResponse) -> String {
+    bearer_token(user.id, user.role)
 }
 
 #[tokio::test]
@@ -43,9 +101,14 @@ async fn register_and_login_round_trip() {
         .await;
 
     assert_eq!(login_resp.status(), 200);
-    let logged_in: UserResponse =
+    let logged_in: LoginResponse =
         serde_json::from_slice(login_resp.body()).expect("login response");
-    assert_eq!(logged_in.id, created.id);
+    assert_eq!(logged_in.user.id, created.id);
+    assert_eq!(logged_in.token_type, "Bearer");
+
+    let claims =
+        decode_access_token(&test_auth_config(), &logged_in.access_token).expect("valid token");
+    assert_eq!(claims.sub, created.id);
 }
 
 #[tokio::test]
@@ -75,6 +138,7 @@ async fn update_user_email_via_route() {
     let update_resp = warp::test::request()
         .method("PUT")
         .path(&format!("/users/{}", created.id))
+        .header("authorization", bearer(&created))
         .json(&update_payload)
         .reply(&filter)
         .await;
@@ -113,6 +177,7 @@ async fn password_reset_requires_correct_old_password() {
     let wrong_resp = warp::test::request()
         .method("PUT")
         .path(&format!("/users/password-reset/{}", created.id))
+        .header("authorization", bearer(&created))
         .json(&wrong_reset)
         .reply(&filter)
         .await;
@@ -126,6 +191,7 @@ async fn password_reset_requires_correct_old_password() {
     let reset_resp = warp::test::request()
         .method("PUT")
         .path(&format!("/users/password-reset/{}", created.id))
+        .header("authorization", bearer(&created))
         .json(&reset_payload)
         .reply(&filter)
         .await;
@@ -154,7 +220,936 @@ async fn password_reset_requires_correct_old_password() {
         .reply(&filter)
         .await;
     assert_eq!(new_login_resp.status(), 200);
-    let logged_in: UserResponse =
+    let logged_in: LoginResponse =
         serde_json::from_slice(new_login_resp.body()).expect("login response");
-    assert_eq!(logged_in.id, created.id);
+    assert_eq!(logged_in.user.id, created.id);
+}
+
+#[tokio::test]
+async fn update_user_requires_matching_bearer_token() {
+    let test_db = setup_postgres();
+    let filter = user_filter(test_db.pool.clone());
+
+    let mut users = Vec::new();
+    for email in ["owner@example.com", "intruder@example.com"] {
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/users")
+            .json(&json!({ "email": email, "password": "OwnerPass9" }))
+            .reply(&filter)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let user: UserResponse = serde_json::from_slice(resp.body()).expect("user response");
+        users.push(user);
+    }
+    let (owner, intruder) = (&users[0], &users[1]);
+    let payload = json!({ "email": "hijacked@example.com" });
+
+    let anonymous_resp = warp::test::request()
+        .method("PUT")
+        .path(&format!("/users/{}", owner.id))
+        .json(&payload)
+        .reply(&filter)
+        .await;
+    assert_eq!(anonymous_resp.status(), 401);
+
+    let foreign_key = AuthConfig::new("some-other-secret");
+    let forged = issue_access_token(&foreign_key, owner.id, Role::Customer).expect("token");
+    let forged_resp = warp::test::request()
+        .method("PUT")
+        .path(&format!("/users/{}", owner.id))
+        .header("authorization", format!("Bearer {}", forged.token))
+        .json(&payload)
+        .reply(&filter)
+        .await;
+    assert_eq!(forged_resp.status(), 401);
+
+    let intruder_resp = warp::test::request()
+        .method("PUT")
+        .path(&format!("/users/{}", owner.id))
+        .header("authorization", bearer(intruder))
+        .json(&payload)
+        .reply(&filter)
+        .await;
+    assert_eq!(intruder_resp.status(), 403);
+    let body: Value = serde_json::from_slice(intruder_resp.body()).expect("body");
+    assert_eq!(body.get("status").and_then(|v| v.as_u64()), Some(403));
+}
+
+#[tokio::test]
+async fn password_reset_is_forbidden_for_other_users() {
+    let test_db = setup_postgres();
+    let filter = user_filter(test_db.pool.clone());
+
+    let victim = register_and_login(&filter, "victim@example.com", "VictimPass9").await;
+    let attacker = register_and_login(&filter, "attacker@example.com", "AttackPass9").await;
+
+    let resp = warp::test::request()
+        .method("PUT")
+        .path(&format!("/users/password-reset/{}", victim.user.id))
+        .header("authorization", format!("Bearer {}", attacker.access_token))
+        .json(&json!({ "old_password": "VictimPass9", "new_password": "Hijacked99" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(resp.status(), 403);
+
+    let anonymous = warp::test::request()
+        .method("PUT")
+        .path(&format!("/users/password-reset/{}", victim.user.id))
+        .json(&json!({ "old_password": "VictimPass9", "new_password": "Hijacked99" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(anonymous.status(), 401);
+
+    let victim_login = warp::test::request()
+        .method("POST")
+        .path("/users/login")
+        .json(&json!({ "email": "victim@example.com", "password": "VictimPass9" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(victim_login.status(), 200);
+}
+
+#[tokio::test]
+async fn admins_can_update_any_user() {
+    let test_db = setup_postgres();
+    let filter = user_filter(test_db.pool.clone());
+
+    let member = register_and_login(&filter, "member@example.com", "MemberPass9").await;
+    let admin_auth = role_token(Role::Admin);
+
+    let update_resp = warp::test::request()
+        .method("PUT")
+        .path(&format!("/users/{}", member.user.id))
+        .header("authorization", &admin_auth)
+        .json(&json!({ "email": "member-renamed@example.com" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(update_resp.status(), 200);
+    let updated: UserResponse = serde_json::from_slice(update_resp.body()).expect("user");
+    assert_eq!(updated.email.as_str(), "member-renamed@example.com");
+
+    let missing_resp = warp::test::request()
+        .method("PUT")
+        .path(&format!("/users/{}", uuid::Uuid::new_v4()))
+        .header("authorization", &admin_auth)
+        .json(&json!({ "email": "nobody@example.com" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(missing_resp.status(), 404);
+}
+
+#[tokio::test]
+async fn only_admins_can_change_roles() {
+    let test_db = setup_postgres();
+    let filter = user_filter(test_db.pool.clone());
+
+    let session = register_and_login(&filter, "promote-me@example.com", "PromotePass9").await;
+    assert_eq!(session.user.role, Role::Customer);
+    let payload = json!({ "role": "staff" });
+
+    let self_promotion = warp::test::request()
+        .method("PUT")
+        .path(&format!("/users/{}/role", session.user.id))
+        .header("authorization", format!("Bearer {}", session.access_token))
+        .json(&payload)
+        .reply(&filter)
+        .await;
+    assert_eq!(self_promotion.status(), 403);
+
+    let admin_resp = warp::test::request()
+        .method("PUT")
+        .path(&format!("/users/{}/role", session.user.id))
+        .header("authorization", role_token(Role::Admin))
+        .json(&payload)
+        .reply(&filter)
+        .await;
+    assert_eq!(admin_resp.status(), 200);
+    let promoted: UserResponse = serde_json::from_slice(admin_resp.body()).expect("user");
+    assert_eq!(promoted.role, Role::Staff);
+
+    // The next login carries the new role in its access token
+    let relogin = warp::test::request()
+        .method("POST")
+        .path("/users/login")
+        .json(&json!({ "email": "promote-me@example.com", "password": "PromotePass9" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(relogin.status(), 200);
+    let relogged: LoginResponse = serde_json::from_slice(relogin.body()).expect("login");
+    let claims =
+        decode_access_token(&test_auth_config(), &relogged.access_token).expect("valid token");
+    assert_eq!(claims.role, Role::Staff);
+}
+
+#[tokio::test]
+async fn forgot_password_token_resets_password_once() {
+    let test_db = setup_postgres();
+    let mailer = InMemoryMailer::new();
+    let filter = user_filter_with(test_db.pool.clone(), test_auth_config(), mailer.clone());
+
+    let session = register_and_login(&filter, "forgetful@example.com", "Forgotten99").await;
+
+    let forgot_resp = warp::test::request()
+        .method("POST")
+        .path("/users/password/forgot")
+        .json(&json!({ "email": "forgetful@example.com" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(forgot_resp.status(), 202);
+
+    let sent = wait_for_mail(&mailer, 2).await;
+    let reset_mail = sent.last().expect("reset email");
+    assert_eq!(reset_mail.to.as_str(), "forgetful@example.com");
+    assert!(reset_mail.subject.contains("Reset"));
+    let token = mailed_token(&mailer, "forgetful@example.com", "Reset token: ");
+
+    let reset_payload = json!({ "token": token, "new_password": "Remembered99" });
+    let reset_resp = warp::test::request()
+        .method("POST")
+        .path("/users/password/reset")
+        .json(&reset_payload)
+        .reply(&filter)
+        .await;
+    assert_eq!(reset_resp.status(), 200);
+
+    let reuse_resp = warp::test::request()
+        .method("POST")
+        .path("/users/password/reset")
+        .json(&reset_payload)
+        .reply(&filter)
+        .await;
+    assert_eq!(reuse_resp.status(), 400);
+
+    let new_login = warp::test::request()
+        .method("POST")
+        .path("/users/login")
+        .json(&json!({ "email": "forgetful@example.com", "password": "Remembered99" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(new_login.status(), 200);
+
+    // Sessions opened before the reset no longer refresh
+    let stale_refresh = warp::test::request()
+        .method("POST")
+        .path("/users/token/refresh")
+        .json(&json!({ "refresh_token": session.refresh_token }))
+        .reply(&filter)
+        .await;
+    assert_eq!(stale_refresh.status(), 401);
+}
+
+#[tokio::test]
+async fn forgot_password_does_not_reveal_unknown_emails() {
+    let test_db = setup_postgres();
+    let mailer = InMemoryMailer::new();
+    let filter = user_filter_with(test_db.pool.clone(), test_auth_config(), mailer.clone());
+
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/users/password/forgot")
+        .json(&json!({ "email": "nobody-here@example.com" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(resp.status(), 202);
+    assert!(mailer.sent().is_empty());
+
+    let bogus_reset = warp::test::request()
+        .method("POST")
+        .path("/users/password/reset")
+        .json(&json!({ "token": "made-up", "new_password": "Whatever99" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(bogus_reset.status(), 400);
+}
+
+async fn forgot_from<F>(filter: &F, ip: [u8; 4], email: &str) -> Response<Bytes>
+where
+    F: Filter + 'static,
+    F::Extract: warp::Reply + Send,
+{
+    warp::test::request()
+        .method("POST")
+        .path("/users/password/forgot")
+        .remote_addr(SocketAddr::from((ip, 40000)))
+        .json(&json!({ "email": email }))
+        .reply(filter)
+        .await
+}
+
+#[tokio::test]
+async fn forgot_password_is_throttled_per_email_and_ip() {
+    let test_db = setup_postgres();
+    let mailer = InMemoryMailer::new();
+    let mut auth = test_auth_config();
+    auth.login_throttle.max_mails_per_email = 2;
+    auth.login_throttle.max_mails_per_ip = 4;
+    auth.login_throttle.base_lockout_secs = 60;
+    let filter = user_filter_with(test_db.pool.clone(), auth, mailer.clone());
+    register_and_login(&filter, "spammed@example.com", "Spammed999").await;
+
+    // Registered and unknown addresses run out of requests alike
+    for email in ["spammed@example.com", "nobody-here@example.com"] {
+        for _ in 0..2 {
+            assert_eq!(
+                forgot_from(&filter, [10, 0, 0, 1], email).await.status(),
+                202
+            );
+        }
+        let locked = forgot_from(&filter, [10, 0, 0, 2], email).await;
+        assert_eq!(locked.status(), 429);
+        assert!(locked.headers().contains_key("retry-after"));
+    }
+    let sent = wait_for_mail(&mailer, 3).await;
+    let resets = sent
+        .iter()
+        .filter(|mail| mail.subject.contains("Reset"))
+        .count();
+    assert_eq!(resets, 2);
+
+    // The IP that asked four times is locked out for any address
+    let by_ip = forgot_from(&filter, [10, 0, 0, 1], "someone-else@example.com").await;
+    assert_eq!(by_ip.status(), 429);
+}
+
+#[tokio::test]
+async fn registration_mails_a_single_use_verification_token() {
+    let test_db = setup_postgres();
+    let mailer = InMemoryMailer::new();
+    let filter = user_filter_with(test_db.pool.clone(), test_auth_config(), mailer.clone());
+
+    let register_resp = warp::test::request()
+        .method("POST")
+        .path("/users")
+        .json(&json!({ "email": "verify-me@example.com", "password": "Verified99" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(register_resp.status(), 200);
+    let created: UserResponse = serde_json::from_slice(register_resp.body()).expect("user");
+    assert!(created.email_verified_at.is_none());
+
+    let token = mailed_token(&mailer, "verify-me@example.com", "Verification token: ");
+    let verify_resp = warp::test::request()
+        .method("POST")
+        .path("/users/verify-email")
+        .json(&json!({ "token": token }))
+        .reply(&filter)
+        .await;
+    assert_eq!(verify_resp.status(), 200);
+    let verified: UserResponse = serde_json::from_slice(verify_resp.body()).expect("user");
+    assert_eq!(verified.id, created.id);
+    assert!(verified.email_verified_at.is_some());
+
+    let reuse_resp = warp::test::request()
+        .method("POST")
+        .path("/users/verify-email")
+        .json(&json!({ "token": token }))
+        .reply(&filter)
+        .await;
+    assert_eq!(reuse_resp.status(), 400);
+
+    // Already verified accounts are not mailed again
+    let sent_before = mailer.sent().len();
+    let resend_resp = warp::test::request()
+        .method("POST")
+        .path("/users/verify-email/resend")
+        .json(&json!({ "email": "verify-me@example.com" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(resend_resp.status(), 202);
+    assert_eq!(mailer.sent().len(), sent_before);
+}
+
+#[tokio::test]
+async fn verification_resends_share_the_mail_throttle() {
+    let test_db = setup_postgres();
+    let mailer = InMemoryMailer::new();
+    let mut auth = test_auth_config();
+    auth.login_throttle.max_mails_per_email = 2;
+    auth.login_throttle.base_lockout_secs = 60;
+    let filter = user_filter_with(test_db.pool.clone(), auth, mailer.clone());
+    register_and_login(&filter, "resent@example.com", "Resent9999").await;
+
+    let resend = || {
+        warp::test::request()
+            .method("POST")
+            .path("/users/verify-email/resend")
+            .json(&json!({ "email": "resent@example.com" }))
+            .reply(&filter)
+    };
+    assert_eq!(resend().await.status(), 202);
+    wait_for_mail(&mailer, 2).await;
+
+    // A reset request uses up the rest of the address's allowance
+    let forgot = warp::test::request()
+        .method("POST")
+        .path("/users/password/forgot")
+        .json(&json!({ "email": "resent@example.com" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(forgot.status(), 202);
+    assert_eq!(resend().await.status(), 429);
+}
+
+#[tokio::test]
+async fn mails_state_short_token_lifetimes_in_minutes() {
+    let test_db = setup_postgres();
+    let mailer = InMemoryMailer::new();
+    let mut auth = test_auth_config();
+    auth.email_verification_ttl_secs = 30 * 60;
+    let filter = user_filter_with(test_db.pool.clone(), auth, mailer.clone());
+
+    let register_resp = warp::test::request()
+        .method("POST")
+        .path("/users")
+        .json(&json!({ "email": "short-ttl@example.com", "password": "Verified99" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(register_resp.status(), 200);
+    let sent = mailer.sent();
+    let mail = sent.last().expect("verification mail");
+    assert!(
+        mail.body.contains("The token expires in 30 minutes."),
+        "{}",
+        mail.body
+    );
+}
+
+#[tokio::test]
+async fn changing_email_requires_verifying_the_new_address() {
+    let test_db = setup_postgres();
+    let mailer = InMemoryMailer::new();
+    let filter = user_filter_with(test_db.pool.clone(), test_auth_config(), mailer.clone());
+
+    let session = register_and_login(&filter, "before-move@example.com", "Relocate99").await;
+    let first_token = mailed_token(&mailer, "before-move@example.com", "Verification token: ");
+    let verify_resp = warp::test::request()
+        .method("POST")
+        .path("/users/verify-email")
+        .json(&json!({ "token": first_token }))
+        .reply(&filter)
+        .await;
+    assert_eq!(verify_resp.status(), 200);
+
+    let update_resp = warp::test::request()
+        .method("PUT")
+        .path(&format!("/users/{}", session.user.id))
+        .header("authorization", bearer(&session.user))
+        .json(&json!({ "email": "after-move@example.com" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(update_resp.status(), 200);
+    let moved: UserResponse = serde_json::from_slice(update_resp.body()).expect("user");
+    assert_eq!(moved.email.as_str(), "after-move@example.com");
+    assert!(moved.email_verified_at.is_none());
+
+    let new_token = mailed_token(&mailer, "after-move@example.com", "Verification token: ");
+    let verify_new = warp::test::request()
+        .method("POST")
+        .path("/users/verify-email")
+        .json(&json!({ "token": new_token }))
+        .reply(&filter)
+        .await;
+    assert_eq!(verify_new.status(), 200);
+    let verified: UserResponse = serde_json::from_slice(verify_new.body()).expect("user");
+    assert!(verified.email_verified_at.is_some());
+}
+
+#[tokio::test]
+async fn login_rule_blocks_unverified_accounts() {
+    let test_db = setup_postgres();
+    let mailer = InMemoryMailer::new();
+    let mut auth = test_auth_config();
+    auth.require_verified_email = EmailVerificationRule::Login;
+    let filter = user_filter_with(test_db.pool.clone(), auth, mailer.clone());
+
+    let credentials = json!({ "email": "unverified@example.com", "password": "NotYet999" });
+    let register_resp = warp::test::request()
+        .method("POST")
+        .path("/users")
+        .json(&credentials)
+        .reply(&filter)
+        .await;
+    assert_eq!(register_resp.status(), 200);
+
+    let blocked = warp::test::request()
+        .method("POST")
+        .path("/users/login")
+        .json(&credentials)
+        .reply(&filter)
+        .await;
+    assert_eq!(blocked.status(), 403);
+
+    // A lost email can be sent again
+    let resend_resp = warp::test::request()
+        .method("POST")
+        .path("/users/verify-email/resend")
+        .json(&json!({ "email": "unverified@example.com" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(resend_resp.status(), 202);
+    wait_for_mail(&mailer, 2).await;
+
+    let token = mailed_token(&mailer, "unverified@example.com", "Verification token: ");
+    let verify_resp = warp::test::request()
+        .method("POST")
+        .path("/users/verify-email")
+        .json(&json!({ "token": token }))
+        .reply(&filter)
+        .await;
+    assert_eq!(verify_resp.status(), 200);
+
+    let allowed = warp::test::request()
+        .method("POST")
+        .path("/users/login")
+        .json(&credentials)
+        .reply(&filter)
+        .await;
+    assert_eq!(allowed.status(), 200);
+}
+
+fn throttled_auth_config() -> AuthConfig {
+    let mut auth = test_auth_config();
+    auth.login_throttle.max_failures_per_account = 3;
+    auth.login_throttle.max_failures_per_ip = 3;
+    auth.login_throttle.base_lockout_secs = 60;
+    auth
+}
+
+async fn login_from<F>(filter: &F, ip: [u8; 4], email: &str, password: &str) -> Response<Bytes>
+where
+    F: Filter + 'static,
+    F::Extract: warp::Reply + Send,
+{
+    warp::test::request()
+        .method("POST")
+        .path("/users/login")
+        .remote_addr(SocketAddr::from((ip, 40000)))
+        .json(&json!({ "email": email, "password": password }))
+        .reply(filter)
+        .await
+}
+
+#[tokio::test]
+async fn repeated_failures_lock_the_account_with_retry_after() {
+    let test_db = setup_postgres();
+    let filter = user_filter_with(
+        test_db.pool.clone(),
+        throttled_auth_config(),
+        InMemoryMailer::new(),
+    );
+    register_and_login(&filter, "guessed@example.com", "RightPass9").await;
+
+    // Spread over several addresses so only the account counter trips
+    for ip in [[10, 0, 0, 1], [10, 0, 0, 2], [10, 0, 0, 3]] {
+        let resp = login_from(&filter, ip, "guessed@example.com", "WrongPass9").await;
+        assert_eq!(resp.status(), 401);
+    }
+
+    let locked = login_from(&filter, [10, 0, 0, 4], "guessed@example.com", "RightPass9").await;
+    assert_eq!(locked.status(), 429);
+    let retry_after: u64 = locked
+        .headers()
+        .get("retry-after")
+        .expect("retry-after header")
+        .to_str()
+        .unwrap()
+        .parse()
+        .expect("seconds");
+    assert!((1..=60).contains(&retry_after));
+}
+
+#[tokio::test]
+async fn failures_from_one_ip_lock_out_that_ip() {
+    let test_db = setup_postgres();
+    let filter = user_filter_with(
+        test_db.pool.clone(),
+        throttled_auth_config(),
+        InMemoryMailer::new(),
+    );
+    register_and_login(&filter, "bystander@example.com", "RightPass9").await;
+
+    // Unknown emails fail exactly like wrong passwords
+    for n in 0..3 {
+        let email = format!("sprayed-{n}@example.com");
+        let resp = login_from(&filter, [192, 0, 2, 7], &email, "Guess1234").await;
+        assert_eq!(resp.status(), 401);
+    }
+
+    let same_ip = login_from(
+        &filter,
+        [192, 0, 2, 7],
+        "bystander@example.com",
+        "RightPass9",
+    )
+    .await;
+    assert_eq!(same_ip.status(), 429);
+
+    let other_ip = login_from(
+        &filter,
+        [192, 0, 2, 8],
+        "bystander@example.com",
+        "RightPass9",
+    )
+    .await;
+    assert_eq!(other_ip.status(), 200);
+}
+
+#[tokio::test]
+async fn successful_login_resets_account_failures() {
+    let test_db = setup_postgres();
+    let filter = user_filter_with(
+        test_db.pool.clone(),
+        throttled_auth_config(),
+        InMemoryMailer::new(),
+    );
+    register_and_login(&filter, "clumsy@example.com", "RightPass9").await;
+
+    for _ in 0..2 {
+        let resp = login_from(&filter, [10, 1, 0, 1], "clumsy@example.com", "Typo12345").await;
+        assert_eq!(resp.status(), 401);
+    }
+    let ok = login_from(&filter, [10, 1, 0, 2], "clumsy@example.com", "RightPass9").await;
+    assert_eq!(ok.status(), 200);
+
+    for _ in 0..2 {
+        let resp = login_from(&filter, [10, 1, 0, 3], "clumsy@example.com", "Typo12345").await;
+        assert_eq!(resp.status(), 401);
+    }
+    let still_ok = login_from(&filter, [10, 1, 0, 4], "clumsy@example.com", "RightPass9").await;
+    assert_eq!(still_ok.status(), 200);
+}
+
+#[tokio::test]
+async fn concurrent_guesses_cannot_exceed_the_allowance() {
+    let test_db = setup_postgres();
+    let filter = user_filter_with(
+        test_db.pool.clone(),
+        throttled_auth_config(),
+        InMemoryMailer::new(),
+    );
+    register_and_login(&filter, "raced@example.com", "RightPass9").await;
+
+    let guesses =
+        (0..10u8).map(|n| login_from(&filter, [10, 2, 0, n], "raced@example.com", "Guess1234"));
+    let statuses: Vec<u16> = join_all(guesses)
+        .await
+        .iter()
+        .map(|resp| resp.status().as_u16())
+        .collect();
+    assert_eq!(statuses.iter().filter(|status| **status == 401).count(), 3);
+    assert_eq!(statuses.iter().filter(|status| **status == 429).count(), 7);
+}
+
+#[tokio::test]
+async fn successful_logins_do_not_use_up_the_ip_allowance() {
+    let test_db = setup_postgres();
+    let filter = user_filter_with(
+        test_db.pool.clone(),
+        throttled_auth_config(),
+        InMemoryMailer::new(),
+    );
+    register_and_login(&filter, "regular@example.com", "RightPass9").await;
+
+    let typo = login_from(&filter, [10, 3, 0, 1], "stranger@example.com", "Guess1234").await;
+    assert_eq!(typo.status(), 401);
+    for _ in 0..4 {
+        let ok = login_from(&filter, [10, 3, 0, 1], "regular@example.com", "RightPass9").await;
+        assert_eq!(ok.status(), 200);
+    }
+    let typo = login_from(&filter, [10, 3, 0, 1], "stranger@example.com", "Guess1234").await;
+    assert_eq!(typo.status(), 401);
+}
+
+#[tokio::test]
+async fn stale_throttle_rows_are_purged() {
+    let test_db = setup_postgres();
+    let pool = test_db.pool.clone();
+    let auth = throttled_auth_config();
+    let filter = user_filter_with(pool.clone(), auth.clone(), InMemoryMailer::new());
+
+    let resp = login_from(&filter, [10, 4, 0, 1], "made-up@example.com", "Guess1234").await;
+    assert_eq!(resp.status(), 401);
+    let throttle = |scope: &str, subject: &str| {
+        let mut conn = get_conn(&pool).expect("conn");
+        login_throttle_repository::get_throttle(&mut conn, scope, subject).expect("lookup")
+    };
+    assert!(throttle("account", "made-up@example.com").is_some());
+
+    // Recent failures are kept until they fall out of the window
+    let now = Utc::now();
+    login_throttle_service::purge_stale(pool.clone(), &auth.login_throttle, now)
+        .await
+        .expect("purge");
+    assert!(throttle("account", "made-up@example.com").is_some());
+
+    let later = now + chrono::Duration::seconds(auth.login_throttle.failure_window_secs + 1);
+    login_throttle_service::purge_stale(pool.clone(), &auth.login_throttle, later)
+        .await
+        .expect("purge");
+    assert!(throttle("account", "made-up@example.com").is_none());
+    assert!(throttle("ip", "10.4.0.1").is_none());
+}
+
+fn stored_password_hash(pool: &PgPool, email: &str) -> String {
+    let mut conn = get_conn(pool).expect("conn");
+    user_repository::get_user_by_email(&mut conn, &Email::parse(email).expect("email"))
+        .expect("lookup")
+        .expect("user exists")
+        .password_hash
+}
+
+#[tokio::test]
+async fn login_upgrades_legacy_bcrypt_hashes() {
+    let test_db = setup_postgres();
+    let pool = test_db.pool.clone();
+    let filter = user_filter(pool.clone());
+
+    {
+        let mut conn = get_conn(&pool).expect("conn");
+        let legacy = NewUser {
+            email: Email::parse("legacy@example.com").expect("email"),
+            password_hash: bcrypt::hash("OldScheme9", 4).expect("bcrypt hash"),
+        };
+        user_repository::create_user(&mut conn, &legacy).expect("create user");
+    }
+
+    let credentials = json!({ "email": "legacy@example.com", "password": "OldScheme9" });
+    let first = warp::test::request()
+        .method("POST")
+        .path("/users/login")
+        .json(&credentials)
+        .reply(&filter)
+        .await;
+    assert_eq!(first.status(), 200);
+    assert!(stored_password_hash(&pool, "legacy@example.com").starts_with("$argon2id$"));
+
+    let second = warp::test::request()
+        .method("POST")
+        .path("/users/login")
+        .json(&credentials)
+        .reply(&filter)
+        .await;
+    assert_eq!(second.status(), 200);
+}
+
+#[tokio::test]
+async fn login_rehashes_when_argon2_parameters_change() {
+    let test_db = setup_postgres();
+    let pool = test_db.pool.clone();
+
+    let mut cheap = test_auth_config();
+    cheap.password_hasher = Arc::new(Argon2idHasher::new(8 * 1024, 1, 1).expect("params"));
+    let old_filter = user_filter_with(pool.clone(), cheap, InMemoryMailer::new());
+    register_and_login(&old_filter, "tuned@example.com", "Tuned12345").await;
+    assert!(stored_password_hash(&pool, "tuned@example.com").contains("m=8192,t=1,p=1"));
+
+    let filter = user_filter(pool.clone());
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/users/login")
+        .json(&json!({ "email": "tuned@example.com", "password": "Tuned12345" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(resp.status(), 200);
+    assert!(stored_password_hash(&pool, "tuned@example.com").contains("m=19456,t=2,p=1"));
+}
+
+fn violated_rules(body: &[u8]) -> Vec<String> {
+    let body: Value = serde_json::from_slice(body).expect("error body");
+    body["violations"]
+        .as_array()
+        .expect("violations")
+        .iter()
+        .map(|v| v["rule"].as_str().expect("rule").to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn password_policy_reports_every_violated_rule() {
+    let test_db = setup_postgres();
+    let mut auth = test_auth_config();
+    auth.password_policy.require_uppercase = true;
+    auth.password_policy.require_digit = true;
+    auth.password_policy.require_symbol = true;
+    auth.password_policy.reject_email_local_part = true;
+    // SHA-1 of "Tr0ub4dor&3" starts with 874572E7A5
+    auth.password_policy.breached_passwords = Some(Arc::new(
+        BreachedPasswordList::parse("0000000000:3\n874572E7A5:12\nFFFFFFFFFF:1\n")
+            .expect("breached list"),
+    ));
+    let filter = user_filter_with(test_db.pool.clone(), auth, InMemoryMailer::new());
+
+    let register = |email: &str, password: &str| {
+        warp::test::request()
+            .method("POST")
+            .path("/users")
+            .json(&json!({ "email": email, "password": password }))
+    };
+
+    // Five characters even though it is ten bytes long
+    let weak = register("weak@example.com", "äöüäö").reply(&filter).await;
+    assert_eq!(weak.status(), 400);
+    assert_eq!(
+        violated_rules(weak.body()),
+        ["min_length", "uppercase", "digit", "symbol"]
+    );
+
+    let personal = register("jane.doe@example.com", "Jane.Doe#2024")
+        .reply(&filter)
+        .await;
+    assert_eq!(personal.status(), 400);
+    assert_eq!(violated_rules(personal.body()), ["contains_email"]);
+
+    let breached = register("breached@example.com", "Tr0ub4dor&3")
+        .reply(&filter)
+        .await;
+    assert_eq!(breached.status(), 400);
+    assert_eq!(violated_rules(breached.body()), ["breached"]);
+
+    let accepted = register("unicode@example.com", "ÄÖÜäöü#1")
+        .reply(&filter)
+        .await;
+    assert_eq!(accepted.status(), 200);
+    let created: UserResponse = serde_json::from_slice(accepted.body()).expect("user");
+
+    let change = warp::test::request()
+        .method("PUT")
+        .path(&format!("/users/password-reset/{}", created.id))
+        .header("authorization", bearer(&created))
+        .json(&json!({ "old_password": "ÄÖÜäöü#1", "new_password": "unicode-only" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(change.status(), 400);
+    assert_eq!(
+        violated_rules(change.body()),
+        ["uppercase", "digit", "contains_email"]
+    );
+}
+
+#[tokio::test]
+async fn refresh_rotates_tokens_and_detects_reuse() {
+    let test_db = setup_postgres();
+    let filter = user_filter(test_db.pool.clone());
+
+    let session = register_and_login(&filter, "rotate@example.com", "RotatePass9").await;
+
+    let first_refresh = warp::test::request()
+        .method("POST")
+        .path("/users/token/refresh")
+        .json(&json!({ "refresh_token": session.refresh_token }))
+        .reply(&filter)
+        .await;
+    assert_eq!(first_refresh.status(), 200);
+    let rotated: LoginResponse = serde_json::from_slice(first_refresh.body()).expect("rotated");
+    assert_ne!(rotated.refresh_token, session.refresh_token);
+    assert_eq!(rotated.user.id, session.user.id);
+
+    // Replaying the original token is treated as theft and kills the whole family
+    let replay = warp::test::request()
+        .method("POST")
+        .path("/users/token/refresh")
+        .json(&json!({ "refresh_token": session.refresh_token }))
+        .reply(&filter)
+        .await;
+    assert_eq!(replay.status(), 401);
+
+    let after_reuse = warp::test::request()
+        .method("POST")
+        .path("/users/token/refresh")
+        .json(&json!({ "refresh_token": rotated.refresh_token }))
+        .reply(&filter)
+        .await;
+    assert_eq!(after_reuse.status(), 401);
+}
+
+#[tokio::test]
+async fn changing_the_password_revokes_refresh_tokens() {
+    let test_db = setup_postgres();
+    let filter = user_filter(test_db.pool.clone());
+
+    let stolen = register_and_login(&filter, "changed@example.com", "ChangePass9").await;
+    let current = warp::test::request()
+        .method("POST")
+        .path("/users/login")
+        .json(&json!({ "email": "changed@example.com", "password": "ChangePass9" }))
+        .reply(&filter)
+        .await;
+    let current: LoginResponse = serde_json::from_slice(current.body()).expect("login response");
+
+    let change = warp::test::request()
+        .method("PUT")
+        .path(&format!("/users/password-reset/{}", current.user.id))
+        .header("authorization", format!("Bearer {}", current.access_token))
+        .json(&json!({ "old_password": "ChangePass9", "new_password": "ChangedPass9" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(change.status(), 200);
+
+    for session in [&stolen, &current] {
+        let refresh = warp::test::request()
+            .method("POST")
+            .path("/users/token/refresh")
+            .json(&json!({ "refresh_token": session.refresh_token }))
+            .reply(&filter)
+            .await;
+        assert_eq!(refresh.status(), 401);
+    }
+}
+
+#[tokio::test]
+async fn logout_revokes_refresh_token() {
+    let test_db = setup_postgres();
+    let filter = user_filter(test_db.pool.clone());
+
+    let session = register_and_login(&filter, "logout@example.com", "LogoutPass9").await;
+
+    let logout_resp = warp::test::request()
+        .method("POST")
+        .path("/users/logout")
+        .json(&json!({ "refresh_token": session.refresh_token }))
+        .reply(&filter)
+        .await;
+    assert_eq!(logout_resp.status(), 204);
+
+    let refresh_resp = warp::test::request()
+        .method("POST")
+        .path("/users/token/refresh")
+        .json(&json!({ "refresh_token": session.refresh_token }))
+        .reply(&filter)
+        .await;
+    assert_eq!(refresh_resp.status(), 401);
+
+    let unknown_resp = warp::test::request()
+        .method("POST")
+        .path("/users/token/refresh")
+        .json(&json!({ "refresh_token": "not-a-real-token" }))
+        .reply(&filter)
+        .await;
+    assert_eq!(unknown_resp.status(), 401);
+}
+
+async fn register_and_login<F>(filter: &F, email: &str, password: &str) -> LoginResponse
+where
+    F: Filter + 'static,
+    F::Extract: warp::Reply + Send,
+{
+    let credentials = json!({ "email": email, "password": password });
+
+    let register_resp = warp::test::request()
+        .method("POST")
+        .path("/users")
+        .json(&credentials)
+        .reply(filter)
+        .await;
+    assert_eq!(register_resp.status(), 200);
+
+    let login_resp = warp::test::request()
+        .method("POST")
+        .path("/users/login")
+        .json(&credentials)
+        .reply(filter)
+        .await;
+    assert_eq!(login_resp.status(), 200);
+    serde_json::from_slice(login_resp.body()).expect("login response")
 }
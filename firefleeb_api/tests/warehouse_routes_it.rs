@@ -0,0 +1,245 @@
+mod common {
+    pub mod auth;
+    pub mod blobs;
+    pub mod config;
+    pub mod db;
+}
+
+use common::auth::{role_token, test_auth_config};
+use common::blobs::test_blob_store;
+use common::config::app_config;
+use common::db::setup_postgres;
+use firefleeb_api::db::PgPool;
+use firefleeb_api::handlers::dtos::{ProductResponse, WarehouseResponse, WarehousesResponse};
+use firefleeb_api::routes::{
+    handle_rejection, product_routes::product_routes, warehouse_routes::warehouse_routes,
+};
+use firefleeb_api::types::role::Role;
+use serde_json::json;
+use uuid::Uuid;
+use warp::Filter;
+
+fn warehouse_filter(
+    pool: PgPool,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = std::convert::Infallible> + Clone {
+    let config = app_config(test_auth_config());
+    warehouse_routes(pool.clone(), config.clone())
+        .or(product_routes(
+            pool,
+            config,
+            test_blob_store().store.clone(),
+        ))
+        .recover(handle_rejection)
+}
+
+async fn create_warehouse<F>(filter: &F, body: serde_json::Value) -> WarehouseResponse
+where
+    F: Filter + Clone + 'static,
+    F::Extract: warp::Reply + Send,
+{
+    let resp = warp::test::request()
+        .method("POST")
+        .path("/warehouses")
+        .header("authorization", role_token(Role::Admin))
+        .json(&body)
+        .reply(filter)
+        .await;
+    assert_eq!(resp.status(), 201, "{:?}", resp.body());
+    serde_json::from_slice(resp.body()).expect("warehouse")
+}
+
+async fn warehouses<F>(filter: &F) -> Vec<WarehouseResponse>
+where
+    F: Filter + Clone + 'static,
+    F::Extract: warp::Reply + Send,
+{
+    let resp = warp::test::request()
+        .method("GET")
+        .path("/warehouses")
+        .header("authorization", role_token(Role::Staff))
+        .reply(filter)
+        .await;
+    assert_eq!(resp.status(), 200, "{:?}", resp.body());
+    serde_json::from_slice::<WarehousesResponse>(resp.body())
+        .expect("warehouses")
+        .warehouses
+}
+
+#[tokio::test]
+async fn admins_manage_warehouses() {
+    let test_db = setup_postgres();
+    let filter = warehouse_filter(test_db.pool.clone());
+
+    // Existing stock was moved into the one warehouse the migration created
+    let initial = warehouses(&filter).await;
+    assert_eq!(initial.len(), 1);
+    assert_eq!(
+        (initial[0].code.as_str(), initial[0].is_default),
+        ("MAIN", true)
+    );
+
+    let create = |token: String, body: serde_json::Value| {
+        warp::test::request()
+            .method("POST")
+            .path("/warehouses")
+            .header("authorization", token)
+            .json(&body)
+            .reply(&filter)
+    };
+    let north =
+        json!({ "code": "NORTH", "name": "North hub", "latitude": 59.33, "longitude": 18.07 });
+    assert_eq!(
+        create(role_token(Role::Staff), north.clone())
+            .await
+            .status(),
+        403
+    );
+    let half_located = json!({ "code": "EAST", "name": "East hub", "latitude": 52.2 });
+    assert_eq!(
+        create(role_token(Role::Admin), half_located).await.status(),
+        400
+    );
+    let off_the_map =
+        json!({ "code": "EAST", "name": "East hub", "latitude": 91.0, "longitude": 0.0 });
+    assert_eq!(
+        create(role_token(Role::Admin), off_the_map).await.status(),
+        400
+    );
+    assert_eq!(
+        create(
+            role_token(Role::Admin),
+            json!({ "code": "BAD CODE", "name": "x" })
+        )
+        .await
+        .status(),
+        400
+    );
+
+    let created = create(role_token(Role::Admin), north.clone()).await;
+    assert_eq!(created.status(), 201, "{:?}", created.body());
+    let north: WarehouseResponse = serde_json::from_slice(created.body()).expect("warehouse");
+    assert_eq!(north.latitude, Some(59.33));
+    assert!(!north.is_default);
+    let duplicate = json!({ "code": "NORTH", "name": "Another north" });
+    assert_eq!(
+        create(role_token(Role::Admin), duplicate).await.status(),
+        409
+    );
+
+    let update = |id: Uuid, body: serde_json::Value| {
+        warp::test::request()
+            .method("PUT")
+            .path(&format!("/warehouses/{id}"))
+            .header("authorization", role_token(Role::Admin))
+            .json(&body)
+            .reply(&filter)
+    };
+    assert_eq!(
+        update(north.id, json!({ "latitude": null })).await.status(),
+        400
+    );
+    assert_eq!(
+        update(Uuid::new_v4(), json!({ "name": "Nowhere" }))
+            .await
+            .status(),
+        404
+    );
+    let moved = update(north.id, json!({ "name": "North DC", "is_default": true })).await;
+    assert_eq!(moved.status(), 200, "{:?}", moved.body());
+
+    let listed = warehouses(&filter).await;
+    assert_eq!(listed.len(), 2);
+    assert_eq!(
+        (listed[0].id, listed[0].name.as_str(), listed[0].is_default),
+        (north.id, "North DC", true)
+    );
+    assert!(!listed[1].is_default);
+}
+
+#[tokio::test]
+async fn stock_is_kept_per_warehouse() {
+    let test_db = setup_postgres();
+    let filter = warehouse_filter(test_db.pool.clone());
+    let auth = role_token(Role::Staff);
+
+    let created = warp::test::request()
+        .method("POST")
+        .path("/products")
+        .header("authorization", &auth)
+        .json(&json!({ "product_name": "Depot Drill", "price": "80.00", "stock": 5 }))
+        .reply(&filter)
+        .await;
+    assert_eq!(created.status(), 200, "{:?}", created.body());
+    let product_id = serde_json::from_slice::<ProductResponse>(created.body())
+        .expect("product")
+        .id;
+    let east = create_warehouse(
+        &filter,
+        json!({ "code": "EAST", "name": "East hub", "latitude": 52.2, "longitude": 21.0 }),
+    )
+    .await;
+
+    let record = |body: serde_json::Value| {
+        warp::test::request()
+            .method("POST")
+            .path(&format!("/products/{product_id}/stock-movements"))
+            .header("authorization", &auth)
+            .json(&body)
+            .reply(&filter)
+    };
+    let receipt = json!({
+        "warehouse_id": east.id, "movement_type": "receipt", "quantity": 3, "reason": "Transfer in"
+    });
+    assert_eq!(record(receipt).await.status(), 201);
+    // The default warehouse holds 5, but this one only 3
+    let oversold = json!({
+        "warehouse_id": east.id, "movement_type": "sale", "quantity": -4, "reason": "Counter sale"
+    });
+    assert_eq!(record(oversold).await.status(), 409);
+    let unknown = json!({
+        "warehouse_id": Uuid::new_v4(), "movement_type": "receipt", "quantity": 1, "reason": "?"
+    });
+    assert_eq!(record(unknown).await.status(), 404);
+
+    let product = |query: &'static str| {
+        warp::test::request()
+            .method("GET")
+            .path(&format!("/products/{product_id}{query}"))
+            .reply(&filter)
+    };
+    let plain: ProductResponse = serde_json::from_slice(product("").await.body()).expect("product");
+    assert_eq!((plain.stock, plain.available_stock), (8, 8));
+    assert!(plain.warehouse_stock.is_none());
+
+    let detailed: ProductResponse =
+        serde_json::from_slice(product("?include_warehouses=true").await.body()).expect("product");
+    let levels: Vec<(&str, i64)> = detailed
+        .warehouse_stock
+        .as_deref()
+        .expect("breakdown")
+        .iter()
+        .map(|level| (level.code.as_str(), level.stock))
+        .collect();
+    assert_eq!(levels, [("MAIN", 5), ("EAST", 3)]);
+
+    // Lowering the total empties the default warehouse before touching the others
+    let lowered = warp::test::request()
+        .method("PUT")
+        .path(&format!("/products/{product_id}"))
+        .header("authorization", &auth)
+        .json(&json!({ "stock": 2 }))
+        .reply(&filter)
+        .await;
+    assert_eq!(lowered.status(), 200, "{:?}", lowered.body());
+    let after: ProductResponse =
+        serde_json::from_slice(product("?include_warehouses=true").await.body()).expect("product");
+    assert_eq!(after.stock, 2);
+    let levels: Vec<(&str, i64)> = after
+        .warehouse_stock
+        .as_deref()
+        .expect("breakdown")
+        .iter()
+        .map(|level| (level.code.as_str(), level.stock))
+        .collect();
+    assert_eq!(levels, [("EAST", 2)]);
+}